@@ -1,12 +1,16 @@
-use crate::model;
 use eframe::egui;
+use sansuyu::geometry::{
+    compute_binding_for_target, compute_connector_binding, compute_port_binding,
+    default_curve_controls, element_center_world, facing_bindings, facing_side_bindings,
+    hit_test_element, polyline_points_world, resolve_binding_point, resolved_line_endpoints_world,
+    snap_element_to_grid, topmost_bind_target_id, translate_element,
+};
+use sansuyu::load::{load_document, save_path_for};
+use sansuyu::routing::{route_inputs_key, update_routes};
+use sansuyu::{dot, drawio, excalidraw, model, pdf, raster, svg, tikz};
 use std::collections::{HashMap, HashSet};
 
-use super::geometry::{
-    compute_binding_for_target, hit_test_element, resolve_binding_point,
-    resolved_line_endpoints_world, snap_element_to_grid, topmost_bind_target_id, translate_element,
-};
-use super::{settings, svg};
+use super::settings;
 use super::{ClipboardPayload, DiagramApp, LineEndpoint, Snapshot};
 
 impl DiagramApp {
@@ -95,8 +99,27 @@ impl DiagramApp {
                 if end_binding.is_some() {
                     *b = model::Point::from_pos2(rb);
                 }
+            } else if let model::ElementKind::Polyline {
+                points,
+                start_binding,
+                end_binding,
+                ..
+            } = &mut element.kind
+            {
+                if start_binding.is_none() && end_binding.is_none() {
+                    continue;
+                }
+                let resolved = polyline_points_world(&before, points, start_binding, end_binding);
+                for (point, p) in points.iter_mut().zip(resolved) {
+                    *point = model::Point::from_pos2(p);
+                }
             }
         }
+        let key = route_inputs_key(&self.doc);
+        if key != self.routes_key {
+            update_routes(&mut self.doc);
+            self.routes_key = key;
+        }
     }
 
     pub(super) fn restore(&mut self, snapshot: Snapshot) {
@@ -319,8 +342,8 @@ impl DiagramApp {
             {
                 let a0 = *a;
                 let b0 = *b;
-                let sb = *start_binding;
-                let eb = *end_binding;
+                let sb = start_binding.clone();
+                let eb = end_binding.clone();
                 if sb
                     .as_ref()
                     .is_some_and(|bind| selected.contains(&bind.element_id))
@@ -337,6 +360,24 @@ impl DiagramApp {
                     *b = model::Point::from_pos2(rb);
                     *end_binding = None;
                 }
+            } else if let model::ElementKind::Polyline {
+                points,
+                start_binding,
+                end_binding,
+                ..
+            } = &mut element.kind
+            {
+                let resolved = polyline_points_world(&before, points, start_binding, end_binding);
+                let last = points.len().saturating_sub(1);
+                for (binding, i) in [(start_binding, 0), (end_binding, last)] {
+                    if binding
+                        .as_ref()
+                        .is_some_and(|bind| selected.contains(&bind.element_id))
+                    {
+                        points[i] = model::Point::from_pos2(resolved[i]);
+                        *binding = None;
+                    }
+                }
             }
         }
         self.clear_selection();
@@ -379,25 +420,26 @@ impl DiagramApp {
                 ..
             } = &mut element.kind
             {
-                let sb = *start_binding;
-                let eb = *end_binding;
+                let sb = start_binding.clone();
+                let eb = end_binding.clone();
                 let (a0, b0) = resolved_line_endpoints_world(&before, *a, *b, &sb, &eb);
                 let desired_a = a0 + delta_world;
                 let desired_b = b0 + delta_world;
 
-                let mut new_sb = sb;
-                let mut new_eb = eb;
+                let mut new_sb = sb.clone();
+                let mut new_eb = eb.clone();
 
-                if let Some(bind) = sb {
-                    if !selected.contains(&bind.element_id) {
+                // Ends pinned to a port stay there; others slide along the outline.
+                if let Some(bind) = &sb {
+                    if !selected.contains(&bind.element_id) && bind.port.is_none() {
                         new_sb = compute_binding_for_target(&before, bind.element_id, desired_a);
                         if new_sb.is_none() {
                             *a = model::Point::from_pos2(desired_a);
                         }
                     }
                 }
-                if let Some(bind) = eb {
-                    if !selected.contains(&bind.element_id) {
+                if let Some(bind) = &eb {
+                    if !selected.contains(&bind.element_id) && bind.port.is_none() {
                         new_eb = compute_binding_for_target(&before, bind.element_id, desired_b);
                         if new_eb.is_none() {
                             *b = model::Point::from_pos2(desired_b);
@@ -414,12 +456,83 @@ impl DiagramApp {
 
                 *start_binding = new_sb;
                 *end_binding = new_eb;
+            } else if let model::ElementKind::Polyline {
+                points,
+                start_binding,
+                end_binding,
+                ..
+            } = &mut element.kind
+            {
+                let resolved = polyline_points_world(&before, points, start_binding, end_binding);
+                for (point, p) in points.iter_mut().zip(&resolved) {
+                    *point = model::Point::from_pos2(*p + delta_world);
+                }
+                // Same as a line's ends: ports hold them, outlines let them slide.
+                let last = points.len().saturating_sub(1);
+                for (binding, i) in [(start_binding, 0), (end_binding, last)] {
+                    let Some(target) = binding
+                        .as_ref()
+                        .filter(|bind| !selected.contains(&bind.element_id) && bind.port.is_none())
+                        .map(|bind| bind.element_id)
+                    else {
+                        continue;
+                    };
+                    *binding = compute_binding_for_target(&before, target, points[i].to_pos2());
+                }
             } else {
                 translate_element(element, delta_world);
             }
         }
     }
 
+    /// Points bindings at the renamed ports of `element_id` to their new names.
+    pub(super) fn rename_port_bindings(&mut self, element_id: u64, renamed: &[(String, String)]) {
+        for element in &mut self.doc.elements {
+            let (model::ElementKind::Line {
+                start_binding,
+                end_binding,
+                ..
+            }
+            | model::ElementKind::Polyline {
+                start_binding,
+                end_binding,
+                ..
+            }) = &mut element.kind
+            else {
+                continue;
+            };
+            for binding in [start_binding, end_binding].into_iter().flatten() {
+                if binding.element_id != element_id {
+                    continue;
+                }
+                if let Some((_, new)) = renamed
+                    .iter()
+                    .find(|(old, _)| binding.port.as_deref() == Some(old.as_str()))
+                {
+                    binding.port = Some(new.clone());
+                }
+            }
+        }
+    }
+
+    /// Binding for a connector end dropped at `world_pos`: the nearest port
+    /// of the shape under it, or else the closest point on its outline. Away
+    /// from shapes the end joins any line or polyline other than `exclude`
+    /// running past.
+    pub(super) fn binding_at(
+        &self,
+        world_pos: egui::Pos2,
+        threshold_world: f32,
+        exclude: Option<u64>,
+    ) -> Option<model::Binding> {
+        let Some(target) = topmost_bind_target_id(&self.doc, world_pos, threshold_world) else {
+            return compute_connector_binding(&self.doc, world_pos, threshold_world, exclude);
+        };
+        // Ports attract from a little further away than the outline does.
+        compute_port_binding(&self.doc, target, world_pos, threshold_world * 2.0)
+            .or_else(|| compute_binding_for_target(&self.doc, target, world_pos))
+    }
+
     pub(super) fn try_bind_line_endpoint(
         &mut self,
         line_id: u64,
@@ -427,8 +540,7 @@ impl DiagramApp {
         world_pos: egui::Pos2,
         threshold_world: f32,
     ) {
-        let target = topmost_bind_target_id(&self.doc, world_pos, threshold_world);
-        let binding = target.and_then(|tid| compute_binding_for_target(&self.doc, tid, world_pos));
+        let binding = self.binding_at(world_pos, threshold_world, Some(line_id));
         let resolved = binding
             .as_ref()
             .and_then(|b| resolve_binding_point(&self.doc, b));
@@ -436,40 +548,94 @@ impl DiagramApp {
             return;
         };
         let element = &mut self.doc.elements[idx];
+        let (point, slot) = match (&mut element.kind, endpoint) {
+            (
+                model::ElementKind::Line {
+                    a, start_binding, ..
+                },
+                LineEndpoint::Start,
+            ) => (a, start_binding),
+            (model::ElementKind::Line { b, end_binding, .. }, LineEndpoint::End) => {
+                (b, end_binding)
+            }
+            (
+                model::ElementKind::Polyline {
+                    points,
+                    start_binding,
+                    ..
+                },
+                LineEndpoint::Start,
+            ) if !points.is_empty() => (&mut points[0], start_binding),
+            (
+                model::ElementKind::Polyline {
+                    points,
+                    end_binding,
+                    ..
+                },
+                LineEndpoint::End,
+            ) if !points.is_empty() => {
+                let last = points.len() - 1;
+                (&mut points[last], end_binding)
+            }
+            _ => return,
+        };
+        *slot = binding;
+        *point = model::Point::from_pos2(resolved.unwrap_or(world_pos));
+    }
+
+    /// Sets the route of new lines and of the selected ones.
+    pub(super) fn set_line_route(&mut self, route: model::LineRoute) {
+        self.push_undo();
+        for idx in 0..self.doc.elements.len() {
+            let element = &self.doc.elements[idx];
+            let differs = match &element.kind {
+                model::ElementKind::Line { route: current, .. } => {
+                    std::mem::discriminant(current) != std::mem::discriminant(&route)
+                }
+                _ => false,
+            };
+            if differs && self.selected.contains(&element.id) {
+                self.apply_line_route(idx, &route);
+            }
+        }
+        self.line_route = route;
+    }
+
+    /// Switches the line at `idx` to the kind of `route`.
+    pub(super) fn apply_line_route(&mut self, idx: usize, route: &model::LineRoute) {
         let model::ElementKind::Line {
             a,
             b,
             start_binding,
             end_binding,
             ..
-        } = &mut element.kind
+        } = &self.doc.elements[idx].kind
         else {
             return;
         };
-        match endpoint {
-            LineEndpoint::Start => {
-                *start_binding = binding;
-                let p = resolved.unwrap_or(world_pos);
-                *a = model::Point::from_pos2(p);
-            }
-            LineEndpoint::End => {
-                *end_binding = binding;
-                let p = resolved.unwrap_or(world_pos);
-                *b = model::Point::from_pos2(p);
-            }
+        let (a, b) = resolved_line_endpoints_world(&self.doc, *a, *b, start_binding, end_binding);
+        let route = self.route_between(route, a, b, start_binding, end_binding);
+        if let model::ElementKind::Line { route: current, .. } = &mut self.doc.elements[idx].kind {
+            *current = route;
         }
     }
 
-    fn element_center_world(&self, element_id: u64) -> Option<egui::Pos2> {
-        let element = self.doc.elements.iter().find(|e| e.id == element_id)?;
-        match &element.kind {
-            model::ElementKind::Rect { rect, .. }
-            | model::ElementKind::Ellipse { rect, .. }
-            | model::ElementKind::Triangle { rect, .. }
-            | model::ElementKind::Parallelogram { rect, .. }
-            | model::ElementKind::Trapezoid { rect, .. } => Some(rect.to_rect().center()),
-            model::ElementKind::Text { pos, .. } => Some(pos.to_pos2()),
-            _ => Some(element.bounds().center()),
+    /// `route` for a line from `a` to `b`; a curve gets control points that
+    /// fit its ends.
+    pub(super) fn route_between(
+        &self,
+        route: &model::LineRoute,
+        a: egui::Pos2,
+        b: egui::Pos2,
+        start_binding: &Option<model::Binding>,
+        end_binding: &Option<model::Binding>,
+    ) -> model::LineRoute {
+        match route {
+            model::LineRoute::Curved { .. } => {
+                let (c1, c2) = default_curve_controls(&self.doc, a, b, start_binding, end_binding);
+                model::LineRoute::Curved { c1, c2 }
+            }
+            route => route.clone(),
         }
     }
 
@@ -490,31 +656,36 @@ impl DiagramApp {
         b_id: u64,
         arrow_style: model::ArrowStyle,
     ) {
-        let Some(ca) = self.element_center_world(a_id) else {
+        let Some(ca) = element_center_world(&self.doc, a_id) else {
             return;
         };
-        let Some(cb) = self.element_center_world(b_id) else {
+        let Some(cb) = element_center_world(&self.doc, b_id) else {
             return;
         };
-        let mut dir = cb - ca;
-        if dir.length() <= f32::EPSILON {
-            dir = egui::vec2(1.0, 0.0);
-        } else {
-            dir /= dir.length();
-        }
-        let probe_a = ca + dir * 1_000_000.0;
-        let probe_b = cb - dir * 1_000_000.0;
-
-        let Some(start_binding) = compute_binding_for_target(&self.doc, a_id, probe_a) else {
+        let (start_binding, end_binding) = match self.line_route {
+            model::LineRoute::Straight => facing_bindings(&self.doc, a_id, b_id),
+            model::LineRoute::Orthogonal { .. } | model::LineRoute::Curved { .. } => {
+                facing_side_bindings(&self.doc, a_id, b_id)
+            }
+        };
+        let Some(start_binding) = start_binding else {
             self.status = Some("Cannot bind start endpoint to selected object".to_string());
             return;
         };
-        let Some(end_binding) = compute_binding_for_target(&self.doc, b_id, probe_b) else {
+        let Some(end_binding) = end_binding else {
             self.status = Some("Cannot bind end endpoint to selected object".to_string());
             return;
         };
         let a_world = resolve_binding_point(&self.doc, &start_binding).unwrap_or(ca);
         let b_world = resolve_binding_point(&self.doc, &end_binding).unwrap_or(cb);
+        let (start_binding, end_binding) = (Some(start_binding), Some(end_binding));
+        let route = self.route_between(
+            &self.line_route,
+            a_world,
+            b_world,
+            &start_binding,
+            &end_binding,
+        );
 
         self.push_undo();
         let id = self.allocate_id();
@@ -530,10 +701,16 @@ impl DiagramApp {
                 b: model::Point::from_pos2(b_world),
                 arrow: false,
                 arrow_style,
-                start_binding: Some(start_binding),
-                end_binding: Some(end_binding),
+                heads: model::ArrowHeads::default(),
+                start_binding,
+                end_binding,
+                route,
+                label: String::new(),
+                label_position: 0.5,
+                label_offset: model::Point::default(),
             },
             style,
+            ports: Vec::new(),
         });
         self.set_selection_single(id);
     }
@@ -758,6 +935,11 @@ impl DiagramApp {
                 start_binding,
                 end_binding,
                 ..
+            }
+            | model::ElementKind::Polyline {
+                start_binding,
+                end_binding,
+                ..
             } = &mut e.kind
             {
                 if let Some(b) = start_binding.as_mut() {
@@ -794,9 +976,23 @@ impl DiagramApp {
         self.paste_from_payload(&payload);
     }
 
+    /// Diagrams saved as `.svg` carry the document inside the SVG, everything
+    /// else is written as JSON.
+    fn document_file_contents(&self, path: &std::path::Path) -> Result<String, String> {
+        let is_svg = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| e.eq_ignore_ascii_case("svg"));
+        if is_svg {
+            Ok(svg::document_to_svg(&self.doc))
+        } else {
+            serde_json::to_string_pretty(&self.doc).map_err(|e| e.to_string())
+        }
+    }
+
     pub(super) fn save_to_path(&mut self) {
-        match serde_json::to_string_pretty(&self.doc) {
-            Ok(json) => match std::fs::write(&self.file_path, json) {
+        match self.document_file_contents(std::path::Path::new(&self.file_path)) {
+            Ok(contents) => match std::fs::write(&self.file_path, contents) {
                 Ok(()) => self.status = Some(format!("Saved {}", self.file_path)),
                 Err(e) => self.status = Some(format!("Save failed: {e}")),
             },
@@ -809,11 +1005,12 @@ impl DiagramApp {
         if let Some(path) = rfd::FileDialog::new()
             .set_file_name(&default_name)
             .add_filter("JSON", &["json"])
+            .add_filter("SVG with embedded diagram", &["svg"])
             .save_file()
         {
             let path_str = path.display().to_string();
-            match serde_json::to_string_pretty(&self.doc) {
-                Ok(json) => match std::fs::write(&path, json) {
+            match self.document_file_contents(&path) {
+                Ok(contents) => match std::fs::write(&path, contents) {
                     Ok(()) => {
                         self.file_path = path_str.clone();
                         if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
@@ -855,29 +1052,211 @@ impl DiagramApp {
         }
     }
 
+    pub(super) fn export_png_dialog(&mut self, ctx: &egui::Context) {
+        let default_name = format!("{}.png", self.diagram_name);
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name(&default_name)
+            .add_filter("PNG", &["png"])
+            .save_file()
+        {
+            let options = raster::RasterOptions {
+                scale: self.png_scale,
+                transparent: self.png_transparent,
+                fonts: ctx.fonts(|f| f.definitions().clone()),
+            };
+            let result = raster::document_to_png(&self.doc, &options)
+                .and_then(|png| std::fs::write(&path, png).map_err(|e| e.to_string()));
+            match result {
+                Ok(()) => self.status = Some(format!("Saved {}", path.display())),
+                Err(e) => self.status = Some(format!("PNG export failed: {e}")),
+            }
+        }
+    }
+
+    pub(super) fn export_pdf_dialog(&mut self) {
+        let default_name = format!("{}.pdf", self.diagram_name);
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name(&default_name)
+            .add_filter("PDF", &["pdf"])
+            .save_file()
+        {
+            match std::fs::write(&path, pdf::document_to_pdf(&self.doc)) {
+                Ok(()) => self.status = Some(format!("Saved {}", path.display())),
+                Err(e) => self.status = Some(format!("PDF save failed: {e}")),
+            }
+        }
+    }
+
+    pub(super) fn export_tikz_dialog(&mut self) {
+        let default_name = format!("{}.tex", self.diagram_name);
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name(&default_name)
+            .add_filter("TikZ", &["tex", "tikz"])
+            .save_file()
+        {
+            let color_names: Vec<(String, model::Rgba)> = self
+                .all_color_names()
+                .into_iter()
+                .filter_map(|name| Some((name.clone(), self.lookup_color_by_name(&name)?)))
+                .collect();
+            let tikz = tikz::document_to_tikz(&self.doc, &color_names);
+            match std::fs::write(&path, tikz) {
+                Ok(()) => self.status = Some(format!("Saved {}", path.display())),
+                Err(e) => self.status = Some(format!("TikZ save failed: {e}")),
+            }
+        }
+    }
+
+    pub(super) fn export_dot_dialog(&mut self) {
+        let default_name = format!("{}.dot", self.diagram_name);
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name(&default_name)
+            .add_filter("Graphviz DOT", &["dot", "gv"])
+            .save_file()
+        {
+            let dot = dot::document_to_dot(&self.doc, &self.diagram_name);
+            match std::fs::write(&path, dot) {
+                Ok(()) => self.status = Some(format!("Saved {}", path.display())),
+                Err(e) => self.status = Some(format!("DOT save failed: {e}")),
+            }
+        }
+    }
+
+    pub(super) fn export_excalidraw_dialog(&mut self) {
+        let default_name = format!("{}.excalidraw", self.diagram_name);
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name(&default_name)
+            .add_filter("Excalidraw", &["excalidraw"])
+            .save_file()
+        {
+            let json = excalidraw::document_to_excalidraw(&self.doc);
+            match std::fs::write(&path, json) {
+                Ok(()) => self.status = Some(format!("Saved {}", path.display())),
+                Err(e) => self.status = Some(format!("Excalidraw save failed: {e}")),
+            }
+        }
+    }
+
+    pub(super) fn export_drawio_dialog(&mut self) {
+        let default_name = format!("{}.drawio", self.diagram_name);
+        if let Some(path) = rfd::FileDialog::new()
+            .set_file_name(&default_name)
+            .add_filter("draw.io", &["drawio"])
+            .save_file()
+        {
+            let xml = drawio::document_to_drawio(&self.doc);
+            match std::fs::write(&path, xml) {
+                Ok(()) => self.status = Some(format!("Saved {}", path.display())),
+                Err(e) => self.status = Some(format!("draw.io save failed: {e}")),
+            }
+        }
+    }
+
+    fn set_document(&mut self, doc: model::Document) {
+        self.doc = doc;
+        self.selected.clear();
+        self.editing_text_id = None;
+        self.next_id = self.doc.elements.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+        self.next_group_id = self.doc.groups.iter().map(|g| g.id).max().unwrap_or(0) + 1;
+    }
+
+    fn set_file_path(&mut self, path: &std::path::Path) {
+        self.file_path = path.display().to_string();
+        if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+            self.diagram_name = stem.to_string();
+        }
+    }
+
+    /// Points Quick Save at `path`, or at a `.json` sibling when saving there
+    /// would overwrite a source in another format; returns the status line.
+    fn set_opened_path(&mut self, path: &std::path::Path) -> String {
+        let target = save_path_for(path);
+        self.set_file_path(&target);
+        if target == path {
+            format!("Loaded {}", path.display())
+        } else {
+            format!("Loaded {} (saves to {})", path.display(), target.display())
+        }
+    }
+
     pub(super) fn open_json_dialog(&mut self) {
         if let Some(path) = rfd::FileDialog::new()
-            .add_filter("JSON", &["json"])
+            .add_filter("Diagram", &["json", "svg"])
             .pick_file()
         {
-            let path_str = path.display().to_string();
-            match std::fs::read_to_string(&path) {
-                Ok(json) => match serde_json::from_str::<model::Document>(&json) {
-                    Ok(doc) => {
-                        self.push_undo();
-                        self.doc = doc;
-                        self.file_path = path_str.clone();
-                        if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
-                            self.diagram_name = stem.to_string();
-                        }
-                        self.selected.clear();
-                        self.next_id = self.doc.elements.iter().map(|e| e.id).max().unwrap_or(0) + 1;
-                        self.status = Some(format!("Loaded {}", path_str));
+            match load_document(&path) {
+                Ok(doc) => {
+                    self.push_undo();
+                    self.set_document(doc);
+                    self.status = Some(self.set_opened_path(&path));
+                }
+                Err(e) => self.status = Some(e),
+            }
+        }
+    }
+
+    pub(super) fn import_document(&mut self, doc: model::Document, source: &str) {
+        if doc.elements.is_empty() {
+            self.status = Some(format!("Nothing to import from {}", source));
+            return;
+        }
+        let payload = ClipboardPayload {
+            elements: doc.elements,
+            groups: doc.groups,
+        };
+        self.paste_from_payload(&payload);
+        self.status = Some(format!(
+            "Imported {} element(s) from {}",
+            payload.elements.len(),
+            source
+        ));
+    }
+
+    /// Picks a file of the given kind and imports it onto the canvas; the
+    /// parser is chosen from the file extension.
+    pub(super) fn import_dialog(&mut self, filter_name: &str, extensions: &[&str]) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter(filter_name, extensions)
+            .pick_file()
+        {
+            match load_document(&path) {
+                Ok(doc) => self.import_document(doc, &path.display().to_string()),
+                Err(e) => self.status = Some(e),
+            }
+        }
+    }
+
+    pub(super) fn open_startup_file(&mut self, path: &std::path::Path) {
+        let path_str = path.display().to_string();
+        if !path.exists() {
+            let target = save_path_for(path);
+            if target != path {
+                self.set_file_path(&target);
+                self.status = Some(format!(
+                    "{} does not exist; Quick Save writes {}",
+                    path_str,
+                    target.display()
+                ));
+                return;
+            }
+            match self.document_file_contents(path) {
+                Ok(contents) => match std::fs::write(path, contents) {
+                    Ok(()) => {
+                        self.set_file_path(path);
+                        self.status = Some(format!("Created {}", path_str));
                     }
-                    Err(e) => self.status = Some(format!("Parse failed: {e}")),
+                    Err(e) => self.status = Some(format!("Create failed: {e}")),
                 },
-                Err(e) => self.status = Some(format!("Read failed: {e}")),
+                Err(e) => self.status = Some(format!("Serialize failed: {e}")),
+            }
+            return;
+        }
+        match load_document(path) {
+            Ok(doc) => {
+                self.set_document(doc);
+                self.status = Some(self.set_opened_path(path));
             }
+            Err(e) => self.status = Some(e),
         }
     }
 
@@ -885,6 +1264,8 @@ impl DiagramApp {
         settings::AppSettings {
             file_path: self.file_path.clone(),
             svg_path: self.svg_path.clone(),
+            png_scale: self.png_scale,
+            png_transparent: self.png_transparent,
             snap_to_grid: self.snap_to_grid,
             grid_size: self.grid_size,
             move_step: self.move_step,
@@ -938,6 +1319,8 @@ impl DiagramApp {
 
         self.file_path = settings.file_path;
         self.svg_path = settings.svg_path;
+        self.png_scale = settings.png_scale;
+        self.png_transparent = settings.png_transparent;
         self.snap_to_grid = settings.snap_to_grid;
         self.grid_size = settings.grid_size;
         self.move_step = settings.move_step;
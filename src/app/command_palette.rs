@@ -1,7 +1,7 @@
-use crate::model;
 use eframe::egui;
 use fuzzy_matcher::FuzzyMatcher;
 use fuzzy_matcher::skim::SkimMatcherV2;
+use sansuyu::model;
 
 use super::doc_ops::{AbutMode, AlignMode, DistributeMode, abut_selected, align_selected, distribute_selected};
 use super::DiagramApp;
@@ -14,6 +14,10 @@ pub(super) enum CommandId {
     ToolTriangle,
     ToolParallelogram,
     ToolTrapezoid,
+    ToolDiamond,
+    ToolHexagon,
+    ToolCylinder,
+    ToolPolygon,
     ToolLine,
     ToolArrow,
     ToolBidirectionalArrow,
@@ -45,8 +49,23 @@ pub(super) enum CommandId {
     ConnectBidirectional,
     SaveJson,
     LoadJson,
+    ImportText,
+    ImportDot,
+    ImportMermaid,
+    ImportSvg,
+    ImportExcalidraw,
+    ImportDrawio,
+    WatchFile,
+    StopWatching,
     ExportSvg,
+    ExportPng,
+    ExportPdf,
+    ExportTikz,
+    ExportDot,
+    ExportExcalidraw,
+    ExportDrawio,
     ToggleSnap,
+    TogglePortNames,
     SnapSelectionToGrid,
     SetTextSize,
     SetStrokeWidth,
@@ -65,6 +84,11 @@ pub(super) enum CommandId {
     LineStyleSolid,
     LineStyleDashed,
     LineStyleDotted,
+    RouteStraight,
+    RouteOrthogonal,
+    RouteCurved,
+    ToggleLineJumps,
+    ToggleJunctionDots,
     ZoomIn,
     ZoomOut,
     ZoomReset,
@@ -88,6 +112,10 @@ const COMMANDS: &[CommandSpec] = &[
     CommandSpec { id: CommandId::ToolTriangle, name: "Tool: Triangle", search: "triangle tool shift t" },
     CommandSpec { id: CommandId::ToolParallelogram, name: "Tool: Parallelogram", search: "parallelogram tool shift p" },
     CommandSpec { id: CommandId::ToolTrapezoid, name: "Tool: Trapezoid", search: "trapezoid tool shift z" },
+    CommandSpec { id: CommandId::ToolDiamond, name: "Tool: Diamond", search: "diamond rhombus decision tool shift d" },
+    CommandSpec { id: CommandId::ToolHexagon, name: "Tool: Hexagon", search: "hexagon tool shift h" },
+    CommandSpec { id: CommandId::ToolCylinder, name: "Tool: Cylinder", search: "cylinder database tool shift c" },
+    CommandSpec { id: CommandId::ToolPolygon, name: "Tool: Polygon", search: "regular polygon pentagon octagon tool shift n" },
     CommandSpec { id: CommandId::ToolLine, name: "Tool: Line", search: "line tool l" },
     CommandSpec { id: CommandId::ToolArrow, name: "Tool: Arrow", search: "arrow tool a" },
     CommandSpec { id: CommandId::ToolBidirectionalArrow, name: "Tool: Bidirectional Arrow", search: "bidirectional arrow both tool shift a" },
@@ -121,8 +149,23 @@ const COMMANDS: &[CommandSpec] = &[
     CommandSpec { id: CommandId::ConnectBidirectional, name: "Connect: Bidirectional", search: "connect bidirectional both auto connection" },
     CommandSpec { id: CommandId::SaveJson, name: "File: Save", search: "save file json" },
     CommandSpec { id: CommandId::LoadJson, name: "File: Load", search: "load open file json" },
+    CommandSpec { id: CommandId::ImportText, name: "File: Import Diagram Text", search: "import diagram text dsl sy file" },
+    CommandSpec { id: CommandId::ImportDot, name: "File: Import Graphviz DOT", search: "import graphviz dot gv graph layout" },
+    CommandSpec { id: CommandId::ImportMermaid, name: "File: Import Mermaid Flowchart", search: "import mermaid mmd flowchart graph" },
+    CommandSpec { id: CommandId::ImportSvg, name: "File: Import SVG", search: "import svg vector drawing shapes" },
+    CommandSpec { id: CommandId::ImportExcalidraw, name: "File: Import Excalidraw", search: "import excalidraw sketch whiteboard" },
+    CommandSpec { id: CommandId::ImportDrawio, name: "File: Import draw.io", search: "import drawio draw.io diagrams.net mxgraph" },
+    CommandSpec { id: CommandId::WatchFile, name: "File: Watch File", search: "watch live reload file preview" },
+    CommandSpec { id: CommandId::StopWatching, name: "File: Stop Watching", search: "stop watching live reload" },
     CommandSpec { id: CommandId::ExportSvg, name: "File: Export SVG", search: "export svg save" },
+    CommandSpec { id: CommandId::ExportPng, name: "File: Export PNG", search: "export png image bitmap raster save" },
+    CommandSpec { id: CommandId::ExportPdf, name: "File: Export PDF", search: "export pdf latex paper vector save" },
+    CommandSpec { id: CommandId::ExportTikz, name: "File: Export TikZ", search: "export tikz latex tex pgf paper save" },
+    CommandSpec { id: CommandId::ExportDot, name: "File: Export Graphviz DOT", search: "export graphviz dot gv graph connections" },
+    CommandSpec { id: CommandId::ExportExcalidraw, name: "File: Export Excalidraw", search: "export excalidraw sketch whiteboard save" },
+    CommandSpec { id: CommandId::ExportDrawio, name: "File: Export draw.io", search: "export drawio draw.io diagrams.net mxgraph save" },
     CommandSpec { id: CommandId::ToggleSnap, name: "Grid: Toggle snap", search: "grid snap toggle" },
+    CommandSpec { id: CommandId::TogglePortNames, name: "View: Toggle Port Names", search: "port pin names labels show hide toggle" },
     CommandSpec { id: CommandId::SnapSelectionToGrid, name: "Grid: Snap selection", search: "grid snap selection" },
     CommandSpec { id: CommandId::SetTextSize, name: "Format: Set Text Size...", search: "text size font pt" },
     CommandSpec { id: CommandId::SetStrokeWidth, name: "Format: Set Stroke Width...", search: "stroke width px line thickness" },
@@ -141,6 +184,11 @@ const COMMANDS: &[CommandSpec] = &[
     CommandSpec { id: CommandId::LineStyleSolid, name: "Format: Line Solid", search: "line style solid" },
     CommandSpec { id: CommandId::LineStyleDashed, name: "Format: Line Dashed", search: "line style dashed" },
     CommandSpec { id: CommandId::LineStyleDotted, name: "Format: Line Dotted", search: "line style dotted" },
+    CommandSpec { id: CommandId::RouteStraight, name: "Format: Straight Connector", search: "route straight line connector direct" },
+    CommandSpec { id: CommandId::RouteOrthogonal, name: "Format: Orthogonal Connector", search: "route orthogonal elbow manhattan right angle connector" },
+    CommandSpec { id: CommandId::RouteCurved, name: "Format: Curved Connector", search: "route curved bezier curve spline connector" },
+    CommandSpec { id: CommandId::ToggleLineJumps, name: "Format: Toggle Line Jumps", search: "line jumps hops crossing bridge arc wires connectors" },
+    CommandSpec { id: CommandId::ToggleJunctionDots, name: "Format: Toggle Junction Dots", search: "junction dots wires meet connectors join" },
     CommandSpec { id: CommandId::ZoomIn, name: "View: Zoom In", search: "zoom in larger" },
     CommandSpec { id: CommandId::ZoomOut, name: "View: Zoom Out", search: "zoom out smaller" },
     CommandSpec { id: CommandId::ZoomReset, name: "View: Reset Zoom", search: "zoom reset 100" },
@@ -181,6 +229,7 @@ pub(super) struct CommandContext {
     pub can_ungroup: bool,
     pub snap_to_grid: bool,
     pub has_resizable: bool,
+    pub watching: bool,
 }
 
 impl CommandPalette {
@@ -222,6 +271,7 @@ impl CommandPalette {
             CommandId::ConnectLine | CommandId::ConnectArrow | CommandId::ConnectBidirectional => cx.selected_len == 2,
             CommandId::SnapSelectionToGrid => cx.selected_len > 0 && cx.snap_to_grid,
             CommandId::SetWidth | CommandId::SetHeight => cx.has_resizable,
+            CommandId::StopWatching => cx.watching,
             _ => true,
         }
     }
@@ -255,7 +305,11 @@ impl CommandPalette {
                 | model::ElementKind::Ellipse { rect, .. }
                 | model::ElementKind::Triangle { rect, .. }
                 | model::ElementKind::Parallelogram { rect, .. }
-                | model::ElementKind::Trapezoid { rect, .. } => {
+                | model::ElementKind::Trapezoid { rect, .. }
+                | model::ElementKind::Diamond { rect, .. }
+                | model::ElementKind::Hexagon { rect, .. }
+                | model::ElementKind::Cylinder { rect, .. }
+                | model::ElementKind::Polygon { rect, .. } => {
                     if let Some(w) = set_width {
                         rect.max.x = rect.min.x + w;
                     }
@@ -269,19 +323,10 @@ impl CommandPalette {
     }
 
     fn parse_color_basic(s: &str) -> Option<model::Rgba> {
-        let s = s.trim().trim_start_matches('#');
-        if s.len() == 6 {
-            let r = u8::from_str_radix(&s[0..2], 16).ok()?;
-            let g = u8::from_str_radix(&s[2..4], 16).ok()?;
-            let b = u8::from_str_radix(&s[4..6], 16).ok()?;
-            return Some(model::Rgba { r, g, b, a: 255 });
-        }
-        if s.len() == 3 {
-            let r = u8::from_str_radix(&s[0..1], 16).ok()? * 17;
-            let g = u8::from_str_radix(&s[1..2], 16).ok()? * 17;
-            let b = u8::from_str_radix(&s[2..3], 16).ok()? * 17;
-            return Some(model::Rgba { r, g, b, a: 255 });
+        if let Some(color) = model::Rgba::from_hex(s) {
+            return Some(color);
         }
+        let s = s.trim();
         let parts: Vec<&str> = s.split(&[',', ' ']).filter(|p| !p.is_empty()).collect();
         if parts.len() == 3 {
             let r: u8 = parts[0].trim().parse().ok()?;
@@ -385,6 +430,10 @@ impl CommandPalette {
             CommandId::ToolTriangle => app.tool = super::Tool::Triangle,
             CommandId::ToolParallelogram => app.tool = super::Tool::Parallelogram,
             CommandId::ToolTrapezoid => app.tool = super::Tool::Trapezoid,
+            CommandId::ToolDiamond => app.tool = super::Tool::Diamond,
+            CommandId::ToolHexagon => app.tool = super::Tool::Hexagon,
+            CommandId::ToolCylinder => app.tool = super::Tool::Cylinder,
+            CommandId::ToolPolygon => app.tool = super::Tool::Polygon,
             CommandId::ToolLine => app.tool = super::Tool::Line,
             CommandId::ToolArrow => app.tool = super::Tool::Arrow,
             CommandId::ToolBidirectionalArrow => app.tool = super::Tool::BidirectionalArrow,
@@ -452,11 +501,26 @@ impl CommandPalette {
             CommandId::ConnectBidirectional => app.auto_connect_selected(model::ArrowStyle::Both),
             CommandId::SaveJson => app.save_json_dialog(),
             CommandId::LoadJson => app.open_json_dialog(),
+            CommandId::ImportText => app.import_dialog("Diagram text", &["sy", "txt"]),
+            CommandId::ImportDot => app.import_dialog("Graphviz DOT", &["dot", "gv"]),
+            CommandId::ImportMermaid => app.import_dialog("Mermaid", &["mmd", "mermaid"]),
+            CommandId::ImportSvg => app.import_dialog("SVG", &["svg"]),
+            CommandId::ImportExcalidraw => app.import_dialog("Excalidraw", &["excalidraw"]),
+            CommandId::ImportDrawio => app.import_dialog("draw.io", &["drawio", "dio"]),
+            CommandId::WatchFile => app.watch_file_dialog(),
+            CommandId::StopWatching => app.stop_watching(),
             CommandId::ExportSvg => app.save_svg_dialog(),
+            CommandId::ExportPng => app.export_png_dialog(ctx),
+            CommandId::ExportPdf => app.export_pdf_dialog(),
+            CommandId::ExportTikz => app.export_tikz_dialog(),
+            CommandId::ExportDot => app.export_dot_dialog(),
+            CommandId::ExportExcalidraw => app.export_excalidraw_dialog(),
+            CommandId::ExportDrawio => app.export_drawio_dialog(),
             CommandId::ToggleSnap => {
                 app.snap_to_grid = !app.snap_to_grid;
                 app.persist_settings();
             }
+            CommandId::TogglePortNames => app.show_port_names = !app.show_port_names,
             CommandId::SnapSelectionToGrid => {
                 app.push_undo();
                 app.snap_selected_to_grid();
@@ -491,6 +555,23 @@ impl CommandPalette {
             CommandId::LineStyleDotted => {
                 Self::apply_style_change(app, |s| s.stroke.line_style = model::LineStyle::Dotted);
             }
+            CommandId::RouteStraight => app.set_line_route(model::LineRoute::Straight),
+            CommandId::RouteOrthogonal => app.set_line_route(model::LineRoute::Orthogonal {
+                bends: Vec::new(),
+                offsets: Vec::new(),
+            }),
+            CommandId::RouteCurved => app.set_line_route(model::LineRoute::Curved {
+                c1: model::Point::default(),
+                c2: model::Point::default(),
+            }),
+            CommandId::ToggleLineJumps => {
+                app.push_undo();
+                app.doc.line_jumps = !app.doc.line_jumps;
+            }
+            CommandId::ToggleJunctionDots => {
+                app.push_undo();
+                app.doc.junction_dots = !app.doc.junction_dots;
+            }
             CommandId::ZoomIn => {
                 app.view.zoom = (app.view.zoom * 1.25).min(8.0);
             }
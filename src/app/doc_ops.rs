@@ -1,9 +1,8 @@
-use crate::model;
 use eframe::egui;
+use sansuyu::geometry::translate_element;
+use sansuyu::model;
 use std::collections::HashSet;
 
-use super::geometry::translate_element;
-
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub(super) enum AlignMode {
     Left,
@@ -157,6 +156,10 @@ pub(super) fn element_label(element: &model::Element) -> String {
             format!("Parallelogram {}{}", element.id, group)
         }
         model::ElementKind::Trapezoid { .. } => format!("Trapezoid {}{}", element.id, group),
+        model::ElementKind::Diamond { .. } => format!("Diamond {}{}", element.id, group),
+        model::ElementKind::Hexagon { .. } => format!("Hexagon {}{}", element.id, group),
+        model::ElementKind::Cylinder { .. } => format!("Cylinder {}{}", element.id, group),
+        model::ElementKind::Polygon { .. } => format!("Polygon {}{}", element.id, group),
         model::ElementKind::Line {
             arrow, arrow_style, ..
         } => {
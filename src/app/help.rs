@@ -40,6 +40,10 @@ pub(super) fn draw_help_window(ctx: &egui::Context, open: &mut bool) {
                 help_row(ui, "⇧T", "Triangle tool");
                 help_row(ui, "⇧P", "Parallelogram tool");
                 help_row(ui, "⇧Z", "Trapezoid tool");
+                help_row(ui, "⇧D", "Diamond tool");
+                help_row(ui, "⇧H", "Hexagon tool");
+                help_row(ui, "⇧C", "Cylinder tool");
+                help_row(ui, "⇧N", "Polygon tool");
                 help_row(ui, "L", "Line tool");
                 help_row(ui, "A", "Arrow tool");
                 help_row(ui, "⇧A", "Bidirectional arrow tool");
@@ -113,6 +117,12 @@ yellow = "#e0af68""##);
                 ui.label("• Use the right panel to edit properties of selected objects");
                 ui.label("• Group objects with the Group command to move them together");
                 ui.label("• Connect shapes with auto-connect to create dynamic connections");
+                ui.label("• 'Format: Orthogonal Connector' routes lines around shapes; drag a segment's handle to move it");
+                ui.label("• 'Format: Curved Connector' bends lines; drag the round handles to shape the curve");
+                ui.label("• Double-click a line to label it; drag the diamond handle to move the label");
+                ui.label("• Pick each end's arrowhead shape and the arrow size for lines in the sidebar");
+                ui.label("• Polyline ends stick to shapes too; drop a connector end on another connector for a T-junction");
+                ui.label("• 'Format: Toggle Line Jumps' hops crossing connectors; 'Toggle Junction Dots' marks where they join");
                 ui.label("• Enable 'Apply to selection' to change styles of multiple objects at once");
             });
         });
@@ -1,7 +1,10 @@
-use crate::model;
 use eframe::egui;
+use sansuyu::geometry::{
+    clamp_corner_radius, line_label_anchor, line_points_world, path_fraction_nearest,
+    path_point_at, polyline_points_world, rotate_vec2,
+};
+use sansuyu::model;
 
-use super::geometry::{resolved_line_endpoints_world, rotate_vec2};
 use super::{ActiveTransform, DiagramApp, LineEndpoint, ResizeHandle, ShapeAdjustKind, View};
 
 impl DiagramApp {
@@ -32,6 +35,8 @@ impl DiagramApp {
         let min_size_world = 8.0;
         let handle_size_screen = 10.0;
         let rotate_offset_screen = 24.0;
+        // Keeps the corner radius handle clear of the NW resize handle.
+        let corner_handle_inset_world = handle_size_screen * 1.5 / view.zoom;
 
         let mut stop_transform = false;
         if let Some(transform) = &mut self.active_transform {
@@ -140,7 +145,11 @@ impl DiagramApp {
                                 | model::ElementKind::Ellipse { rect, .. }
                                 | model::ElementKind::Triangle { rect, .. }
                                 | model::ElementKind::Parallelogram { rect, .. }
-                                | model::ElementKind::Trapezoid { rect, .. } => {
+                                | model::ElementKind::Trapezoid { rect, .. }
+                                | model::ElementKind::Diamond { rect, .. }
+                                | model::ElementKind::Hexagon { rect, .. }
+                                | model::ElementKind::Cylinder { rect, .. }
+                                | model::ElementKind::Polygon { rect, .. } => {
                                     *rect = rectf;
                                 }
                                 _ => stop_transform = true,
@@ -183,43 +192,53 @@ impl DiagramApp {
                             LineEndpoint::Start => a += delta,
                             LineEndpoint::End => b += delta,
                         }
-                        if shift {
-                            let (moved, fixed) = match endpoint {
-                                LineEndpoint::Start => (a, b),
-                                LineEndpoint::End => (b, a),
-                            };
-                            let d = moved - fixed;
-                            if d.x.abs() >= d.y.abs() {
-                                if matches!(endpoint, LineEndpoint::Start) {
-                                    a.y = b.y;
-                                } else {
-                                    b.y = a.y;
-                                }
-                            } else {
-                                if matches!(endpoint, LineEndpoint::Start) {
-                                    a.x = b.x;
-                                } else {
-                                    b.x = a.x;
-                                }
-                            }
-                        }
                         if let Some(element) = self.doc.elements.get_mut(idx) {
-                            if let model::ElementKind::Line {
-                                a: pa,
-                                b: pb,
-                                start_binding,
-                                end_binding,
-                                ..
-                            } = &mut element.kind
-                            {
-                                *pa = model::Point::from_pos2(a);
-                                *pb = model::Point::from_pos2(b);
-                                match endpoint {
-                                    LineEndpoint::Start => *start_binding = None,
-                                    LineEndpoint::End => *end_binding = None,
+                            match &mut element.kind {
+                                model::ElementKind::Line {
+                                    a: pa,
+                                    b: pb,
+                                    start_binding,
+                                    end_binding,
+                                    ..
+                                } => {
+                                    // Shift keeps the line level or plumb.
+                                    match endpoint {
+                                        LineEndpoint::Start => {
+                                            if shift {
+                                                a = axis_aligned(a, b);
+                                            }
+                                            *start_binding = None;
+                                        }
+                                        LineEndpoint::End => {
+                                            if shift {
+                                                b = axis_aligned(b, a);
+                                            }
+                                            *end_binding = None;
+                                        }
+                                    }
+                                    *pa = model::Point::from_pos2(a);
+                                    *pb = model::Point::from_pos2(b);
+                                }
+                                model::ElementKind::Polyline {
+                                    points,
+                                    start_binding,
+                                    end_binding,
+                                    ..
+                                } if points.len() >= 2 => {
+                                    // The end moves on its own; shift squares
+                                    // it up with the point next to it.
+                                    let n = points.len();
+                                    let (i, next, mut moved, binding) = match endpoint {
+                                        LineEndpoint::Start => (0, 1, a, start_binding),
+                                        LineEndpoint::End => (n - 1, n - 2, b, end_binding),
+                                    };
+                                    if shift {
+                                        moved = axis_aligned(moved, points[next].to_pos2());
+                                    }
+                                    points[i] = model::Point::from_pos2(moved);
+                                    *binding = None;
                                 }
-                            } else {
-                                stop_transform = true;
+                                _ => stop_transform = true,
                             }
                         }
                     }
@@ -230,6 +249,13 @@ impl DiagramApp {
                     } else if let Some(p) = pointer_world {
                         if let Some(element) = self.doc.elements.get_mut(idx) {
                             match (&mut element.kind, *kind) {
+                                (model::ElementKind::Rect { rect, corner_radius, .. }, ShapeAdjustKind::RectCornerRadius) => {
+                                    let r = rect.to_rect();
+                                    let center = r.center();
+                                    let local = rotate_vec2(p - center, -element.rotation);
+                                    let max = r.size().min_elem() * 0.5;
+                                    *corner_radius = (local.x + r.width() * 0.5 - corner_handle_inset_world).clamp(0.0, max);
+                                }
                                 (model::ElementKind::Triangle { rect, apex_ratio, .. }, ShapeAdjustKind::TriangleApex) => {
                                     let r = rect.to_rect();
                                     let center = r.center();
@@ -258,11 +284,138 @@ impl DiagramApp {
                                         *top_inset_ratio = (inset / hw).clamp(0.0, 0.95);
                                     }
                                 }
+                                (model::ElementKind::Hexagon { rect, inset_ratio, .. }, ShapeAdjustKind::HexagonInset) => {
+                                    let r = rect.to_rect();
+                                    let center = r.center();
+                                    let local = rotate_vec2(p - center, -element.rotation);
+                                    let hw = r.width() * 0.5;
+                                    if hw > f32::EPSILON {
+                                        *inset_ratio = ((local.x + hw) / hw).clamp(0.0, 0.95);
+                                    }
+                                }
+                                (model::ElementKind::Cylinder { rect, cap_ratio, .. }, ShapeAdjustKind::CylinderCap) => {
+                                    let r = rect.to_rect();
+                                    let center = r.center();
+                                    let local = rotate_vec2(p - center, -element.rotation);
+                                    let h = r.height();
+                                    if h > f32::EPSILON {
+                                        *cap_ratio = ((local.y + h * 0.5) / h).clamp(0.0, 0.5);
+                                    }
+                                }
+                                (model::ElementKind::Polygon { rect, sides, .. }, ShapeAdjustKind::PolygonSides) => {
+                                    let r = rect.to_rect();
+                                    let center = r.center();
+                                    let local = rotate_vec2(p - center, -element.rotation);
+                                    let w = r.width();
+                                    if w > f32::EPSILON {
+                                        let t = ((local.x + w * 0.5) / w).clamp(0.0, 1.0);
+                                        *sides = 3 + (t * 21.0).round() as u32;
+                                    }
+                                }
                                 _ => stop_transform = true,
                             }
                         }
                     }
                 }
+                ActiveTransform::RouteSegment {
+                    element_id,
+                    segment,
+                    horizontal,
+                    start_offset,
+                    start_pointer_world,
+                } => {
+                    if *element_id != selected_id {
+                        stop_transform = true;
+                    } else if let Some(p) = pointer_world {
+                        let delta = p - *start_pointer_world;
+                        let moved = if *horizontal { delta.y } else { delta.x };
+                        if let model::ElementKind::Line {
+                            route: model::LineRoute::Orthogonal { offsets, .. },
+                            ..
+                        } = &mut self.doc.elements[idx].kind
+                            && let Some(offset) = offsets.get_mut(*segment)
+                        {
+                            *offset = *start_offset + moved;
+                        } else {
+                            stop_transform = true;
+                        }
+                    }
+                }
+                ActiveTransform::CurveControl {
+                    element_id,
+                    endpoint,
+                    start_offset,
+                    start_pointer_world,
+                } => {
+                    if *element_id != selected_id {
+                        stop_transform = true;
+                    } else if let Some(p) = pointer_world {
+                        let offset = *start_offset + (p - *start_pointer_world);
+                        if let model::ElementKind::Line {
+                            route: model::LineRoute::Curved { c1, c2 },
+                            ..
+                        } = &mut self.doc.elements[idx].kind
+                        {
+                            let control = match endpoint {
+                                LineEndpoint::Start => c1,
+                                LineEndpoint::End => c2,
+                            };
+                            *control = model::Point {
+                                x: offset.x,
+                                y: offset.y,
+                            };
+                        } else {
+                            stop_transform = true;
+                        }
+                    }
+                }
+                ActiveTransform::LineLabel {
+                    element_id,
+                    start_anchor,
+                    start_pointer_world,
+                } => {
+                    if *element_id != selected_id {
+                        stop_transform = true;
+                    } else if let Some(p) = pointer_world {
+                        // Slide along the line to the nearest point and keep
+                        // the rest as the offset.
+                        let anchor = *start_anchor + (p - *start_pointer_world);
+                        let points = match &self.doc.elements[idx].kind {
+                            model::ElementKind::Line {
+                                a,
+                                b,
+                                route,
+                                start_binding,
+                                end_binding,
+                                ..
+                            } => line_points_world(
+                                &self.doc,
+                                *a,
+                                *b,
+                                route,
+                                start_binding,
+                                end_binding,
+                            ),
+                            _ => Vec::new(),
+                        };
+                        if let model::ElementKind::Line {
+                            label_position,
+                            label_offset,
+                            ..
+                        } = &mut self.doc.elements[idx].kind
+                            && !points.is_empty()
+                        {
+                            *label_position = path_fraction_nearest(&points, anchor);
+                            let offset = anchor - path_point_at(&points, *label_position);
+                            *label_offset = model::Point {
+                                x: offset.x,
+                                y: offset.y,
+                            };
+                        } else {
+                            stop_transform = true;
+                        }
+                    }
+                }
             }
         }
         if stop_transform {
@@ -396,14 +549,23 @@ impl DiagramApp {
                         self.active_transform = None;
                     }
                     if resp.hovered() || resp.dragged() {
-                        ctx.set_cursor_icon(egui::CursorIcon::ResizeHorizontal);
+                        ctx.set_cursor_icon(if matches!(kind, ShapeAdjustKind::CylinderCap) {
+                            egui::CursorIcon::ResizeVertical
+                        } else {
+                            egui::CursorIcon::ResizeHorizontal
+                        });
                     }
                 }
         };
 
+        // Ends of the selected line or polyline, which get drag handles.
+        let mut ends = None;
         match kind {
-            model::ElementKind::Rect { rect, .. } => {
-                draw_rect_handles(rect, rotation, None);
+            model::ElementKind::Rect { rect, corner_radius, .. } => {
+                let r = rect.to_rect();
+                let radius = clamp_corner_radius(r, corner_radius);
+                let local = egui::vec2(-r.width() * 0.5 + corner_handle_inset_world + radius, -r.height() * 0.5);
+                draw_rect_handles(rect, rotation, Some((ShapeAdjustKind::RectCornerRadius, local)));
             }
             model::ElementKind::Ellipse { rect, .. } => {
                 draw_rect_handles(rect, rotation, None);
@@ -424,55 +586,231 @@ impl DiagramApp {
                 let local = egui::vec2(-hw + inset, -hh);
                 draw_rect_handles(rect, rotation, Some((ShapeAdjustKind::TrapezoidTopInset, local)));
             }
+            model::ElementKind::Diamond { rect, .. } => {
+                draw_rect_handles(rect, rotation, None);
+            }
+            model::ElementKind::Hexagon { rect, inset_ratio, .. } => {
+                let r = rect.to_rect();
+                let hw = r.width() * 0.5;
+                let local = egui::vec2(-hw + inset_ratio.clamp(0.0, 0.95) * hw, -r.height() * 0.5);
+                draw_rect_handles(rect, rotation, Some((ShapeAdjustKind::HexagonInset, local)));
+            }
+            model::ElementKind::Cylinder { rect, cap_ratio, .. } => {
+                let r = rect.to_rect();
+                let local = egui::vec2(0.0, (cap_ratio.clamp(0.0, 0.5) - 0.5) * r.height());
+                draw_rect_handles(rect, rotation, Some((ShapeAdjustKind::CylinderCap, local)));
+            }
+            model::ElementKind::Polygon { rect, sides, .. } => {
+                let r = rect.to_rect();
+                let t = (sides.clamp(3, 24) - 3) as f32 / 21.0;
+                let local = egui::vec2((t - 0.5) * r.width(), -r.height() * 0.5);
+                draw_rect_handles(rect, rotation, Some((ShapeAdjustKind::PolygonSides, local)));
+            }
             model::ElementKind::Line {
                 a,
                 b,
                 start_binding,
                 end_binding,
+                route,
+                label,
                 ..
             } => {
-                let (a, b) =
-                    resolved_line_endpoints_world(&self.doc, a, b, &start_binding, &end_binding);
-                let pts = [(LineEndpoint::Start, a), (LineEndpoint::End, b)];
-                for (endpoint, p) in pts {
-                    let screen = view.world_to_screen(origin, p);
+                let points =
+                    line_points_world(&self.doc, a, b, &route, &start_binding, &end_binding);
+                let (a, b) = (points[0], points[points.len() - 1]);
+                // Inner segments of an orthogonal route slide sideways; the
+                // first and last stay attached to the ends.
+                let inner_segments = match route {
+                    model::LineRoute::Orthogonal { .. } => points.len().saturating_sub(2),
+                    _ => 0,
+                };
+                for segment in 1..inner_segments {
+                    let (p, q) = (points[segment], points[segment + 1]);
+                    let horizontal = (p.y - q.y).abs() <= (p.x - q.x).abs();
+                    let screen = view.world_to_screen(origin, p.lerp(q, 0.5));
                     let r = egui::Rect::from_center_size(
                         screen,
                         egui::vec2(handle_size_screen, handle_size_screen),
                     );
-                    let id = ui.id().with(("endpoint", selected_id, endpoint as u8));
+                    let id = ui.id().with(("route_segment", selected_id, segment));
                     let resp = ui.interact(r, id, egui::Sense::drag());
                     painter.rect_filled(r, 1.0, handle_fill);
                     painter.rect_stroke(r, 1.0, handle_stroke, egui::StrokeKind::Middle);
-                    if resp.drag_started() {
-                        if let Some(pw) = pointer_world {
+                    if resp.drag_started()
+                        && let Some(pw) = pointer_world
+                    {
+                        self.push_undo();
+                        if let model::ElementKind::Line {
+                            route: model::LineRoute::Orthogonal { offsets, .. },
+                            ..
+                        } = &mut self.doc.elements[idx].kind
+                        {
+                            offsets.resize(points.len() - 1, 0.0);
+                            self.active_transform = Some(ActiveTransform::RouteSegment {
+                                element_id: selected_id,
+                                segment,
+                                horizontal,
+                                start_offset: offsets[segment],
+                                start_pointer_world: pw,
+                            });
+                        }
+                    }
+                    if resp.drag_stopped() {
+                        self.active_transform = None;
+                    }
+                    if resp.hovered() || resp.dragged() {
+                        ctx.set_cursor_icon(if horizontal {
+                            egui::CursorIcon::ResizeVertical
+                        } else {
+                            egui::CursorIcon::ResizeHorizontal
+                        });
+                    }
+                }
+                // Control points of a curve hang off the end they belong to.
+                if let model::LineRoute::Curved { c1, c2 } = route {
+                    let controls = [(LineEndpoint::Start, a, c1), (LineEndpoint::End, b, c2)];
+                    for (endpoint, end, offset) in controls {
+                        let offset = offset.to_pos2().to_vec2();
+                        let end_screen = view.world_to_screen(origin, end);
+                        let screen = view.world_to_screen(origin, end + offset);
+                        painter.line_segment([end_screen, screen], handle_stroke);
+                        let r = egui::Rect::from_center_size(
+                            screen,
+                            egui::vec2(handle_size_screen, handle_size_screen),
+                        );
+                        let id = ui.id().with(("curve_control", selected_id, endpoint as u8));
+                        let resp = ui.interact(r, id, egui::Sense::drag());
+                        painter.add(egui::Shape::circle_filled(
+                            screen,
+                            handle_size_screen * 0.5,
+                            handle_fill,
+                        ));
+                        painter.add(egui::Shape::circle_stroke(
+                            screen,
+                            handle_size_screen * 0.5,
+                            handle_stroke,
+                        ));
+                        if resp.drag_started()
+                            && let Some(pw) = pointer_world
+                        {
                             self.push_undo();
-                            self.active_transform = Some(ActiveTransform::LineEndpoint {
+                            self.active_transform = Some(ActiveTransform::CurveControl {
                                 element_id: selected_id,
                                 endpoint,
-                                start_a: a,
-                                start_b: b,
+                                start_offset: offset,
                                 start_pointer_world: pw,
                             });
                         }
+                        if resp.drag_stopped() {
+                            self.active_transform = None;
+                        }
+                        if resp.hovered() || resp.dragged() {
+                            ctx.set_cursor_icon(egui::CursorIcon::Grab);
+                        }
+                    }
+                }
+                if !label.is_empty()
+                    && let Some(anchor) = line_label_anchor(&self.doc, &self.doc.elements[idx])
+                {
+                    let screen = view.world_to_screen(origin, anchor);
+                    let r = egui::Rect::from_center_size(
+                        screen,
+                        egui::vec2(handle_size_screen, handle_size_screen),
+                    );
+                    let id = ui.id().with(("line_label", selected_id));
+                    let resp = ui.interact(r, id, egui::Sense::drag());
+                    painter.add(egui::Shape::convex_polygon(
+                        vec![
+                            screen - egui::vec2(0.0, handle_size_screen * 0.6),
+                            screen + egui::vec2(handle_size_screen * 0.6, 0.0),
+                            screen + egui::vec2(0.0, handle_size_screen * 0.6),
+                            screen - egui::vec2(handle_size_screen * 0.6, 0.0),
+                        ],
+                        handle_fill,
+                        handle_stroke,
+                    ));
+                    if resp.drag_started()
+                        && let Some(pw) = pointer_world
+                    {
+                        self.push_undo();
+                        self.active_transform = Some(ActiveTransform::LineLabel {
+                            element_id: selected_id,
+                            start_anchor: anchor,
+                            start_pointer_world: pw,
+                        });
                     }
                     if resp.drag_stopped() {
-                        if let Some(pw) = pointer_world {
-                            self.try_bind_line_endpoint(selected_id, endpoint, pw, threshold_world);
-                        }
                         self.active_transform = None;
                     }
                     if resp.hovered() || resp.dragged() {
-                        ctx.set_cursor_icon(egui::CursorIcon::Crosshair);
+                        ctx.set_cursor_icon(egui::CursorIcon::Move);
                     }
                 }
+                ends = Some((a, b));
                 if let Some(p) = pointer_world {
                     if self.topmost_hit(p, threshold_world).is_some() {
                         ctx.set_cursor_icon(egui::CursorIcon::PointingHand);
                     }
                 }
             }
+            model::ElementKind::Polyline {
+                points,
+                start_binding,
+                end_binding,
+                ..
+            } if points.len() >= 2 => {
+                let points =
+                    polyline_points_world(&self.doc, &points, &start_binding, &end_binding);
+                ends = Some((points[0], points[points.len() - 1]));
+            }
             _ => {}
         }
+
+        let Some((a, b)) = ends else {
+            return;
+        };
+        let pts = [(LineEndpoint::Start, a), (LineEndpoint::End, b)];
+        for (endpoint, p) in pts {
+            let screen = view.world_to_screen(origin, p);
+            let r = egui::Rect::from_center_size(
+                screen,
+                egui::vec2(handle_size_screen, handle_size_screen),
+            );
+            let id = ui.id().with(("endpoint", selected_id, endpoint as u8));
+            let resp = ui.interact(r, id, egui::Sense::drag());
+            painter.rect_filled(r, 1.0, handle_fill);
+            painter.rect_stroke(r, 1.0, handle_stroke, egui::StrokeKind::Middle);
+            if resp.drag_started() {
+                if let Some(pw) = pointer_world {
+                    self.push_undo();
+                    self.active_transform = Some(ActiveTransform::LineEndpoint {
+                        element_id: selected_id,
+                        endpoint,
+                        start_a: a,
+                        start_b: b,
+                        start_pointer_world: pw,
+                    });
+                }
+            }
+            if resp.drag_stopped() {
+                if let Some(pw) = pointer_world {
+                    self.try_bind_line_endpoint(selected_id, endpoint, pw, threshold_world);
+                }
+                self.active_transform = None;
+            }
+            if resp.hovered() || resp.dragged() {
+                ctx.set_cursor_icon(egui::CursorIcon::Crosshair);
+            }
+        }
+    }
+}
+
+/// `moved` pulled level with or plumb under `fixed`, whichever it is closer to.
+fn axis_aligned(moved: egui::Pos2, fixed: egui::Pos2) -> egui::Pos2 {
+    let d = moved - fixed;
+    if d.x.abs() >= d.y.abs() {
+        egui::pos2(moved.x, fixed.y)
+    } else {
+        egui::pos2(fixed.x, moved.y)
     }
 }
@@ -1,5 +1,5 @@
-use crate::model;
 use eframe::egui;
+use sansuyu::model;
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 
@@ -11,8 +11,8 @@ mod help;
 mod interaction;
 mod render;
 mod settings;
-mod svg;
 mod update;
+mod watch;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Tool {
@@ -22,6 +22,10 @@ enum Tool {
     Triangle,
     Parallelogram,
     Trapezoid,
+    Diamond,
+    Hexagon,
+    Cylinder,
+    Polygon,
     Line,
     Arrow,
     BidirectionalArrow,
@@ -76,9 +80,13 @@ enum LineEndpoint {
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum ShapeAdjustKind {
+    RectCornerRadius,
     TriangleApex,
     ParallelogramSkew,
     TrapezoidTopInset,
+    HexagonInset,
+    CylinderCap,
+    PolygonSides,
 }
 
 #[derive(Clone, Debug)]
@@ -106,6 +114,24 @@ enum ActiveTransform {
         element_id: u64,
         kind: ShapeAdjustKind,
     },
+    RouteSegment {
+        element_id: u64,
+        segment: usize,
+        horizontal: bool,
+        start_offset: f32,
+        start_pointer_world: egui::Pos2,
+    },
+    CurveControl {
+        element_id: u64,
+        endpoint: LineEndpoint,
+        start_offset: egui::Vec2,
+        start_pointer_world: egui::Pos2,
+    },
+    LineLabel {
+        element_id: u64,
+        start_anchor: egui::Pos2,
+        start_pointer_world: egui::Pos2,
+    },
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -170,6 +196,9 @@ pub struct DiagramApp {
     next_id: u64,
     next_group_id: u64,
     style: model::Style,
+    line_route: model::LineRoute,
+    /// Inputs the orthogonal routes were last computed from.
+    routes_key: u64,
     in_progress: Option<InProgress>,
     context_world_pos: Option<egui::Pos2>,
     context_hit: Option<u64>,
@@ -182,6 +211,8 @@ pub struct DiagramApp {
     diagram_name: String,
     file_path: String,
     svg_path: String,
+    png_scale: f32,
+    png_transparent: bool,
     settings_path: String,
     status: Option<String>,
     editing_text_id: Option<u64>,
@@ -198,6 +229,8 @@ pub struct DiagramApp {
     font_directory: Option<String>,
     loaded_fonts: Vec<String>,
     show_help: bool,
+    show_port_names: bool,
+    watch: Option<watch::FileWatch>,
 }
 
 impl DiagramApp {
@@ -214,7 +247,11 @@ impl DiagramApp {
         None
     }
 
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        initial_file: Option<std::path::PathBuf>,
+        watch: bool,
+    ) -> Self {
         let settings_path = Self::config_path().unwrap_or_else(|| "settings.toml".to_string());
         let settings = settings::load_settings(&settings_path)
             .or_else(|| settings::load_settings("settings.json"))
@@ -229,7 +266,7 @@ impl DiagramApp {
 
         let diagram_name = Self::generate_default_name();
 
-        Self {
+        let mut app = Self {
             doc: model::Document::default(),
             selected: HashSet::new(),
             tool: Tool::Select,
@@ -238,6 +275,8 @@ impl DiagramApp {
             next_id: 1,
             next_group_id: 1,
             style,
+            line_route: model::LineRoute::Straight,
+            routes_key: 0,
             in_progress: None,
             context_world_pos: None,
             context_hit: None,
@@ -250,6 +289,8 @@ impl DiagramApp {
             diagram_name,
             file_path: settings.file_path,
             svg_path: settings.svg_path,
+            png_scale: settings.png_scale,
+            png_transparent: settings.png_transparent,
             settings_path,
             status: None,
             editing_text_id: None,
@@ -266,7 +307,15 @@ impl DiagramApp {
             font_directory: settings.font_directory,
             loaded_fonts,
             show_help: false,
+            show_port_names: false,
+            watch: None,
+        };
+        match initial_file {
+            Some(path) if watch => app.start_watching(path),
+            Some(path) => app.open_startup_file(&path),
+            None => {}
         }
+        app
     }
 
     fn generate_default_name() -> String {
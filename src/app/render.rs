@@ -1,12 +1,19 @@
-use crate::{model, text_format};
 use eframe::egui;
+use sansuyu::geometry::{
+    ArrowheadPart, arrow_tails, arrowhead_parts_world, arrowhead_size, hopped_path, junction_dots,
+    line_curve_world, line_hop_radius, line_hops, line_label_rect, line_points_world,
+    polyline_points_world, port_world_position, rotate_vec2,
+};
+use sansuyu::{model, text_format};
 use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 
 use super::geometry::{
-    resolved_line_endpoints_world, rotated_ellipse_points_screen,
-    rotated_parallelogram_points_screen, rotated_rect_points_screen,
-    rotated_trapezoid_points_screen, rotated_triangle_points_screen,
+    rotated_cylinder_points_screen, rotated_cylinder_rim_points_screen,
+    rotated_diamond_points_screen, rotated_ellipse_points_screen, rotated_hexagon_points_screen,
+    rotated_parallelogram_points_screen, rotated_polygon_points_screen,
+    rotated_rounded_rect_points_screen, rotated_trapezoid_points_screen,
+    rotated_triangle_points_screen,
 };
 use super::{InProgress, Tool, View};
 
@@ -322,6 +329,69 @@ pub(super) fn style_editor(ui: &mut egui::Ui, style: &mut model::Style, theme_co
     changed
 }
 
+/// Editor for a shape's ports. Returns whether they changed, along with the
+/// `(old, new)` names of ports renamed this frame.
+pub(super) fn ports_editor(
+    ui: &mut egui::Ui,
+    ports: &mut Vec<model::Port>,
+) -> (bool, Vec<(String, String)>) {
+    let mut changed = false;
+    let mut renamed = Vec::new();
+    let mut remove = None;
+    for (i, port) in ports.iter_mut().enumerate() {
+        ui.horizontal(|ui| {
+            let old = port.name.clone();
+            if ui
+                .add(egui::TextEdit::singleline(&mut port.name).desired_width(64.0))
+                .changed()
+            {
+                changed = true;
+                renamed.push((old, port.name.clone()));
+            }
+            egui::ComboBox::from_id_salt(("port_side", i))
+                .width(64.0)
+                .selected_text(port.side.name())
+                .show_ui(ui, |ui| {
+                    for side in model::PortSide::ALL {
+                        if ui
+                            .selectable_value(&mut port.side, side, side.name())
+                            .changed()
+                        {
+                            changed = true;
+                        }
+                    }
+                });
+            changed |= ui
+                .add(
+                    egui::DragValue::new(&mut port.offset)
+                        .range(0.0..=1.0)
+                        .speed(0.01),
+                )
+                .changed();
+            if ui.small_button("✖").on_hover_text("Remove port").clicked() {
+                remove = Some(i);
+            }
+        });
+    }
+    if let Some(i) = remove {
+        ports.remove(i);
+        changed = true;
+    }
+    if ui.button("Add port").clicked() {
+        let name = (1..)
+            .map(|n| format!("p{n}"))
+            .find(|name| ports.iter().all(|p| &p.name != name))
+            .unwrap_or_default();
+        ports.push(model::Port {
+            name,
+            side: model::PortSide::default(),
+            offset: 0.5,
+        });
+        changed = true;
+    }
+    (changed, renamed)
+}
+
 pub(super) fn draw_background(painter: &egui::Painter, rect: egui::Rect, view: &View) {
     let bg = painter.ctx().style().visuals.extreme_bg_color;
     painter.rect_filled(rect, 0.0, bg);
@@ -358,6 +428,7 @@ pub(super) fn draw_elements(
     doc: &model::Document,
     selected: &HashSet<u64>,
 ) {
+    let hops = line_hops(doc);
     for element in &doc.elements {
         draw_element(
             painter,
@@ -366,6 +437,14 @@ pub(super) fn draw_elements(
             doc,
             element,
             selected.contains(&element.id),
+            hops.get(&element.id).map_or(&[], Vec::as_slice),
+        );
+    }
+    for dot in junction_dots(doc) {
+        painter.circle_filled(
+            view.world_to_screen(origin, dot.center),
+            dot.radius * view.zoom,
+            dot.color.to_color32(),
         );
     }
 }
@@ -438,6 +517,55 @@ pub(super) fn draw_group_selection_boxes(
     }
 }
 
+/// Markers for the ports of `element`, labelled outside their side when
+/// `show_names` is set.
+pub(super) fn draw_ports(
+    painter: &egui::Painter,
+    origin: egui::Pos2,
+    view: &View,
+    element: &model::Element,
+    show_names: bool,
+) {
+    let color = egui::Color32::from_rgb(90, 160, 255);
+    for port in &element.ports {
+        let Some(p) = port_world_position(element, port) else {
+            continue;
+        };
+        let p = view.world_to_screen(origin, p);
+        painter.circle(
+            p,
+            4.0,
+            egui::Color32::from_rgb(250, 250, 250),
+            egui::Stroke::new(1.5, color),
+        );
+        if show_names && !port.name.is_empty() {
+            let normal = match port.side {
+                model::PortSide::Top => egui::vec2(0.0, -1.0),
+                model::PortSide::Right => egui::vec2(1.0, 0.0),
+                model::PortSide::Bottom => egui::vec2(0.0, 1.0),
+                model::PortSide::Left => egui::vec2(-1.0, 0.0),
+            };
+            let dir = rotate_vec2(normal, element.rotation);
+            let align = |d: f32| {
+                if d > 0.3 {
+                    egui::Align::Min
+                } else if d < -0.3 {
+                    egui::Align::Max
+                } else {
+                    egui::Align::Center
+                }
+            };
+            painter.text(
+                p + dir * 8.0,
+                egui::Align2([align(dir.x), align(dir.y)]),
+                &port.name,
+                egui::FontId::proportional(11.0),
+                color,
+            );
+        }
+    }
+}
+
 fn draw_element(
     painter: &egui::Painter,
     origin: egui::Pos2,
@@ -445,15 +573,27 @@ fn draw_element(
     doc: &model::Document,
     element: &model::Element,
     is_selected: bool,
+    hops: &[f32],
 ) {
+    let hop_radius = line_hop_radius(element.style.stroke.width);
     let stroke = egui::Stroke::new(
         element.style.stroke.width * view.zoom,
         element.style.stroke.color.to_color32(),
     );
     let fill = element.style.fill.map(|c| c.to_color32());
     match &element.kind {
-        model::ElementKind::Rect { rect, label } => {
-            let points = rotated_rect_points_screen(origin, view, rect.to_rect(), element.rotation);
+        model::ElementKind::Rect {
+            rect,
+            label,
+            corner_radius,
+        } => {
+            let points = rotated_rounded_rect_points_screen(
+                origin,
+                view,
+                rect.to_rect(),
+                element.rotation,
+                *corner_radius,
+            );
             painter.add(egui::Shape::convex_polygon(
                 points.clone(),
                 fill.unwrap_or(egui::Color32::TRANSPARENT),
@@ -653,18 +793,203 @@ fn draw_element(
                 draw_polygon_selection(painter, &points);
             }
         }
+        model::ElementKind::Diamond { rect, label } => {
+            let points =
+                rotated_diamond_points_screen(origin, view, rect.to_rect(), element.rotation);
+            painter.add(egui::Shape::convex_polygon(
+                points.clone(),
+                fill.unwrap_or(egui::Color32::TRANSPARENT),
+                stroke,
+            ));
+            if !label.is_empty() {
+                let r = rect.to_rect();
+                let c = r.center();
+                let padding = 8.0_f32.min(r.width() * 0.25);
+                let anchor_unrot = match element.style.text_align {
+                    model::TextAlign::Left => egui::pos2(r.left() + padding, c.y),
+                    model::TextAlign::Center => c,
+                    model::TextAlign::Right => egui::pos2(r.right() - padding, c.y),
+                };
+                let anchor_world = rotate_pos_about(c, anchor_unrot, element.rotation);
+                let center_screen = view.world_to_screen(origin, anchor_world);
+                draw_rich_text(
+                    painter,
+                    center_screen,
+                    label,
+                    element.style.text_size * view.zoom,
+                    &element.style.font_family,
+                    element.style.text_color.to_color32(),
+                    element.rotation,
+                    element.style.text_align,
+                    VAlign::Center,
+                );
+            }
+            if is_selected {
+                draw_polygon_selection(painter, &points);
+            }
+        }
+        model::ElementKind::Hexagon {
+            rect,
+            label,
+            inset_ratio,
+        } => {
+            let points = rotated_hexagon_points_screen(
+                origin,
+                view,
+                rect.to_rect(),
+                element.rotation,
+                *inset_ratio,
+            );
+            painter.add(egui::Shape::convex_polygon(
+                points.clone(),
+                fill.unwrap_or(egui::Color32::TRANSPARENT),
+                stroke,
+            ));
+            if !label.is_empty() {
+                let r = rect.to_rect();
+                let c = r.center();
+                let padding = 8.0_f32.min(r.width() * 0.25);
+                let anchor_unrot = match element.style.text_align {
+                    model::TextAlign::Left => egui::pos2(r.left() + padding, c.y),
+                    model::TextAlign::Center => c,
+                    model::TextAlign::Right => egui::pos2(r.right() - padding, c.y),
+                };
+                let anchor_world = rotate_pos_about(c, anchor_unrot, element.rotation);
+                let center_screen = view.world_to_screen(origin, anchor_world);
+                draw_rich_text(
+                    painter,
+                    center_screen,
+                    label,
+                    element.style.text_size * view.zoom,
+                    &element.style.font_family,
+                    element.style.text_color.to_color32(),
+                    element.rotation,
+                    element.style.text_align,
+                    VAlign::Center,
+                );
+            }
+            if is_selected {
+                draw_polygon_selection(painter, &points);
+            }
+        }
+        model::ElementKind::Cylinder {
+            rect,
+            label,
+            cap_ratio,
+        } => {
+            let points = rotated_cylinder_points_screen(
+                origin,
+                view,
+                rect.to_rect(),
+                element.rotation,
+                *cap_ratio,
+            );
+            painter.add(egui::Shape::convex_polygon(
+                points.clone(),
+                fill.unwrap_or(egui::Color32::TRANSPARENT),
+                stroke,
+            ));
+            let rim = rotated_cylinder_rim_points_screen(
+                origin,
+                view,
+                rect.to_rect(),
+                element.rotation,
+                *cap_ratio,
+            );
+            painter.add(egui::Shape::line(rim, stroke));
+            if !label.is_empty() {
+                let r = rect.to_rect();
+                let c = r.center();
+                let padding = 8.0_f32.min(r.width() * 0.25);
+                let anchor_unrot = match element.style.text_align {
+                    model::TextAlign::Left => egui::pos2(r.left() + padding, c.y),
+                    model::TextAlign::Center => c,
+                    model::TextAlign::Right => egui::pos2(r.right() - padding, c.y),
+                };
+                let anchor_world = rotate_pos_about(c, anchor_unrot, element.rotation);
+                let center_screen = view.world_to_screen(origin, anchor_world);
+                draw_rich_text(
+                    painter,
+                    center_screen,
+                    label,
+                    element.style.text_size * view.zoom,
+                    &element.style.font_family,
+                    element.style.text_color.to_color32(),
+                    element.rotation,
+                    element.style.text_align,
+                    VAlign::Center,
+                );
+            }
+            if is_selected {
+                draw_polygon_selection(painter, &points);
+            }
+        }
+        model::ElementKind::Polygon { rect, label, sides } => {
+            let points = rotated_polygon_points_screen(
+                origin,
+                view,
+                rect.to_rect(),
+                element.rotation,
+                *sides,
+            );
+            painter.add(egui::Shape::convex_polygon(
+                points.clone(),
+                fill.unwrap_or(egui::Color32::TRANSPARENT),
+                stroke,
+            ));
+            if !label.is_empty() {
+                let r = rect.to_rect();
+                let c = r.center();
+                let padding = 8.0_f32.min(r.width() * 0.25);
+                let anchor_unrot = match element.style.text_align {
+                    model::TextAlign::Left => egui::pos2(r.left() + padding, c.y),
+                    model::TextAlign::Center => c,
+                    model::TextAlign::Right => egui::pos2(r.right() - padding, c.y),
+                };
+                let anchor_world = rotate_pos_about(c, anchor_unrot, element.rotation);
+                let center_screen = view.world_to_screen(origin, anchor_world);
+                draw_rich_text(
+                    painter,
+                    center_screen,
+                    label,
+                    element.style.text_size * view.zoom,
+                    &element.style.font_family,
+                    element.style.text_color.to_color32(),
+                    element.rotation,
+                    element.style.text_align,
+                    VAlign::Center,
+                );
+            }
+            if is_selected {
+                draw_polygon_selection(painter, &points);
+            }
+        }
         model::ElementKind::Line {
             a,
             b,
             arrow,
             arrow_style,
+            heads,
             start_binding,
             end_binding,
+            route,
+            label,
+            ..
         } => {
-            let (a, b) = resolved_line_endpoints_world(doc, *a, *b, start_binding, end_binding);
-            let a = view.world_to_screen(origin, a);
-            let b = view.world_to_screen(origin, b);
-            draw_styled_line(painter, a, b, stroke, element.style.stroke.line_style);
+            let world = line_points_world(doc, *a, *b, route, start_binding, end_binding);
+            let pts: Vec<egui::Pos2> = hopped_path(&world, hops, hop_radius)
+                .into_iter()
+                .map(|p| view.world_to_screen(origin, p))
+                .collect();
+            if let [a, b] = pts[..] {
+                draw_styled_line(painter, a, b, stroke, element.style.stroke.line_style);
+            } else {
+                draw_styled_polyline(painter, &pts, stroke, element.style.stroke.line_style);
+            }
+            let n = pts.len();
+            let curve = line_curve_world(doc, *a, *b, route, start_binding, end_binding)
+                .map(|c| c.map(|p| view.world_to_screen(origin, p)));
+            let (start_tail, end_tail) = arrow_tails(&pts, curve.as_ref());
             let has_end_arrow = *arrow
                 || matches!(
                     arrow_style,
@@ -674,25 +999,55 @@ fn draw_element(
                 arrow_style,
                 model::ArrowStyle::Start | model::ArrowStyle::Both
             );
+            let size = arrowhead_size(element.style.stroke.width, heads.scale) * view.zoom;
             if has_end_arrow {
-                draw_arrowhead(painter, a, b, stroke);
+                draw_arrowhead(painter, heads.end, end_tail, pts[n - 1], size, stroke);
             }
             if has_start_arrow {
-                draw_arrowhead(painter, b, a, stroke);
+                draw_arrowhead(painter, heads.start, start_tail, pts[0], size, stroke);
+            }
+            let label_rect = line_label_rect(doc, element).map(|r| {
+                egui::Rect::from_min_max(
+                    view.world_to_screen(origin, r.min),
+                    view.world_to_screen(origin, r.max),
+                )
+            });
+            if let Some(r) = label_rect {
+                if let Some(fill) = fill {
+                    painter.rect_filled(r, 0.0, fill);
+                }
+                draw_rich_text(
+                    painter,
+                    r.center(),
+                    label,
+                    element.style.text_size * view.zoom,
+                    &element.style.font_family,
+                    element.style.text_color.to_color32(),
+                    0.0,
+                    model::TextAlign::Center,
+                    VAlign::Center,
+                );
             }
             if is_selected {
-                let r = egui::Rect::from_two_pos(a, b).expand(6.0);
-                draw_selection_bounds(painter, r);
+                let mut r = egui::Rect::from_points(&pts);
+                if let Some(label_rect) = label_rect {
+                    r = r.union(label_rect);
+                }
+                draw_selection_bounds(painter, r.expand(6.0));
             }
         }
         model::ElementKind::Polyline {
             points,
             arrow_style,
+            heads,
+            start_binding,
+            end_binding,
         } => {
             if points.len() >= 2 {
-                let pts: Vec<egui::Pos2> = points
-                    .iter()
-                    .map(|p| view.world_to_screen(origin, p.to_pos2()))
+                let world = polyline_points_world(doc, points, start_binding, end_binding);
+                let pts: Vec<egui::Pos2> = hopped_path(&world, hops, hop_radius)
+                    .into_iter()
+                    .map(|p| view.world_to_screen(origin, p))
                     .collect();
                 draw_styled_polyline(painter, &pts, stroke, element.style.stroke.line_style);
                 let has_end_arrow = matches!(
@@ -703,15 +1058,16 @@ fn draw_element(
                     arrow_style,
                     model::ArrowStyle::Start | model::ArrowStyle::Both
                 );
+                let size = arrowhead_size(element.style.stroke.width, heads.scale) * view.zoom;
                 if has_end_arrow && pts.len() >= 2 {
                     let last = pts[pts.len() - 1];
                     let second_last = pts[pts.len() - 2];
-                    draw_arrowhead(painter, second_last, last, stroke);
+                    draw_arrowhead(painter, heads.end, second_last, last, size, stroke);
                 }
                 if has_start_arrow && pts.len() >= 2 {
                     let first = pts[0];
                     let second = pts[1];
-                    draw_arrowhead(painter, second, first, stroke);
+                    draw_arrowhead(painter, heads.start, second, first, size, stroke);
                 }
                 if is_selected {
                     let mut b: Option<egui::Rect> = None;
@@ -818,6 +1174,25 @@ pub(super) fn draw_in_progress(
                     let pts = rotated_trapezoid_points_screen(origin, view, world_rect, 0.0, 0.25);
                     painter.add(egui::Shape::closed_line(pts, stroke));
                 }
+                Tool::Diamond => {
+                    let pts = rotated_diamond_points_screen(origin, view, world_rect, 0.0);
+                    painter.add(egui::Shape::closed_line(pts, stroke));
+                }
+                Tool::Hexagon => {
+                    let pts = rotated_hexagon_points_screen(origin, view, world_rect, 0.0, 0.5);
+                    painter.add(egui::Shape::closed_line(pts, stroke));
+                }
+                Tool::Cylinder => {
+                    let pts = rotated_cylinder_points_screen(origin, view, world_rect, 0.0, 0.2);
+                    painter.add(egui::Shape::closed_line(pts, stroke));
+                    let rim =
+                        rotated_cylinder_rim_points_screen(origin, view, world_rect, 0.0, 0.2);
+                    painter.add(egui::Shape::line(rim, stroke));
+                }
+                Tool::Polygon => {
+                    let pts = rotated_polygon_points_screen(origin, view, world_rect, 0.0, 5);
+                    painter.add(egui::Shape::closed_line(pts, stroke));
+                }
                 _ => {
                     let r = egui::Rect::from_two_pos(
                         view.world_to_screen(origin, *start),
@@ -843,11 +1218,12 @@ pub(super) fn draw_in_progress(
                 arrow_style,
                 model::ArrowStyle::Start | model::ArrowStyle::Both
             );
+            let size = arrowhead_size(style.stroke.width, 1.0) * view.zoom;
             if has_end_arrow {
-                draw_arrowhead(painter, a, b, stroke);
+                draw_arrowhead(painter, model::ArrowHead::Triangle, a, b, size, stroke);
             }
             if has_start_arrow {
-                draw_arrowhead(painter, b, a, stroke);
+                draw_arrowhead(painter, model::ArrowHead::Triangle, b, a, size, stroke);
             }
         }
         InProgress::Polyline {
@@ -870,15 +1246,17 @@ pub(super) fn draw_in_progress(
                     arrow_style,
                     model::ArrowStyle::Start | model::ArrowStyle::Both
                 );
+                let size = arrowhead_size(style.stroke.width, 1.0) * view.zoom;
+                let head = model::ArrowHead::Triangle;
                 if has_end_arrow {
                     let last = all_pts[all_pts.len() - 1];
                     let second_last = all_pts[all_pts.len() - 2];
-                    draw_arrowhead(painter, second_last, last, stroke);
+                    draw_arrowhead(painter, head, second_last, last, size, stroke);
                 }
                 if has_start_arrow && all_pts.len() >= 2 {
                     let first = all_pts[0];
                     let second = all_pts[1];
-                    draw_arrowhead(painter, second, first, stroke);
+                    draw_arrowhead(painter, head, second, first, size, stroke);
                 }
             }
         }
@@ -987,21 +1365,33 @@ fn draw_dashed_line(
     }
 }
 
-fn draw_arrowhead(painter: &egui::Painter, a: egui::Pos2, b: egui::Pos2, stroke: egui::Stroke) {
-    let v = b - a;
-    if v.length_sq() <= f32::EPSILON {
-        return;
+fn draw_arrowhead(
+    painter: &egui::Painter,
+    head: model::ArrowHead,
+    a: egui::Pos2,
+    b: egui::Pos2,
+    size: f32,
+    stroke: egui::Stroke,
+) {
+    for part in arrowhead_parts_world(head, a, b, size) {
+        match part {
+            ArrowheadPart::Filled(points) => {
+                painter.add(egui::Shape::convex_polygon(
+                    points,
+                    stroke.color,
+                    egui::Stroke::NONE,
+                ));
+            }
+            ArrowheadPart::Hollow(points) => {
+                painter.add(egui::Shape::convex_polygon(
+                    points,
+                    egui::Color32::WHITE,
+                    stroke,
+                ));
+            }
+            ArrowheadPart::Stroked(points) => {
+                painter.add(egui::Shape::line(points, stroke));
+            }
+        }
     }
-    let dir = v.normalized();
-    let size = 10.0;
-    let perp = egui::vec2(-dir.y, dir.x);
-    let tip = b;
-    let base = b - dir * size;
-    let left = base + perp * (size * 0.6);
-    let right = base - perp * (size * 0.6);
-    painter.add(egui::Shape::convex_polygon(
-        vec![tip, left, right],
-        stroke.color,
-        egui::Stroke::NONE,
-    ));
 }
@@ -1,4 +1,4 @@
-use crate::model;
+use sansuyu::model;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -14,6 +14,8 @@ pub(super) struct ColorTheme {
 pub(super) struct AppSettings {
     pub file_path: String,
     pub svg_path: String,
+    pub png_scale: f32,
+    pub png_transparent: bool,
     pub snap_to_grid: bool,
     pub grid_size: f32,
     pub move_step: f32,
@@ -32,6 +34,8 @@ impl Default for AppSettings {
         Self {
             file_path: "diagram.json".to_string(),
             svg_path: "diagram.svg".to_string(),
+            png_scale: 2.0,
+            png_transparent: false,
             snap_to_grid: true,
             grid_size: 64.0,
             move_step: 1.0,
@@ -47,27 +51,10 @@ impl Default for AppSettings {
 impl ColorTheme {
     pub fn get_color(&self, name: &str) -> Option<model::Rgba> {
         let hex = self.colors.get(name)?;
-        parse_hex_color(hex)
+        model::Rgba::from_hex(hex)
     }
 }
 
-pub(super) fn parse_hex_color(s: &str) -> Option<model::Rgba> {
-    let s = s.trim().trim_start_matches('#');
-    if s.len() == 6 {
-        let r = u8::from_str_radix(&s[0..2], 16).ok()?;
-        let g = u8::from_str_radix(&s[2..4], 16).ok()?;
-        let b = u8::from_str_radix(&s[4..6], 16).ok()?;
-        return Some(model::Rgba { r, g, b, a: 255 });
-    }
-    if s.len() == 3 {
-        let r = u8::from_str_radix(&s[0..1], 16).ok()? * 17;
-        let g = u8::from_str_radix(&s[1..2], 16).ok()? * 17;
-        let b = u8::from_str_radix(&s[2..3], 16).ok()? * 17;
-        return Some(model::Rgba { r, g, b, a: 255 });
-    }
-    None
-}
-
 pub(super) fn load_settings(path: &str) -> Option<AppSettings> {
     let s = std::fs::read_to_string(path).ok()?;
     if path.ends_with(".toml") {
@@ -1,21 +1,28 @@
-use crate::model;
 use eframe::egui;
+use sansuyu::geometry::{
+    line_label_anchor, resolve_binding_point, shape_outline_world, topmost_bind_target_id,
+};
+use sansuyu::model;
 use std::collections::HashSet;
 
 use super::doc_ops::{
     AbutMode, AlignMode, DistributeMode, abut_selected, align_selected, distribute_selected,
     element_label,
 };
-use super::geometry::{compute_binding_for_target, resolve_binding_point, topmost_bind_target_id};
 use super::render::{
-    draw_background, draw_elements, draw_group_selection_boxes, draw_in_progress, style_editor,
+    draw_background, draw_elements, draw_group_selection_boxes, draw_in_progress, draw_ports,
+    ports_editor, style_editor,
     tool_button,
 };
 use super::command_palette::CommandContext;
-use super::{DiagramApp, InProgress, Tool};
+use super::{ActiveTransform, DiagramApp, InProgress, Tool};
 
 impl eframe::App for DiagramApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.poll_watch();
+        if self.watch.is_some() {
+            ctx.request_repaint_after(super::watch::POLL_INTERVAL);
+        }
         self.sync_bound_line_endpoints();
         let wants_keyboard = ctx.wants_keyboard_input();
         ctx.input_mut(|i| {
@@ -108,6 +115,18 @@ impl eframe::App for DiagramApp {
                 if i.consume_key(egui::Modifiers::SHIFT, egui::Key::Z) {
                     self.tool = Tool::Trapezoid;
                 }
+                if i.consume_key(egui::Modifiers::SHIFT, egui::Key::D) {
+                    self.tool = Tool::Diamond;
+                }
+                if i.consume_key(egui::Modifiers::SHIFT, egui::Key::H) {
+                    self.tool = Tool::Hexagon;
+                }
+                if i.consume_key(egui::Modifiers::SHIFT, egui::Key::C) {
+                    self.tool = Tool::Cylinder;
+                }
+                if i.consume_key(egui::Modifiers::SHIFT, egui::Key::N) {
+                    self.tool = Tool::Polygon;
+                }
                 if i.consume_key(egui::Modifiers::NONE, egui::Key::L) {
                     self.tool = Tool::Line;
                 }
@@ -186,6 +205,39 @@ impl eframe::App for DiagramApp {
                             self.open_json_dialog();
                             ui.close_menu();
                         }
+                        if ui.button("Import Diagram Text...").clicked() {
+                            self.import_dialog("Diagram text", &["sy", "txt"]);
+                            ui.close_menu();
+                        }
+                        if ui.button("Import Graphviz DOT...").clicked() {
+                            self.import_dialog("Graphviz DOT", &["dot", "gv"]);
+                            ui.close_menu();
+                        }
+                        if ui.button("Import Mermaid Flowchart...").clicked() {
+                            self.import_dialog("Mermaid", &["mmd", "mermaid"]);
+                            ui.close_menu();
+                        }
+                        if ui.button("Import SVG...").clicked() {
+                            self.import_dialog("SVG", &["svg"]);
+                            ui.close_menu();
+                        }
+                        if ui.button("Import Excalidraw...").clicked() {
+                            self.import_dialog("Excalidraw", &["excalidraw"]);
+                            ui.close_menu();
+                        }
+                        if ui.button("Import draw.io...").clicked() {
+                            self.import_dialog("draw.io", &["drawio", "dio"]);
+                            ui.close_menu();
+                        }
+                        if self.watch.is_some() {
+                            if ui.button("Stop Watching").clicked() {
+                                self.stop_watching();
+                                ui.close_menu();
+                            }
+                        } else if ui.button("Watch File...").clicked() {
+                            self.watch_file_dialog();
+                            ui.close_menu();
+                        }
                         if ui.button("Save JSON... (⌘S)").clicked() {
                             self.save_json_dialog();
                             ui.close_menu();
@@ -194,6 +246,42 @@ impl eframe::App for DiagramApp {
                             self.save_svg_dialog();
                             ui.close_menu();
                         }
+                        if ui.button("Export PDF...").clicked() {
+                            self.export_pdf_dialog();
+                            ui.close_menu();
+                        }
+                        if ui.button("Export TikZ...").clicked() {
+                            self.export_tikz_dialog();
+                            ui.close_menu();
+                        }
+                        if ui.button("Export PNG...").clicked() {
+                            self.export_png_dialog(ctx);
+                            ui.close_menu();
+                        }
+                        ui.horizontal(|ui| {
+                            ui.small("PNG scale:");
+                            let scale = egui::DragValue::new(&mut self.png_scale)
+                                .range(0.25..=8.0)
+                                .speed(0.05)
+                                .suffix("x");
+                            let mut changed = ui.add(scale).changed();
+                            changed |= ui.checkbox(&mut self.png_transparent, "Transparent").changed();
+                            if changed {
+                                self.persist_settings();
+                            }
+                        });
+                        if ui.button("Export Graphviz DOT...").clicked() {
+                            self.export_dot_dialog();
+                            ui.close_menu();
+                        }
+                        if ui.button("Export Excalidraw...").clicked() {
+                            self.export_excalidraw_dialog();
+                            ui.close_menu();
+                        }
+                        if ui.button("Export draw.io...").clicked() {
+                            self.export_drawio_dialog();
+                            ui.close_menu();
+                        }
                         ui.separator();
                         ui.label("Quick save paths:");
                         ui.small("JSON:");
@@ -601,6 +689,8 @@ impl eframe::App for DiagramApp {
                                 self.persist_settings();
                             }
                         });
+                        ui.separator();
+                        ui.checkbox(&mut self.show_port_names, "Show Port Names");
                     });
                 });
                 ui.menu_button("Tools", |ui| {
@@ -631,6 +721,22 @@ impl eframe::App for DiagramApp {
                             self.tool = Tool::Trapezoid;
                             ui.close_menu();
                         }
+                        if ui.button("Diamond (⇧D)").clicked() {
+                            self.tool = Tool::Diamond;
+                            ui.close_menu();
+                        }
+                        if ui.button("Hexagon (⇧H)").clicked() {
+                            self.tool = Tool::Hexagon;
+                            ui.close_menu();
+                        }
+                        if ui.button("Cylinder (⇧C)").clicked() {
+                            self.tool = Tool::Cylinder;
+                            ui.close_menu();
+                        }
+                        if ui.button("Polygon (⇧N)").clicked() {
+                            self.tool = Tool::Polygon;
+                            ui.close_menu();
+                        }
                         ui.separator();
                         ui.label("Lines");
                         if ui.button("Line (L)").clicked() {
@@ -683,6 +789,10 @@ impl eframe::App for DiagramApp {
                 tool_button(ui, "△", Tool::Triangle, &mut self.tool);
                 tool_button(ui, "▱", Tool::Parallelogram, &mut self.tool);
                 tool_button(ui, "⏢", Tool::Trapezoid, &mut self.tool);
+                tool_button(ui, "◇", Tool::Diamond, &mut self.tool);
+                tool_button(ui, "⬣", Tool::Hexagon, &mut self.tool);
+                tool_button(ui, "⛃", Tool::Cylinder, &mut self.tool);
+                tool_button(ui, "⬟", Tool::Polygon, &mut self.tool);
                 tool_button(ui, "L", Tool::Line, &mut self.tool);
                 tool_button(ui, "→", Tool::Arrow, &mut self.tool);
                 tool_button(ui, "↔", Tool::BidirectionalArrow, &mut self.tool);
@@ -719,6 +829,7 @@ impl eframe::App for DiagramApp {
                         let style_changed = style_editor(ui, &mut style, &theme_colors) && style != original_style;
 
                         let mut push_undo_on_focus = false;
+                        let mut next_route = None;
                         match &mut self.doc.elements[idx].kind {
                             model::ElementKind::Text { text, .. } => {
                                 ui.separator();
@@ -731,11 +842,15 @@ impl eframe::App for DiagramApp {
                                 }
                                 self.editing_text_id = Some(selected_id);
                             }
-                            model::ElementKind::Rect { rect, label }
+                            model::ElementKind::Rect { rect, label, .. }
                             | model::ElementKind::Ellipse { rect, label }
                             | model::ElementKind::Triangle { rect, label, .. }
                             | model::ElementKind::Parallelogram { rect, label, .. }
-                            | model::ElementKind::Trapezoid { rect, label, .. } => {
+                            | model::ElementKind::Trapezoid { rect, label, .. }
+                            | model::ElementKind::Diamond { rect, label }
+                            | model::ElementKind::Hexagon { rect, label, .. }
+                            | model::ElementKind::Cylinder { rect, label, .. }
+                            | model::ElementKind::Polygon { rect, label, .. } => {
                                 ui.separator();
                                 ui.label("Label");
                                 let response =
@@ -778,8 +893,13 @@ impl eframe::App for DiagramApp {
                                 b,
                                 arrow,
                                 arrow_style,
+                                heads,
                                 start_binding,
                                 end_binding,
+                                route,
+                                label,
+                                label_position,
+                                label_offset,
                             } => {
                                 ui.separator();
                                 ui.label("Line");
@@ -821,6 +941,65 @@ impl eframe::App for DiagramApp {
                                     );
                                     self.status = None;
                                 }
+                                push_undo_on_focus |= arrowhead_controls(ui, "line", heads);
+                                let current_route = match route {
+                                    model::LineRoute::Straight => "Straight",
+                                    model::LineRoute::Orthogonal { .. } => "Orthogonal",
+                                    model::LineRoute::Curved { .. } => "Curved",
+                                };
+                                let mut selected_route = current_route;
+                                egui::ComboBox::from_id_salt("line_route")
+                                    .selected_text(current_route)
+                                    .show_ui(ui, |ui| {
+                                        for name in ["Straight", "Orthogonal", "Curved"] {
+                                            ui.selectable_value(&mut selected_route, name, name);
+                                        }
+                                    });
+                                if selected_route != current_route {
+                                    next_route = Some(match selected_route {
+                                        "Orthogonal" => model::LineRoute::Orthogonal {
+                                            bends: Vec::new(),
+                                            offsets: Vec::new(),
+                                        },
+                                        "Curved" => model::LineRoute::Curved {
+                                            c1: model::Point::default(),
+                                            c2: model::Point::default(),
+                                        },
+                                        _ => model::LineRoute::Straight,
+                                    });
+                                    self.status = None;
+                                }
+                                if let model::LineRoute::Orthogonal { offsets, .. } = route
+                                    && offsets.iter().any(|o| *o != 0.0)
+                                    && ui.button("Reset Segments").clicked()
+                                {
+                                    offsets.clear();
+                                }
+
+                                ui.separator();
+                                ui.label("Label");
+                                let response =
+                                    ui.add(egui::TextEdit::multiline(label).desired_rows(2));
+                                push_undo_on_focus |= response.gained_focus();
+                                ui.horizontal(|ui| {
+                                    ui.label("Position:");
+                                    let r = ui.add(
+                                        egui::DragValue::new(label_position)
+                                            .range(0.0..=1.0)
+                                            .speed(0.01),
+                                    );
+                                    push_undo_on_focus |= r.gained_focus();
+                                });
+                                ui.horizontal(|ui| {
+                                    ui.label("Offset:");
+                                    let rx = ui.add(
+                                        egui::DragValue::new(&mut label_offset.x).speed(1.0),
+                                    );
+                                    let ry = ui.add(
+                                        egui::DragValue::new(&mut label_offset.y).speed(1.0),
+                                    );
+                                    push_undo_on_focus |= rx.gained_focus() || ry.gained_focus();
+                                });
 
                                 ui.separator();
                                 ui.label("Endpoints");
@@ -849,7 +1028,9 @@ impl eframe::App for DiagramApp {
                                     self.status = None;
                                 }
                             }
-                            model::ElementKind::Polyline { arrow_style, .. } => {
+                            model::ElementKind::Polyline {
+                                arrow_style, heads, ..
+                            } => {
                                 ui.separator();
                                 ui.label("Polyline");
                                 let mut next_style = *arrow_style;
@@ -886,16 +1067,27 @@ impl eframe::App for DiagramApp {
                                     *arrow_style = next_style;
                                     self.status = None;
                                 }
+                                push_undo_on_focus |= arrowhead_controls(ui, "polyline", heads);
                             }
                             _ => {}
                         }
+                        if let Some(route) = next_route {
+                            self.apply_line_route(idx, &route);
+                        }
                         #[derive(Clone, Copy)]
                         enum ShapeParamKind {
+                            Rect,
                             Triangle,
                             Parallelogram,
                             Trapezoid,
+                            Hexagon,
+                            Cylinder,
+                            Polygon,
                         }
                         let shape = match &self.doc.elements[idx].kind {
+                            model::ElementKind::Rect { corner_radius, .. } => {
+                                Some((ShapeParamKind::Rect, *corner_radius))
+                            }
                             model::ElementKind::Triangle { apex_ratio, .. } => {
                                 Some((ShapeParamKind::Triangle, *apex_ratio))
                             }
@@ -905,11 +1097,40 @@ impl eframe::App for DiagramApp {
                             model::ElementKind::Trapezoid { top_inset_ratio, .. } => {
                                 Some((ShapeParamKind::Trapezoid, *top_inset_ratio))
                             }
+                            model::ElementKind::Hexagon { inset_ratio, .. } => {
+                                Some((ShapeParamKind::Hexagon, *inset_ratio))
+                            }
+                            model::ElementKind::Cylinder { cap_ratio, .. } => {
+                                Some((ShapeParamKind::Cylinder, *cap_ratio))
+                            }
+                            model::ElementKind::Polygon { sides, .. } => {
+                                Some((ShapeParamKind::Polygon, *sides as f32))
+                            }
                             _ => None,
                         };
                         if let Some((kind, current)) = shape {
                             let mut next = None;
                             match kind {
+                                ShapeParamKind::Rect => {
+                                    ui.separator();
+                                    ui.label("Rectangle");
+                                    let max = match &self.doc.elements[idx].kind {
+                                        model::ElementKind::Rect { rect, .. } => {
+                                            rect.to_rect().size().min_elem() * 0.5
+                                        }
+                                        _ => 0.0,
+                                    };
+                                    let mut v = current.min(max);
+                                    let resp = ui.add(
+                                        egui::Slider::new(&mut v, 0.0..=max)
+                                            .text("Corner radius")
+                                            .clamp_to_range(true),
+                                    );
+                                    push_undo_on_focus |= resp.gained_focus();
+                                    if resp.changed() {
+                                        next = Some(v);
+                                    }
+                                }
                                 ShapeParamKind::Triangle => {
                                     ui.separator();
                                     ui.label("Triangle");
@@ -952,17 +1173,75 @@ impl eframe::App for DiagramApp {
                                         next = Some(v);
                                     }
                                 }
+                                ShapeParamKind::Hexagon => {
+                                    ui.separator();
+                                    ui.label("Hexagon");
+                                    let mut v = current;
+                                    let resp = ui.add(
+                                        egui::Slider::new(&mut v, 0.0..=0.95)
+                                            .text("Inset")
+                                            .clamp_to_range(true),
+                                    );
+                                    push_undo_on_focus |= resp.gained_focus();
+                                    if resp.changed() {
+                                        next = Some(v);
+                                    }
+                                }
+                                ShapeParamKind::Cylinder => {
+                                    ui.separator();
+                                    ui.label("Cylinder");
+                                    let mut v = current;
+                                    let resp = ui.add(
+                                        egui::Slider::new(&mut v, 0.0..=0.5)
+                                            .text("Cap")
+                                            .clamp_to_range(true),
+                                    );
+                                    push_undo_on_focus |= resp.gained_focus();
+                                    if resp.changed() {
+                                        next = Some(v);
+                                    }
+                                }
+                                ShapeParamKind::Polygon => {
+                                    ui.separator();
+                                    ui.label("Polygon");
+                                    let mut v = current;
+                                    let resp = ui.add(
+                                        egui::Slider::new(&mut v, 3.0..=24.0)
+                                            .text("Sides")
+                                            .integer()
+                                            .clamp_to_range(true),
+                                    );
+                                    push_undo_on_focus |= resp.gained_focus();
+                                    if resp.changed() {
+                                        next = Some(v);
+                                    }
+                                }
                             }
                             if let Some(v) = next {
                                 self.push_undo();
                                 match (&mut self.doc.elements[idx].kind, kind) {
+                                    (model::ElementKind::Rect { corner_radius, .. }, ShapeParamKind::Rect) => *corner_radius = v,
                                     (model::ElementKind::Triangle { apex_ratio, .. }, ShapeParamKind::Triangle) => *apex_ratio = v,
                                     (model::ElementKind::Parallelogram { skew_ratio, .. }, ShapeParamKind::Parallelogram) => *skew_ratio = v,
                                     (model::ElementKind::Trapezoid { top_inset_ratio, .. }, ShapeParamKind::Trapezoid) => *top_inset_ratio = v,
+                                    (model::ElementKind::Hexagon { inset_ratio, .. }, ShapeParamKind::Hexagon) => *inset_ratio = v,
+                                    (model::ElementKind::Cylinder { cap_ratio, .. }, ShapeParamKind::Cylinder) => *cap_ratio = v,
+                                    (model::ElementKind::Polygon { sides, .. }, ShapeParamKind::Polygon) => *sides = v.round() as u32,
                                     _ => {}
                                 }
                             }
                         }
+                        if shape_outline_world(&self.doc.elements[idx]).is_some() {
+                            ui.separator();
+                            ui.label("Ports");
+                            let mut ports = self.doc.elements[idx].ports.clone();
+                            let (changed, renamed) = ports_editor(ui, &mut ports);
+                            if changed {
+                                self.push_undo();
+                                self.doc.elements[idx].ports = ports;
+                                self.rename_port_bindings(selected_id, &renamed);
+                            }
+                        }
                         if style_changed {
                             self.push_undo();
                             self.doc.elements[idx].style = style;
@@ -1163,6 +1442,10 @@ impl eframe::App for DiagramApp {
                 }
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     ui.label(format!("Zoom: {:.0}%", self.view.zoom * 100.0));
+                    if let Some(watch) = &self.watch {
+                        ui.separator();
+                        ui.label(format!("Watching: {}", watch.path.display()));
+                    }
                     ui.separator();
                     ui.label(format!("Objects: {}", self.doc.elements.len()));
                     ui.separator();
@@ -1256,6 +1539,11 @@ impl eframe::App for DiagramApp {
                                     | model::ElementKind::Triangle { .. }
                                     | model::ElementKind::Parallelogram { .. }
                                     | model::ElementKind::Trapezoid { .. }
+                                    | model::ElementKind::Diamond { .. }
+                                    | model::ElementKind::Hexagon { .. }
+                                    | model::ElementKind::Cylinder { .. }
+                                    | model::ElementKind::Polygon { .. }
+                                    | model::ElementKind::Line { .. }
                             );
                             if has_text {
                                 self.set_selection_single(hit_id);
@@ -1301,7 +1589,11 @@ impl eframe::App for DiagramApp {
                         | Tool::Ellipse
                         | Tool::Triangle
                         | Tool::Parallelogram
-                        | Tool::Trapezoid => {
+                        | Tool::Trapezoid
+                        | Tool::Diamond
+                        | Tool::Hexagon
+                        | Tool::Cylinder
+                        | Tool::Polygon => {
                             self.in_progress = Some(InProgress::DragShape {
                                 start: world_pos,
                                 current: world_pos,
@@ -1355,6 +1647,7 @@ impl eframe::App for DiagramApp {
                                     text: String::new(),
                                 },
                                 style,
+                                ports: Vec::new(),
                             };
                             self.doc.elements.push(element);
                             self.set_selection_single(id);
@@ -1448,6 +1741,7 @@ impl eframe::App for DiagramApp {
                                     Tool::Rectangle => model::ElementKind::Rect {
                                         rect,
                                         label: String::new(),
+                                        corner_radius: 0.0,
                                     },
                                     Tool::Ellipse => model::ElementKind::Ellipse {
                                         rect,
@@ -1468,9 +1762,29 @@ impl eframe::App for DiagramApp {
                                         label: String::new(),
                                         top_inset_ratio: 0.25,
                                     },
+                                    Tool::Diamond => model::ElementKind::Diamond {
+                                        rect,
+                                        label: String::new(),
+                                    },
+                                    Tool::Hexagon => model::ElementKind::Hexagon {
+                                        rect,
+                                        label: String::new(),
+                                        inset_ratio: 0.5,
+                                    },
+                                    Tool::Cylinder => model::ElementKind::Cylinder {
+                                        rect,
+                                        label: String::new(),
+                                        cap_ratio: 0.2,
+                                    },
+                                    Tool::Polygon => model::ElementKind::Polygon {
+                                        rect,
+                                        label: String::new(),
+                                        sides: 5,
+                                    },
                                     _ => model::ElementKind::Rect {
                                         rect,
                                         label: String::new(),
+                                        corner_radius: 0.0,
                                     },
                                 };
                                 let element = model::Element {
@@ -1480,6 +1794,7 @@ impl eframe::App for DiagramApp {
                                     snap_enabled: true,
                                     kind,
                                     style: self.style.clone(),
+                                    ports: Vec::new(),
                                 };
                                 self.doc.elements.push(element);
                                 self.set_selection_single(id);
@@ -1493,16 +1808,8 @@ impl eframe::App for DiagramApp {
                             if (current - start).length() >= threshold_world {
                                 self.push_undo();
                                 let id = self.allocate_id();
-                                let start_binding =
-                                    topmost_bind_target_id(&self.doc, start, threshold_world)
-                                        .and_then(|tid| {
-                                            compute_binding_for_target(&self.doc, tid, start)
-                                        });
-                                let end_binding =
-                                    topmost_bind_target_id(&self.doc, current, threshold_world)
-                                        .and_then(|tid| {
-                                            compute_binding_for_target(&self.doc, tid, current)
-                                        });
+                                let start_binding = self.binding_at(start, threshold_world, None);
+                                let end_binding = self.binding_at(current, threshold_world, None);
                                 let a = start_binding
                                     .as_ref()
                                     .and_then(|b| resolve_binding_point(&self.doc, b))
@@ -1515,6 +1822,13 @@ impl eframe::App for DiagramApp {
                                     arrow_style,
                                     model::ArrowStyle::End | model::ArrowStyle::Both
                                 );
+                                let route = self.route_between(
+                                    &self.line_route,
+                                    a,
+                                    b,
+                                    &start_binding,
+                                    &end_binding,
+                                );
                                 let element = model::Element {
                                     id,
                                     group_id: None,
@@ -1525,10 +1839,16 @@ impl eframe::App for DiagramApp {
                                         b: model::Point::from_pos2(b),
                                         arrow,
                                         arrow_style,
+                                        heads: model::ArrowHeads::default(),
                                         start_binding,
                                         end_binding,
+                                        route,
+                                        label: String::new(),
+                                        label_position: 0.5,
+                                        label_offset: model::Point::default(),
                                     },
                                     style: self.style.clone(),
+                                    ports: Vec::new(),
                                 };
                                 self.doc.elements.push(element);
                                 self.set_selection_single(id);
@@ -1544,6 +1864,17 @@ impl eframe::App for DiagramApp {
                             if pts.len() >= 2 {
                                 self.push_undo();
                                 let id = self.allocate_id();
+                                let last = pts.len() - 1;
+                                let start_binding = self.binding_at(pts[0], threshold_world, None);
+                                let end_binding = self.binding_at(pts[last], threshold_world, None);
+                                for (binding, i) in [(&start_binding, 0), (&end_binding, last)] {
+                                    if let Some(p) = binding
+                                        .as_ref()
+                                        .and_then(|b| resolve_binding_point(&self.doc, b))
+                                    {
+                                        pts[i] = p;
+                                    }
+                                }
                                 let element = model::Element {
                                     id,
                                     group_id: None,
@@ -1555,8 +1886,12 @@ impl eframe::App for DiagramApp {
                                             .map(model::Point::from_pos2)
                                             .collect(),
                                         arrow_style,
+                                        heads: model::ArrowHeads::default(),
+                                        start_binding,
+                                        end_binding,
                                     },
                                     style: self.style.clone(),
+                                    ports: Vec::new(),
                                 };
                                 self.doc.elements.push(element);
                                 self.set_selection_single(id);
@@ -1578,6 +1913,7 @@ impl eframe::App for DiagramApp {
                                             .collect(),
                                     },
                                     style: self.style.clone(),
+                                    ports: Vec::new(),
                                 };
                                 self.doc.elements.push(element);
                                 self.set_selection_single(id);
@@ -1613,6 +1949,24 @@ impl eframe::App for DiagramApp {
             draw_background(&painter, rect, &self.view);
             draw_elements(&painter, origin, &self.view, &self.doc, &self.selected);
             draw_group_selection_boxes(&painter, origin, &self.view, &self.doc, &self.selected);
+            let connecting = matches!(
+                self.tool,
+                Tool::Line | Tool::Arrow | Tool::BidirectionalArrow
+            ) || matches!(
+                self.active_transform,
+                Some(ActiveTransform::LineEndpoint { .. })
+            );
+            let port_target = pointer_world
+                .filter(|_| connecting)
+                .and_then(|p| topmost_bind_target_id(&self.doc, p, threshold_world));
+            for element in &self.doc.elements {
+                if self.show_port_names
+                    || port_target == Some(element.id)
+                    || self.selected.contains(&element.id)
+                {
+                    draw_ports(&painter, origin, &self.view, element, self.show_port_names);
+                }
+            }
             if let Some(in_progress) = &self.in_progress {
                 draw_in_progress(
                     &painter,
@@ -1651,7 +2005,11 @@ impl eframe::App for DiagramApp {
                             | model::ElementKind::Ellipse { rect, .. }
                             | model::ElementKind::Triangle { rect, .. }
                             | model::ElementKind::Parallelogram { rect, .. }
-                            | model::ElementKind::Trapezoid { rect, .. } => {
+                            | model::ElementKind::Trapezoid { rect, .. }
+                            | model::ElementKind::Diamond { rect, .. }
+                            | model::ElementKind::Hexagon { rect, .. }
+                            | model::ElementKind::Cylinder { rect, .. }
+                            | model::ElementKind::Polygon { rect, .. } => {
                                 let world_rect = rect.to_rect();
                                 let margin = 10.0;
                                 let edit_rect = egui::Rect::from_center_size(
@@ -1663,6 +2021,15 @@ impl eframe::App for DiagramApp {
                                 );
                                 (edit_rect, idx)
                             }
+                            model::ElementKind::Line { .. } => {
+                                let anchor = line_label_anchor(&self.doc, element)
+                                    .unwrap_or(egui::Pos2::ZERO);
+                                let size = egui::vec2(
+                                    element.style.text_size * 10.0,
+                                    element.style.text_size * 3.0,
+                                );
+                                (egui::Rect::from_center_size(anchor, size), idx)
+                            }
                             _ => {
                                 self.inline_text_editing = false;
                                 self.editing_text_id = None;
@@ -1700,7 +2067,12 @@ impl eframe::App for DiagramApp {
                                             | model::ElementKind::Ellipse { label, .. }
                                             | model::ElementKind::Triangle { label, .. }
                                             | model::ElementKind::Parallelogram { label, .. }
-                                            | model::ElementKind::Trapezoid { label, .. } => label,
+                                            | model::ElementKind::Trapezoid { label, .. }
+                                            | model::ElementKind::Diamond { label, .. }
+                                            | model::ElementKind::Hexagon { label, .. }
+                                            | model::ElementKind::Cylinder { label, .. }
+                                            | model::ElementKind::Polygon { label, .. }
+                                            | model::ElementKind::Line { label, .. } => label,
                                             _ => {
                                                 self.inline_text_editing = false;
                                                 return;
@@ -1887,6 +2259,10 @@ impl eframe::App for DiagramApp {
                         | model::ElementKind::Triangle { .. }
                         | model::ElementKind::Parallelogram { .. }
                         | model::ElementKind::Trapezoid { .. }
+                        | model::ElementKind::Diamond { .. }
+                        | model::ElementKind::Hexagon { .. }
+                        | model::ElementKind::Cylinder { .. }
+                        | model::ElementKind::Polygon { .. }
                 )
             })
         });
@@ -1897,6 +2273,7 @@ impl eframe::App for DiagramApp {
             can_ungroup: self.selected.iter().any(|id| self.group_of(*id).is_some()),
             snap_to_grid: self.snap_to_grid,
             has_resizable,
+            watching: self.watch.is_some(),
         };
         let submit_input = self.command_palette.is_awaiting_input()
             && ctx.input(|i| i.key_pressed(egui::Key::Enter));
@@ -1916,3 +2293,33 @@ impl eframe::App for DiagramApp {
         super::help::draw_help_window(ctx, &mut self.show_help);
     }
 }
+
+/// Start and end head pickers plus the arrow size; returns true when the size
+/// field gains focus so the caller can push an undo step.
+fn arrowhead_controls(ui: &mut egui::Ui, id: &str, heads: &mut model::ArrowHeads) -> bool {
+    for (label, head) in [
+        ("Start head:", &mut heads.start),
+        ("End head:", &mut heads.end),
+    ] {
+        ui.horizontal(|ui| {
+            ui.label(label);
+            egui::ComboBox::from_id_salt((id, label))
+                .selected_text(head.name())
+                .show_ui(ui, |ui| {
+                    for option in model::ArrowHead::ALL {
+                        ui.selectable_value(head, option, option.name());
+                    }
+                });
+        });
+    }
+    ui.horizontal(|ui| {
+        ui.label("Arrow size:");
+        ui.add(
+            egui::DragValue::new(&mut heads.scale)
+                .range(0.25..=4.0)
+                .speed(0.05),
+        )
+        .gained_focus()
+    })
+    .inner
+}
@@ -0,0 +1,93 @@
+use sansuyu::load::load_document;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use super::DiagramApp;
+
+pub(super) const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+pub(super) struct FileWatch {
+    pub path: PathBuf,
+    modified: Option<SystemTime>,
+    last_poll: Instant,
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl DiagramApp {
+    pub(super) fn watch_file_dialog(&mut self) {
+        if let Some(path) = rfd::FileDialog::new()
+            .add_filter("Diagram", &["json", "svg", "sy", "txt", "dot", "gv", "mmd", "mermaid", "excalidraw", "drawio"])
+            .pick_file()
+        {
+            self.start_watching(path);
+        }
+    }
+
+    pub(super) fn start_watching(&mut self, path: PathBuf) {
+        if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+            self.diagram_name = stem.to_string();
+        }
+        let modified = modified_time(&path);
+        if modified.is_some() {
+            self.reload_watched(&path);
+        } else {
+            self.status = Some(format!("Waiting for {}", path.display()));
+        }
+        self.watch = Some(FileWatch {
+            path,
+            modified,
+            last_poll: Instant::now(),
+        });
+    }
+
+    pub(super) fn stop_watching(&mut self) {
+        if let Some(watch) = self.watch.take() {
+            self.status = Some(format!("Stopped watching {}", watch.path.display()));
+        }
+    }
+
+    /// Reloads the watched file if it changed since the last poll. Edits in
+    /// progress on the canvas are never interrupted; the reload waits for them.
+    pub(super) fn poll_watch(&mut self) {
+        let Some(watch) = &mut self.watch else {
+            return;
+        };
+        if watch.last_poll.elapsed() < POLL_INTERVAL
+            || self.in_progress.is_some()
+            || self.active_transform.is_some()
+        {
+            return;
+        }
+        watch.last_poll = Instant::now();
+        let modified = modified_time(&watch.path);
+        if modified.is_none() || modified == watch.modified {
+            return;
+        }
+        watch.modified = modified;
+        let path = watch.path.clone();
+        self.reload_watched(&path);
+    }
+
+    /// Replaces the document without recording an undo step, keeping the
+    /// view and any selected ids that still exist.
+    fn reload_watched(&mut self, path: &Path) {
+        match load_document(path) {
+            Ok(doc) => {
+                self.doc = doc;
+                let ids: std::collections::HashSet<u64> =
+                    self.doc.elements.iter().map(|e| e.id).collect();
+                self.selected.retain(|id| ids.contains(id));
+                if self.editing_text_id.is_some_and(|id| !ids.contains(&id)) {
+                    self.editing_text_id = None;
+                }
+                self.next_id = self.doc.elements.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+                self.next_group_id = self.doc.groups.iter().map(|g| g.id).max().unwrap_or(0) + 1;
+                self.status = Some(format!("Reloaded {}", path.display()));
+            }
+            Err(e) => self.status = Some(format!("Reload failed: {e}")),
+        }
+    }
+}
@@ -0,0 +1,403 @@
+use sansuyu::load::load_document;
+use sansuyu::{dot, drawio, excalidraw, model, pdf, raster, svg, tikz};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+pub const USAGE: &str = "\
+Usage:
+  sansuyu [FILE]                                   Open the editor (optionally with FILE)
+  sansuyu --watch <FILE>                           Open the editor and reload FILE when it changes
+  sansuyu export <INPUT>... [-o OUTPUT | --out-dir DIR] [--format svg|png|pdf|tikz|dot|excalidraw|drawio]
+                 [--scale N | --dpi N] [--transparent]
+                                                   Export diagrams (format defaults to the
+                                                   extension of OUTPUT, then svg; PNGs render
+                                                   at --scale, default 1, or --dpi / 96;
+                                                   never overwrites an input without -o)
+  sansuyu validate <INPUT>...                      Check that diagrams parse and references resolve
+  sansuyu info <INPUT>...                          Print a summary of each diagram
+  sansuyu help                                     Show this message";
+
+const EXIT_OK: i32 = 0;
+const EXIT_FAILURE: i32 = 1;
+pub const EXIT_USAGE: i32 = 2;
+
+pub fn is_subcommand(arg: &str) -> bool {
+    matches!(arg, "export" | "validate" | "info" | "help" | "-h" | "--help")
+}
+
+/// Splits the editor's own arguments into the file to open and whether to watch it.
+pub fn launch_args(args: &[String]) -> Result<(Option<PathBuf>, bool), String> {
+    match args {
+        [] => Ok((None, false)),
+        [flag, file] if flag == "--watch" => Ok((Some(PathBuf::from(file)), true)),
+        [flag] if flag == "--watch" => Err("Missing file for --watch".to_string()),
+        [file] if !file.starts_with('-') => Ok((Some(PathBuf::from(file)), false)),
+        _ => Err(format!("Unexpected arguments: {}", args.join(" "))),
+    }
+}
+
+pub fn run(args: &[String]) -> i32 {
+    let Some((cmd, rest)) = args.split_first() else {
+        eprintln!("{USAGE}");
+        return EXIT_USAGE;
+    };
+    match cmd.as_str() {
+        "export" => export(rest),
+        "validate" => validate(rest),
+        "info" => info(rest),
+        "help" | "-h" | "--help" => {
+            println!("{USAGE}");
+            EXIT_OK
+        }
+        other => {
+            eprintln!("Unknown command: {other}\n\n{USAGE}");
+            EXIT_USAGE
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum ExportFormat {
+    Svg,
+    Png,
+    Pdf,
+    Tikz,
+    Dot,
+    Excalidraw,
+    Drawio,
+}
+
+impl ExportFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "svg" => Some(Self::Svg),
+            "png" => Some(Self::Png),
+            "pdf" => Some(Self::Pdf),
+            "tikz" | "tex" => Some(Self::Tikz),
+            "dot" | "gv" => Some(Self::Dot),
+            "excalidraw" => Some(Self::Excalidraw),
+            "drawio" | "dio" => Some(Self::Drawio),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Svg => "svg",
+            Self::Png => "png",
+            Self::Pdf => "pdf",
+            Self::Tikz => "tex",
+            Self::Dot => "dot",
+            Self::Excalidraw => "excalidraw",
+            Self::Drawio => "drawio",
+        }
+    }
+
+    fn render(
+        self,
+        doc: &model::Document,
+        name: &str,
+        raster: &raster::RasterOptions,
+    ) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Svg => Ok(svg::document_to_svg(doc).into_bytes()),
+            Self::Png => raster::document_to_png(doc, raster),
+            Self::Pdf => Ok(pdf::document_to_pdf(doc)),
+            Self::Tikz => Ok(tikz::document_to_tikz(doc, &[]).into_bytes()),
+            Self::Dot => Ok(dot::document_to_dot(doc, name).into_bytes()),
+            Self::Excalidraw => Ok(excalidraw::document_to_excalidraw(doc).into_bytes()),
+            Self::Drawio => Ok(drawio::document_to_drawio(doc).into_bytes()),
+        }
+    }
+}
+
+fn export(args: &[String]) -> i32 {
+    let mut inputs: Vec<PathBuf> = Vec::new();
+    let mut output: Option<PathBuf> = None;
+    let mut out_dir: Option<PathBuf> = None;
+    let mut format: Option<ExportFormat> = None;
+    let mut raster = raster::RasterOptions::default();
+    let mut it = args.iter();
+    while let Some(arg) = it.next() {
+        match arg.as_str() {
+            "-o" | "--output" => {
+                let Some(v) = it.next() else {
+                    eprintln!("Missing value for {arg}");
+                    return EXIT_USAGE;
+                };
+                output = Some(PathBuf::from(v));
+            }
+            "--out-dir" => {
+                let Some(v) = it.next() else {
+                    eprintln!("Missing value for {arg}");
+                    return EXIT_USAGE;
+                };
+                out_dir = Some(PathBuf::from(v));
+            }
+            "--format" => {
+                let Some(v) = it.next() else {
+                    eprintln!("Missing value for {arg}");
+                    return EXIT_USAGE;
+                };
+                let Some(f) = ExportFormat::from_name(v) else {
+                    eprintln!("Unknown format: {v}");
+                    return EXIT_USAGE;
+                };
+                format = Some(f);
+            }
+            "--scale" | "--dpi" => {
+                let Some(v) = it.next() else {
+                    eprintln!("Missing value for {arg}");
+                    return EXIT_USAGE;
+                };
+                let Some(n) = v.parse::<f32>().ok().filter(|n| n.is_finite() && *n > 0.0) else {
+                    eprintln!("Invalid value for {arg}: {v}");
+                    return EXIT_USAGE;
+                };
+                raster.scale = if arg == "--dpi" { n / 96.0 } else { n };
+            }
+            "--transparent" => raster.transparent = true,
+            _ if arg.starts_with('-') => {
+                eprintln!("Unknown option: {arg}\n\n{USAGE}");
+                return EXIT_USAGE;
+            }
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+    if inputs.is_empty() {
+        eprintln!("No input files\n\n{USAGE}");
+        return EXIT_USAGE;
+    }
+    if output.is_some() && (inputs.len() > 1 || out_dir.is_some()) {
+        eprintln!("-o can only be used with a single input and without --out-dir");
+        return EXIT_USAGE;
+    }
+    if let Some(dir) = &out_dir
+        && let Err(e) = std::fs::create_dir_all(dir)
+    {
+        eprintln!("{}: {e}", dir.display());
+        return EXIT_FAILURE;
+    }
+
+    let format = format
+        .or_else(|| {
+            output
+                .as_ref()
+                .and_then(|o| o.extension())
+                .and_then(|e| e.to_str())
+                .and_then(ExportFormat::from_name)
+        })
+        .unwrap_or(ExportFormat::Svg);
+    let ext = format.extension();
+
+    let mut failed = 0;
+    for input in &inputs {
+        let target = match (&output, &out_dir) {
+            (Some(o), _) => o.clone(),
+            (None, Some(dir)) => dir.join(input.with_extension(ext).file_name().unwrap_or_default()),
+            (None, None) => input.with_extension(ext),
+        };
+        // Exporting to the input's own format would replace it with a lossy
+        // copy, so that needs an explicit `-o`.
+        if output.is_none() && same_file(input, &target) {
+            eprintln!(
+                "{}: export would overwrite the input; use -o to write it anyway",
+                input.display()
+            );
+            failed += 1;
+            continue;
+        }
+        let name = input
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("diagram");
+        let result = load_document(input)
+            .and_then(|doc| format.render(&doc, name, &raster))
+            .and_then(|bytes| {
+                std::fs::write(&target, bytes).map_err(|e| format!("Write failed: {e}"))
+            });
+        match result {
+            Ok(()) => println!("{} -> {}", input.display(), target.display()),
+            Err(e) => {
+                eprintln!("{}: {e}", input.display());
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 { EXIT_FAILURE } else { EXIT_OK }
+}
+
+fn same_file(a: &std::path::Path, b: &std::path::Path) -> bool {
+    a == b
+        || matches!(
+            (a.canonicalize(), b.canonicalize()),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+fn document_problems(doc: &model::Document) -> Vec<String> {
+    let mut problems = Vec::new();
+    let mut ids = HashSet::new();
+    for e in &doc.elements {
+        if !ids.insert(e.id) {
+            problems.push(format!("duplicate element id {}", e.id));
+        }
+    }
+    let group_ids: HashSet<u64> = doc.groups.iter().map(|g| g.id).collect();
+    for e in &doc.elements {
+        if let Some(g) = e.group_id
+            && !group_ids.contains(&g)
+        {
+            problems.push(format!("element {} references missing group {}", e.id, g));
+        }
+        if let model::ElementKind::Line {
+            start_binding,
+            end_binding,
+            ..
+        }
+        | model::ElementKind::Polyline {
+            start_binding,
+            end_binding,
+            ..
+        } = &e.kind
+        {
+            for binding in [start_binding, end_binding].into_iter().flatten() {
+                if !ids.contains(&binding.element_id) {
+                    problems.push(format!(
+                        "line {} is bound to missing element {}",
+                        e.id, binding.element_id
+                    ));
+                } else if let Some(port) = &binding.port
+                    && doc
+                        .elements
+                        .iter()
+                        .find(|t| t.id == binding.element_id)
+                        .is_some_and(|t| t.port(port).is_none())
+                {
+                    problems.push(format!(
+                        "line {} is bound to missing port '{}' of element {}",
+                        e.id, port, binding.element_id
+                    ));
+                }
+            }
+        }
+    }
+    for g in &doc.groups {
+        if let Some(pid) = g.parent_id
+            && !group_ids.contains(&pid)
+        {
+            problems.push(format!("group {} references missing parent group {}", g.id, pid));
+        }
+    }
+    problems
+}
+
+fn validate(args: &[String]) -> i32 {
+    if args.is_empty() {
+        eprintln!("No input files\n\n{USAGE}");
+        return EXIT_USAGE;
+    }
+    let mut failed = 0;
+    for input in args {
+        let path = Path::new(input);
+        match load_document(path) {
+            Ok(doc) => {
+                let problems = document_problems(&doc);
+                if problems.is_empty() {
+                    println!("{}: ok", path.display());
+                } else {
+                    for p in problems {
+                        eprintln!("{}: {p}", path.display());
+                    }
+                    failed += 1;
+                }
+            }
+            Err(e) => {
+                eprintln!("{}: {e}", path.display());
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 { EXIT_FAILURE } else { EXIT_OK }
+}
+
+fn kind_name(kind: &model::ElementKind) -> &'static str {
+    match kind {
+        model::ElementKind::Rect { .. } => "rect",
+        model::ElementKind::Ellipse { .. } => "ellipse",
+        model::ElementKind::Triangle { .. } => "triangle",
+        model::ElementKind::Parallelogram { .. } => "parallelogram",
+        model::ElementKind::Trapezoid { .. } => "trapezoid",
+        model::ElementKind::Diamond { .. } => "diamond",
+        model::ElementKind::Hexagon { .. } => "hexagon",
+        model::ElementKind::Cylinder { .. } => "cylinder",
+        model::ElementKind::Polygon { .. } => "polygon",
+        model::ElementKind::Line { .. } => "line",
+        model::ElementKind::Polyline { .. } => "polyline",
+        model::ElementKind::Pen { .. } => "pen",
+        model::ElementKind::Text { .. } => "text",
+    }
+}
+
+fn info(args: &[String]) -> i32 {
+    if args.is_empty() {
+        eprintln!("No input files\n\n{USAGE}");
+        return EXIT_USAGE;
+    }
+    let mut failed = 0;
+    for input in args {
+        let path = Path::new(input);
+        let doc = match load_document(path) {
+            Ok(doc) => doc,
+            Err(e) => {
+                eprintln!("{}: {e}", path.display());
+                failed += 1;
+                continue;
+            }
+        };
+        let mut counts: Vec<(&'static str, usize)> = Vec::new();
+        for e in &doc.elements {
+            let name = kind_name(&e.kind);
+            match counts.iter_mut().find(|(n, _)| *n == name) {
+                Some((_, c)) => *c += 1,
+                None => counts.push((name, 1)),
+            }
+        }
+        let bound_lines = doc
+            .elements
+            .iter()
+            .filter(|e| {
+                matches!(
+                    &e.kind,
+                    model::ElementKind::Line { start_binding, end_binding, .. }
+                    | model::ElementKind::Polyline { start_binding, end_binding, .. }
+                        if start_binding.is_some() || end_binding.is_some()
+                )
+            })
+            .count();
+        println!("{}", path.display());
+        println!("  elements: {}", doc.elements.len());
+        for (name, count) in counts {
+            println!("    {name}: {count}");
+        }
+        println!("  groups: {}", doc.groups.len());
+        println!("  bound lines: {bound_lines}");
+        let bounds = doc
+            .elements
+            .iter()
+            .map(|e| e.bounds())
+            .reduce(|a, b| a.union(b));
+        if let Some(b) = bounds {
+            println!(
+                "  bounds: ({:.1}, {:.1}) - ({:.1}, {:.1}), {:.1} x {:.1}",
+                b.min.x,
+                b.min.y,
+                b.max.x,
+                b.max.y,
+                b.width(),
+                b.height()
+            );
+        }
+    }
+    if failed > 0 { EXIT_FAILURE } else { EXIT_OK }
+}
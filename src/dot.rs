@@ -0,0 +1,1141 @@
+//! Graphviz DOT import and export.
+//!
+//! On import, nodes become shapes, edges become bound lines carrying their
+//! `label`, and `cluster*`
+//! subgraphs become groups. Graphviz positions are ignored; the graph is placed
+//! with [`layout::layered`](crate::layout::layered) honouring `rankdir`, and
+//! `splines=ortho` turns the edges into orthogonal connectors and
+//! `splines=curved` (or `spline`/`true`) into curved ones.
+//!
+//! Export goes the other way and writes the connection graph: every shape is a
+//! node and every line bound at both ends is an edge.
+
+use crate::graph::{Edge, Graph, Node, Placement, ShapeKind, edge_style};
+use crate::layout::Direction;
+use crate::model;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+/// Graphviz sizes are in inches.
+const POINTS_PER_INCH: f32 = 72.0;
+
+#[derive(Clone, Debug, PartialEq)]
+enum Tok {
+    Id(String),
+    Punct(&'static str),
+}
+
+struct Token {
+    tok: Tok,
+    line: usize,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = src.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut line = 1;
+    let mut at_line_start = true;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '\n' {
+            line += 1;
+            at_line_start = true;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        // `#` lines are C preprocessor output and are skipped like comments.
+        if (c == '#' && at_line_start) || (c == '/' && chars.get(i + 1) == Some(&'/')) {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            continue;
+        }
+        at_line_start = false;
+        if c == '/' && chars.get(i + 1) == Some(&'*') {
+            i += 2;
+            while i < chars.len() && !(chars[i] == '*' && chars.get(i + 1) == Some(&'/')) {
+                if chars[i] == '\n' {
+                    line += 1;
+                }
+                i += 1;
+            }
+            i += 2;
+            continue;
+        }
+        let start_line = line;
+        if c == '"' {
+            let mut s = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(format!("line {start_line}: unterminated string")),
+                    Some('"') => {
+                        i += 1;
+                        break;
+                    }
+                    Some('\\') if chars.get(i + 1) == Some(&'"') => {
+                        s.push('"');
+                        i += 2;
+                    }
+                    Some('\\') if chars.get(i + 1) == Some(&'\n') => {
+                        line += 1;
+                        i += 2;
+                    }
+                    Some(&ch) => {
+                        if ch == '\n' {
+                            line += 1;
+                        }
+                        s.push(ch);
+                        i += 1;
+                    }
+                }
+            }
+            // "a" + "b" concatenation
+            if let Some(Token {
+                tok: Tok::Punct("+"),
+                ..
+            }) = tokens.last()
+            {
+                tokens.pop();
+                if let Some(Token {
+                    tok: Tok::Id(prev), ..
+                }) = tokens.last_mut()
+                {
+                    prev.push_str(&s);
+                    continue;
+                }
+                return Err(format!("line {start_line}: '+' must join two strings"));
+            }
+            tokens.push(Token {
+                tok: Tok::Id(s),
+                line: start_line,
+            });
+            continue;
+        }
+        if c == '<' {
+            let mut depth = 0;
+            let mut s = String::new();
+            while i < chars.len() {
+                let ch = chars[i];
+                if ch == '<' {
+                    depth += 1;
+                } else if ch == '>' {
+                    depth -= 1;
+                } else if ch == '\n' {
+                    line += 1;
+                }
+                s.push(ch);
+                i += 1;
+                if depth == 0 {
+                    break;
+                }
+            }
+            if depth != 0 {
+                return Err(format!("line {start_line}: unterminated HTML label"));
+            }
+            tokens.push(Token {
+                tok: Tok::Id(strip_html(&s[1..s.len() - 1])),
+                line: start_line,
+            });
+            continue;
+        }
+        if c == '-' && matches!(chars.get(i + 1), Some('>') | Some('-')) {
+            let op = if chars[i + 1] == '>' { "->" } else { "--" };
+            tokens.push(Token {
+                tok: Tok::Punct(op),
+                line,
+            });
+            i += 2;
+            continue;
+        }
+        let punct = match c {
+            '{' => Some("{"),
+            '}' => Some("}"),
+            '[' => Some("["),
+            ']' => Some("]"),
+            '=' => Some("="),
+            ';' => Some(";"),
+            ',' => Some(","),
+            ':' => Some(":"),
+            '+' => Some("+"),
+            _ => None,
+        };
+        if let Some(p) = punct {
+            tokens.push(Token {
+                tok: Tok::Punct(p),
+                line,
+            });
+            i += 1;
+            continue;
+        }
+        if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' || !c.is_ascii() {
+            let mut s = String::new();
+            while i < chars.len() {
+                let ch = chars[i];
+                let leading_minus = ch == '-' && s.is_empty();
+                if ch.is_alphanumeric() || ch == '_' || ch == '.' || !ch.is_ascii() || leading_minus
+                {
+                    s.push(ch);
+                    i += 1;
+                } else {
+                    break;
+                }
+            }
+            tokens.push(Token {
+                tok: Tok::Id(s),
+                line,
+            });
+            continue;
+        }
+        return Err(format!("line {line}: unexpected character '{c}'"));
+    }
+    Ok(tokens)
+}
+
+fn strip_html(s: &str) -> String {
+    let mut out = String::new();
+    let mut in_tag = false;
+    let mut tag = String::new();
+    for c in s.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                tag.clear();
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                let name = tag.trim_start_matches('/').to_ascii_lowercase();
+                if name.starts_with("br") || (name == "tr" && tag.starts_with('/')) {
+                    out.push('\n');
+                }
+            }
+            _ if in_tag => tag.push(c),
+            _ => out.push(c),
+        }
+    }
+    out.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+        .trim()
+        .to_string()
+}
+
+type Attrs = Vec<(String, String)>;
+
+#[derive(Clone, Default)]
+struct Scope {
+    node_attrs: Attrs,
+    edge_attrs: Attrs,
+    group_id: Option<u64>,
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    directed: bool,
+    direction: Direction,
+    /// Route from `splines`, which may come after the edges it applies to.
+    splines: model::LineRoute,
+    graph: Graph,
+    node_index: HashMap<String, usize>,
+}
+
+pub fn parse(src: &str) -> Result<model::Document, String> {
+    let tokens = tokenize(src)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        directed: false,
+        direction: Direction::TopBottom,
+        splines: model::LineRoute::Straight,
+        graph: Graph::default(),
+        node_index: HashMap::new(),
+    };
+    parser.parse_graph()?;
+    if parser.splines != model::LineRoute::Straight {
+        for edge in &mut parser.graph.edges {
+            edge.route = parser.splines.clone();
+        }
+    }
+    let direction = parser.direction;
+    Ok(parser.graph.into_document(Placement::Layered(direction)))
+}
+
+fn color(value: &str) -> Option<model::Rgba> {
+    // Color lists ("red:blue") and weighted colors ("red;0.3") use the first entry.
+    let value = value.split([':', ';']).next()?.trim();
+    if let Some(hex) = value.strip_prefix('#')
+        && hex.len() == 8
+        && let Some(mut c) = model::Rgba::parse(&hex[..6])
+    {
+        c.a = u8::from_str_radix(&hex[6..], 16).ok()?;
+        return Some(c);
+    }
+    if let Some(c) = model::Rgba::parse(value) {
+        return Some(c);
+    }
+    let rgb = |r, g, b| Some(model::Rgba { r, g, b, a: 255 });
+    match value.to_ascii_lowercase().as_str() {
+        "lightgrey" | "lightgray" => rgb(211, 211, 211),
+        "darkgrey" | "darkgray" => rgb(169, 169, 169),
+        "lightblue" => rgb(173, 216, 230),
+        "lightyellow" => rgb(255, 255, 224),
+        "lightgreen" => rgb(144, 238, 144),
+        "lightpink" | "pink" => rgb(255, 192, 203),
+        "darkgreen" => rgb(0, 100, 0),
+        "navy" => rgb(0, 0, 128),
+        "cyan" => rgb(0, 255, 255),
+        "magenta" => rgb(255, 0, 255),
+        "brown" => rgb(165, 42, 42),
+        "gold" => rgb(255, 215, 0),
+        "transparent" => Some(model::Rgba { r: 0, g: 0, b: 0, a: 0 }),
+        _ => None,
+    }
+}
+
+fn unescape_label(label: &str, name: &str) -> String {
+    let mut out = String::new();
+    let mut chars = label.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('l') | Some('r') => out.push('\n'),
+            Some('N') => out.push_str(name),
+            Some('G') | Some('E') | Some('T') | Some('H') => {}
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out.trim_end_matches('\n').to_string()
+}
+
+fn apply_line_style(style: &mut model::Style, value: &str) {
+    for part in value.split(',').map(str::trim) {
+        match part {
+            "dashed" => style.stroke.line_style = model::LineStyle::Dashed,
+            "dotted" => style.stroke.line_style = model::LineStyle::Dotted,
+            "solid" => style.stroke.line_style = model::LineStyle::Solid,
+            "invis" => style.stroke.color.a = 0,
+            "bold" => style.stroke.width = style.stroke.width.max(3.0),
+            _ => {}
+        }
+    }
+}
+
+fn apply_node_attrs(node: &mut Node, name: &str, attrs: &Attrs) {
+    let mut filled = false;
+    let mut fill_color = None;
+    let mut stroke_color = None;
+    let sides = attrs
+        .iter()
+        .rev()
+        .find(|(key, _)| key == "sides")
+        .and_then(|(_, value)| value.parse::<u32>().ok())
+        .map(|n| n.clamp(3, 24));
+    for (key, value) in attrs {
+        match key.as_str() {
+            "label" => node.label = unescape_label(value, name),
+            "sides" => {
+                if let (ShapeKind::Polygon(n), Some(sides)) = (&mut node.kind, sides) {
+                    *n = sides;
+                }
+            }
+            "shape" => match value.as_str() {
+                "ellipse" | "oval" | "circle" | "doublecircle" | "point" | "egg" => {
+                    node.kind = ShapeKind::Ellipse
+                }
+                "triangle" | "invtriangle" => node.kind = ShapeKind::Triangle,
+                "parallelogram" => node.kind = ShapeKind::Parallelogram,
+                "trapezium" | "invtrapezium" => node.kind = ShapeKind::Trapezoid,
+                "diamond" | "Mdiamond" => node.kind = ShapeKind::Diamond,
+                "hexagon" => node.kind = ShapeKind::Hexagon,
+                "cylinder" => node.kind = ShapeKind::Cylinder,
+                "pentagon" => node.kind = ShapeKind::Polygon(5),
+                "septagon" => node.kind = ShapeKind::Polygon(7),
+                "octagon" | "doubleoctagon" | "tripleoctagon" => {
+                    node.kind = ShapeKind::Polygon(8)
+                }
+                "polygon" => node.kind = ShapeKind::Polygon(sides.unwrap_or(4)),
+                "plaintext" | "plain" | "none" => {
+                    node.kind = ShapeKind::Rect;
+                    node.style.stroke.color.a = 0;
+                }
+                _ => node.kind = ShapeKind::Rect,
+            },
+            "style" => {
+                filled |= value.split(',').any(|p| p.trim() == "filled");
+                apply_line_style(&mut node.style, value);
+            }
+            "color" => stroke_color = color(value),
+            "fillcolor" => fill_color = color(value),
+            "fontcolor" => {
+                if let Some(c) = color(value) {
+                    node.style.text_color = c;
+                }
+            }
+            "fontsize" => {
+                if let Ok(size) = value.parse::<f32>()
+                    && size > 0.0
+                {
+                    node.style.text_size = size;
+                }
+            }
+            "fontname" => {
+                let lower = value.to_ascii_lowercase();
+                if lower.contains("mono") || lower.contains("courier") {
+                    node.style.font_family = model::FontFamily::Monospace;
+                }
+            }
+            "penwidth" => {
+                if let Ok(width) = value.parse::<f32>() {
+                    node.style.stroke.width = width.max(0.0);
+                }
+            }
+            "width" => {
+                if let Ok(w) = value.parse::<f32>()
+                    && w > 0.0
+                {
+                    node.size.x = w * POINTS_PER_INCH;
+                }
+            }
+            "height" => {
+                if let Ok(h) = value.parse::<f32>()
+                    && h > 0.0
+                {
+                    node.size.y = h * POINTS_PER_INCH;
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some(c) = stroke_color {
+        node.style.stroke.color = c;
+    }
+    if let Some(c) = fill_color.or(if filled { stroke_color } else { None }) {
+        node.style.fill = Some(c);
+    } else if filled {
+        node.style.fill = color("lightgrey");
+    }
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Tok> {
+        self.tokens.get(self.pos).map(|t| &t.tok)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .or(self.tokens.last())
+            .map(|t| t.line)
+            .unwrap_or(1)
+    }
+
+    fn error(&self, msg: impl std::fmt::Display) -> String {
+        format!("line {}: {}", self.line(), msg)
+    }
+
+    fn eat(&mut self, p: &str) -> bool {
+        if matches!(self.peek(), Some(Tok::Punct(q)) if *q == p) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, p: &str) -> Result<(), String> {
+        if self.eat(p) {
+            Ok(())
+        } else {
+            Err(self.error(format!("expected '{p}'")))
+        }
+    }
+
+    fn peek_keyword(&self, kw: &str) -> bool {
+        matches!(self.peek(), Some(Tok::Id(id)) if id.eq_ignore_ascii_case(kw))
+    }
+
+    fn id(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Tok::Id(id)) => {
+                let id = id.clone();
+                self.pos += 1;
+                Ok(id)
+            }
+            _ => Err(self.error("expected an identifier")),
+        }
+    }
+
+    fn parse_graph(&mut self) -> Result<(), String> {
+        if self.peek_keyword("strict") {
+            self.pos += 1;
+        }
+        if self.peek_keyword("digraph") {
+            self.directed = true;
+        } else if !self.peek_keyword("graph") {
+            return Err(self.error("expected 'graph' or 'digraph'"));
+        }
+        self.pos += 1;
+        if matches!(self.peek(), Some(Tok::Id(_))) {
+            self.pos += 1;
+        }
+        self.expect("{")?;
+        let mut scope = Scope::default();
+        self.stmt_list(&mut scope, true)?;
+        if self.pos < self.tokens.len() {
+            return Err(self.error("unexpected content after graph"));
+        }
+        Ok(())
+    }
+
+    /// Parses statements up to the closing brace and returns every node mentioned.
+    fn stmt_list(&mut self, scope: &mut Scope, root: bool) -> Result<Vec<usize>, String> {
+        let mut members = Vec::new();
+        loop {
+            if self.eat("}") {
+                return Ok(members);
+            }
+            if self.peek().is_none() {
+                return Err(self.error("missing '}'"));
+            }
+            if self.eat(";") {
+                continue;
+            }
+            if self.peek_keyword("graph") || self.peek_keyword("node") || self.peek_keyword("edge")
+            {
+                let kind = self.id()?;
+                let attrs = self.attr_lists()?;
+                match kind.to_ascii_lowercase().as_str() {
+                    "node" => scope.node_attrs.extend(attrs),
+                    "edge" => scope.edge_attrs.extend(attrs),
+                    _ => self.graph_attrs(&attrs, root),
+                }
+                continue;
+            }
+            if let Some(Tok::Id(_)) = self.peek()
+                && matches!(self.tokens.get(self.pos + 1).map(|t| &t.tok), Some(Tok::Punct("=")))
+            {
+                let key = self.id()?;
+                self.expect("=")?;
+                let value = self.id()?;
+                self.graph_attrs(&[(key, value)], root);
+                continue;
+            }
+            members.extend(self.edge_or_node_stmt(scope)?);
+        }
+    }
+
+    fn graph_attrs(&mut self, attrs: &[(String, String)], root: bool) {
+        if !root {
+            return;
+        }
+        for (key, value) in attrs {
+            if key == "rankdir" {
+                self.direction = match value.to_ascii_uppercase().as_str() {
+                    "LR" => Direction::LeftRight,
+                    "RL" => Direction::RightLeft,
+                    "BT" => Direction::BottomTop,
+                    _ => Direction::TopBottom,
+                };
+            } else if key == "splines" {
+                self.splines = match value.as_str() {
+                    "ortho" => model::LineRoute::Orthogonal {
+                        bends: Vec::new(),
+                        offsets: Vec::new(),
+                    },
+                    "curved" | "spline" | "true" => model::LineRoute::Curved {
+                        c1: model::Point::default(),
+                        c2: model::Point::default(),
+                    },
+                    _ => model::LineRoute::Straight,
+                };
+            }
+        }
+    }
+
+    fn attr_lists(&mut self) -> Result<Attrs, String> {
+        let mut attrs = Vec::new();
+        while self.eat("[") {
+            loop {
+                if self.eat("]") {
+                    break;
+                }
+                let key = self.id()?;
+                let value = if self.eat("=") {
+                    self.id()?
+                } else {
+                    "true".to_string()
+                };
+                attrs.push((key, value));
+                let _ = self.eat(",") || self.eat(";");
+            }
+        }
+        Ok(attrs)
+    }
+
+    fn subgraph(&mut self, scope: &Scope) -> Result<Vec<usize>, String> {
+        let mut name = String::new();
+        if self.peek_keyword("subgraph") {
+            self.pos += 1;
+            if let Some(Tok::Id(_)) = self.peek() {
+                name = self.id()?;
+            }
+        }
+        self.expect("{")?;
+        let mut inner = scope.clone();
+        if name.starts_with("cluster") {
+            inner.group_id = Some(self.graph.add_group(scope.group_id));
+        }
+        self.stmt_list(&mut inner, false)
+    }
+
+    fn endpoint(&mut self, scope: &Scope) -> Result<Vec<usize>, String> {
+        if self.peek_keyword("subgraph") || matches!(self.peek(), Some(Tok::Punct("{"))) {
+            return self.subgraph(scope);
+        }
+        let name = self.id()?;
+        // Ports (`node:port:compass`) do not map onto anything and are dropped.
+        while self.eat(":") {
+            self.id()?;
+        }
+        Ok(vec![self.node_for_name(&name, scope)])
+    }
+
+    fn node_for_name(&mut self, name: &str, scope: &Scope) -> usize {
+        if let Some(&idx) = self.node_index.get(name) {
+            // A node mentioned again inside a nested cluster moves into it.
+            if let Some(g) = scope.group_id {
+                let current = self.graph.nodes[idx].group_id;
+                if current.is_none_or(|c| self.graph.is_ancestor(c, g)) {
+                    self.graph.nodes[idx].group_id = Some(g);
+                }
+            }
+            return idx;
+        }
+        let mut node = Node::new(name);
+        node.kind = ShapeKind::Ellipse;
+        node.group_id = scope.group_id;
+        apply_node_attrs(&mut node, name, &scope.node_attrs);
+        node.fit_label();
+        let idx = self.graph.nodes.len();
+        self.graph.nodes.push(node);
+        self.node_index.insert(name.to_string(), idx);
+        idx
+    }
+
+    fn edge_or_node_stmt(&mut self, scope: &Scope) -> Result<Vec<usize>, String> {
+        let is_node = matches!(self.peek(), Some(Tok::Id(id)) if !id.eq_ignore_ascii_case("subgraph"));
+        let first_name = if is_node {
+            match self.peek() {
+                Some(Tok::Id(id)) => Some(id.clone()),
+                _ => None,
+            }
+        } else {
+            None
+        };
+        let mut chain = vec![self.endpoint(scope)?];
+        let mut ops = Vec::new();
+        while let Some(Tok::Punct(op @ ("->" | "--"))) = self.peek() {
+            ops.push(*op);
+            self.pos += 1;
+            chain.push(self.endpoint(scope)?);
+        }
+        let attrs = self.attr_lists()?;
+        let members: Vec<usize> = chain.iter().flatten().copied().collect();
+        if ops.is_empty() {
+            if let (Some(name), [idx]) = (first_name, chain[0].as_slice()) {
+                let node = &mut self.graph.nodes[*idx];
+                apply_node_attrs(node, &name, &attrs);
+                node.fit_label();
+            }
+            return Ok(members);
+        }
+
+        let mut all_attrs = scope.edge_attrs.clone();
+        all_attrs.extend(attrs);
+        let mut style = edge_style();
+        let mut dir = if self.directed { "forward" } else { "none" }.to_string();
+        let mut label = String::new();
+        let mut head = true;
+        let mut tail = true;
+        let mut heads = model::ArrowHeads::default();
+        for (key, value) in &all_attrs {
+            match key.as_str() {
+                "dir" => dir = value.to_ascii_lowercase(),
+                "label" => label = unescape_label(value, ""),
+                "arrowhead" => {
+                    head = value != "none";
+                    heads.end = arrow_head(value);
+                }
+                "arrowtail" => {
+                    tail = value != "none";
+                    heads.start = arrow_head(value);
+                }
+                "arrowsize" => {
+                    if let Ok(s) = value.parse::<f32>() {
+                        heads.scale = s.max(0.1);
+                    }
+                }
+                "style" => apply_line_style(&mut style, value),
+                "color" => {
+                    if let Some(c) = color(value) {
+                        style.stroke.color = c;
+                    }
+                }
+                "penwidth" => {
+                    if let Ok(w) = value.parse::<f32>() {
+                        style.stroke.width = w.max(0.0);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let (start, end) = match dir.as_str() {
+            "forward" => (false, head),
+            "back" => (tail, false),
+            "both" => (tail, head),
+            _ => (false, false),
+        };
+        let arrow_style = match (start, end) {
+            (false, false) => model::ArrowStyle::None,
+            (false, true) => model::ArrowStyle::End,
+            (true, false) => model::ArrowStyle::Start,
+            (true, true) => model::ArrowStyle::Both,
+        };
+        for pair in chain.windows(2) {
+            for &from in &pair[0] {
+                for &to in &pair[1] {
+                    let group_id = self.graph.nodes[from].group_id;
+                    let shared = group_id == self.graph.nodes[to].group_id;
+                    self.graph.edges.push(Edge {
+                        from,
+                        to,
+                        arrow_style,
+                        heads,
+                        style: style.clone(),
+                        group_id: if shared { group_id } else { None },
+                        label: label.clone(),
+                        from_port: None,
+                        to_port: None,
+                        route: model::LineRoute::Straight,
+                    });
+                }
+            }
+        }
+        Ok(members)
+    }
+}
+
+/// Closest arrowhead to a Graphviz arrow shape, ignoring modifiers other
+/// than the `o` that makes diamonds hollow.
+fn arrow_head(value: &str) -> model::ArrowHead {
+    match value.trim_start_matches(['l', 'r']) {
+        v if v.contains("crow") => model::ArrowHead::CrowsFoot,
+        "vee" | "ovee" | "open" => model::ArrowHead::Open,
+        "diamond" => model::ArrowHead::Diamond,
+        "odiamond" | "ediamond" => model::ArrowHead::HollowDiamond,
+        "dot" | "odot" => model::ArrowHead::Circle,
+        "tee" | "otee" => model::ArrowHead::Bar,
+        _ => model::ArrowHead::Triangle,
+    }
+}
+
+fn arrow_head_name(head: model::ArrowHead) -> &'static str {
+    match head {
+        model::ArrowHead::Triangle => "normal",
+        model::ArrowHead::Open => "vee",
+        model::ArrowHead::Diamond => "diamond",
+        model::ArrowHead::HollowDiamond => "odiamond",
+        model::ArrowHead::Circle => "dot",
+        model::ArrowHead::Bar => "tee",
+        model::ArrowHead::CrowsFoot => "crow",
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+fn dot_color(c: model::Rgba) -> String {
+    if c.a == 255 {
+        format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+    } else {
+        format!("#{:02x}{:02x}{:02x}{:02x}", c.r, c.g, c.b, c.a)
+    }
+}
+
+fn node_attrs(element: &model::Element) -> Option<String> {
+    let (shape, label) = match &element.kind {
+        model::ElementKind::Rect { label, .. } => ("box", label),
+        model::ElementKind::Ellipse { label, .. } => ("ellipse", label),
+        model::ElementKind::Triangle { label, .. } => ("triangle", label),
+        model::ElementKind::Parallelogram { label, .. } => ("parallelogram", label),
+        model::ElementKind::Trapezoid { label, .. } => ("trapezium", label),
+        model::ElementKind::Diamond { label, .. } => ("diamond", label),
+        model::ElementKind::Hexagon { label, .. } => ("hexagon", label),
+        model::ElementKind::Cylinder { label, .. } => ("cylinder", label),
+        model::ElementKind::Polygon { label, .. } => ("polygon", label),
+        _ => return None,
+    };
+    let mut attrs = format!("label=\"{}\", shape={}", escape_dot(label), shape);
+    if let model::ElementKind::Polygon { sides, .. } = &element.kind {
+        let _ = write!(attrs, ", sides={sides}");
+    }
+    let style = &element.style;
+    if style.stroke.color.a > 0 {
+        let _ = write!(attrs, ", color=\"{}\"", dot_color(style.stroke.color));
+    }
+    if let Some(fill) = style.fill
+        && fill.a > 0
+    {
+        let _ = write!(attrs, ", style=filled, fillcolor=\"{}\"", dot_color(fill));
+    }
+    Some(attrs)
+}
+
+fn write_cluster(
+    out: &mut String,
+    doc: &model::Document,
+    group_id: u64,
+    nodes_by_group: &HashMap<u64, Vec<String>>,
+    populated: &HashSet<u64>,
+    depth: usize,
+) {
+    let indent = "  ".repeat(depth);
+    let _ = writeln!(out, "{indent}subgraph cluster_{group_id} {{");
+    for line in nodes_by_group.get(&group_id).into_iter().flatten() {
+        let _ = writeln!(out, "{indent}  {line}");
+    }
+    for g in &doc.groups {
+        if g.parent_id == Some(group_id) && populated.contains(&g.id) {
+            write_cluster(out, doc, g.id, nodes_by_group, populated, depth + 1);
+        }
+    }
+    let _ = writeln!(out, "{indent}}}");
+}
+
+pub fn document_to_dot(doc: &model::Document, name: &str) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "digraph \"{}\" {{", escape_dot(name));
+    let mut node_ids = HashSet::new();
+    let mut top_level = Vec::new();
+    let mut nodes_by_group: HashMap<u64, Vec<String>> = HashMap::new();
+    for e in &doc.elements {
+        let Some(attrs) = node_attrs(e) else {
+            continue;
+        };
+        node_ids.insert(e.id);
+        let line = format!("n{} [{}];", e.id, attrs);
+        match e.group_id {
+            Some(g) if doc.groups.iter().any(|x| x.id == g) => {
+                nodes_by_group.entry(g).or_default().push(line)
+            }
+            _ => top_level.push(line),
+        }
+    }
+
+    // A cluster is written when it or one of its subgroups holds a node.
+    let mut populated = HashSet::new();
+    for &g in nodes_by_group.keys() {
+        let mut current = Some(g);
+        while let Some(id) = current {
+            if !populated.insert(id) {
+                break;
+            }
+            current = doc.groups.iter().find(|x| x.id == id).and_then(|x| x.parent_id);
+        }
+    }
+    for line in &top_level {
+        let _ = writeln!(out, "  {line}");
+    }
+    for g in &doc.groups {
+        let is_root = g
+            .parent_id
+            .is_none_or(|p| !doc.groups.iter().any(|x| x.id == p));
+        if is_root && populated.contains(&g.id) {
+            write_cluster(&mut out, doc, g.id, &nodes_by_group, &populated, 1);
+        }
+    }
+
+    for e in &doc.elements {
+        let (arrow, arrow_style, start, end, heads, label) = match &e.kind {
+            model::ElementKind::Line {
+                arrow,
+                arrow_style,
+                start_binding: Some(start),
+                end_binding: Some(end),
+                heads,
+                label,
+                ..
+            } => (*arrow, arrow_style, start, end, heads, label.as_str()),
+            model::ElementKind::Polyline {
+                arrow_style,
+                start_binding: Some(start),
+                end_binding: Some(end),
+                heads,
+                ..
+            } => (false, arrow_style, start, end, heads, ""),
+            _ => continue,
+        };
+        if !node_ids.contains(&start.element_id) || !node_ids.contains(&end.element_id) {
+            continue;
+        }
+        let has_end = arrow || matches!(arrow_style, model::ArrowStyle::End | model::ArrowStyle::Both);
+        let has_start = matches!(arrow_style, model::ArrowStyle::Start | model::ArrowStyle::Both);
+        let dir = match (has_start, has_end) {
+            (false, false) => "none",
+            (false, true) => "forward",
+            (true, false) => "back",
+            (true, true) => "both",
+        };
+        let mut attrs = format!("dir={dir}");
+        match e.style.stroke.line_style {
+            model::LineStyle::Solid => {}
+            model::LineStyle::Dashed => attrs.push_str(", style=dashed"),
+            model::LineStyle::Dotted => attrs.push_str(", style=dotted"),
+        }
+        if has_end && heads.end != model::ArrowHead::Triangle {
+            let _ = write!(attrs, ", arrowhead={}", arrow_head_name(heads.end));
+        }
+        if has_start && heads.start != model::ArrowHead::Triangle {
+            let _ = write!(attrs, ", arrowtail={}", arrow_head_name(heads.start));
+        }
+        if heads.scale != 1.0 {
+            let _ = write!(attrs, ", arrowsize={}", heads.scale);
+        }
+        if !label.is_empty() {
+            let _ = write!(attrs, ", label=\"{}\"", escape_dot(label));
+        }
+        let _ = writeln!(
+            out,
+            "  n{} -> n{} [{}];",
+            start.element_id, end.element_id, attrs
+        );
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(e: &model::Element) -> Option<(String, &'static str)> {
+        let (label, kind) = match &e.kind {
+            model::ElementKind::Rect { label, .. } => (label, "rect"),
+            model::ElementKind::Ellipse { label, .. } => (label, "ellipse"),
+            model::ElementKind::Diamond { label, .. } => (label, "diamond"),
+            model::ElementKind::Cylinder { label, .. } => (label, "cylinder"),
+            model::ElementKind::Polygon { label, .. } => (label, "polygon"),
+            _ => return None,
+        };
+        Some((label.clone(), kind))
+    }
+
+    /// Shapes and the lines bound between them, sorted so documents can be
+    /// compared regardless of element order and ids.
+    fn summary(doc: &model::Document) -> (Vec<(String, &'static str)>, Vec<String>) {
+        let label_of = |id: u64| {
+            let element = doc.elements.iter().find(|e| e.id == id);
+            element
+                .and_then(shape)
+                .map(|(label, _)| label)
+                .unwrap_or_default()
+        };
+        let mut nodes: Vec<_> = doc.elements.iter().filter_map(shape).collect();
+        nodes.sort();
+        let mut edges: Vec<_> = doc
+            .elements
+            .iter()
+            .filter_map(|e| match &e.kind {
+                model::ElementKind::Line {
+                    start_binding: Some(s),
+                    end_binding: Some(t),
+                    arrow_style,
+                    heads,
+                    label,
+                    ..
+                } => Some(format!(
+                    "{:?} -> {:?} {arrow_style:?} {heads:?} {label:?} {:?}",
+                    label_of(s.element_id),
+                    label_of(t.element_id),
+                    e.style.stroke.line_style
+                )),
+                _ => None,
+            })
+            .collect();
+        edges.sort();
+        (nodes, edges)
+    }
+
+    fn line_kinds(doc: &model::Document) -> Vec<&model::ElementKind> {
+        doc.elements
+            .iter()
+            .map(|e| &e.kind)
+            .filter(|k| matches!(k, model::ElementKind::Line { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn parses_nodes_edges_and_clusters() {
+        let doc = parse(
+            r##"
+            // a comment
+            digraph G {
+                rankdir=LR;
+                node [shape=box];
+                start [shape=ellipse, label="Start\nhere", style=filled, fillcolor="#ffcc00"];
+                db [shape=cylinder];
+                subgraph cluster_work {
+                    a; b [shape=diamond];
+                    a -> b [label="go", style=dashed];
+                }
+                start -> a -> db [arrowhead=vee];
+                /* trailing block comment */
+            }
+            "##,
+        )
+        .unwrap();
+        let (nodes, _) = summary(&doc);
+        assert_eq!(
+            nodes,
+            [
+                ("Start\nhere".to_string(), "ellipse"),
+                ("a".to_string(), "rect"),
+                ("b".to_string(), "diamond"),
+                ("db".to_string(), "cylinder"),
+            ]
+        );
+        assert_eq!(doc.groups.len(), 1);
+        let start = &doc.elements[0];
+        assert_eq!(start.style.fill, model::Rgba::from_hex("#ffcc00"));
+
+        let lines = line_kinds(&doc);
+        assert_eq!(lines.len(), 3);
+        let model::ElementKind::Line { label, .. } = lines[0] else {
+            unreachable!();
+        };
+        assert_eq!(label, "go");
+        let model::ElementKind::Line {
+            arrow_style, heads, ..
+        } = lines[2]
+        else {
+            unreachable!();
+        };
+        assert_eq!(*arrow_style, model::ArrowStyle::End);
+        assert_eq!(heads.end, model::ArrowHead::Open);
+    }
+
+    #[test]
+    fn edge_direction_follows_graph_kind_and_dir() {
+        let doc = parse("graph { a -- b; a -- c [dir=back, arrowtail=odiamond] }").unwrap();
+        let styles: Vec<_> = line_kinds(&doc)
+            .into_iter()
+            .map(|k| match k {
+                model::ElementKind::Line {
+                    arrow_style, heads, ..
+                } => (*arrow_style, heads.start),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            styles,
+            [
+                (model::ArrowStyle::None, model::ArrowHead::Triangle),
+                (model::ArrowStyle::Start, model::ArrowHead::HollowDiamond),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let cases = [
+            (
+                "digraph {\n  a -> b [label=\"x]\n}",
+                "line 2: unterminated string",
+            ),
+            (
+                "digraph {\n  a -> b;\n  c @ d\n}",
+                "line 3: unexpected character '@'",
+            ),
+            ("\n\ntree { a }", "line 3: expected 'graph' or 'digraph'"),
+            ("graph {\n  a -- b\n", "line 2: missing '}'"),
+            ("graph { a }\nb", "line 2: unexpected content after graph"),
+        ];
+        for (src, expected) in cases {
+            assert_eq!(parse(src).unwrap_err(), expected, "for {src:?}");
+        }
+    }
+
+    #[test]
+    fn export_round_trips_through_import() {
+        let doc = crate::dsl::parse(
+            r#"
+            node a rect "Alpha" fill=#ddeeff
+            node b ellipse "Beta \"quoted\""
+            group {
+                node c diamond "Gamma"
+                node d cylinder "Two\nlines"
+            }
+            a -> b "calls" head=open
+            b <-> c line=dashed tail=diamond
+            c -- d
+            "#,
+        )
+        .unwrap();
+        let dot = document_to_dot(&doc, "round trip");
+        let back = parse(&dot).unwrap();
+        assert_eq!(summary(&doc).1.len(), 3);
+        assert_eq!(summary(&back), summary(&doc), "{dot}");
+        assert_eq!(back.groups.len(), 1);
+    }
+
+    #[test]
+    fn exports_bound_polylines_as_edges() {
+        let mut doc = crate::dsl::parse("a -> b").unwrap();
+        let line = doc.elements.pop().unwrap();
+        let model::ElementKind::Line {
+            a,
+            b,
+            arrow_style,
+            heads,
+            start_binding,
+            end_binding,
+            ..
+        } = line.kind
+        else {
+            unreachable!();
+        };
+        doc.elements.push(model::Element {
+            kind: model::ElementKind::Polyline {
+                points: vec![a, model::Point { x: a.x, y: b.y }, b],
+                arrow_style,
+                heads,
+                start_binding,
+                end_binding,
+            },
+            ..line
+        });
+        let dot = document_to_dot(&doc, "poly");
+        assert!(dot.contains("n1 -> n2 [dir=forward];"), "{dot}");
+    }
+}
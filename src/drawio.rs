@@ -0,0 +1,1624 @@
+//! draw.io / diagrams.net (`.drawio`) import and export.
+//!
+//! Vertices map onto the element kind named by their style (`ellipse`,
+//! `triangle`, `rhombus`, `shape=parallelogram`, `shape=trapezoid`,
+//! `shape=hexagon`, `shape=cylinder3`, `text`; anything else becomes a rect),
+//! edges with a `source`/`target` become bound lines (orthogonal connectors
+//! for `orthogonalEdgeStyle`) carrying the edge's label, and other edges with
+//! waypoints polylines, bound at their ends too, grouped with their label as
+//! free text. Cells nested under another vertex become a group together with
+//! their container. Compressed diagrams are inflated before parsing; exports
+//! are written uncompressed.
+
+use crate::geometry::{
+    binding_norm, compute_binding_for_target, cubic_bezier_point, element_center_world,
+    facing_bindings, facing_side_bindings, line_curve_world, line_points_world, path_midpoint,
+    polyline_points_world, resolve_binding_point, resolved_line_endpoints_world,
+    rotated_polygon_points_world,
+};
+use crate::model;
+use crate::xml::{self, XmlElement, escape_xml};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+const DEFAULT_FONT_SIZE: f32 = 12.0;
+const LINE_HEIGHT: f32 = 1.2;
+
+struct Style {
+    /// Bare names such as `ellipse` or `text`, plus every `key=value` pair.
+    names: Vec<String>,
+    values: HashMap<String, String>,
+}
+
+impl Style {
+    fn parse(src: &str) -> Self {
+        let mut names = Vec::new();
+        let mut values = HashMap::new();
+        for part in src.split(';').map(str::trim).filter(|p| !p.is_empty()) {
+            match part.split_once('=') {
+                Some((k, v)) => {
+                    values.insert(k.trim().to_string(), v.trim().to_string());
+                }
+                None => names.push(part.to_string()),
+            }
+        }
+        Self { names, values }
+    }
+
+    fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    fn number(&self, key: &str) -> Option<f32> {
+        self.get(key).and_then(|v| v.parse().ok())
+    }
+
+    fn flag(&self, key: &str) -> bool {
+        self.get(key) == Some("1")
+    }
+
+    /// The arrowhead at the `start` or `end` of an edge.
+    fn arrow_head(&self, end: &str) -> model::ArrowHead {
+        let hollow = self.get(&format!("{end}Fill")) == Some("0");
+        match self.get(&format!("{end}Arrow")).unwrap_or("classic") {
+            "open" | "openThin" => model::ArrowHead::Open,
+            "diamond" | "diamondThin" if hollow => model::ArrowHead::HollowDiamond,
+            "diamond" | "diamondThin" => model::ArrowHead::Diamond,
+            "oval" => model::ArrowHead::Circle,
+            "dash" | "ERone" | "ERmandOne" => model::ArrowHead::Bar,
+            "ERmany" | "ERoneToMany" | "ERzeroToMany" => model::ArrowHead::CrowsFoot,
+            _ => model::ArrowHead::Triangle,
+        }
+    }
+
+    /// The shape name: `shape=...` wins over a leading bare style name.
+    fn shape(&self) -> &str {
+        self.get("shape")
+            .or_else(|| self.names.first().map(String::as_str))
+            .unwrap_or("")
+    }
+
+    fn opacity(&self, key: &str) -> f32 {
+        let overall = self.number("opacity").unwrap_or(100.0);
+        let own = self.number(key).unwrap_or(100.0);
+        (overall / 100.0 * own / 100.0).clamp(0.0, 1.0)
+    }
+
+    /// `None` for `none`, the default for a missing key.
+    fn color(&self, key: &str, default: model::Rgba, opacity: f32) -> Option<model::Rgba> {
+        let mut c = match self.get(key) {
+            Some("none") => return None,
+            Some(v) => model::Rgba::parse(v).unwrap_or(default),
+            None => default,
+        };
+        c.a = (c.a as f32 * opacity).round() as u8;
+        Some(c)
+    }
+
+    fn model_style(&self, vertex: bool) -> model::Style {
+        let black = model::Rgba {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 255,
+        };
+        let white = model::Rgba {
+            r: 255,
+            g: 255,
+            b: 255,
+            a: 255,
+        };
+        let mut style = model::Style::default_for_shapes();
+        match self.color("strokeColor", black, self.opacity("strokeOpacity")) {
+            Some(c) => style.stroke.color = c,
+            None => style.stroke.color.a = 0,
+        }
+        if let Some(c) = self.color("fillColor", white, self.opacity("fillOpacity")) {
+            style.fill = Some(c);
+        }
+        if !vertex {
+            style.fill = None;
+        }
+        style.stroke.width = self.number("strokeWidth").unwrap_or(1.0);
+        if self.flag("dashed") {
+            let dash = self
+                .get("dashPattern")
+                .and_then(|p| p.split_whitespace().next()?.parse::<f32>().ok());
+            style.stroke.line_style = match dash {
+                Some(d) if d <= 1.0 => model::LineStyle::Dotted,
+                _ => model::LineStyle::Dashed,
+            };
+        }
+        if let Some(c) = self.color("fontColor", black, self.opacity("textOpacity")) {
+            style.text_color = c;
+        }
+        style.text_size = self
+            .number("fontSize")
+            .filter(|size| size.is_finite() && *size > 0.0)
+            .unwrap_or(DEFAULT_FONT_SIZE);
+        style.text_align = match self.get("align") {
+            Some("left") => model::TextAlign::Left,
+            Some("right") => model::TextAlign::Right,
+            _ => model::TextAlign::Center,
+        };
+        if let Some(family) = self.get("fontFamily") {
+            let lower = family.to_ascii_lowercase();
+            if lower.contains("courier") || lower.contains("mono") {
+                style.font_family = model::FontFamily::Monospace;
+            }
+        }
+        style
+    }
+}
+
+struct Geometry {
+    rect: egui::Rect,
+    source: Option<egui::Pos2>,
+    target: Option<egui::Pos2>,
+    points: Vec<egui::Pos2>,
+    offset: egui::Vec2,
+}
+
+struct Cell {
+    id: String,
+    value: String,
+    style: Style,
+    vertex: bool,
+    edge: bool,
+    parent: Option<String>,
+    source: Option<String>,
+    target: Option<String>,
+    geometry: Option<Geometry>,
+}
+
+fn attr_number(element: &XmlElement, key: &str) -> f32 {
+    element
+        .attr(key)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0.0)
+}
+
+fn point(element: &XmlElement) -> egui::Pos2 {
+    egui::pos2(attr_number(element, "x"), attr_number(element, "y"))
+}
+
+fn geometry(element: &XmlElement) -> Geometry {
+    let min = point(element);
+    let size = egui::vec2(
+        attr_number(element, "width"),
+        attr_number(element, "height"),
+    );
+    let mut geometry = Geometry {
+        rect: egui::Rect::from_min_size(min, size),
+        source: None,
+        target: None,
+        points: Vec::new(),
+        offset: egui::Vec2::ZERO,
+    };
+    for child in element.elements() {
+        match (child.local_name(), child.attr("as")) {
+            ("mxPoint", Some("sourcePoint")) => geometry.source = Some(point(child)),
+            ("mxPoint", Some("targetPoint")) => geometry.target = Some(point(child)),
+            ("mxPoint", Some("offset")) => geometry.offset = point(child).to_vec2(),
+            ("Array", Some("points")) => {
+                geometry.points = child.elements().map(point).collect();
+            }
+            _ => {}
+        }
+    }
+    geometry
+}
+
+fn cell(element: &XmlElement) -> Option<Cell> {
+    // `UserObject`/`object` wrappers carry the id and label of their mxCell.
+    let (wrapper, inner) = match element.local_name() {
+        "mxCell" => (None, element),
+        "UserObject" | "object" => (Some(element), element.child("mxCell")?),
+        _ => return None,
+    };
+    let outer = wrapper.unwrap_or(inner);
+    let raw = wrapper
+        .and_then(|w| w.attr("label"))
+        .or_else(|| inner.attr("value"))
+        .unwrap_or("");
+    let style = Style::parse(inner.attr("style").unwrap_or(""));
+    let value = if style.flag("html") {
+        html_to_text(raw)
+    } else {
+        raw.to_string()
+    };
+    Some(Cell {
+        id: outer.attr("id")?.to_string(),
+        value,
+        style,
+        vertex: inner.attr("vertex") == Some("1"),
+        edge: inner.attr("edge") == Some("1"),
+        parent: inner.attr("parent").map(str::to_string),
+        source: inner.attr("source").map(str::to_string),
+        target: inner.attr("target").map(str::to_string),
+        geometry: inner.child("mxGeometry").map(geometry),
+    })
+}
+
+fn html_to_text(html: &str) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        out.push_str(&rest[..open]);
+        let Some(close) = rest[open..].find('>') else {
+            break;
+        };
+        let tag = rest[open + 1..open + close]
+            .trim_start_matches('/')
+            .to_ascii_lowercase();
+        let name = tag.split(|c: char| !c.is_ascii_alphanumeric()).next();
+        if matches!(name, Some("br" | "div" | "p" | "li" | "tr"))
+            && !out.is_empty()
+            && !out.ends_with('\n')
+        {
+            out.push('\n');
+        }
+        rest = &rest[open + close + 1..];
+    }
+    out.push_str(rest);
+    xml::unescape_xml(&out.replace("&nbsp;", " "))
+        .lines()
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+fn base64_decode(src: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(src.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in src.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            b'=' => break,
+            c if c.is_ascii_whitespace() => continue,
+            _ => return Err("invalid base64 in compressed diagram".to_string()),
+        };
+        acc = (acc << 6) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((acc >> bits) as u8);
+        }
+    }
+    Ok(out)
+}
+
+fn percent_decode(src: &str) -> String {
+    let bytes = src.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(hex) = src.get(i + 1..i + 3)
+            && let Ok(b) = u8::from_str_radix(hex, 16)
+        {
+            out.push(b);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Compressed diagrams are base64 of raw-deflated, URI-encoded XML.
+fn inflate_diagram(text: &str) -> Result<XmlElement, String> {
+    let bytes = base64_decode(text.trim())?;
+    let inflated = miniz_oxide::inflate::decompress_to_vec(&bytes)
+        .map_err(|e| format!("cannot inflate compressed diagram: {e}"))?;
+    xml::parse(&percent_decode(&String::from_utf8_lossy(&inflated)))
+}
+
+/// The first page's `<mxGraphModel>`, inflating it if it is compressed.
+fn graph_model(root: XmlElement) -> Result<XmlElement, String> {
+    let name = root.local_name().to_string();
+    match name.as_str() {
+        "mxGraphModel" => Ok(root),
+        "mxfile" => graph_model(
+            root.into_child("diagram")
+                .ok_or("draw.io file has no diagram")?,
+        ),
+        "diagram" => {
+            let text = root.text();
+            match root.into_child("mxGraphModel") {
+                Some(model) => Ok(model),
+                None => graph_model(inflate_diagram(&text)?),
+            }
+        }
+        other => Err(format!(
+            "expected <mxfile> or <mxGraphModel>, found <{other}>"
+        )),
+    }
+}
+
+struct Importer {
+    cells: Vec<Cell>,
+    index: HashMap<String, usize>,
+    doc: model::Document,
+    next_id: u64,
+    element_ids: HashMap<String, u64>,
+    group_ids: HashMap<String, u64>,
+}
+
+impl Importer {
+    fn cell(&self, id: &str) -> Option<&Cell> {
+        self.index.get(id).map(|i| &self.cells[*i])
+    }
+
+    fn is_container(&self, id: &str) -> bool {
+        self.cell(id).is_some_and(|c| c.vertex)
+    }
+
+    /// Offset of a cell's child coordinates: the sum of its containers' origins.
+    fn origin(&self, parent: Option<&str>) -> egui::Vec2 {
+        let mut offset = egui::Vec2::ZERO;
+        let mut current = parent;
+        let mut depth = 0;
+        while let Some(cell) = current.and_then(|id| self.cell(id)) {
+            if !cell.vertex || depth > self.cells.len() {
+                break;
+            }
+            if let Some(g) = &cell.geometry {
+                offset += g.rect.min.to_vec2();
+            }
+            current = cell.parent.as_deref();
+            depth += 1;
+        }
+        offset
+    }
+
+    /// The group for the children of container `id`, created on first use
+    /// together with those of the containers around it.
+    fn group_for(&mut self, id: &str) -> Option<u64> {
+        // Containers from `id` outward that have no group yet. Cells naming
+        // each other as parent never reach the top, so give up past the
+        // number of cells, as `origin` does.
+        let mut pending = Vec::new();
+        let mut group = None;
+        let mut current = Some(id.to_string());
+        while let Some(cell) = current.filter(|c| self.is_container(c)) {
+            if let Some(existing) = self.group_ids.get(&cell) {
+                group = Some(*existing);
+                break;
+            }
+            if pending.len() > self.cells.len() {
+                return None;
+            }
+            current = self.cell(&cell).and_then(|c| c.parent.clone());
+            pending.push(cell);
+        }
+        for cell in pending.into_iter().rev() {
+            let id = self.next_id();
+            self.doc.groups.push(model::Group {
+                id,
+                parent_id: group,
+            });
+            self.group_ids.insert(cell, id);
+            group = Some(id);
+        }
+        group
+    }
+
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn push(
+        &mut self,
+        kind: model::ElementKind,
+        style: model::Style,
+        rotation: f32,
+        group: Option<u64>,
+    ) -> u64 {
+        let id = self.next_id();
+        self.doc.elements.push(model::Element {
+            id,
+            group_id: group,
+            rotation,
+            snap_enabled: true,
+            kind,
+            style,
+            ports: Vec::new(),
+        });
+        id
+    }
+
+    fn vertex(&mut self, index: usize) {
+        let cell = &self.cells[index];
+        let Some(geometry) = &cell.geometry else {
+            return;
+        };
+        let shape = cell.style.shape().to_string();
+        if shape == "group"
+            || self
+                .cell(cell.parent.as_deref().unwrap_or(""))
+                .is_some_and(|p| p.edge)
+        {
+            return;
+        }
+        let rect = geometry.rect.translate(self.origin(cell.parent.as_deref()));
+        let mut style = cell.style.model_style(true);
+        let mut rotation = cell.style.number("rotation").unwrap_or(0.0).to_radians();
+        let label = cell.value.clone();
+        let id = cell.id.clone();
+        let parent = cell.parent.clone();
+        let has_children = self.cells.iter().any(|c| c.parent.as_deref() == Some(&id));
+        // A container moves with its children, so it joins their group.
+        let group = if has_children {
+            self.group_for(&id)
+        } else {
+            parent.and_then(|p| self.group_for(&p))
+        };
+        let cell = &self.cells[index];
+        let rect_f = model::RectF::from_min_max(rect.min, rect.max);
+        let kind = match shape.as_str() {
+            "text" | "edgeLabel" => {
+                let lines = label.lines().count().max(1) as f32;
+                let height = lines * style.text_size * LINE_HEIGHT;
+                let x = match style.text_align {
+                    model::TextAlign::Left => rect.min.x,
+                    model::TextAlign::Center => rect.center().x,
+                    model::TextAlign::Right => rect.max.x,
+                };
+                let y = match cell.style.get("verticalAlign") {
+                    Some("top") => rect.min.y,
+                    Some("bottom") => rect.max.y - height,
+                    _ => rect.center().y - height * 0.5,
+                };
+                style.fill = None;
+                style.stroke.color.a = 0;
+                model::ElementKind::Text {
+                    pos: model::Point { x, y },
+                    text: label,
+                }
+            }
+            "rhombus" => model::ElementKind::Diamond {
+                rect: rect_f,
+                label,
+            },
+            "hexagon" => {
+                let size = cell.style.number("size").unwrap_or(0.25);
+                let inset = if cell.style.flag("fixedSize") {
+                    size / rect.width().max(1.0)
+                } else {
+                    size
+                };
+                model::ElementKind::Hexagon {
+                    rect: rect_f,
+                    label,
+                    inset_ratio: inset * 2.0,
+                }
+            }
+            "cylinder" | "cylinder3" => {
+                // `size` is the cap's vertical radius; legacy cylinders derive it.
+                let ry = match cell.style.shape() {
+                    "cylinder3" => cell.style.number("size").unwrap_or(15.0),
+                    _ => (rect.height() / 5.0).min(40.0),
+                };
+                model::ElementKind::Cylinder {
+                    rect: rect_f,
+                    label,
+                    cap_ratio: ry * 2.0 / rect.height().max(1.0),
+                }
+            }
+            "mxgraph.basic.polygon" if let Some(sides) = regular_polygon_sides(&cell.style) => {
+                model::ElementKind::Polygon {
+                    rect: rect_f,
+                    label,
+                    sides,
+                }
+            }
+            "ellipse" | "doubleEllipse" => model::ElementKind::Ellipse {
+                rect: rect_f,
+                label,
+            },
+            "triangle" => {
+                // draw.io triangles point east unless told otherwise.
+                let turn = match cell.style.get("direction") {
+                    Some("north") => 0.0,
+                    Some("south") => 180.0,
+                    Some("west") => -90.0,
+                    _ => 90.0,
+                };
+                let mut rect_f = rect_f;
+                if turn == 90.0 || turn == -90.0 {
+                    let half = egui::vec2(rect.height(), rect.width()) * 0.5;
+                    rect_f = model::RectF::from_min_max(rect.center() - half, rect.center() + half);
+                }
+                rotation += f32::to_radians(turn);
+                model::ElementKind::Triangle {
+                    rect: rect_f,
+                    label,
+                    apex_ratio: 0.0,
+                }
+            }
+            "parallelogram" => {
+                let size = cell.style.number("size").unwrap_or(0.2);
+                let skew_ratio = if cell.style.flag("fixedSize") {
+                    size / rect.width().max(1.0)
+                } else {
+                    size
+                };
+                model::ElementKind::Parallelogram {
+                    rect: rect_f,
+                    label,
+                    skew_ratio,
+                }
+            }
+            "trapezoid" => {
+                let size = cell.style.number("size").unwrap_or(0.2);
+                let inset = if cell.style.flag("fixedSize") {
+                    size / rect.width().max(1.0)
+                } else {
+                    size
+                };
+                model::ElementKind::Trapezoid {
+                    rect: rect_f,
+                    label,
+                    top_inset_ratio: inset * 2.0,
+                }
+            }
+            _ => {
+                let corner_radius = if !cell.style.flag("rounded") {
+                    0.0
+                } else if cell.style.flag("absoluteArcSize") {
+                    cell.style.number("arcSize").unwrap_or(20.0) * 0.5
+                } else {
+                    let percent = cell.style.number("arcSize").unwrap_or(15.0);
+                    rect.width().min(rect.height()) * percent / 100.0
+                };
+                model::ElementKind::Rect {
+                    rect: rect_f,
+                    label,
+                    corner_radius,
+                }
+            }
+        };
+        if !rect_f.is_valid() && !matches!(kind, model::ElementKind::Text { .. }) {
+            return;
+        }
+        let element_id = self.push(kind, style, rotation, group);
+        self.element_ids.insert(id, element_id);
+    }
+
+    fn binding_toward(&self, cell: Option<&str>, toward: egui::Pos2) -> Option<model::Binding> {
+        let target = *self.element_ids.get(cell?)?;
+        let center = element_center_world(&self.doc, target)?;
+        let mut dir = toward - center;
+        if dir.length() <= f32::EPSILON {
+            dir = egui::vec2(1.0, 0.0);
+        }
+        compute_binding_for_target(&self.doc, target, center + dir.normalized() * 1_000_000.0)
+    }
+
+    /// A binding at the `exitX`/`exitY` (or `entryX`/`entryY`) constraint.
+    fn constrained_binding(
+        &self,
+        cell: Option<&str>,
+        style: &Style,
+        prefix: &str,
+    ) -> Option<model::Binding> {
+        let element_id = *self.element_ids.get(cell?)?;
+        let x = style.number(&format!("{prefix}X"))?;
+        let y = style.number(&format!("{prefix}Y"))?;
+        Some(model::Binding {
+            element_id,
+            norm: model::Point {
+                x: x - 0.5,
+                y: y - 0.5,
+            },
+            port: None,
+        })
+    }
+
+    fn edge(&mut self, index: usize) {
+        let cell = &self.cells[index];
+        let Some(geometry) = &cell.geometry else {
+            return;
+        };
+        let offset = self.origin(cell.parent.as_deref());
+        let mut style = cell.style.model_style(false);
+        // draw.io sets jumps per edge; any of them turns them on for the
+        // whole diagram.
+        if cell.style.get("jumpStyle").is_some_and(|j| j != "none") {
+            self.doc.line_jumps = true;
+        }
+        let arrow_style = match (
+            cell.style.get("startArrow").is_some_and(|a| a != "none"),
+            cell.style.get("endArrow") != Some("none"),
+        ) {
+            (true, true) => model::ArrowStyle::Both,
+            (true, false) => model::ArrowStyle::Start,
+            (false, true) => model::ArrowStyle::End,
+            (false, false) => model::ArrowStyle::None,
+        };
+        let heads = model::ArrowHeads {
+            start: cell.style.arrow_head("start"),
+            end: cell.style.arrow_head("end"),
+            scale: cell
+                .style
+                .number("endSize")
+                .or(cell.style.number("startSize"))
+                .map_or(1.0, |size| (size / 6.0).max(0.1)),
+        };
+        let (source, target) = (cell.source.as_deref(), cell.target.as_deref());
+        let center = |id: Option<&str>| {
+            let element = *self.element_ids.get(id?)?;
+            element_center_world(&self.doc, element)
+        };
+        let start = center(source).or(geometry.source.map(|p| p + offset));
+        let end = center(target).or(geometry.target.map(|p| p + offset));
+        let (Some(start), Some(end)) = (start, end) else {
+            return;
+        };
+        let waypoints: Vec<egui::Pos2> = geometry.points.iter().map(|p| *p + offset).collect();
+        let mut labels: Vec<String> = Vec::new();
+        if !cell.value.is_empty() {
+            labels.push(cell.value.clone());
+        }
+        let children: Vec<&Cell> = self
+            .cells
+            .iter()
+            .filter(|c| c.parent.as_deref() == Some(&cell.id) && c.vertex && !c.value.is_empty())
+            .collect();
+        labels.extend(children.iter().map(|c| c.value.clone()));
+        // The edge's own label sits at its geometry's relative x (-1 at the
+        // source, 1 at the target), as do `edgeLabel` children.
+        let label_cell = if cell.value.is_empty() {
+            children.first().copied()
+        } else {
+            Some(cell)
+        };
+        let (label_position, label_offset, label_fill) = match label_cell {
+            Some(c) => {
+                let (x, offset) = c
+                    .geometry
+                    .as_ref()
+                    .map_or((0.0, egui::Vec2::ZERO), |g| (g.rect.min.x, g.offset));
+                let fill = c.style.get("labelBackgroundColor").and_then(|_| {
+                    c.style
+                        .color("labelBackgroundColor", model::Rgba::default(), 1.0)
+                });
+                (((x + 1.0) * 0.5).clamp(0.0, 1.0), offset, fill)
+            }
+            None => (0.5, egui::Vec2::ZERO, None),
+        };
+        let label_size = style.text_size;
+        let label_color = style.text_color;
+        let group = cell
+            .parent
+            .as_deref()
+            .and_then(|p| self.group_ids.get(p).copied());
+        let cell_style = &self.cells[index].style;
+
+        // Elbow edges are rerouted around the shapes instead of following
+        // draw.io's waypoints.
+        let orthogonal = matches!(
+            cell_style.get("edgeStyle"),
+            Some("orthogonalEdgeStyle" | "elbowEdgeStyle")
+        );
+        let (path, line_id) = if waypoints.is_empty() || orthogonal {
+            let (mut start_binding, mut end_binding) = match (
+                source.and_then(|s| self.element_ids.get(s)),
+                target.and_then(|t| self.element_ids.get(t)),
+            ) {
+                (Some(s), Some(t)) if orthogonal => facing_side_bindings(&self.doc, *s, *t),
+                (Some(s), Some(t)) => facing_bindings(&self.doc, *s, *t),
+                _ => (
+                    self.binding_toward(source, end),
+                    self.binding_toward(target, start),
+                ),
+            };
+            if let Some(b) = self.constrained_binding(source, cell_style, "exit") {
+                start_binding = Some(b);
+            }
+            if let Some(b) = self.constrained_binding(target, cell_style, "entry") {
+                end_binding = Some(b);
+            }
+            let (a, b) = resolved_line_endpoints_world(
+                &self.doc,
+                model::Point::from_pos2(start),
+                model::Point::from_pos2(end),
+                &start_binding,
+                &end_binding,
+            );
+            let route = if orthogonal {
+                model::LineRoute::Orthogonal {
+                    bends: Vec::new(),
+                    offsets: Vec::new(),
+                }
+            } else {
+                model::LineRoute::Straight
+            };
+            style.fill = label_fill;
+            self.push(
+                model::ElementKind::Line {
+                    a: model::Point::from_pos2(a),
+                    b: model::Point::from_pos2(b),
+                    arrow: false,
+                    arrow_style,
+                    heads,
+                    start_binding,
+                    end_binding,
+                    route,
+                    label: labels.join("\n"),
+                    label_position,
+                    label_offset: model::Point::from_pos2(label_offset.to_pos2()),
+                },
+                style,
+                0.0,
+                group,
+            );
+            return;
+        } else {
+            let first = waypoints[0];
+            let last = waypoints[waypoints.len() - 1];
+            let start_binding = self
+                .constrained_binding(source, cell_style, "exit")
+                .or_else(|| self.binding_toward(source, first));
+            let end_binding = self
+                .constrained_binding(target, cell_style, "entry")
+                .or_else(|| self.binding_toward(target, last));
+            let resolve = |binding: &Option<model::Binding>, fallback: egui::Pos2| {
+                binding
+                    .as_ref()
+                    .and_then(|b| resolve_binding_point(&self.doc, b))
+                    .unwrap_or(fallback)
+            };
+            let a = resolve(&start_binding, start);
+            let b = resolve(&end_binding, end);
+            let mut points = vec![a];
+            points.extend(waypoints);
+            points.push(b);
+            let kind = if cell_style.flag("curved") && arrow_style == model::ArrowStyle::None {
+                model::ElementKind::Pen {
+                    points: points.iter().map(|p| model::Point::from_pos2(*p)).collect(),
+                }
+            } else {
+                model::ElementKind::Polyline {
+                    points: points.iter().map(|p| model::Point::from_pos2(*p)).collect(),
+                    arrow_style,
+                    heads,
+                    start_binding,
+                    end_binding,
+                }
+            };
+            let line_id = self.push(kind, style, 0.0, group);
+            (points, line_id)
+        };
+
+        // Polylines have no label of their own, so the label is free text
+        // grouped with the polyline to move along with it.
+        if !labels.is_empty() {
+            let label_group = self.next_id();
+            self.doc.groups.push(model::Group {
+                id: label_group,
+                parent_id: group,
+            });
+            if let Some(line) = self.doc.elements.iter_mut().find(|e| e.id == line_id) {
+                line.group_id = Some(label_group);
+            }
+            let text = labels.join("\n");
+            let mid = path_midpoint(&path);
+            let lines = text.lines().count().max(1) as f32;
+            let mut text_style = model::Style::default_for_shapes();
+            text_style.fill = None;
+            text_style.stroke.color.a = 0;
+            text_style.text_size = label_size;
+            text_style.text_color = label_color;
+            let pos = mid - egui::vec2(0.0, label_size * LINE_HEIGHT * lines + 4.0);
+            self.push(
+                model::ElementKind::Text {
+                    pos: model::Point::from_pos2(pos),
+                    text,
+                },
+                text_style,
+                0.0,
+                Some(label_group),
+            );
+        }
+    }
+}
+
+/// Side count of a `mxgraph.basic.polygon` whose `polyCoords` trace a
+/// regular polygon as exported by [`document_to_drawio`].
+fn regular_polygon_sides(style: &Style) -> Option<u32> {
+    let coords: Vec<[f32; 2]> = serde_json::from_str(style.get("polyCoords")?).ok()?;
+    let sides = u32::try_from(coords.len())
+        .ok()
+        .filter(|n| (3..=24).contains(n))?;
+    let unit = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+    let expected = rotated_polygon_points_world(unit, 0.0, sides);
+    let bounds = polygon_bounds(&expected);
+    coords
+        .iter()
+        .zip(&expected)
+        .all(|([x, y], p)| {
+            let p = (*p - bounds.min) / bounds.size();
+            (p.x - x).abs() < 0.01 && (p.y - y).abs() < 0.01
+        })
+        .then_some(sides)
+}
+
+fn polygon_bounds(points: &[egui::Pos2]) -> egui::Rect {
+    points.iter().fold(egui::Rect::NOTHING, |r, p| {
+        r.union(egui::Rect::from_pos(*p))
+    })
+}
+
+pub fn parse(src: &str) -> Result<model::Document, String> {
+    let model = graph_model(xml::parse(src)?)?;
+    let root = model.child("root").ok_or("mxGraphModel has no <root>")?;
+    let cells: Vec<Cell> = root.elements().filter_map(cell).collect();
+    let index = cells
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.id.clone(), i))
+        .collect();
+    let mut importer = Importer {
+        cells,
+        index,
+        doc: model::Document::default(),
+        next_id: 1,
+        element_ids: HashMap::new(),
+        group_ids: HashMap::new(),
+    };
+    // Vertices first, so edges can bind to them.
+    for i in 0..importer.cells.len() {
+        if importer.cells[i].vertex {
+            importer.vertex(i);
+        }
+    }
+    for i in 0..importer.cells.len() {
+        if importer.cells[i].edge {
+            importer.edge(i);
+        }
+    }
+    if importer.doc.elements.is_empty() {
+        return Err("diagram has no shapes or connectors".to_string());
+    }
+    Ok(importer.doc)
+}
+
+fn num(v: f32) -> String {
+    let r = (v * 1000.0).round() / 1000.0;
+    if r == 0.0 {
+        "0".to_string()
+    } else {
+        r.to_string()
+    }
+}
+
+fn hex(c: model::Rgba) -> String {
+    format!("#{:02X}{:02X}{:02X}", c.r, c.g, c.b)
+}
+
+/// Attribute value escaping; newlines survive as character references.
+fn escape_attr(s: &str) -> String {
+    escape_xml(s).replace('\n', "&#xa;")
+}
+
+fn style_string(element: &model::Element, vertex: bool) -> String {
+    let style = &element.style;
+    let mut out = String::new();
+    let opacity = |c: model::Rgba| (c.a as f32 / 255.0 * 100.0).round();
+    if style.stroke.color.a == 0 {
+        out.push_str("strokeColor=none;");
+    } else {
+        let _ = write!(out, "strokeColor={};", hex(style.stroke.color));
+        if style.stroke.color.a < 255 {
+            let _ = write!(out, "strokeOpacity={};", opacity(style.stroke.color));
+        }
+    }
+    let _ = write!(out, "strokeWidth={};", num(style.stroke.width));
+    match style.stroke.line_style {
+        model::LineStyle::Solid => {}
+        model::LineStyle::Dashed => out.push_str("dashed=1;"),
+        model::LineStyle::Dotted => out.push_str("dashed=1;dashPattern=1 2;"),
+    }
+    if vertex {
+        match style.fill.filter(|c| c.a > 0) {
+            Some(c) => {
+                let _ = write!(out, "fillColor={};", hex(c));
+                if c.a < 255 {
+                    let _ = write!(out, "fillOpacity={};", opacity(c));
+                }
+            }
+            None => out.push_str("fillColor=none;"),
+        }
+    }
+    let _ = write!(
+        out,
+        "fontColor={};fontSize={};align={};",
+        hex(style.text_color),
+        num(style.text_size),
+        match style.text_align {
+            model::TextAlign::Left => "left",
+            model::TextAlign::Center => "center",
+            model::TextAlign::Right => "right",
+        }
+    );
+    if style.font_family == model::FontFamily::Monospace {
+        out.push_str("fontFamily=Courier New;");
+    }
+    if element.rotation != 0.0 {
+        let _ = write!(out, "rotation={};", num(element.rotation.to_degrees()));
+    }
+    out
+}
+
+struct Exporter<'a> {
+    doc: &'a model::Document,
+    out: String,
+    /// World origin of each group cell, which its children are relative to.
+    group_origins: HashMap<u64, egui::Pos2>,
+}
+
+impl Exporter<'_> {
+    fn parent(&self, group: Option<u64>) -> (String, egui::Vec2) {
+        match group.and_then(|g| self.group_origins.get(&g).map(|o| (g, *o))) {
+            Some((g, origin)) => (format!("g{g}"), origin.to_vec2()),
+            None => ("1".to_string(), egui::Vec2::ZERO),
+        }
+    }
+
+    fn vertex(&mut self, id: &str, value: &str, style: &str, parent: &str, rect: egui::Rect) {
+        let _ = writeln!(
+            self.out,
+            r#"        <mxCell id="{id}" value="{}" style="{}" vertex="1" parent="{parent}">"#,
+            escape_attr(value),
+            escape_attr(style)
+        );
+        let _ = writeln!(
+            self.out,
+            r#"          <mxGeometry x="{}" y="{}" width="{}" height="{}" as="geometry" />"#,
+            num(rect.min.x),
+            num(rect.min.y),
+            num(rect.width()),
+            num(rect.height())
+        );
+        self.out.push_str("        </mxCell>\n");
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn edge(
+        &mut self,
+        id: &str,
+        style: &str,
+        parent: &str,
+        source: Option<u64>,
+        target: Option<u64>,
+        points: &[egui::Pos2],
+        offset: egui::Vec2,
+    ) {
+        let mut attrs = String::new();
+        if let Some(s) = source {
+            let _ = write!(attrs, r#" source="sy{s}""#);
+        }
+        if let Some(t) = target {
+            let _ = write!(attrs, r#" target="sy{t}""#);
+        }
+        let _ = writeln!(
+            self.out,
+            r#"        <mxCell id="{id}" style="{}" edge="1" parent="{parent}"{attrs}>"#,
+            escape_attr(style)
+        );
+        self.out
+            .push_str("          <mxGeometry relative=\"1\" as=\"geometry\">\n");
+        let pt = |p: egui::Pos2, tag: &str| {
+            let p = p - offset;
+            format!(r#"<mxPoint x="{}" y="{}"{tag} />"#, num(p.x), num(p.y))
+        };
+        if let (Some(first), Some(last)) = (points.first(), points.last()) {
+            let _ = writeln!(
+                self.out,
+                "            {}",
+                pt(*first, r#" as="sourcePoint""#)
+            );
+            let _ = writeln!(
+                self.out,
+                "            {}",
+                pt(*last, r#" as="targetPoint""#)
+            );
+        }
+        if points.len() > 2 {
+            self.out.push_str("            <Array as=\"points\">\n");
+            for p in &points[1..points.len() - 1] {
+                let _ = writeln!(self.out, "              {}", pt(*p, ""));
+            }
+            self.out.push_str("            </Array>\n");
+        }
+        self.out
+            .push_str("          </mxGeometry>\n        </mxCell>\n");
+    }
+
+    /// Writes a line's label as an `edgeLabel` child of its edge cell.
+    fn edge_label(
+        &mut self,
+        edge_id: &str,
+        label: &str,
+        position: f32,
+        offset: model::Point,
+        element: &model::Element,
+    ) {
+        let style = &element.style;
+        let background = style
+            .fill
+            .filter(|c| c.a > 0)
+            .map_or_else(|| "none".to_string(), hex);
+        let mut label_style = format!(
+            "edgeLabel;resizable=0;points=[];labelBackgroundColor={background};fontColor={};fontSize={};",
+            hex(style.text_color),
+            num(style.text_size)
+        );
+        if style.font_family == model::FontFamily::Monospace {
+            label_style.push_str("fontFamily=Courier New;");
+        }
+        let _ = writeln!(
+            self.out,
+            r#"        <mxCell id="{edge_id}-label" value="{}" style="{}" vertex="1" connectable="0" parent="{edge_id}">"#,
+            escape_attr(label),
+            escape_attr(&label_style)
+        );
+        let _ = writeln!(
+            self.out,
+            r#"          <mxGeometry x="{}" relative="1" as="geometry">"#,
+            num(position * 2.0 - 1.0)
+        );
+        let _ = writeln!(
+            self.out,
+            r#"            <mxPoint x="{}" y="{}" as="offset" />"#,
+            num(offset.x),
+            num(offset.y)
+        );
+        self.out
+            .push_str("          </mxGeometry>\n        </mxCell>\n");
+    }
+
+    fn arrows(style: model::ArrowStyle, heads: &model::ArrowHeads) -> String {
+        let start = matches!(style, model::ArrowStyle::Start | model::ArrowStyle::Both);
+        let end = matches!(style, model::ArrowStyle::End | model::ArrowStyle::Both);
+        let mut out = String::new();
+        for (on, head, prefix) in [(start, heads.start, "start"), (end, heads.end, "end")] {
+            let (name, fill) = match head {
+                _ if !on => ("none", true),
+                model::ArrowHead::Triangle => ("classic", true),
+                model::ArrowHead::Open => ("open", true),
+                model::ArrowHead::Diamond => ("diamond", true),
+                model::ArrowHead::HollowDiamond => ("diamond", false),
+                model::ArrowHead::Circle => ("oval", true),
+                model::ArrowHead::Bar => ("dash", true),
+                model::ArrowHead::CrowsFoot => ("ERmany", true),
+            };
+            let _ = write!(out, "{prefix}Arrow={name};");
+            if !fill {
+                let _ = write!(out, "{prefix}Fill=0;");
+            }
+            if on && heads.scale != 1.0 {
+                let _ = write!(out, "{prefix}Size={};", num(6.0 * heads.scale));
+            }
+        }
+        out
+    }
+
+    fn jumps(&self) -> &'static str {
+        if self.doc.line_jumps {
+            "jumpStyle=arc;"
+        } else {
+            ""
+        }
+    }
+
+    /// Pins each bound end to its attachment point on the shape. Ends
+    /// joined to another connector stay unpinned and draw.io picks the spot.
+    fn constraints(
+        &self,
+        start_binding: &Option<model::Binding>,
+        end_binding: &Option<model::Binding>,
+    ) -> String {
+        let mut out = String::new();
+        for (binding, prefix) in [(start_binding, "exit"), (end_binding, "entry")] {
+            let Some(b) = binding else {
+                continue;
+            };
+            let on_connector = self.doc.elements.iter().any(|e| {
+                e.id == b.element_id
+                    && matches!(
+                        e.kind,
+                        model::ElementKind::Line { .. } | model::ElementKind::Polyline { .. }
+                    )
+            });
+            if on_connector {
+                continue;
+            }
+            let norm = binding_norm(self.doc, b);
+            let _ = write!(
+                out,
+                "{prefix}X={};{prefix}Y={};{prefix}Dx=0;{prefix}Dy=0;",
+                num(norm.x + 0.5),
+                num(norm.y + 0.5)
+            );
+        }
+        out
+    }
+
+    fn element(&mut self, element: &model::Element) {
+        let id = format!("sy{}", element.id);
+        let (parent, offset) = self.parent(element.group_id);
+        match &element.kind {
+            model::ElementKind::Rect { rect, label, .. }
+            | model::ElementKind::Ellipse { rect, label }
+            | model::ElementKind::Triangle { rect, label, .. }
+            | model::ElementKind::Parallelogram { rect, label, .. }
+            | model::ElementKind::Trapezoid { rect, label, .. }
+            | model::ElementKind::Diamond { rect, label }
+            | model::ElementKind::Hexagon { rect, label, .. }
+            | model::ElementKind::Cylinder { rect, label, .. }
+            | model::ElementKind::Polygon { rect, label, .. } => {
+                let shape = match &element.kind {
+                    model::ElementKind::Rect { corner_radius, .. } if *corner_radius > 0.0 => {
+                        format!(
+                            "rounded=1;absoluteArcSize=1;arcSize={};",
+                            num(corner_radius * 2.0)
+                        )
+                    }
+                    model::ElementKind::Rect { .. } => "rounded=0;".to_string(),
+                    model::ElementKind::Ellipse { .. } => "ellipse;".to_string(),
+                    model::ElementKind::Triangle { .. } => "triangle;direction=north;".to_string(),
+                    model::ElementKind::Parallelogram { skew_ratio, .. } => format!(
+                        "shape=parallelogram;perimeter=parallelogramPerimeter;fixedSize=0;size={};",
+                        num(*skew_ratio)
+                    ),
+                    model::ElementKind::Trapezoid {
+                        top_inset_ratio, ..
+                    } => format!(
+                        "shape=trapezoid;perimeter=trapezoidPerimeter;fixedSize=0;size={};",
+                        num(top_inset_ratio * 0.5)
+                    ),
+                    model::ElementKind::Diamond { .. } => "rhombus;".to_string(),
+                    model::ElementKind::Hexagon { inset_ratio, .. } => format!(
+                        "shape=hexagon;perimeter=hexagonPerimeter2;fixedSize=0;size={};",
+                        num(inset_ratio * 0.5)
+                    ),
+                    model::ElementKind::Cylinder { cap_ratio, .. } => format!(
+                        "shape=cylinder3;boundedLbl=1;backgroundOutline=1;size={};",
+                        num(cap_ratio * rect.to_rect().height() * 0.5)
+                    ),
+                    model::ElementKind::Polygon { sides, .. } => {
+                        let points = rotated_polygon_points_world(rect.to_rect(), 0.0, *sides);
+                        let bounds = polygon_bounds(&points);
+                        let coords: Vec<String> = points
+                            .iter()
+                            .map(|p| {
+                                let p = (*p - bounds.min) / bounds.size();
+                                format!("[{},{}]", num(p.x), num(p.y))
+                            })
+                            .collect();
+                        format!(
+                            "shape=mxgraph.basic.polygon;polyCoords=[{}];polyline=0;",
+                            coords.join(",")
+                        )
+                    }
+                    _ => unreachable!(),
+                };
+                let style = format!("{shape}whiteSpace=wrap;{}", style_string(element, true));
+                let rect = rect.to_rect().translate(-offset);
+                self.vertex(&id, label, &style, &parent, rect);
+            }
+            model::ElementKind::Line {
+                a,
+                b,
+                arrow,
+                arrow_style,
+                heads,
+                start_binding,
+                end_binding,
+                route,
+                label,
+                label_position,
+                label_offset,
+            } => {
+                // draw.io smooths `curved=1` edges through their waypoints, so
+                // a few points along the curve are enough.
+                let points =
+                    match line_curve_world(self.doc, *a, *b, route, start_binding, end_binding) {
+                        Some(curve) => [0.0, 0.25, 0.5, 0.75, 1.0]
+                            .map(|t| cubic_bezier_point(&curve, t))
+                            .to_vec(),
+                        None => {
+                            line_points_world(self.doc, *a, *b, route, start_binding, end_binding)
+                        }
+                    };
+                let arrow_style = if *arrow && *arrow_style == model::ArrowStyle::None {
+                    model::ArrowStyle::End
+                } else {
+                    *arrow_style
+                };
+                let edge_style = match route {
+                    model::LineRoute::Straight => "none",
+                    model::LineRoute::Orthogonal { .. } => "orthogonalEdgeStyle",
+                    model::LineRoute::Curved { .. } => "none;curved=1",
+                };
+                let style = format!(
+                    "edgeStyle={edge_style};html=1;{}{}{}{}",
+                    Self::arrows(arrow_style, heads),
+                    self.jumps(),
+                    self.constraints(start_binding, end_binding),
+                    style_string(element, false)
+                );
+                self.edge(
+                    &id,
+                    &style,
+                    &parent,
+                    start_binding.as_ref().map(|b| b.element_id),
+                    end_binding.as_ref().map(|b| b.element_id),
+                    &points,
+                    offset,
+                );
+                if !label.is_empty() {
+                    self.edge_label(&id, label, *label_position, *label_offset, element);
+                }
+            }
+            model::ElementKind::Polyline {
+                points,
+                arrow_style,
+                heads,
+                start_binding,
+                end_binding,
+            } => {
+                let pts = polyline_points_world(self.doc, points, start_binding, end_binding);
+                let style = format!(
+                    "edgeStyle=none;html=1;{}{}{}{}",
+                    Self::arrows(*arrow_style, heads),
+                    self.jumps(),
+                    self.constraints(start_binding, end_binding),
+                    style_string(element, false)
+                );
+                self.edge(
+                    &id,
+                    &style,
+                    &parent,
+                    start_binding.as_ref().map(|b| b.element_id),
+                    end_binding.as_ref().map(|b| b.element_id),
+                    &pts,
+                    offset,
+                );
+            }
+            model::ElementKind::Pen { points } => {
+                let pts: Vec<egui::Pos2> = points.iter().map(|p| p.to_pos2()).collect();
+                let style = format!(
+                    "edgeStyle=none;curved=1;html=1;{}{}",
+                    Self::arrows(model::ArrowStyle::None, &model::ArrowHeads::default()),
+                    style_string(element, false)
+                );
+                self.edge(&id, &style, &parent, None, None, &pts, offset);
+            }
+            model::ElementKind::Text { pos, text } => {
+                let s = &element.style;
+                let lines = text.lines().count().max(1) as f32;
+                let widest = text
+                    .lines()
+                    .map(crate::text_format::visual_char_count)
+                    .max()
+                    .unwrap_or(0);
+                let size = egui::vec2(
+                    (widest as f32 * s.text_size * 0.6).max(s.text_size),
+                    lines * s.text_size * LINE_HEIGHT,
+                );
+                let x = match s.text_align {
+                    model::TextAlign::Left => pos.x,
+                    model::TextAlign::Center => pos.x - size.x * 0.5,
+                    model::TextAlign::Right => pos.x - size.x,
+                };
+                let rect = egui::Rect::from_min_size(egui::pos2(x, pos.y), size).translate(-offset);
+                let mut text_element = element.clone();
+                text_element.rotation = 0.0;
+                text_element.style.stroke.color.a = 0;
+                text_element.style.fill = None;
+                let style = format!(
+                    "text;verticalAlign=top;whiteSpace=wrap;{}",
+                    style_string(&text_element, true)
+                );
+                self.vertex(&id, text, &style, &parent, rect);
+            }
+        }
+    }
+}
+
+/// World bounds of everything in a group, including nested groups.
+fn group_bounds(doc: &model::Document, group: u64) -> egui::Rect {
+    let mut bounds = egui::Rect::NOTHING;
+    for element in &doc.elements {
+        let mut current = element.group_id;
+        let mut depth = 0;
+        while let Some(g) = current {
+            if g == group {
+                bounds = bounds.union(element.bounds());
+                break;
+            }
+            if depth > doc.groups.len() {
+                break;
+            }
+            current = doc
+                .groups
+                .iter()
+                .find(|x| x.id == g)
+                .and_then(|x| x.parent_id);
+            depth += 1;
+        }
+    }
+    bounds
+}
+
+pub fn document_to_drawio(doc: &model::Document) -> String {
+    let mut exporter = Exporter {
+        doc,
+        out: String::new(),
+        group_origins: HashMap::new(),
+    };
+    exporter
+        .out
+        .push_str("<mxfile host=\"sansuyu\">\n  <diagram id=\"sansuyu\" name=\"Page-1\">\n");
+    exporter.out.push_str(
+        "    <mxGraphModel grid=\"1\" gridSize=\"10\" guides=\"1\" connect=\"1\" arrows=\"1\" page=\"0\">\n      <root>\n",
+    );
+    exporter
+        .out
+        .push_str("        <mxCell id=\"0\" />\n        <mxCell id=\"1\" parent=\"0\" />\n");
+    // Parents before children: repeatedly emit groups whose parent is done.
+    let mut pending = doc.groups.clone();
+    while !pending.is_empty() {
+        let before = pending.len();
+        let mut i = 0;
+        while i < pending.len() {
+            let group = pending[i];
+            let parent_ready = group.parent_id.is_none_or(|p| {
+                exporter.group_origins.contains_key(&p) || !pending.iter().any(|g| g.id == p)
+            });
+            if !parent_ready {
+                i += 1;
+                continue;
+            }
+            pending.remove(i);
+            let bounds = group_bounds(doc, group.id);
+            if !bounds.is_finite() {
+                continue;
+            }
+            let (parent, offset) = exporter.parent(group.parent_id);
+            exporter.vertex(
+                &format!("g{}", group.id),
+                "",
+                "group;connectable=0;",
+                &parent,
+                bounds.translate(-offset),
+            );
+            exporter.group_origins.insert(group.id, bounds.min);
+        }
+        if pending.len() == before {
+            break;
+        }
+    }
+    for element in &doc.elements {
+        exporter.element(element);
+    }
+    exporter
+        .out
+        .push_str("      </root>\n    </mxGraphModel>\n  </diagram>\n</mxfile>\n");
+    exporter.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r##"<mxfile><diagram name="Page-1"><mxGraphModel><root>
+        <mxCell id="0"/>
+        <mxCell id="1" parent="0"/>
+        <mxCell id="a" value="Start" style="ellipse;fillColor=#dae8fc;" vertex="1" parent="1">
+            <mxGeometry x="0" y="0" width="120" height="60" as="geometry"/>
+        </mxCell>
+        <mxCell id="box" value="" style="rounded=0;" vertex="1" parent="1">
+            <mxGeometry x="200" y="0" width="200" height="160" as="geometry"/>
+        </mxCell>
+        <mxCell id="b" value="Check" style="rhombus;" vertex="1" parent="box">
+            <mxGeometry x="20" y="20" width="80" height="80" as="geometry"/>
+        </mxCell>
+        <mxCell id="e1" value="go" style="edgeStyle=orthogonalEdgeStyle;dashed=1;" edge="1" parent="1" source="a" target="b">
+            <mxGeometry relative="1" as="geometry"/>
+        </mxCell>
+        <mxCell id="e2" style="endArrow=none;" edge="1" parent="1">
+            <mxGeometry relative="1" as="geometry">
+                <mxPoint x="0" y="200" as="sourcePoint"/>
+                <mxPoint x="100" y="200" as="targetPoint"/>
+            </mxGeometry>
+        </mxCell>
+    </root></mxGraphModel></diagram></mxfile>"##;
+
+    fn shape(e: &model::Element) -> Option<(String, &'static str)> {
+        let (label, kind) = match &e.kind {
+            model::ElementKind::Rect { label, .. } => (label, "rect"),
+            model::ElementKind::Ellipse { label, .. } => (label, "ellipse"),
+            model::ElementKind::Diamond { label, .. } => (label, "diamond"),
+            model::ElementKind::Hexagon { label, .. } => (label, "hexagon"),
+            model::ElementKind::Cylinder { label, .. } => (label, "cylinder"),
+            _ => return None,
+        };
+        Some((label.clone(), kind))
+    }
+
+    /// Shapes and the lines bound between them, sorted so documents can be
+    /// compared regardless of element order and ids.
+    fn summary(doc: &model::Document) -> (Vec<(String, &'static str)>, Vec<String>) {
+        let label_of = |id: u64| {
+            let element = doc.elements.iter().find(|e| e.id == id);
+            element
+                .and_then(shape)
+                .map(|(label, _)| label)
+                .unwrap_or_default()
+        };
+        let mut nodes: Vec<_> = doc.elements.iter().filter_map(shape).collect();
+        nodes.sort();
+        let mut edges: Vec<_> = doc
+            .elements
+            .iter()
+            .filter_map(|e| match &e.kind {
+                model::ElementKind::Line {
+                    start_binding: Some(s),
+                    end_binding: Some(t),
+                    arrow_style,
+                    heads,
+                    label,
+                    route,
+                    ..
+                } => Some(format!(
+                    "{:?} -> {:?} {arrow_style:?} {heads:?} {label:?} {:?} {}",
+                    label_of(s.element_id),
+                    label_of(t.element_id),
+                    e.style.stroke.line_style,
+                    matches!(route, model::LineRoute::Orthogonal { .. }),
+                )),
+                _ => None,
+            })
+            .collect();
+        edges.sort();
+        (nodes, edges)
+    }
+
+    #[test]
+    fn parses_vertices_edges_and_containers() {
+        let doc = parse(SAMPLE).unwrap();
+        let (nodes, edges) = summary(&doc);
+        assert_eq!(
+            nodes,
+            [
+                (String::new(), "rect"),
+                ("Check".to_string(), "diamond"),
+                ("Start".to_string(), "ellipse"),
+            ]
+        );
+        assert_eq!(edges.len(), 1);
+        let model::ElementKind::Line {
+            arrow_style,
+            route,
+            label,
+            ..
+        } = &doc.elements[3].kind
+        else {
+            panic!("e1 should be a line");
+        };
+        assert_eq!(*arrow_style, model::ArrowStyle::End);
+        assert!(matches!(route, model::LineRoute::Orthogonal { .. }));
+        assert_eq!(label, "go");
+        assert_eq!(
+            doc.elements[3].style.stroke.line_style,
+            model::LineStyle::Dashed
+        );
+        assert_eq!(doc.elements[0].style.fill, model::Rgba::from_hex("#dae8fc"));
+
+        // The container and its child share a group, and child coordinates
+        // are relative to the container.
+        let container = &doc.elements[1];
+        let child = &doc.elements[2];
+        assert!(container.group_id.is_some());
+        assert_eq!(child.group_id, container.group_id);
+        assert_eq!(child.bounds().center(), egui::pos2(260.0, 60.0));
+
+        let unbound = doc
+            .elements
+            .iter()
+            .find_map(|e| match &e.kind {
+                model::ElementKind::Line {
+                    a,
+                    b,
+                    arrow_style,
+                    start_binding: None,
+                    ..
+                } => Some((*a, *b, *arrow_style)),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(
+            unbound,
+            (
+                model::Point { x: 0.0, y: 200.0 },
+                model::Point { x: 100.0, y: 200.0 },
+                model::ArrowStyle::None
+            )
+        );
+    }
+
+    #[test]
+    fn inflates_compressed_diagrams() {
+        let src = "<mxfile><diagram>jZBNDsIgEIVPM3sEF66t2pUXcEfCpJCAEECF20vKaO2iiQuSed/w5g/E4MoYZdBXr9CCOIMYove5R64MaC1wZhSIE3DO2gN+2cju5iwLMuI9/2Pg3fCU9oGd3EwIqDpOuVrCzWZCauJIDowZy2bXGVHLEb3DHGv7Un5nYnWlXkZl3cmBkEYzaaq4JyZT19O36rJeC2jDj1wuOedWh34D</diagram></mxfile>";
+        let doc = parse(src).unwrap();
+        assert_eq!(summary(&doc).0, [("Zipped".to_string(), "ellipse")]);
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_diagrams() {
+        let cases = [
+            ("<svg/>", "expected <mxfile> or <mxGraphModel>, found <svg>"),
+            ("<mxfile></mxfile>", "draw.io file has no diagram"),
+            ("<mxGraphModel/>", "mxGraphModel has no <root>"),
+            (
+                "<mxGraphModel><root><mxCell id=\"0\"/></root></mxGraphModel>",
+                "diagram has no shapes or connectors",
+            ),
+            ("<mxfile><diagram>", "unclosed <diagram>"),
+            (
+                "<mxfile><diagram>!!!</diagram></mxfile>",
+                "invalid base64 in compressed diagram",
+            ),
+        ];
+        for (src, expected) in cases {
+            assert_eq!(parse(src).unwrap_err(), expected, "for {src:?}");
+        }
+    }
+
+    #[test]
+    fn ignores_non_positive_font_sizes() {
+        let src = SAMPLE.replace("style=\"rhombus;\"", "style=\"rhombus;fontSize=-4;\"");
+        let doc = parse(&src).unwrap();
+        assert_eq!(doc.elements[2].style.text_size, DEFAULT_FONT_SIZE);
+    }
+
+    #[test]
+    fn export_round_trips_through_import() {
+        let doc = crate::dsl::parse(
+            r#"
+            node a rect "Alpha" fill=#ddeeff
+            node b ellipse "Beta & <co>"
+            group {
+                node c diamond "Gamma"
+                node d cylinder "Two\nlines"
+            }
+            a -> b "calls" head=open
+            b <-> c line=dashed route=orthogonal
+            c -- d
+            "#,
+        )
+        .unwrap();
+        let xml = document_to_drawio(&doc);
+        let back = parse(&xml).unwrap();
+        assert_eq!(summary(&doc).1.len(), 3);
+        assert_eq!(summary(&back), summary(&doc), "{xml}");
+        assert_eq!(back.groups.len(), doc.groups.len());
+        let fills: Vec<_> = back.elements.iter().map(|e| e.style.fill).collect();
+        assert!(fills.contains(&doc.elements[0].style.fill));
+    }
+}
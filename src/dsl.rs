@@ -0,0 +1,670 @@
+//! A small line-oriented text format that compiles to a [`model::Document`].
+//!
+//! ```text
+//! # comments start with '#'
+//! node api rect "API Gateway" fill=#e8f0ff
+//! node db ellipse "Database" at=320,0 size=160x80
+//! group backend {
+//!     node worker "Worker"
+//!     worker -> db line=dashed
+//! }
+//! api -> worker <-> db "sync"
+//! ```
+//!
+//! Nodes without `at=` are placed on a grid. Edges are bound to the facing
+//! sides of their endpoints, and names used in an edge before being declared
+//! become plain rectangles.
+
+use crate::graph::{Edge, Graph, Node, Placement, ShapeKind, edge_style};
+use crate::model;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Word(String),
+    Str(String),
+    Open,
+    Close,
+}
+
+#[derive(Default)]
+struct Parser {
+    graph: Graph,
+    node_index: HashMap<String, usize>,
+    declared: HashSet<usize>,
+    /// Nodes given an explicit `size=`; the rest grow to fit their label.
+    sized: HashSet<usize>,
+    group_stack: Vec<u64>,
+}
+
+pub fn parse(src: &str) -> Result<model::Document, String> {
+    let mut parser = Parser::default();
+    for (i, line) in src.lines().enumerate() {
+        let line_no = i + 1;
+        let tokens = tokenize(line).map_err(|e| format!("line {line_no}: {e}"))?;
+        parser
+            .statement(&tokens)
+            .map_err(|e| format!("line {line_no}: {e}"))?;
+    }
+    if !parser.group_stack.is_empty() {
+        return Err("unclosed group at end of input".to_string());
+    }
+    parser.check_ports()?;
+    for (i, node) in parser.graph.nodes.iter_mut().enumerate() {
+        if !parser.sized.contains(&i) {
+            node.fit_label();
+        }
+    }
+    Ok(parser.graph.into_document(Placement::Grid))
+}
+
+fn tokenize(line: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == '#' {
+            break;
+        } else if c == '{' {
+            chars.next();
+            tokens.push(Token::Open);
+        } else if c == '}' {
+            chars.next();
+            tokens.push(Token::Close);
+        } else if c == '"' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => s.push('\n'),
+                        Some(other) => s.push(other),
+                        None => return Err("unterminated string".to_string()),
+                    },
+                    Some(other) => s.push(other),
+                    None => return Err("unterminated string".to_string()),
+                }
+            }
+            tokens.push(Token::Str(s));
+        } else {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || c == '{' || c == '}' {
+                    break;
+                }
+                if c == '"' {
+                    // Allow `label="..."` style attributes with spaces in the value.
+                    chars.next();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match chars.next() {
+                                Some('n') => word.push('\n'),
+                                Some(other) => word.push(other),
+                                None => return Err("unterminated string".to_string()),
+                            },
+                            Some(other) => word.push(other),
+                            None => return Err("unterminated string".to_string()),
+                        }
+                    }
+                    continue;
+                }
+                word.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Word(word));
+        }
+    }
+    Ok(tokens)
+}
+
+fn arrow_for_op(op: &str) -> Option<model::ArrowStyle> {
+    match op {
+        "->" => Some(model::ArrowStyle::End),
+        "<-" => Some(model::ArrowStyle::Start),
+        "<->" => Some(model::ArrowStyle::Both),
+        "--" => Some(model::ArrowStyle::None),
+        _ => None,
+    }
+}
+
+fn shape_for_word(word: &str) -> Option<ShapeKind> {
+    match word {
+        "rect" | "box" => Some(ShapeKind::Rect),
+        "ellipse" | "oval" | "circle" => Some(ShapeKind::Ellipse),
+        "triangle" => Some(ShapeKind::Triangle),
+        "parallelogram" => Some(ShapeKind::Parallelogram),
+        "trapezoid" => Some(ShapeKind::Trapezoid),
+        "diamond" => Some(ShapeKind::Diamond),
+        "hexagon" => Some(ShapeKind::Hexagon),
+        "cylinder" => Some(ShapeKind::Cylinder),
+        "polygon" => Some(ShapeKind::Polygon(5)),
+        _ => None,
+    }
+}
+
+fn parse_pair(value: &str, sep: char) -> Option<(f32, f32)> {
+    let (a, b) = value.split_once(sep)?;
+    Some((a.trim().parse().ok()?, b.trim().parse().ok()?))
+}
+
+/// `NAME:SIDE[:OFFSET]`, with the offset running from 0 to 1 along the side.
+fn parse_port(value: &str) -> Result<model::Port, String> {
+    let invalid = || format!("invalid port '{value}'");
+    let mut parts = value.split(':');
+    let name = parts
+        .next()
+        .filter(|n| !n.is_empty() && !n.contains('.'))
+        .ok_or_else(invalid)?;
+    let side = parts
+        .next()
+        .and_then(|s| {
+            model::PortSide::ALL
+                .into_iter()
+                .find(|side| side.name() == s)
+        })
+        .ok_or_else(invalid)?;
+    let offset = match parts.next() {
+        Some(v) => v
+            .parse::<f32>()
+            .ok()
+            .filter(|o| (0.0..=1.0).contains(o))
+            .ok_or_else(invalid)?,
+        None => 0.5,
+    };
+    if parts.next().is_some() {
+        return Err(invalid());
+    }
+    Ok(model::Port {
+        name: name.to_string(),
+        side,
+        offset,
+    })
+}
+
+fn parse_color(value: &str) -> Result<model::Rgba, String> {
+    model::Rgba::parse(value).ok_or_else(|| format!("invalid color '{value}'"))
+}
+
+fn parse_number(key: &str, value: &str) -> Result<f32, String> {
+    value
+        .parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite() && *v >= 0.0)
+        .ok_or_else(|| format!("invalid value for {key}: '{value}'"))
+}
+
+fn apply_style_attr(style: &mut model::Style, key: &str, value: &str) -> Result<bool, String> {
+    match key {
+        "fill" => {
+            style.fill = if value == "none" {
+                None
+            } else {
+                Some(parse_color(value)?)
+            };
+        }
+        "stroke" => {
+            if value == "none" {
+                style.stroke.color.a = 0;
+            } else {
+                style.stroke.color = parse_color(value)?;
+            }
+        }
+        "stroke-width" => style.stroke.width = parse_number(key, value)?,
+        "line" => {
+            style.stroke.line_style = match value {
+                "solid" => model::LineStyle::Solid,
+                "dashed" => model::LineStyle::Dashed,
+                "dotted" => model::LineStyle::Dotted,
+                _ => return Err(format!("unknown line style '{value}'")),
+            };
+        }
+        "text-size" => match parse_number(key, value)? {
+            size if size > 0.0 => style.text_size = size,
+            _ => return Err(format!("invalid value for {key}: '{value}'")),
+        },
+        "text-color" => style.text_color = parse_color(value)?,
+        "font" => {
+            style.font_family = match value {
+                "proportional" => model::FontFamily::Proportional,
+                "monospace" | "mono" => model::FontFamily::Monospace,
+                other => model::FontFamily::Custom(other.to_string()),
+            };
+        }
+        "align" => {
+            style.text_align = match value {
+                "left" => model::TextAlign::Left,
+                "center" => model::TextAlign::Center,
+                "right" => model::TextAlign::Right,
+                _ => return Err(format!("unknown alignment '{value}'")),
+            };
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+impl Parser {
+    fn statement(&mut self, tokens: &[Token]) -> Result<(), String> {
+        let Some(first) = tokens.first() else {
+            return Ok(());
+        };
+        match first {
+            Token::Close => {
+                if tokens.len() > 1 {
+                    return Err("unexpected tokens after '}'".to_string());
+                }
+                if self.group_stack.pop().is_none() {
+                    return Err("'}' without matching group".to_string());
+                }
+                Ok(())
+            }
+            Token::Word(w) if w == "group" => self.group(&tokens[1..]),
+            Token::Word(w) if w == "node" => self.node(&tokens[1..]),
+            Token::Word(_) => self.edges(tokens),
+            _ => Err("expected 'node', 'group' or an edge".to_string()),
+        }
+    }
+
+    fn group(&mut self, rest: &[Token]) -> Result<(), String> {
+        if !matches!(
+            rest,
+            [Token::Open] | [Token::Word(_) | Token::Str(_), Token::Open]
+        ) {
+            return Err("expected 'group [NAME] {'".to_string());
+        }
+        let id = self.graph.add_group(self.group_stack.last().copied());
+        self.group_stack.push(id);
+        Ok(())
+    }
+
+    fn node_for_name(&mut self, name: &str) -> usize {
+        if let Some(&idx) = self.node_index.get(name) {
+            return idx;
+        }
+        let idx = self.graph.nodes.len();
+        self.graph.nodes.push(Node::new(name));
+        self.node_index.insert(name.to_string(), idx);
+        idx
+    }
+
+    fn node(&mut self, rest: &[Token]) -> Result<(), String> {
+        let Some(Token::Word(name)) = rest.first() else {
+            return Err("expected node name after 'node'".to_string());
+        };
+        if self.node_index.get(name).is_some_and(|i| self.declared.contains(i)) {
+            return Err(format!("node '{name}' declared twice"));
+        }
+        let group_id = self.group_stack.last().copied();
+        let idx = self.node_for_name(name);
+        self.declared.insert(idx);
+        let node = &mut self.graph.nodes[idx];
+        node.group_id = group_id;
+        for token in &rest[1..] {
+            match token {
+                Token::Str(s) => node.label = s.clone(),
+                Token::Word(w) => {
+                    if let Some(kind) = shape_for_word(w) {
+                        // Keep a side count given by an earlier `sides=`.
+                        if !matches!(
+                            (kind, node.kind),
+                            (ShapeKind::Polygon(_), ShapeKind::Polygon(_))
+                        ) {
+                            node.kind = kind;
+                        }
+                        continue;
+                    }
+                    let Some((key, value)) = w.split_once('=') else {
+                        return Err(format!("unknown shape '{w}'"));
+                    };
+                    match key {
+                        "label" => node.label = value.to_string(),
+                        "at" => {
+                            let (x, y) = parse_pair(value, ',')
+                                .ok_or_else(|| format!("invalid position '{value}'"))?;
+                            node.at = Some(egui::pos2(x, y));
+                        }
+                        "size" => {
+                            let (w, h) = parse_pair(value, 'x')
+                                .filter(|(w, h)| *w > 0.0 && *h > 0.0)
+                                .ok_or_else(|| format!("invalid size '{value}'"))?;
+                            node.size = egui::vec2(w, h);
+                            self.sized.insert(idx);
+                        }
+                        "sides" => {
+                            let sides = value
+                                .parse::<u32>()
+                                .ok()
+                                .filter(|n| (3..=24).contains(n))
+                                .ok_or_else(|| format!("invalid side count '{value}'"))?;
+                            node.kind = ShapeKind::Polygon(sides);
+                        }
+                        "port" => {
+                            let port = parse_port(value)?;
+                            if node.ports.iter().any(|p| p.name == port.name) {
+                                return Err(format!("port '{}' declared twice", port.name));
+                            }
+                            node.ports.push(port);
+                        }
+                        _ => {
+                            if !apply_style_attr(&mut node.style, key, value)? {
+                                return Err(format!("unknown node attribute '{key}'"));
+                            }
+                        }
+                    }
+                }
+                Token::Open | Token::Close => return Err("unexpected brace".to_string()),
+            }
+        }
+        Ok(())
+    }
+
+    fn edges(&mut self, tokens: &[Token]) -> Result<(), String> {
+        let mut names: Vec<&str> = Vec::new();
+        let mut ops: Vec<model::ArrowStyle> = Vec::new();
+        let mut style = edge_style();
+        let mut i = 0;
+        while i < tokens.len() {
+            let w = match &tokens[i] {
+                Token::Word(w) => w,
+                Token::Str(_) => break,
+                _ => return Err("unexpected token in edge".to_string()),
+            };
+            if names.len() == ops.len() {
+                if w.contains('=') {
+                    break;
+                }
+                names.push(w);
+            } else if let Some(arrow) = arrow_for_op(w) {
+                ops.push(arrow);
+            } else {
+                break;
+            }
+            i += 1;
+        }
+        if ops.is_empty() || names.len() != ops.len() + 1 {
+            return Err("expected an edge like 'a -> b'".to_string());
+        }
+        let mut label = String::new();
+        let mut route = model::LineRoute::Straight;
+        let mut heads = model::ArrowHeads::default();
+        for token in &tokens[i..] {
+            let w = match token {
+                Token::Word(w) => w,
+                Token::Str(s) => {
+                    label = s.clone();
+                    continue;
+                }
+                _ => return Err("unexpected token in edge".to_string()),
+            };
+            let Some((key, value)) = w.split_once('=') else {
+                return Err(format!("expected attribute, found '{w}'"));
+            };
+            if key == "label" {
+                label = value.to_string();
+            } else if key == "route" {
+                route = match value {
+                    "straight" => model::LineRoute::Straight,
+                    "orthogonal" => model::LineRoute::Orthogonal {
+                        bends: Vec::new(),
+                        offsets: Vec::new(),
+                    },
+                    "curved" => model::LineRoute::Curved {
+                        c1: model::Point::default(),
+                        c2: model::Point::default(),
+                    },
+                    _ => return Err(format!("unknown route '{value}'")),
+                };
+            } else if key == "head" || key == "tail" {
+                let head = model::ArrowHead::from_name(value)
+                    .ok_or_else(|| format!("unknown arrowhead '{value}'"))?;
+                if key == "head" {
+                    heads.end = head;
+                } else {
+                    heads.start = head;
+                }
+            } else if key == "arrow-size" {
+                heads.scale = value
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|s| *s > 0.0)
+                    .ok_or_else(|| format!("invalid arrow-size '{value}'"))?;
+            } else if !apply_style_attr(&mut style, key, value)? {
+                return Err(format!("unknown edge attribute '{key}'"));
+            }
+        }
+        let group_id = self.group_stack.last().copied();
+        for (pair, arrow_style) in names.windows(2).zip(ops) {
+            let (from_name, from_port) = self.endpoint(pair[0]);
+            let (to_name, to_port) = self.endpoint(pair[1]);
+            let from = self.node_for_name(from_name);
+            let to = self.node_for_name(to_name);
+            self.graph.edges.push(Edge {
+                from,
+                to,
+                arrow_style,
+                heads,
+                style: style.clone(),
+                group_id,
+                label: label.clone(),
+                from_port: from_port.map(str::to_string),
+                to_port: to_port.map(str::to_string),
+                route: route.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Splits a `node.port` edge end; a name already used for a node wins.
+    fn endpoint<'a>(&self, word: &'a str) -> (&'a str, Option<&'a str>) {
+        if !self.node_index.contains_key(word)
+            && let Some((node, port)) = word.rsplit_once('.')
+            && !node.is_empty()
+            && !port.is_empty()
+        {
+            return (node, Some(port));
+        }
+        (word, None)
+    }
+
+    fn check_ports(&self) -> Result<(), String> {
+        for edge in &self.graph.edges {
+            for (idx, port) in [(edge.from, &edge.from_port), (edge.to, &edge.to_port)] {
+                let Some(port) = port else {
+                    continue;
+                };
+                if self.graph.nodes[idx].ports.iter().all(|p| &p.name != port) {
+                    let node = self
+                        .node_index
+                        .iter()
+                        .find(|(_, i)| **i == idx)
+                        .map_or("", |(name, _)| name.as_str());
+                    return Err(format!("unknown port '{port}' on node '{node}'"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = r#"
+# comments start with '#'
+node api rect "API Gateway" fill=#e8f0ff
+node db ellipse "Database" at=320,0 size=160x80
+group backend {
+    node worker "Worker"
+    worker -> db line=dashed
+}
+api -> worker <-> db "sync"
+"#;
+
+    fn lines(doc: &model::Document) -> Vec<&model::Element> {
+        doc.elements
+            .iter()
+            .filter(|e| matches!(e.kind, model::ElementKind::Line { .. }))
+            .collect()
+    }
+
+    #[test]
+    fn parses_the_documented_example() {
+        let doc = parse(EXAMPLE).unwrap();
+        assert_eq!(doc.elements.len(), 6);
+        assert_eq!(doc.groups.len(), 1);
+
+        let model::ElementKind::Rect { label, .. } = &doc.elements[0].kind else {
+            panic!("api should be a rect");
+        };
+        assert_eq!(label, "API Gateway");
+        assert_eq!(doc.elements[0].style.fill, model::Rgba::from_hex("#e8f0ff"));
+
+        let model::ElementKind::Ellipse { rect, label } = &doc.elements[1].kind else {
+            panic!("db should be an ellipse");
+        };
+        assert_eq!(label, "Database");
+        assert_eq!((rect.min.x, rect.min.y), (320.0, 0.0));
+        assert_eq!((rect.max.x, rect.max.y), (480.0, 80.0));
+
+        assert_eq!(doc.elements[2].group_id, Some(1));
+
+        let edges: Vec<_> = lines(&doc)
+            .into_iter()
+            .map(|e| match &e.kind {
+                model::ElementKind::Line {
+                    arrow_style,
+                    start_binding,
+                    end_binding,
+                    label,
+                    ..
+                } => (
+                    start_binding.as_ref().map(|b| b.element_id),
+                    end_binding.as_ref().map(|b| b.element_id),
+                    *arrow_style,
+                    label.as_str(),
+                    e.style.stroke.line_style,
+                    e.group_id,
+                ),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(
+            edges,
+            [
+                (
+                    Some(3),
+                    Some(2),
+                    model::ArrowStyle::End,
+                    "",
+                    model::LineStyle::Dashed,
+                    Some(1)
+                ),
+                (
+                    Some(1),
+                    Some(3),
+                    model::ArrowStyle::End,
+                    "sync",
+                    model::LineStyle::Solid,
+                    None
+                ),
+                (
+                    Some(3),
+                    Some(2),
+                    model::ArrowStyle::Both,
+                    "sync",
+                    model::LineStyle::Solid,
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn edge_names_create_rectangles() {
+        let doc = parse("a -- b").unwrap();
+        assert_eq!(doc.elements.len(), 3);
+        assert!(matches!(
+            &doc.elements[0].kind,
+            model::ElementKind::Rect { label, .. } if label == "a"
+        ));
+    }
+
+    #[test]
+    fn edges_attach_to_named_ports() {
+        let doc = parse("node a port=out:right:0.25\nnode b port=in:left\na.out -> b.in").unwrap();
+        let model::ElementKind::Line {
+            start_binding: Some(start),
+            end_binding: Some(end),
+            ..
+        } = &lines(&doc)[0].kind
+        else {
+            panic!("edge should be bound at both ends");
+        };
+        assert_eq!(start.port.as_deref(), Some("out"));
+        assert_eq!(end.port.as_deref(), Some("in"));
+    }
+
+    #[test]
+    fn edge_attributes_set_heads_and_route() {
+        let doc = parse("a -> b head=open tail=diamond arrow-size=1.5 route=orthogonal").unwrap();
+        let model::ElementKind::Line { heads, route, .. } = &lines(&doc)[0].kind else {
+            unreachable!();
+        };
+        assert_eq!(heads.end, model::ArrowHead::Open);
+        assert_eq!(heads.start, model::ArrowHead::Diamond);
+        assert_eq!(heads.scale, 1.5);
+        assert!(matches!(route, model::LineRoute::Orthogonal { .. }));
+    }
+
+    #[test]
+    fn unsized_nodes_fit_their_labels() {
+        let doc =
+            parse("node a \"A label far wider than the default node\"\nnode b size=50x20").unwrap();
+        let widths: Vec<f32> = doc
+            .elements
+            .iter()
+            .map(|e| match &e.kind {
+                model::ElementKind::Rect { rect, .. } => rect.max.x - rect.min.x,
+                _ => unreachable!(),
+            })
+            .collect();
+        assert!(widths[0] > crate::graph::NODE_WIDTH);
+        assert_eq!(widths[1], 50.0);
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let cases = [
+            ("node a\nnode a", "line 2: node 'a' declared twice"),
+            ("\n\nnode a \"open", "line 3: unterminated string"),
+            ("node a blob", "line 1: unknown shape 'blob'"),
+            (
+                "node a text-size=0",
+                "line 1: invalid value for text-size: '0'",
+            ),
+            ("node a fill=nope", "line 1: invalid color 'nope'"),
+            ("a -> b route=wiggly", "line 1: unknown route 'wiggly'"),
+            ("node a\n}", "line 2: '}' without matching group"),
+            ("a ->", "line 1: expected an edge like 'a -> b'"),
+        ];
+        for (src, expected) in cases {
+            assert_eq!(parse(src).unwrap_err(), expected, "for {src:?}");
+        }
+    }
+
+    #[test]
+    fn rejects_unclosed_groups_and_unknown_ports() {
+        assert_eq!(
+            parse("group {\nnode a").unwrap_err(),
+            "unclosed group at end of input"
+        );
+        assert_eq!(
+            parse("node a\na.out -> b").unwrap_err(),
+            "unknown port 'out' on node 'a'"
+        );
+    }
+}
@@ -0,0 +1,996 @@
+//! Excalidraw (`.excalidraw`) import and export.
+//!
+//! Rectangles, diamonds, ellipses, lines, arrows, freehand strokes and text
+//! map onto the matching element kinds. Arrow bindings become line bindings,
+//! text bound to a container (a shape or a straight arrow) becomes its label,
+//! and `groupIds` (innermost first) become nested groups. Shapes Excalidraw
+//! lacks export as closed lines tracing their outline.
+
+use crate::geometry::{
+    compute_binding_for_target, cubic_bezier_point, line_curve_world, line_label_anchor,
+    line_points_world, polyline_points_world, rotate_vec2, shape_outline_world,
+};
+use crate::{model, text_format};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const LINE_HEIGHT: f32 = 1.25;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct File {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    version: u32,
+    #[serde(default)]
+    source: String,
+    #[serde(default)]
+    elements: Vec<Element>,
+    #[serde(default)]
+    app_state: serde_json::Value,
+    #[serde(default)]
+    files: serde_json::Value,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+struct Element {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    x: f32,
+    y: f32,
+    width: f32,
+    height: f32,
+    angle: f32,
+    stroke_color: String,
+    background_color: String,
+    fill_style: String,
+    stroke_width: f32,
+    stroke_style: String,
+    roughness: u32,
+    #[serde(default = "full_opacity")]
+    opacity: f32,
+    group_ids: Vec<String>,
+    frame_id: Option<String>,
+    roundness: Option<serde_json::Value>,
+    seed: u64,
+    version: u32,
+    version_nonce: u64,
+    is_deleted: bool,
+    bound_elements: Option<Vec<BoundElement>>,
+    updated: u64,
+    link: Option<String>,
+    locked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    points: Option<Vec<[f32; 2]>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_binding: Option<PointBinding>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_binding: Option<PointBinding>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start_arrowhead: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_arrowhead: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    polygon: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pressures: Option<Vec<f32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    simulate_pressure: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    original_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    font_size: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    font_family: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text_align: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    vertical_align: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    container_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line_height: Option<f32>,
+}
+
+fn full_opacity() -> f32 {
+    100.0
+}
+
+#[derive(Serialize, Deserialize)]
+struct BoundElement {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PointBinding {
+    element_id: String,
+    #[serde(default)]
+    focus: f32,
+    #[serde(default)]
+    gap: f32,
+}
+
+/// Excalidraw font families: 3 (Cascadia) and 8 (Comic Shanns) are monospaced.
+const FONT_HELVETICA: u32 = 2;
+const FONT_CASCADIA: u32 = 3;
+
+fn color(value: &str, opacity: f32) -> Option<model::Rgba> {
+    let value = value.trim();
+    if value == "transparent" {
+        return Some(model::Rgba {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 0,
+        });
+    }
+    let mut c = match value.strip_prefix('#') {
+        Some(hex) if hex.len() == 8 => {
+            let mut c = model::Rgba::parse(&hex[..6])?;
+            c.a = u8::from_str_radix(&hex[6..], 16).ok()?;
+            c
+        }
+        _ => model::Rgba::parse(value)?,
+    };
+    c.a = (c.a as f32 * (opacity / 100.0).clamp(0.0, 1.0)).round() as u8;
+    Some(c)
+}
+
+fn hex_color(c: model::Rgba) -> String {
+    if c.a == 0 {
+        "transparent".to_string()
+    } else {
+        format!("#{:02x}{:02x}{:02x}", c.r, c.g, c.b)
+    }
+}
+
+fn import_style(e: &Element) -> model::Style {
+    let mut style = model::Style::default_for_shapes();
+    if let Some(c) = color(&e.stroke_color, e.opacity) {
+        style.stroke.color = c;
+        style.text_color = c;
+    }
+    if let Some(c) = color(&e.background_color, e.opacity)
+        && c.a > 0
+    {
+        style.fill = Some(c);
+    }
+    if e.stroke_width > 0.0 {
+        style.stroke.width = e.stroke_width;
+    }
+    style.stroke.line_style = match e.stroke_style.as_str() {
+        "dashed" => model::LineStyle::Dashed,
+        "dotted" => model::LineStyle::Dotted,
+        _ => model::LineStyle::Solid,
+    };
+    apply_text_style(&mut style, e);
+    style
+}
+
+fn apply_text_style(style: &mut model::Style, e: &Element) {
+    if let Some(size) = e.font_size
+        && size.is_finite()
+        && size > 0.0
+    {
+        style.text_size = size;
+    }
+    if let Some(family) = e.font_family {
+        style.font_family = match family {
+            3 | 8 => model::FontFamily::Monospace,
+            _ => model::FontFamily::Proportional,
+        };
+    }
+    if let Some(align) = &e.text_align {
+        style.text_align = match align.as_str() {
+            "left" => model::TextAlign::Left,
+            "right" => model::TextAlign::Right,
+            _ => model::TextAlign::Center,
+        };
+    }
+}
+
+fn arrow_style(start: &Option<String>, end: &Option<String>) -> model::ArrowStyle {
+    match (start.is_some(), end.is_some()) {
+        (true, true) => model::ArrowStyle::Both,
+        (true, false) => model::ArrowStyle::Start,
+        (false, true) => model::ArrowStyle::End,
+        (false, false) => model::ArrowStyle::None,
+    }
+}
+
+fn arrow_head(name: &Option<String>) -> model::ArrowHead {
+    match name.as_deref() {
+        Some("arrow") => model::ArrowHead::Open,
+        Some("diamond") => model::ArrowHead::Diamond,
+        Some("diamond_outline") => model::ArrowHead::HollowDiamond,
+        Some("dot" | "circle" | "circle_outline") => model::ArrowHead::Circle,
+        Some("bar" | "crowfoot_one") => model::ArrowHead::Bar,
+        Some("crowfoot_many" | "crowfoot_one_or_many") => model::ArrowHead::CrowsFoot,
+        _ => model::ArrowHead::Triangle,
+    }
+}
+
+fn arrow_heads(e: &Element) -> model::ArrowHeads {
+    model::ArrowHeads {
+        start: arrow_head(&e.start_arrowhead),
+        end: arrow_head(&e.end_arrowhead),
+        ..Default::default()
+    }
+}
+
+/// Excalidraw's corner radius: a quarter of the short side, capped by the
+/// adaptive radius (32 unless `value` says otherwise).
+fn corner_radius(e: &Element) -> f32 {
+    let Some(roundness) = &e.roundness else {
+        return 0.0;
+    };
+    let side = e.width.abs().min(e.height.abs());
+    match roundness.get("type").and_then(|t| t.as_u64()) {
+        Some(3) => {
+            let fixed = roundness
+                .get("value")
+                .and_then(|v| v.as_f64())
+                .unwrap_or(32.0) as f32;
+            if side <= fixed / 0.25 {
+                side * 0.25
+            } else {
+                fixed
+            }
+        }
+        Some(_) => side * 0.25,
+        None => 0.0,
+    }
+}
+
+/// World positions of a linear element's points, rotated about its box center.
+fn world_points(e: &Element) -> Vec<egui::Pos2> {
+    let origin = egui::pos2(e.x, e.y);
+    let center = origin + egui::vec2(e.width, e.height) * 0.5;
+    e.points
+        .iter()
+        .flatten()
+        .map(|[x, y]| {
+            let p = origin + egui::vec2(*x, *y);
+            center + rotate_vec2(p - center, e.angle)
+        })
+        .collect()
+}
+
+pub fn parse(src: &str) -> Result<model::Document, String> {
+    let file: File = serde_json::from_str(src).map_err(|e| e.to_string())?;
+    if file.kind != "excalidraw" && file.kind != "excalidraw/clipboard" {
+        return Err(format!(
+            "expected an Excalidraw file, found type '{}'",
+            file.kind
+        ));
+    }
+    let live: Vec<&Element> = file.elements.iter().filter(|e| !e.is_deleted).collect();
+    let by_id: HashMap<&str, &Element> = live.iter().map(|e| (e.id.as_str(), *e)).collect();
+    let is_container = |id: &Option<String>| {
+        id.as_deref()
+            .and_then(|id| by_id.get(id))
+            .is_some_and(|c| match c.kind.as_str() {
+                "rectangle" | "ellipse" | "diamond" => true,
+                // Only two-point arrows become lines, which can carry a label.
+                "line" | "arrow" => c.points.as_ref().is_some_and(|p| p.len() == 2),
+                _ => false,
+            })
+    };
+
+    let mut doc = model::Document::default();
+    let mut next_id = 1u64;
+    let mut group_ids: HashMap<&str, u64> = HashMap::new();
+    let mut element_ids: HashMap<&str, u64> = HashMap::new();
+    let labels: HashMap<&str, &Element> = live
+        .iter()
+        .filter(|e| e.kind == "text" && is_container(&e.container_id))
+        .filter_map(|e| Some((e.container_id.as_deref()?, *e)))
+        .collect();
+    let mut pending_bindings = Vec::new();
+
+    for e in &live {
+        if e.kind == "text" && is_container(&e.container_id) {
+            continue;
+        }
+        // Nested groups: each id's parent is the next one out.
+        let mut parent = None;
+        for gid in e.group_ids.iter().rev() {
+            let id = *group_ids.entry(gid.as_str()).or_insert_with(|| {
+                let id = next_id;
+                next_id += 1;
+                doc.groups.push(model::Group {
+                    id,
+                    parent_id: parent,
+                });
+                id
+            });
+            parent = Some(id);
+        }
+        let mut style = import_style(e);
+        let mut rotation = 0.0;
+        let kind = match e.kind.as_str() {
+            "rectangle" | "diamond" | "ellipse" => {
+                let min = egui::pos2(e.x, e.y);
+                let rect = model::RectF::from_min_max(min, min + egui::vec2(e.width, e.height));
+                if !rect.is_valid() {
+                    continue;
+                }
+                rotation = e.angle;
+                let mut label = String::new();
+                if let Some(text) = labels.get(e.id.as_str()) {
+                    label = text.text.clone().unwrap_or_default();
+                    if let Some(c) = color(&text.stroke_color, text.opacity) {
+                        style.text_color = c;
+                    }
+                    apply_text_style(&mut style, text);
+                }
+                match e.kind.as_str() {
+                    "ellipse" => model::ElementKind::Ellipse { rect, label },
+                    "diamond" => model::ElementKind::Diamond { rect, label },
+                    _ => model::ElementKind::Rect {
+                        rect,
+                        label,
+                        corner_radius: corner_radius(e),
+                    },
+                }
+            }
+            "line" | "arrow" => {
+                style.fill = None;
+                let points = world_points(e);
+                let arrow_style = arrow_style(&e.start_arrowhead, &e.end_arrowhead);
+                let heads = arrow_heads(e);
+                if points.len() < 2 {
+                    continue;
+                }
+                pending_bindings.push((
+                    doc.elements.len(),
+                    e.start_binding.as_ref().map(|b| b.element_id.as_str()),
+                    e.end_binding.as_ref().map(|b| b.element_id.as_str()),
+                ));
+                match points.as_slice() {
+                    [a, b] => {
+                        let mut label = String::new();
+                        if let Some(text) = labels.get(e.id.as_str()) {
+                            label = text.text.clone().unwrap_or_default();
+                            if let Some(c) = color(&text.stroke_color, text.opacity) {
+                                style.text_color = c;
+                            }
+                            apply_text_style(&mut style, text);
+                        }
+                        model::ElementKind::Line {
+                            a: model::Point::from_pos2(*a),
+                            b: model::Point::from_pos2(*b),
+                            arrow: false,
+                            arrow_style,
+                            heads,
+                            start_binding: None,
+                            end_binding: None,
+                            route: model::LineRoute::Straight,
+                            label,
+                            label_position: 0.5,
+                            label_offset: model::Point::default(),
+                        }
+                    }
+                    _ => model::ElementKind::Polyline {
+                        points: points.iter().map(|p| model::Point::from_pos2(*p)).collect(),
+                        arrow_style,
+                        heads,
+                        start_binding: None,
+                        end_binding: None,
+                    },
+                }
+            }
+            "freedraw" => {
+                style.fill = None;
+                let points = world_points(e);
+                if points.len() < 2 {
+                    continue;
+                }
+                model::ElementKind::Pen {
+                    points: points.iter().map(|p| model::Point::from_pos2(*p)).collect(),
+                }
+            }
+            "text" => {
+                style.fill = None;
+                style.stroke.color.a = 0;
+                let x = match style.text_align {
+                    model::TextAlign::Left => e.x,
+                    model::TextAlign::Center => e.x + e.width * 0.5,
+                    model::TextAlign::Right => e.x + e.width,
+                };
+                model::ElementKind::Text {
+                    pos: model::Point { x, y: e.y },
+                    text: e.text.clone().unwrap_or_default(),
+                }
+            }
+            _ => continue,
+        };
+        let id = next_id;
+        next_id += 1;
+        element_ids.insert(e.id.as_str(), id);
+        doc.elements.push(model::Element {
+            id,
+            group_id: parent,
+            rotation,
+            snap_enabled: true,
+            kind,
+            style,
+            ports: Vec::new(),
+        });
+    }
+
+    for (index, start, end) in pending_bindings {
+        let (a, b) = match &doc.elements[index].kind {
+            model::ElementKind::Line { a, b, .. } => (*a, *b),
+            model::ElementKind::Polyline { points, .. } => (points[0], points[points.len() - 1]),
+            _ => continue,
+        };
+        let bind = |target: Option<&str>, pos: model::Point| {
+            let target = *element_ids.get(target?)?;
+            compute_binding_for_target(&doc, target, pos.to_pos2())
+        };
+        let (start, end) = (bind(start, a), bind(end, b));
+        if let model::ElementKind::Line {
+            start_binding,
+            end_binding,
+            ..
+        }
+        | model::ElementKind::Polyline {
+            start_binding,
+            end_binding,
+            ..
+        } = &mut doc.elements[index].kind
+        {
+            *start_binding = start;
+            *end_binding = end;
+        }
+    }
+
+    if doc.elements.is_empty() {
+        return Err("Excalidraw file has no supported elements".to_string());
+    }
+    Ok(doc)
+}
+
+fn text_size(text: &str, style: &model::Style) -> egui::Vec2 {
+    let widest = text
+        .lines()
+        .map(text_format::visual_char_count)
+        .max()
+        .unwrap_or(0);
+    egui::vec2(
+        widest as f32 * style.text_size * 0.6,
+        text.lines().count().max(1) as f32 * style.text_size * LINE_HEIGHT,
+    )
+}
+
+struct Exporter<'a> {
+    doc: &'a model::Document,
+    out: Vec<Element>,
+    /// (target id, arrow id) pairs recorded on the targets once all are written.
+    arrow_bindings: Vec<(String, String)>,
+}
+
+impl Exporter<'_> {
+    fn base(&self, element: &model::Element, id: String, kind: &str) -> Element {
+        let style = &element.style;
+        let fill = style.fill.filter(|c| c.a > 0);
+        let seed = element.id.wrapping_mul(2_654_435_761) % 2_147_483_647;
+        Element {
+            id,
+            kind: kind.to_string(),
+            angle: element.rotation,
+            stroke_color: hex_color(style.stroke.color),
+            background_color: fill.map_or("transparent".to_string(), hex_color),
+            fill_style: "solid".to_string(),
+            stroke_width: style.stroke.width,
+            stroke_style: match style.stroke.line_style {
+                model::LineStyle::Solid => "solid",
+                model::LineStyle::Dashed => "dashed",
+                model::LineStyle::Dotted => "dotted",
+            }
+            .to_string(),
+            roughness: 0,
+            opacity: 100.0,
+            group_ids: self.group_chain(element.group_id),
+            seed,
+            version: 1,
+            version_nonce: seed,
+            bound_elements: Some(Vec::new()),
+            ..Element::default()
+        }
+    }
+
+    fn group_chain(&self, mut group: Option<u64>) -> Vec<String> {
+        let mut out = Vec::new();
+        while let Some(id) = group {
+            if out.len() > self.doc.groups.len() {
+                break;
+            }
+            out.push(format!("group-{id}"));
+            group = self
+                .doc
+                .groups
+                .iter()
+                .find(|g| g.id == id)
+                .and_then(|g| g.parent_id);
+        }
+        out
+    }
+
+    /// Writes `label` as text bound to the element at `container`, centered
+    /// on `center`.
+    fn bound_label(
+        &mut self,
+        element: &model::Element,
+        container: usize,
+        label: &str,
+        center: egui::Pos2,
+    ) {
+        if label.is_empty() {
+            return;
+        }
+        let id = format!("sy-{}", element.id);
+        let label_id = format!("{id}-label");
+        let size = text_size(label, &element.style);
+        let top = center - egui::vec2(0.0, size.y * 0.5);
+        let index = self.text(element, label_id.clone(), label, top);
+        let text = &mut self.out[index];
+        text.container_id = Some(id);
+        text.text_align = Some("center".to_string());
+        text.vertical_align = Some("middle".to_string());
+        text.x = center.x - size.x * 0.5;
+        text.angle = element.rotation;
+        self.out[container]
+            .bound_elements
+            .get_or_insert_with(Vec::new)
+            .push(BoundElement {
+                id: label_id,
+                kind: "text".to_string(),
+            });
+    }
+
+    fn text(&mut self, element: &model::Element, id: String, text: &str, pos: egui::Pos2) -> usize {
+        let style = &element.style;
+        let size = text_size(text, style);
+        let x = match style.text_align {
+            model::TextAlign::Left => pos.x,
+            model::TextAlign::Center => pos.x - size.x * 0.5,
+            model::TextAlign::Right => pos.x - size.x,
+        };
+        let mut e = self.base(element, id, "text");
+        e.angle = 0.0;
+        e.x = x;
+        e.y = pos.y;
+        e.width = size.x;
+        e.height = size.y;
+        e.stroke_color = hex_color(style.text_color);
+        e.background_color = "transparent".to_string();
+        e.stroke_width = 1.0;
+        e.stroke_style = "solid".to_string();
+        e.text = Some(text.to_string());
+        e.original_text = Some(text.to_string());
+        e.font_size = Some(style.text_size);
+        e.font_family = Some(match style.font_family {
+            model::FontFamily::Monospace => FONT_CASCADIA,
+            _ => FONT_HELVETICA,
+        });
+        e.text_align = Some(
+            match style.text_align {
+                model::TextAlign::Left => "left",
+                model::TextAlign::Center => "center",
+                model::TextAlign::Right => "right",
+            }
+            .to_string(),
+        );
+        e.vertical_align = Some("top".to_string());
+        e.line_height = Some(LINE_HEIGHT);
+        self.out.push(e);
+        self.out.len() - 1
+    }
+
+    fn linear(&mut self, element: &model::Element, kind: &str, points: &[egui::Pos2]) -> usize {
+        let bounds = egui::Rect::from_points(points);
+        let mut e = self.base(element, format!("sy-{}", element.id), kind);
+        e.angle = 0.0;
+        e.x = bounds.min.x;
+        e.y = bounds.min.y;
+        e.width = bounds.width();
+        e.height = bounds.height();
+        e.points = Some(
+            points
+                .iter()
+                .map(|p| [p.x - bounds.min.x, p.y - bounds.min.y])
+                .collect(),
+        );
+        self.out.push(e);
+        self.out.len() - 1
+    }
+
+    fn arrowheads(e: &mut Element, style: model::ArrowStyle, heads: &model::ArrowHeads) {
+        let arrow = |head| {
+            Some(
+                match head {
+                    model::ArrowHead::Triangle => "triangle",
+                    model::ArrowHead::Open => "arrow",
+                    model::ArrowHead::Diamond => "diamond",
+                    model::ArrowHead::HollowDiamond => "diamond_outline",
+                    model::ArrowHead::Circle => "circle",
+                    model::ArrowHead::Bar => "bar",
+                    model::ArrowHead::CrowsFoot => "crowfoot_many",
+                }
+                .to_string(),
+            )
+        };
+        if matches!(style, model::ArrowStyle::Start | model::ArrowStyle::Both) {
+            e.start_arrowhead = arrow(heads.start);
+        }
+        if matches!(style, model::ArrowStyle::End | model::ArrowStyle::Both) {
+            e.end_arrowhead = arrow(heads.end);
+        }
+    }
+
+    fn bind(&mut self, binding: &Option<model::Binding>, arrow_id: &str) -> Option<PointBinding> {
+        let binding = binding.as_ref()?;
+        // Excalidraw arrows only attach to its own shapes; everything else is
+        // exported as a linear element.
+        let target = self
+            .doc
+            .elements
+            .iter()
+            .find(|e| e.id == binding.element_id)?;
+        if !matches!(
+            target.kind,
+            model::ElementKind::Rect { .. }
+                | model::ElementKind::Ellipse { .. }
+                | model::ElementKind::Diamond { .. }
+        ) {
+            return None;
+        }
+        let element_id = format!("sy-{}", binding.element_id);
+        self.arrow_bindings
+            .push((element_id.clone(), arrow_id.to_string()));
+        Some(PointBinding {
+            element_id,
+            focus: 0.0,
+            gap: 0.0,
+        })
+    }
+
+    fn element(&mut self, element: &model::Element) {
+        let id = format!("sy-{}", element.id);
+        match &element.kind {
+            model::ElementKind::Rect { rect, label, .. }
+            | model::ElementKind::Ellipse { rect, label }
+            | model::ElementKind::Diamond { rect, label } => {
+                let kind = match &element.kind {
+                    model::ElementKind::Rect { .. } => "rectangle",
+                    model::ElementKind::Ellipse { .. } => "ellipse",
+                    _ => "diamond",
+                };
+                let r = rect.to_rect();
+                let mut e = self.base(element, id.clone(), kind);
+                e.x = r.min.x;
+                e.y = r.min.y;
+                e.width = r.width();
+                e.height = r.height();
+                if let model::ElementKind::Rect { corner_radius, .. } = &element.kind
+                    && *corner_radius > 0.0
+                {
+                    e.roundness = Some(serde_json::json!({ "type": 3, "value": corner_radius }));
+                }
+                self.out.push(e);
+                let container = self.out.len() - 1;
+                self.bound_label(element, container, label, r.center());
+            }
+            model::ElementKind::Triangle { rect, label, .. }
+            | model::ElementKind::Parallelogram { rect, label, .. }
+            | model::ElementKind::Trapezoid { rect, label, .. }
+            | model::ElementKind::Hexagon { rect, label, .. }
+            | model::ElementKind::Cylinder { rect, label, .. }
+            | model::ElementKind::Polygon { rect, label, .. } => {
+                let r = rect.to_rect();
+                let mut points = shape_outline_world(element).unwrap_or_default();
+                if let Some(first) = points.first().copied() {
+                    points.push(first);
+                }
+                let index = self.linear(element, "line", &points);
+                self.out[index].polygon = Some(true);
+                if !label.is_empty() {
+                    let size = text_size(label, &element.style);
+                    let top = r.center() - egui::vec2(0.0, size.y * 0.5);
+                    let mut style_element = element.clone();
+                    style_element.style.text_align = model::TextAlign::Center;
+                    self.text(&style_element, format!("{id}-label"), label, top);
+                }
+            }
+            model::ElementKind::Line {
+                a,
+                b,
+                arrow,
+                arrow_style,
+                heads,
+                start_binding,
+                end_binding,
+                route,
+                label,
+                ..
+            } => {
+                // Excalidraw bends lines through their points, so a handful
+                // along the curve carry its shape.
+                let curve = line_curve_world(self.doc, *a, *b, route, start_binding, end_binding);
+                let points = match &curve {
+                    Some(curve) => (0..=8)
+                        .map(|i| cubic_bezier_point(curve, i as f32 / 8.0))
+                        .collect(),
+                    None => line_points_world(self.doc, *a, *b, route, start_binding, end_binding),
+                };
+                let style = if *arrow && *arrow_style == model::ArrowStyle::None {
+                    model::ArrowStyle::End
+                } else {
+                    *arrow_style
+                };
+                let kind = if style == model::ArrowStyle::None {
+                    "line"
+                } else {
+                    "arrow"
+                };
+                let index = self.linear(element, kind, &points);
+                let start = self.bind(start_binding, &id);
+                let end = self.bind(end_binding, &id);
+                let e = &mut self.out[index];
+                Self::arrowheads(e, style, heads);
+                if curve.is_some() {
+                    e.roundness = Some(serde_json::json!({ "type": 2 }));
+                }
+                e.start_binding = start;
+                e.end_binding = end;
+                if let Some(anchor) = line_label_anchor(self.doc, element) {
+                    self.bound_label(element, index, label, anchor);
+                }
+            }
+            model::ElementKind::Polyline {
+                points,
+                arrow_style,
+                heads,
+                start_binding,
+                end_binding,
+            } => {
+                if points.len() < 2 {
+                    return;
+                }
+                let kind = if *arrow_style == model::ArrowStyle::None {
+                    "line"
+                } else {
+                    "arrow"
+                };
+                let pts = polyline_points_world(self.doc, points, start_binding, end_binding);
+                let index = self.linear(element, kind, &pts);
+                let start = self.bind(start_binding, &id);
+                let end = self.bind(end_binding, &id);
+                let e = &mut self.out[index];
+                Self::arrowheads(e, *arrow_style, heads);
+                e.start_binding = start;
+                e.end_binding = end;
+            }
+            model::ElementKind::Pen { points } => {
+                if points.len() < 2 {
+                    return;
+                }
+                let pts: Vec<egui::Pos2> = points.iter().map(|p| p.to_pos2()).collect();
+                let index = self.linear(element, "freedraw", &pts);
+                let e = &mut self.out[index];
+                e.pressures = Some(Vec::new());
+                e.simulate_pressure = Some(true);
+            }
+            model::ElementKind::Text { pos, text } => {
+                self.text(element, id, text, pos.to_pos2());
+            }
+        }
+    }
+}
+
+pub fn document_to_excalidraw(doc: &model::Document) -> String {
+    let mut exporter = Exporter {
+        doc,
+        out: Vec::new(),
+        arrow_bindings: Vec::new(),
+    };
+    for element in &doc.elements {
+        exporter.element(element);
+    }
+    for (target, arrow) in std::mem::take(&mut exporter.arrow_bindings) {
+        if let Some(e) = exporter.out.iter_mut().find(|e| e.id == target) {
+            e.bound_elements
+                .get_or_insert_with(Vec::new)
+                .push(BoundElement {
+                    id: arrow,
+                    kind: "arrow".to_string(),
+                });
+        }
+    }
+    let file = File {
+        kind: "excalidraw".to_string(),
+        version: 2,
+        source: "sansuyu".to_string(),
+        elements: exporter.out,
+        app_state: serde_json::json!({ "viewBackgroundColor": "#ffffff", "gridSize": null }),
+        files: serde_json::json!({}),
+    };
+    serde_json::to_string_pretty(&file).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r##"{
+  "type": "excalidraw",
+  "version": 2,
+  "elements": [
+    {"id": "box", "type": "rectangle", "x": 0, "y": 0, "width": 120, "height": 60,
+     "strokeColor": "#1e1e1e", "backgroundColor": "#a5d8ff", "groupIds": ["inner", "outer"],
+     "boundElements": [{"id": "label", "type": "text"}, {"id": "arrow", "type": "arrow"}]},
+    {"id": "label", "type": "text", "x": 10, "y": 20, "width": 100, "height": 20,
+     "text": "Box", "fontSize": 20, "containerId": "box", "strokeColor": "#1e1e1e"},
+    {"id": "oval", "type": "ellipse", "x": 300, "y": 0, "width": 100, "height": 60,
+     "strokeStyle": "dashed", "groupIds": ["outer"]},
+    {"id": "arrow", "type": "arrow", "x": 120, "y": 30, "width": 180, "height": 0,
+     "points": [[0, 0], [180, 0]], "endArrowhead": "arrow", "startArrowhead": "dot",
+     "startBinding": {"elementId": "box", "focus": 0, "gap": 0},
+     "endBinding": {"elementId": "oval", "focus": 0, "gap": 0}},
+    {"id": "gone", "type": "rectangle", "x": 0, "y": 0, "width": 10, "height": 10,
+     "isDeleted": true},
+    {"id": "note", "type": "text", "x": 0, "y": 100, "width": 50, "height": 20,
+     "text": "Free", "fontSize": -4}
+  ]
+}"##;
+
+    fn shape(e: &model::Element) -> Option<(String, &'static str)> {
+        let (label, kind) = match &e.kind {
+            model::ElementKind::Rect { label, .. } => (label, "rect"),
+            model::ElementKind::Ellipse { label, .. } => (label, "ellipse"),
+            model::ElementKind::Diamond { label, .. } => (label, "diamond"),
+            _ => return None,
+        };
+        Some((label.clone(), kind))
+    }
+
+    /// Shapes and the lines bound between them, sorted so documents can be
+    /// compared regardless of element order and ids.
+    fn summary(doc: &model::Document) -> (Vec<(String, &'static str)>, Vec<String>) {
+        let label_of = |id: u64| {
+            let element = doc.elements.iter().find(|e| e.id == id);
+            element
+                .and_then(shape)
+                .map(|(label, _)| label)
+                .unwrap_or_default()
+        };
+        let mut nodes: Vec<_> = doc.elements.iter().filter_map(shape).collect();
+        nodes.sort();
+        let mut edges: Vec<_> = doc
+            .elements
+            .iter()
+            .filter_map(|e| match &e.kind {
+                model::ElementKind::Line {
+                    start_binding: Some(s),
+                    end_binding: Some(t),
+                    arrow_style,
+                    label,
+                    ..
+                } => Some(format!(
+                    "{:?} -> {:?} {arrow_style:?} {label:?} {:?}",
+                    label_of(s.element_id),
+                    label_of(t.element_id),
+                    e.style.stroke.line_style,
+                )),
+                _ => None,
+            })
+            .collect();
+        edges.sort();
+        (nodes, edges)
+    }
+
+    #[test]
+    fn parses_shapes_labels_bindings_and_groups() {
+        let doc = parse(SAMPLE).unwrap();
+        assert_eq!(
+            summary(&doc),
+            (
+                vec![("".to_string(), "ellipse"), ("Box".to_string(), "rect")],
+                vec![r#""Box" -> "" Both "" Solid"#.to_string()],
+            )
+        );
+
+        let rect = doc
+            .elements
+            .iter()
+            .find(|e| shape(e).is_some_and(|s| s.0 == "Box"))
+            .unwrap();
+        assert_eq!(rect.style.fill, model::Rgba::from_hex("#a5d8ff"));
+        assert_eq!(rect.style.text_size, 20.0);
+
+        // "inner" nests inside "outer", which also holds the ellipse.
+        assert_eq!(doc.groups.len(), 2);
+        let inner = doc
+            .groups
+            .iter()
+            .find(|g| Some(g.id) == rect.group_id)
+            .unwrap();
+        let oval = doc
+            .elements
+            .iter()
+            .find(|e| matches!(e.kind, model::ElementKind::Ellipse { .. }))
+            .unwrap();
+        assert_eq!(inner.parent_id, oval.group_id);
+        assert_eq!(oval.style.stroke.line_style, model::LineStyle::Dashed);
+
+        let heads = doc
+            .elements
+            .iter()
+            .find_map(|e| match &e.kind {
+                model::ElementKind::Line { heads, .. } => Some(*heads),
+                _ => None,
+            })
+            .unwrap();
+        assert_eq!(heads.start, model::ArrowHead::Circle);
+        assert_eq!(heads.end, model::ArrowHead::Open);
+
+        let free = doc.elements.iter().find_map(|e| match &e.kind {
+            model::ElementKind::Text { text, .. } => Some((text.as_str(), e.style.text_size)),
+            _ => None,
+        });
+        assert_eq!(
+            free,
+            Some(("Free", model::Style::default_for_shapes().text_size))
+        );
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert_eq!(
+            parse(r#"{"type": "drawio"}"#).unwrap_err(),
+            "expected an Excalidraw file, found type 'drawio'"
+        );
+        assert_eq!(
+            parse(r#"{"type": "excalidraw", "elements": []}"#).unwrap_err(),
+            "Excalidraw file has no supported elements"
+        );
+        let err = parse(
+            "{\n  \"type\": \"excalidraw\",\n  \"elements\": [\n    {\"x\": \"far\"}\n  ]\n}",
+        )
+        .unwrap_err();
+        assert!(err.contains("line 4"), "{err}");
+    }
+
+    #[test]
+    fn export_round_trips_through_import() {
+        let doc = crate::dsl::parse(
+            r#"
+            node a rect "Alpha" fill=#ddeeff
+            node b ellipse "Beta"
+            group {
+                node c diamond "Gamma"
+                node d rect "Two\nlines" line=dotted
+            }
+            a -> b "calls"
+            b <-> c line=dashed
+            c -- d
+            "#,
+        )
+        .unwrap();
+        let json = document_to_excalidraw(&doc);
+        let back = parse(&json).unwrap();
+        assert_eq!(summary(&doc).1.len(), 3);
+        assert_eq!(summary(&back), summary(&doc), "{json}");
+        assert_eq!(back.groups.len(), doc.groups.len());
+    }
+}
@@ -0,0 +1,1629 @@
+use crate::model;
+use std::collections::{HashMap, HashSet};
+
+pub fn rotate_vec2(v: egui::Vec2, angle: f32) -> egui::Vec2 {
+    let sin = angle.sin();
+    let cos = angle.cos();
+    egui::vec2(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
+}
+
+pub fn rotated_rect_points_world(rect: egui::Rect, rotation: f32) -> Vec<egui::Pos2> {
+    let center = rect.center();
+    let corners = [
+        rect.left_top(),
+        rect.right_top(),
+        rect.right_bottom(),
+        rect.left_bottom(),
+    ];
+    corners
+        .into_iter()
+        .map(|p| {
+            let v = p - center;
+            center + rotate_vec2(v, rotation)
+        })
+        .collect()
+}
+
+/// Largest usable corner radius for `rect`: half of its shorter side.
+pub fn clamp_corner_radius(rect: egui::Rect, corner_radius: f32) -> f32 {
+    corner_radius.clamp(0.0, rect.width().min(rect.height()).max(0.0) * 0.5)
+}
+
+pub fn rotated_rounded_rect_points_world(
+    rect: egui::Rect,
+    rotation: f32,
+    corner_radius: f32,
+) -> Vec<egui::Pos2> {
+    let r = clamp_corner_radius(rect, corner_radius);
+    if r <= f32::EPSILON {
+        return rotated_rect_points_world(rect, rotation);
+    }
+    let center = rect.center();
+    let hw = rect.width() * 0.5 - r;
+    let hh = rect.height() * 0.5 - r;
+    let corners = [
+        (egui::vec2(-hw, -hh), std::f32::consts::PI),
+        (egui::vec2(hw, -hh), std::f32::consts::PI * 1.5),
+        (egui::vec2(hw, hh), 0.0),
+        (egui::vec2(-hw, hh), std::f32::consts::FRAC_PI_2),
+    ];
+    let steps = 8;
+    let mut points = Vec::with_capacity(corners.len() * (steps + 1));
+    for (corner, start) in corners {
+        for i in 0..=steps {
+            let t = start + (i as f32) / (steps as f32) * std::f32::consts::FRAC_PI_2;
+            let local = corner + egui::vec2(t.cos(), t.sin()) * r;
+            points.push(center + rotate_vec2(local, rotation));
+        }
+    }
+    points
+}
+
+pub fn rotated_ellipse_points_world(rect: egui::Rect, rotation: f32) -> Vec<egui::Pos2> {
+    let center = rect.center();
+    let rx = rect.width() * 0.5;
+    let ry = rect.height() * 0.5;
+    if rx <= f32::EPSILON || ry <= f32::EPSILON {
+        return vec![];
+    }
+    let steps = 48;
+    (0..steps)
+        .map(|i| {
+            let t = (i as f32) / (steps as f32) * std::f32::consts::TAU;
+            let local = egui::vec2(t.cos() * rx, t.sin() * ry);
+            center + rotate_vec2(local, rotation)
+        })
+        .collect()
+}
+
+pub fn rotated_triangle_points_world(
+    rect: egui::Rect,
+    rotation: f32,
+    apex_ratio: f32,
+) -> Vec<egui::Pos2> {
+    let center = rect.center();
+    let half_w = rect.width() * 0.5;
+    let half_h = rect.height() * 0.5;
+    let apex = (apex_ratio.clamp(-1.0, 1.0)) * half_w;
+    let corners = [
+        egui::vec2(apex, -half_h),
+        egui::vec2(half_w, half_h),
+        egui::vec2(-half_w, half_h),
+    ];
+    corners
+        .into_iter()
+        .map(|v| center + rotate_vec2(v, rotation))
+        .collect()
+}
+
+pub fn rotated_parallelogram_points_world(
+    rect: egui::Rect,
+    rotation: f32,
+    skew_ratio: f32,
+) -> Vec<egui::Pos2> {
+    let center = rect.center();
+    let half_w = rect.width() * 0.5;
+    let half_h = rect.height() * 0.5;
+    let skew = (skew_ratio.clamp(-0.95, 0.95)) * half_w;
+    let corners = [
+        egui::vec2(-half_w + skew, -half_h),
+        egui::vec2(half_w + skew, -half_h),
+        egui::vec2(half_w - skew, half_h),
+        egui::vec2(-half_w - skew, half_h),
+    ];
+    corners
+        .into_iter()
+        .map(|v| center + rotate_vec2(v, rotation))
+        .collect()
+}
+
+pub fn rotated_trapezoid_points_world(
+    rect: egui::Rect,
+    rotation: f32,
+    top_inset_ratio: f32,
+) -> Vec<egui::Pos2> {
+    let center = rect.center();
+    let half_w = rect.width() * 0.5;
+    let half_h = rect.height() * 0.5;
+    let top_inset = (top_inset_ratio.clamp(0.0, 0.95)) * half_w;
+    let corners = [
+        egui::vec2(-half_w + top_inset, -half_h),
+        egui::vec2(half_w - top_inset, -half_h),
+        egui::vec2(half_w, half_h),
+        egui::vec2(-half_w, half_h),
+    ];
+    corners
+        .into_iter()
+        .map(|v| center + rotate_vec2(v, rotation))
+        .collect()
+}
+
+pub fn rotated_diamond_points_world(rect: egui::Rect, rotation: f32) -> Vec<egui::Pos2> {
+    let center = rect.center();
+    let half_w = rect.width() * 0.5;
+    let half_h = rect.height() * 0.5;
+    let corners = [
+        egui::vec2(0.0, -half_h),
+        egui::vec2(half_w, 0.0),
+        egui::vec2(0.0, half_h),
+        egui::vec2(-half_w, 0.0),
+    ];
+    corners
+        .into_iter()
+        .map(|v| center + rotate_vec2(v, rotation))
+        .collect()
+}
+
+pub fn rotated_hexagon_points_world(
+    rect: egui::Rect,
+    rotation: f32,
+    inset_ratio: f32,
+) -> Vec<egui::Pos2> {
+    let center = rect.center();
+    let half_w = rect.width() * 0.5;
+    let half_h = rect.height() * 0.5;
+    let inset = (inset_ratio.clamp(0.0, 0.95)) * half_w;
+    let corners = [
+        egui::vec2(-half_w + inset, -half_h),
+        egui::vec2(half_w - inset, -half_h),
+        egui::vec2(half_w, 0.0),
+        egui::vec2(half_w - inset, half_h),
+        egui::vec2(-half_w + inset, half_h),
+        egui::vec2(-half_w, 0.0),
+    ];
+    corners
+        .into_iter()
+        .map(|v| center + rotate_vec2(v, rotation))
+        .collect()
+}
+
+/// Height of a cylinder's elliptical caps; `cap_ratio` is a fraction of the
+/// full height.
+fn cylinder_cap_height(rect: egui::Rect, cap_ratio: f32) -> f32 {
+    cap_ratio.clamp(0.0, 0.5) * rect.height()
+}
+
+/// Silhouette of a cylinder: the top of the upper cap, the sides and the
+/// bottom of the lower cap.
+pub fn rotated_cylinder_points_world(
+    rect: egui::Rect,
+    rotation: f32,
+    cap_ratio: f32,
+) -> Vec<egui::Pos2> {
+    let center = rect.center();
+    let half_w = rect.width() * 0.5;
+    let half_h = rect.height() * 0.5;
+    let ry = cylinder_cap_height(rect, cap_ratio) * 0.5;
+    let steps = 16;
+    let mut points = Vec::with_capacity(2 * (steps + 1));
+    for (cy, start) in [(-half_h + ry, std::f32::consts::PI), (half_h - ry, 0.0)] {
+        for i in 0..=steps {
+            let t = start + (i as f32) / (steps as f32) * std::f32::consts::PI;
+            let local = egui::vec2(t.cos() * half_w, cy + t.sin() * ry);
+            points.push(center + rotate_vec2(local, rotation));
+        }
+    }
+    points
+}
+
+/// Front edge of a cylinder's top cap, drawn over the body as an open path.
+pub fn rotated_cylinder_rim_points_world(
+    rect: egui::Rect,
+    rotation: f32,
+    cap_ratio: f32,
+) -> Vec<egui::Pos2> {
+    let center = rect.center();
+    let half_w = rect.width() * 0.5;
+    let ry = cylinder_cap_height(rect, cap_ratio) * 0.5;
+    let cy = -rect.height() * 0.5 + ry;
+    let steps = 16;
+    (0..=steps)
+        .map(|i| {
+            let t = (i as f32) / (steps as f32) * std::f32::consts::PI;
+            let local = egui::vec2(t.cos() * half_w, cy + t.sin() * ry);
+            center + rotate_vec2(local, rotation)
+        })
+        .collect()
+}
+
+/// Regular polygon inscribed in the ellipse of `rect`, resting on a flat side.
+pub fn rotated_polygon_points_world(
+    rect: egui::Rect,
+    rotation: f32,
+    sides: u32,
+) -> Vec<egui::Pos2> {
+    let center = rect.center();
+    let half_w = rect.width() * 0.5;
+    let half_h = rect.height() * 0.5;
+    let n = sides.clamp(3, 24);
+    (0..n)
+        .map(|i| {
+            let t =
+                std::f32::consts::FRAC_PI_2 + (i as f32 + 0.5) / (n as f32) * std::f32::consts::TAU;
+            let local = egui::vec2(t.cos() * half_w, t.sin() * half_h);
+            center + rotate_vec2(local, rotation)
+        })
+        .collect()
+}
+
+/// Closed outline of a shape element in world space; `None` for lines, pen
+/// strokes and text.
+pub fn shape_outline_world(element: &model::Element) -> Option<Vec<egui::Pos2>> {
+    let rotation = element.rotation;
+    let points = match &element.kind {
+        model::ElementKind::Rect {
+            rect,
+            corner_radius,
+            ..
+        } => rotated_rounded_rect_points_world(rect.to_rect(), rotation, *corner_radius),
+        model::ElementKind::Ellipse { rect, .. } => {
+            rotated_ellipse_points_world(rect.to_rect(), rotation)
+        }
+        model::ElementKind::Triangle {
+            rect, apex_ratio, ..
+        } => rotated_triangle_points_world(rect.to_rect(), rotation, *apex_ratio),
+        model::ElementKind::Parallelogram {
+            rect, skew_ratio, ..
+        } => rotated_parallelogram_points_world(rect.to_rect(), rotation, *skew_ratio),
+        model::ElementKind::Trapezoid {
+            rect,
+            top_inset_ratio,
+            ..
+        } => rotated_trapezoid_points_world(rect.to_rect(), rotation, *top_inset_ratio),
+        model::ElementKind::Diamond { rect, .. } => {
+            rotated_diamond_points_world(rect.to_rect(), rotation)
+        }
+        model::ElementKind::Hexagon {
+            rect, inset_ratio, ..
+        } => rotated_hexagon_points_world(rect.to_rect(), rotation, *inset_ratio),
+        model::ElementKind::Cylinder {
+            rect, cap_ratio, ..
+        } => rotated_cylinder_points_world(rect.to_rect(), rotation, *cap_ratio),
+        model::ElementKind::Polygon { rect, sides, .. } => {
+            rotated_polygon_points_world(rect.to_rect(), rotation, *sides)
+        }
+        _ => return None,
+    };
+    Some(points)
+}
+
+/// Closest point to `p` on the closed outline through `points`.
+fn closest_point_on_outline(points: &[egui::Pos2], p: egui::Pos2) -> Option<egui::Pos2> {
+    let mut best: Option<(f32, egui::Pos2)> = None;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        let ab = b - *a;
+        let len2 = ab.length_sq();
+        let t = if len2 <= f32::EPSILON {
+            0.0
+        } else {
+            ((p - *a).dot(ab) / len2).clamp(0.0, 1.0)
+        };
+        let q = *a + ab * t;
+        let d = (p - q).length_sq();
+        if best.is_none_or(|(bd, _)| d < bd) {
+            best = Some((d, q));
+        }
+    }
+    best.map(|(_, q)| q)
+}
+
+/// Where the outline meets the ray from `center` through `p`, or the closest
+/// outline point when `p` lies inside.
+fn outline_point_toward(
+    points: &[egui::Pos2],
+    center: egui::Pos2,
+    p: egui::Pos2,
+) -> Option<egui::Pos2> {
+    if point_in_polygon(points, p) || (p - center).length() <= f32::EPSILON {
+        return closest_point_on_outline(points, p);
+    }
+    outline_point_along_ray(points, center, p).or_else(|| closest_point_on_outline(points, p))
+}
+
+/// Farthest point where the ray from `center` through `p` crosses the outline.
+fn outline_point_along_ray(
+    points: &[egui::Pos2],
+    center: egui::Pos2,
+    p: egui::Pos2,
+) -> Option<egui::Pos2> {
+    let d = p - center;
+    if d.length() <= f32::EPSILON {
+        return None;
+    }
+    let mut best: Option<f32> = None;
+    for (i, a) in points.iter().enumerate() {
+        let b = points[(i + 1) % points.len()];
+        let e = b - *a;
+        let denom = d.x * e.y - d.y * e.x;
+        if denom.abs() <= f32::EPSILON {
+            continue;
+        }
+        let w = *a - center;
+        let t = (w.x * e.y - w.y * e.x) / denom;
+        let u = (w.x * d.y - w.y * d.x) / denom;
+        if t >= 0.0 && (0.0..=1.0).contains(&u) && best.is_none_or(|bt| t > bt) {
+            best = Some(t);
+        }
+    }
+    best.map(|t| center + d * t)
+}
+
+fn point_in_polygon(points: &[egui::Pos2], p: egui::Pos2) -> bool {
+    let mut inside = false;
+    let mut j = points.len().saturating_sub(1);
+    for (i, a) in points.iter().enumerate() {
+        let b = points[j];
+        if (a.y > p.y) != (b.y > p.y) && p.x < (b.x - a.x) * (p.y - a.y) / (b.y - a.y) + a.x {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+fn hit_test_outline(points: &[egui::Pos2], world_pos: egui::Pos2, threshold_world: f32) -> bool {
+    if points.len() < 3 {
+        return false;
+    }
+    point_in_polygon(points, world_pos)
+        || closest_point_on_outline(points, world_pos)
+            .is_some_and(|q| (world_pos - q).length() <= threshold_world)
+}
+
+fn aabb_of_points(points: &[egui::Pos2]) -> egui::Rect {
+    let mut min = egui::pos2(f32::INFINITY, f32::INFINITY);
+    let mut max = egui::pos2(f32::NEG_INFINITY, f32::NEG_INFINITY);
+    for p in points {
+        min.x = min.x.min(p.x);
+        min.y = min.y.min(p.y);
+        max.x = max.x.max(p.x);
+        max.y = max.y.max(p.y);
+    }
+    if min.x.is_finite() && min.y.is_finite() && max.x.is_finite() && max.y.is_finite() {
+        egui::Rect::from_min_max(min, max)
+    } else {
+        egui::Rect::NOTHING
+    }
+}
+
+fn hit_test_rotated_rect(
+    rect: egui::Rect,
+    rotation: f32,
+    corner_radius: f32,
+    world_pos: egui::Pos2,
+    threshold_world: f32,
+) -> bool {
+    let center = rect.center();
+    let half = rect.size() * 0.5;
+    let v = world_pos - center;
+    let local = rotate_vec2(v, -rotation);
+    if local.x.abs() > half.x + threshold_world || local.y.abs() > half.y + threshold_world {
+        return false;
+    }
+    let r = clamp_corner_radius(rect, corner_radius);
+    let corner = egui::vec2(local.x.abs() - (half.x - r), local.y.abs() - (half.y - r));
+    if r <= f32::EPSILON || corner.x <= 0.0 || corner.y <= 0.0 {
+        return true;
+    }
+    corner.length() <= r + threshold_world
+}
+
+fn hit_test_rotated_ellipse(
+    rect: egui::Rect,
+    rotation: f32,
+    world_pos: egui::Pos2,
+    threshold_world: f32,
+) -> bool {
+    let center = rect.center();
+    let rx = rect.width() * 0.5 + threshold_world;
+    let ry = rect.height() * 0.5 + threshold_world;
+    if rx <= f32::EPSILON || ry <= f32::EPSILON {
+        return false;
+    }
+    let v = world_pos - center;
+    let local = rotate_vec2(v, -rotation);
+    let dx = local.x / rx;
+    let dy = local.y / ry;
+    dx * dx + dy * dy <= 1.0
+}
+
+pub fn resolved_line_endpoints_world(
+    doc: &model::Document,
+    a: model::Point,
+    b: model::Point,
+    start_binding: &Option<model::Binding>,
+    end_binding: &Option<model::Binding>,
+) -> (egui::Pos2, egui::Pos2) {
+    let a0 = a.to_pos2();
+    let b0 = b.to_pos2();
+    let a = start_binding
+        .as_ref()
+        .and_then(|bind| resolve_binding_point(doc, bind))
+        .unwrap_or(a0);
+    let b = end_binding
+        .as_ref()
+        .and_then(|bind| resolve_binding_point(doc, bind))
+        .unwrap_or(b0);
+    (a, b)
+}
+
+/// Points a line passes through in world space: its resolved ends with the
+/// bends of an orthogonal route in between.
+pub fn line_points_world(
+    doc: &model::Document,
+    a: model::Point,
+    b: model::Point,
+    route: &model::LineRoute,
+    start_binding: &Option<model::Binding>,
+    end_binding: &Option<model::Binding>,
+) -> Vec<egui::Pos2> {
+    let (a, b) = resolved_line_endpoints_world(doc, a, b, start_binding, end_binding);
+    match route {
+        model::LineRoute::Straight => vec![a, b],
+        model::LineRoute::Orthogonal { bends, .. } => std::iter::once(a)
+            .chain(bends.iter().map(|p| p.to_pos2()))
+            .chain(std::iter::once(b))
+            .collect(),
+        model::LineRoute::Curved { c1, c2 } => {
+            flatten_cubic_bezier(&[a, a + c1.to_pos2().to_vec2(), b + c2.to_pos2().to_vec2(), b])
+        }
+    }
+}
+
+/// Points a polyline passes through in world space, with its ends moved onto
+/// whatever they are bound to.
+pub fn polyline_points_world(
+    doc: &model::Document,
+    points: &[model::Point],
+    start_binding: &Option<model::Binding>,
+    end_binding: &Option<model::Binding>,
+) -> Vec<egui::Pos2> {
+    let mut out: Vec<egui::Pos2> = points.iter().map(|p| p.to_pos2()).collect();
+    let resolve = |binding: &Option<model::Binding>| {
+        binding.as_ref().and_then(|b| resolve_binding_point(doc, b))
+    };
+    if let (Some(p), Some(first)) = (resolve(start_binding), out.first_mut()) {
+        *first = p;
+    }
+    if let (Some(p), Some(last)) = (resolve(end_binding), out.last_mut()) {
+        *last = p;
+    }
+    out
+}
+
+/// Start, control points and end of a curved line in world space, or `None`
+/// when the line is not curved.
+pub fn line_curve_world(
+    doc: &model::Document,
+    a: model::Point,
+    b: model::Point,
+    route: &model::LineRoute,
+    start_binding: &Option<model::Binding>,
+    end_binding: &Option<model::Binding>,
+) -> Option<[egui::Pos2; 4]> {
+    let model::LineRoute::Curved { c1, c2 } = route else {
+        return None;
+    };
+    let (a, b) = resolved_line_endpoints_world(doc, a, b, start_binding, end_binding);
+    Some([a, a + c1.to_pos2().to_vec2(), b + c2.to_pos2().to_vec2(), b])
+}
+
+/// Control point offsets for a new curve from `a` to `b`. A bound end leaves
+/// straight out of its side; a free end starts off towards the other one.
+pub fn default_curve_controls(
+    doc: &model::Document,
+    a: egui::Pos2,
+    b: egui::Pos2,
+    start_binding: &Option<model::Binding>,
+    end_binding: &Option<model::Binding>,
+) -> (model::Point, model::Point) {
+    let reach = (a.distance(b) * 0.4).max(24.0);
+    let control = |binding: &Option<model::Binding>, from: egui::Pos2, to: egui::Pos2| {
+        let offset = binding
+            .as_ref()
+            .and_then(|bind| binding_exit_direction(doc, bind))
+            .map_or((to - from) / 3.0, |dir| dir * reach);
+        model::Point {
+            x: offset.x,
+            y: offset.y,
+        }
+    };
+    (control(start_binding, a, b), control(end_binding, b, a))
+}
+
+pub fn cubic_bezier_point(curve: &[egui::Pos2; 4], t: f32) -> egui::Pos2 {
+    let u = 1.0 - t;
+    let [p0, p1, p2, p3] = curve.map(|p| p.to_vec2());
+    (p0 * (u * u * u) + p1 * (3.0 * u * u * t) + p2 * (3.0 * u * t * t) + p3 * (t * t * t))
+        .to_pos2()
+}
+
+/// Points along a cubic Bézier, spaced finely enough to draw it as a polyline.
+pub fn flatten_cubic_bezier(curve: &[egui::Pos2; 4]) -> Vec<egui::Pos2> {
+    let hull = curve.windows(2).map(|w| w[0].distance(w[1])).sum::<f32>();
+    let segments = ((hull / 4.0).ceil() as usize).clamp(8, 128);
+    (0..=segments)
+        .map(|i| cubic_bezier_point(curve, i as f32 / segments as f32))
+        .collect()
+}
+
+/// Points the arrowheads at the start and end of a line are aimed from: the
+/// neighbouring path points, or on a curve the nearest control point off the
+/// end, which lies along the tangent there.
+pub fn arrow_tails(
+    points: &[egui::Pos2],
+    curve: Option<&[egui::Pos2; 4]>,
+) -> (egui::Pos2, egui::Pos2) {
+    let Some(&[p0, p1, p2, p3]) = curve else {
+        return (points[1], points[points.len() - 2]);
+    };
+    let off = |end: egui::Pos2, candidates: [egui::Pos2; 3]| {
+        candidates
+            .into_iter()
+            .find(|p| p.distance(end) > 1e-3)
+            .unwrap_or(end)
+    };
+    (off(p0, [p1, p2, p3]), off(p3, [p2, p1, p0]))
+}
+
+/// Length of an arrowhead on a line of the given stroke width.
+pub fn arrowhead_size(stroke_width: f32, scale: f32) -> f32 {
+    (6.0 + stroke_width * 2.0) * scale
+}
+
+/// One piece of an arrowhead, painted in the line's stroke color.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArrowheadPart {
+    /// Closed outline filled with the stroke color.
+    Filled(Vec<egui::Pos2>),
+    /// Closed outline filled white and stroked, hiding the line under it.
+    Hollow(Vec<egui::Pos2>),
+    /// Open polyline stroked like the line.
+    Stroked(Vec<egui::Pos2>),
+}
+
+/// An arrowhead `size` long with its tip at the origin, pointing along +x.
+/// The line runs up to the tip, so heads such as the crow's foot rely on it
+/// for their middle stroke.
+pub fn arrowhead_parts(head: model::ArrowHead, size: f32) -> Vec<ArrowheadPart> {
+    let (l, w) = (size, size * 0.6);
+    let p = egui::pos2;
+    match head {
+        model::ArrowHead::Triangle => {
+            vec![ArrowheadPart::Filled(vec![
+                p(0.0, 0.0),
+                p(-l, w),
+                p(-l, -w),
+            ])]
+        }
+        model::ArrowHead::Open => {
+            vec![ArrowheadPart::Stroked(vec![
+                p(-l, w),
+                p(0.0, 0.0),
+                p(-l, -w),
+            ])]
+        }
+        model::ArrowHead::Diamond | model::ArrowHead::HollowDiamond => {
+            let outline = vec![
+                p(0.0, 0.0),
+                p(-l * 0.6, w * 0.6),
+                p(-l * 1.2, 0.0),
+                p(-l * 0.6, -w * 0.6),
+            ];
+            if head == model::ArrowHead::Diamond {
+                vec![ArrowheadPart::Filled(outline)]
+            } else {
+                vec![ArrowheadPart::Hollow(outline)]
+            }
+        }
+        model::ArrowHead::Circle => {
+            let r = l * 0.4;
+            let outline = (0..16)
+                .map(|i| {
+                    let a = i as f32 / 16.0 * std::f32::consts::TAU;
+                    p(-r + r * a.cos(), r * a.sin())
+                })
+                .collect();
+            vec![ArrowheadPart::Filled(outline)]
+        }
+        model::ArrowHead::Bar => {
+            vec![ArrowheadPart::Stroked(vec![
+                p(-l * 0.5, w),
+                p(-l * 0.5, -w),
+            ])]
+        }
+        model::ArrowHead::CrowsFoot => {
+            vec![ArrowheadPart::Stroked(vec![
+                p(0.0, w),
+                p(-l, 0.0),
+                p(0.0, -w),
+            ])]
+        }
+    }
+}
+
+/// [`arrowhead_parts`] placed with the tip on `tip`, aimed from `tail`.
+pub fn arrowhead_parts_world(
+    head: model::ArrowHead,
+    tail: egui::Pos2,
+    tip: egui::Pos2,
+    size: f32,
+) -> Vec<ArrowheadPart> {
+    let v = tip - tail;
+    if v.length_sq() <= f32::EPSILON {
+        return Vec::new();
+    }
+    let angle = v.y.atan2(v.x);
+    let place = |points: Vec<egui::Pos2>| -> Vec<egui::Pos2> {
+        points
+            .into_iter()
+            .map(|q| tip + rotate_vec2(q.to_vec2(), angle))
+            .collect()
+    };
+    arrowhead_parts(head, size)
+        .into_iter()
+        .map(|part| match part {
+            ArrowheadPart::Filled(points) => ArrowheadPart::Filled(place(points)),
+            ArrowheadPart::Hollow(points) => ArrowheadPart::Hollow(place(points)),
+            ArrowheadPart::Stroked(points) => ArrowheadPart::Stroked(place(points)),
+        })
+        .collect()
+}
+
+/// Distance from `p` to a cubic Bézier: the closest of a few samples, refined
+/// by bisecting around it.
+pub fn distance_to_cubic_bezier(p: egui::Pos2, curve: &[egui::Pos2; 4]) -> f32 {
+    const SAMPLES: usize = 32;
+    let dist = |t: f32| cubic_bezier_point(curve, t).distance(p);
+    let mut best = 0.0;
+    let mut best_dist = dist(0.0);
+    for i in 1..=SAMPLES {
+        let t = i as f32 / SAMPLES as f32;
+        let d = dist(t);
+        if d < best_dist {
+            best = t;
+            best_dist = d;
+        }
+    }
+    let mut step = 1.0 / SAMPLES as f32;
+    for _ in 0..16 {
+        step *= 0.5;
+        for t in [best - step, best + step] {
+            let t = t.clamp(0.0, 1.0);
+            let d = dist(t);
+            if d < best_dist {
+                best = t;
+                best_dist = d;
+            }
+        }
+    }
+    best_dist
+}
+
+/// Point halfway along the path through `points`.
+pub fn path_midpoint(points: &[egui::Pos2]) -> egui::Pos2 {
+    path_point_at(points, 0.5)
+}
+
+/// Point `fraction` of the way along the path through `points`.
+pub fn path_point_at(points: &[egui::Pos2], fraction: f32) -> egui::Pos2 {
+    let total: f32 = points.windows(2).map(|w| w[0].distance(w[1])).sum();
+    let mut remaining = total * fraction.clamp(0.0, 1.0);
+    for w in points.windows(2) {
+        let len = w[0].distance(w[1]);
+        if len >= remaining && len > 0.0 {
+            return w[0] + (w[1] - w[0]) * (remaining / len);
+        }
+        remaining -= len;
+    }
+    points.last().copied().unwrap_or(egui::Pos2::ZERO)
+}
+
+/// How far along the path through `points` its closest point to `p` lies, as
+/// a fraction of the path's length.
+pub fn path_fraction_nearest(points: &[egui::Pos2], p: egui::Pos2) -> f32 {
+    let total: f32 = points.windows(2).map(|w| w[0].distance(w[1])).sum();
+    if total <= f32::EPSILON {
+        return 0.0;
+    }
+    let mut best = (f32::INFINITY, 0.0);
+    let mut walked = 0.0;
+    for w in points.windows(2) {
+        let v = w[1] - w[0];
+        let len = v.length();
+        let t = if len > 0.0 {
+            ((p - w[0]).dot(v) / (len * len)).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let d = p.distance(w[0] + v * t);
+        if d < best.0 {
+            best = (d, walked + len * t);
+        }
+        walked += len;
+    }
+    best.1 / total
+}
+
+/// Center of a line's label in world space.
+pub fn line_label_anchor(doc: &model::Document, element: &model::Element) -> Option<egui::Pos2> {
+    let model::ElementKind::Line {
+        a,
+        b,
+        start_binding,
+        end_binding,
+        route,
+        label_position,
+        label_offset,
+        ..
+    } = &element.kind
+    else {
+        return None;
+    };
+    let points = line_points_world(doc, *a, *b, route, start_binding, end_binding);
+    Some(path_point_at(&points, *label_position) + label_offset.to_pos2().to_vec2())
+}
+
+/// Estimated box of a line's label in world space, or `None` when it has no
+/// label.
+pub fn line_label_rect(doc: &model::Document, element: &model::Element) -> Option<egui::Rect> {
+    let model::ElementKind::Line { label, .. } = &element.kind else {
+        return None;
+    };
+    if label.is_empty() {
+        return None;
+    }
+    let size = model::estimated_text_size(label, element.style.text_size);
+    Some(egui::Rect::from_center_size(line_label_anchor(doc, element)?, size).expand(2.0))
+}
+
+/// World-space path a line or polyline is drawn along, or `None` for other
+/// kinds.
+pub fn connector_path_world(
+    doc: &model::Document,
+    element: &model::Element,
+) -> Option<Vec<egui::Pos2>> {
+    match &element.kind {
+        model::ElementKind::Line {
+            a,
+            b,
+            route,
+            start_binding,
+            end_binding,
+            ..
+        } => Some(line_points_world(
+            doc,
+            *a,
+            *b,
+            route,
+            start_binding,
+            end_binding,
+        )),
+        model::ElementKind::Polyline {
+            points,
+            start_binding,
+            end_binding,
+            ..
+        } if points.len() >= 2 => Some(polyline_points_world(
+            doc,
+            points,
+            start_binding,
+            end_binding,
+        )),
+        _ => None,
+    }
+}
+
+/// A line's or polyline's path following only those of its ends bound to
+/// shapes. Ends bound to a connector resolve against this, so connectors
+/// bound to each other cannot chase one another in circles.
+fn connector_anchor_path(
+    doc: &model::Document,
+    element: &model::Element,
+) -> Option<Vec<egui::Pos2>> {
+    let on_shape = |binding: &Option<model::Binding>| {
+        binding.clone().filter(|b| {
+            doc.elements
+                .iter()
+                .find(|e| e.id == b.element_id)
+                .is_some_and(|e| {
+                    !matches!(
+                        e.kind,
+                        model::ElementKind::Line { .. } | model::ElementKind::Polyline { .. }
+                    )
+                })
+        })
+    };
+    match &element.kind {
+        model::ElementKind::Line {
+            a,
+            b,
+            route,
+            start_binding,
+            end_binding,
+            ..
+        } => Some(line_points_world(
+            doc,
+            *a,
+            *b,
+            route,
+            &on_shape(start_binding),
+            &on_shape(end_binding),
+        )),
+        model::ElementKind::Polyline {
+            points,
+            start_binding,
+            end_binding,
+            ..
+        } if points.len() >= 2 => Some(polyline_points_world(
+            doc,
+            points,
+            &on_shape(start_binding),
+            &on_shape(end_binding),
+        )),
+        _ => None,
+    }
+}
+
+fn binding_along(element_id: u64, path: &[egui::Pos2], world_pos: egui::Pos2) -> model::Binding {
+    model::Binding {
+        element_id,
+        norm: model::Point {
+            x: path_fraction_nearest(path, world_pos),
+            y: 0.0,
+        },
+        port: None,
+    }
+}
+
+/// Binding to the topmost line or polyline other than `exclude` passing
+/// within `threshold_world` of `world_pos`, for a connector end that joins it
+/// in a T-junction.
+pub fn compute_connector_binding(
+    doc: &model::Document,
+    world_pos: egui::Pos2,
+    threshold_world: f32,
+    exclude: Option<u64>,
+) -> Option<model::Binding> {
+    doc.elements
+        .iter()
+        .rev()
+        .filter(|e| Some(e.id) != exclude)
+        .find_map(|e| {
+            let path = connector_anchor_path(doc, e)?;
+            path.windows(2)
+                .any(|w| model::distance_to_segment(world_pos, w[0], w[1]) <= threshold_world)
+                .then(|| binding_along(e.id, &path, world_pos))
+        })
+}
+
+/// Where segment `a0`-`a1` crosses `b0`-`b1`, as a fraction along the first.
+fn segment_crossing(a0: egui::Pos2, a1: egui::Pos2, b0: egui::Pos2, b1: egui::Pos2) -> Option<f32> {
+    let (r, s) = (a1 - a0, b1 - b0);
+    let denom = r.x * s.y - r.y * s.x;
+    if denom.abs() <= 1e-6 * r.length() * s.length() {
+        return None;
+    }
+    let q = b0 - a0;
+    let t = (q.x * s.y - q.y * s.x) / denom;
+    let u = (q.x * r.y - q.y * r.x) / denom;
+    ((0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u)).then_some(t)
+}
+
+/// Half the width of the hop a connector makes over another.
+pub fn line_hop_radius(stroke_width: f32) -> f32 {
+    4.0 + stroke_width
+}
+
+/// Hops each connector makes over the connectors below it in z-order, as
+/// distances along its path keyed by element id; empty unless the document
+/// has line jumps on. Crossings within a hop of a corner or of either
+/// connector's end are left alone, so connectors that meet there still look
+/// joined.
+pub fn line_hops(doc: &model::Document) -> HashMap<u64, Vec<f32>> {
+    // Cosine of the sharpest turn a hop may straddle.
+    const MIN_TURN_COS: f32 = 0.94;
+    let mut hops = HashMap::new();
+    if !doc.line_jumps {
+        return hops;
+    }
+    let wires: Vec<(&model::Element, Vec<egui::Pos2>, egui::Rect)> = doc
+        .elements
+        .iter()
+        .filter_map(|e| {
+            let path = connector_path_world(doc, e)?;
+            let bounds = aabb_of_points(&path);
+            Some((e, path, bounds))
+        })
+        .collect();
+    for (i, (element, path, bounds)) in wires.iter().enumerate() {
+        let radius = line_hop_radius(element.style.stroke.width);
+        let mut walked = vec![0.0];
+        for w in path.windows(2) {
+            walked.push(walked[walked.len() - 1] + w[0].distance(w[1]));
+        }
+        let total = walked[walked.len() - 1];
+        let corners: Vec<f32> = path
+            .windows(3)
+            .zip(&walked[1..])
+            .filter(|(w, _)| {
+                (w[1] - w[0]).normalized().dot((w[2] - w[1]).normalized()) < MIN_TURN_COS
+            })
+            .map(|(_, d)| *d)
+            .collect();
+        let mut found = Vec::new();
+        for (_, below, below_bounds) in &wires[..i] {
+            if !bounds.intersects(*below_bounds) {
+                continue;
+            }
+            let below_ends = [below[0], below[below.len() - 1]];
+            for (segment, w) in path.windows(2).enumerate() {
+                for v in below.windows(2) {
+                    let Some(t) = segment_crossing(w[0], w[1], v[0], v[1]) else {
+                        continue;
+                    };
+                    let along = walked[segment] + t * w[0].distance(w[1]);
+                    let p = w[0] + (w[1] - w[0]) * t;
+                    if along >= radius
+                        && along <= total - radius
+                        && corners.iter().all(|c| (c - along).abs() >= radius)
+                        && below_ends.iter().all(|end| end.distance(p) >= radius)
+                    {
+                        found.push(along);
+                    }
+                }
+            }
+        }
+        if found.is_empty() {
+            continue;
+        }
+        found.sort_by(f32::total_cmp);
+        // Crossings closer together than a hop share one.
+        found.dedup_by(|next, kept| *next - *kept < 2.0 * radius);
+        hops.insert(element.id, found);
+    }
+    hops
+}
+
+/// Which way a hop along `dir` bulges: up, or left on vertical segments.
+pub fn hop_side(dir: egui::Vec2) -> egui::Vec2 {
+    let normal = egui::vec2(dir.y, -dir.x);
+    if normal.y > 0.0 || (normal.y == 0.0 && normal.x > 0.0) {
+        -normal
+    } else {
+        normal
+    }
+}
+
+/// The pieces of the path through `points` left between its hops, given as
+/// sorted distances along it. Each hop is a half circle from the end of one
+/// piece to the start of the next.
+pub fn hop_runs(points: &[egui::Pos2], hops: &[f32], radius: f32) -> Vec<Vec<egui::Pos2>> {
+    let Some(&first) = points.first() else {
+        return Vec::new();
+    };
+    let mut runs = vec![vec![first]];
+    let mut cuts = hops
+        .iter()
+        .flat_map(|along| [along - radius, along + radius])
+        .peekable();
+    let mut in_hop = false;
+    let mut walked = 0.0;
+    for w in points.windows(2) {
+        let len = w[0].distance(w[1]);
+        while let Some(cut) = cuts.next_if(|cut| *cut <= walked + len) {
+            let p = w[0] + (w[1] - w[0]).normalized() * (cut - walked);
+            if in_hop {
+                runs.push(vec![p]);
+            } else if let Some(run) = runs.last_mut() {
+                run.push(p);
+            }
+            in_hop = !in_hop;
+        }
+        if !in_hop && let Some(run) = runs.last_mut() {
+            run.push(w[1]);
+        }
+        walked += len;
+    }
+    runs
+}
+
+/// `points` with a half circle of `radius` over each hop.
+pub fn hopped_path(points: &[egui::Pos2], hops: &[f32], radius: f32) -> Vec<egui::Pos2> {
+    const STEPS: usize = 8;
+    let mut out: Vec<egui::Pos2> = Vec::new();
+    for run in hop_runs(points, hops, radius) {
+        if let (Some(&from), Some(&to)) = (out.last(), run.first()) {
+            let center = from + (to - from) * 0.5;
+            let r = from.distance(to) * 0.5;
+            let dir = (to - from).normalized();
+            let side = hop_side(dir);
+            for i in 1..STEPS {
+                let angle = std::f32::consts::PI * i as f32 / STEPS as f32;
+                out.push(center - dir * (r * angle.cos()) + side * (r * angle.sin()));
+            }
+        }
+        out.extend(run);
+    }
+    out
+}
+
+/// A dot where a connector end meets another connector.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JunctionDot {
+    pub center: egui::Pos2,
+    pub radius: f32,
+    pub color: model::Rgba,
+}
+
+/// Dots where connectors meet; empty unless the document has junction dots
+/// on. An end resting on another connector's side always gets one, while
+/// ends meeting only other ends need three of them, since two are just a
+/// corner. Ends bound to a shape never count.
+pub fn junction_dots(doc: &model::Document) -> Vec<JunctionDot> {
+    const TOLERANCE: f32 = 1.0;
+    let mut dots: Vec<JunctionDot> = Vec::new();
+    if !doc.junction_dots {
+        return dots;
+    }
+    let wires: Vec<(&model::Element, Vec<egui::Pos2>)> = doc
+        .elements
+        .iter()
+        .filter_map(|e| Some((e, connector_path_world(doc, e)?)))
+        .collect();
+    let wire_ids: HashSet<u64> = wires.iter().map(|(e, _)| e.id).collect();
+    let on_shape = |binding: &Option<model::Binding>| {
+        binding
+            .as_ref()
+            .is_some_and(|b| !wire_ids.contains(&b.element_id))
+    };
+    for (i, (element, path)) in wires.iter().enumerate() {
+        let bound = match &element.kind {
+            model::ElementKind::Line {
+                start_binding,
+                end_binding,
+                ..
+            }
+            | model::ElementKind::Polyline {
+                start_binding,
+                end_binding,
+                ..
+            } => [on_shape(start_binding), on_shape(end_binding)],
+            _ => [false, false],
+        };
+        let ends = [path[0], path[path.len() - 1]];
+        for (end, bound) in ends.into_iter().zip(bound) {
+            if bound || dots.iter().any(|d| d.center.distance(end) < TOLERANCE) {
+                continue;
+            }
+            let mut ends_met = 1;
+            let mut on_side = false;
+            for (j, (_, other)) in wires.iter().enumerate() {
+                if j == i {
+                    continue;
+                }
+                if [other[0], other[other.len() - 1]]
+                    .iter()
+                    .any(|p| p.distance(end) < TOLERANCE)
+                {
+                    ends_met += 1;
+                } else if other
+                    .windows(2)
+                    .any(|w| model::distance_to_segment(end, w[0], w[1]) < TOLERANCE)
+                {
+                    on_side = true;
+                }
+            }
+            if on_side || ends_met >= 3 {
+                dots.push(JunctionDot {
+                    center: end,
+                    radius: 2.0 + element.style.stroke.width,
+                    color: element.style.stroke.color,
+                });
+            }
+        }
+    }
+    dots
+}
+
+pub fn topmost_bind_target_id(
+    doc: &model::Document,
+    world_pos: egui::Pos2,
+    threshold_world: f32,
+) -> Option<u64> {
+    for element in doc.elements.iter().rev() {
+        match &element.kind {
+            model::ElementKind::Rect { .. }
+            | model::ElementKind::Ellipse { .. }
+            | model::ElementKind::Triangle { .. }
+            | model::ElementKind::Parallelogram { .. }
+            | model::ElementKind::Trapezoid { .. }
+            | model::ElementKind::Diamond { .. }
+            | model::ElementKind::Hexagon { .. }
+            | model::ElementKind::Cylinder { .. }
+            | model::ElementKind::Polygon { .. }
+                if hit_test_element(doc, element, world_pos, threshold_world) =>
+            {
+                return Some(element.id);
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+pub fn compute_binding_for_target(
+    doc: &model::Document,
+    target_id: u64,
+    world_pos: egui::Pos2,
+) -> Option<model::Binding> {
+    let element = doc.elements.iter().find(|e| e.id == target_id)?;
+    if let Some(path) = connector_anchor_path(doc, element) {
+        return Some(binding_along(target_id, &path, world_pos));
+    }
+    compute_binding_for_element(element, world_pos)
+}
+
+fn compute_binding_for_element(
+    element: &model::Element,
+    world_pos: egui::Pos2,
+) -> Option<model::Binding> {
+    let rect = match &element.kind {
+        model::ElementKind::Rect { rect, .. }
+        | model::ElementKind::Ellipse { rect, .. }
+        | model::ElementKind::Triangle { rect, .. }
+        | model::ElementKind::Parallelogram { rect, .. }
+        | model::ElementKind::Trapezoid { rect, .. }
+        | model::ElementKind::Diamond { rect, .. }
+        | model::ElementKind::Hexagon { rect, .. }
+        | model::ElementKind::Cylinder { rect, .. }
+        | model::ElementKind::Polygon { rect, .. } => rect.to_rect(),
+        _ => return None,
+    };
+    let size = rect.size();
+    if size.x <= f32::EPSILON || size.y <= f32::EPSILON {
+        return None;
+    }
+    let center = rect.center();
+    let local = rotate_vec2(world_pos - center, -element.rotation);
+    let mut nx = local.x / size.x;
+    let mut ny = local.y / size.y;
+    nx = nx.clamp(-0.5, 0.5);
+    ny = ny.clamp(-0.5, 0.5);
+    match &element.kind {
+        model::ElementKind::Rect { .. } => {
+            let dx = 0.5 - nx.abs();
+            let dy = 0.5 - ny.abs();
+            if dx < dy {
+                nx = nx.signum() * 0.5;
+            } else {
+                ny = ny.signum() * 0.5;
+            }
+        }
+        model::ElementKind::Ellipse { .. } => {
+            let v = egui::vec2(nx, ny);
+            let n = v.length();
+            if n <= f32::EPSILON {
+                nx = 0.5;
+                ny = 0.0;
+            } else {
+                let u = v / n * 0.5;
+                nx = u.x;
+                ny = u.y;
+            }
+        }
+        model::ElementKind::Triangle { .. }
+        | model::ElementKind::Parallelogram { .. }
+        | model::ElementKind::Trapezoid { .. }
+        | model::ElementKind::Diamond { .. }
+        | model::ElementKind::Hexagon { .. }
+        | model::ElementKind::Cylinder { .. }
+        | model::ElementKind::Polygon { .. } => {
+            let outline = shape_outline_world(element)?;
+            let q = outline_point_toward(&outline, center, world_pos)?;
+            let local = rotate_vec2(q - center, -element.rotation);
+            nx = local.x / size.x;
+            ny = local.y / size.y;
+        }
+        _ => {}
+    }
+    if let model::ElementKind::Rect { corner_radius, .. } = &element.kind {
+        let r = clamp_corner_radius(rect, *corner_radius);
+        let corner = egui::vec2(
+            nx.abs() * size.x - (size.x * 0.5 - r),
+            ny.abs() * size.y - (size.y * 0.5 - r),
+        );
+        if r > f32::EPSILON && corner.x > 0.0 && corner.y > 0.0 {
+            // Snap onto the arc, keeping the direction from the corner's center.
+            let inner = egui::vec2(
+                local.x.abs() - (size.x * 0.5 - r),
+                local.y.abs() - (size.y * 0.5 - r),
+            )
+            .max(egui::Vec2::ZERO);
+            let dir = if inner.length() <= f32::EPSILON {
+                egui::vec2(
+                    std::f32::consts::FRAC_1_SQRT_2,
+                    std::f32::consts::FRAC_1_SQRT_2,
+                )
+            } else {
+                inner / inner.length()
+            };
+            nx = nx.signum() * (size.x * 0.5 - r + dir.x * r) / size.x;
+            ny = ny.signum() * (size.y * 0.5 - r + dir.y * r) / size.y;
+        }
+    }
+    Some(model::Binding {
+        element_id: element.id,
+        norm: model::Point { x: nx, y: ny },
+        port: None,
+    })
+}
+
+/// Binding to the port of `target_id` nearest to `world_pos`, if one lies
+/// within `radius_world`.
+pub fn compute_port_binding(
+    doc: &model::Document,
+    target_id: u64,
+    world_pos: egui::Pos2,
+    radius_world: f32,
+) -> Option<model::Binding> {
+    let element = doc.elements.iter().find(|e| e.id == target_id)?;
+    element
+        .ports
+        .iter()
+        .filter_map(|port| {
+            let d = (port_world_position(element, port)? - world_pos).length();
+            (d <= radius_world).then_some((port, d))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(port, _)| model::Binding {
+            element_id: target_id,
+            norm: port.norm(),
+            port: Some(port.name.clone()),
+        })
+}
+
+pub fn port_world_position(element: &model::Element, port: &model::Port) -> Option<egui::Pos2> {
+    norm_point_world(element, port.norm(), false)
+}
+
+/// The norm a binding attaches at, taken from its port while that exists.
+pub fn binding_norm(doc: &model::Document, binding: &model::Binding) -> model::Point {
+    doc.elements
+        .iter()
+        .find(|e| e.id == binding.element_id)
+        .and_then(|e| e.port(binding.port.as_deref()?))
+        .map_or(binding.norm, |port| port.norm())
+}
+
+/// Unit vector out of the side a binding sits on in world space: its port's
+/// side, or the side of the shape nearest its norm.
+pub fn binding_exit_direction(
+    doc: &model::Document,
+    binding: &model::Binding,
+) -> Option<egui::Vec2> {
+    let element = doc.elements.iter().find(|e| e.id == binding.element_id)?;
+    // Connectors have no sides to leave from.
+    if connector_anchor_path(doc, element).is_some() {
+        return None;
+    }
+    let local = match binding.port.as_deref().and_then(|name| element.port(name)) {
+        Some(port) => match port.side {
+            model::PortSide::Top => egui::vec2(0.0, -1.0),
+            model::PortSide::Right => egui::vec2(1.0, 0.0),
+            model::PortSide::Bottom => egui::vec2(0.0, 1.0),
+            model::PortSide::Left => egui::vec2(-1.0, 0.0),
+        },
+        None => {
+            let norm = binding.norm;
+            if norm.x.abs() >= norm.y.abs() {
+                egui::vec2(norm.x.signum(), 0.0)
+            } else {
+                egui::vec2(0.0, norm.y.signum())
+            }
+        }
+    };
+    Some(rotate_vec2(local, element.rotation))
+}
+
+pub fn resolve_binding_point(
+    doc: &model::Document,
+    binding: &model::Binding,
+) -> Option<egui::Pos2> {
+    let element = doc.elements.iter().find(|e| e.id == binding.element_id)?;
+    if let Some(path) = connector_anchor_path(doc, element) {
+        return Some(path_point_at(&path, binding.norm.x));
+    }
+    match binding.port.as_deref().and_then(|name| element.port(name)) {
+        Some(port) => port_world_position(element, port),
+        None => norm_point_world(element, binding.norm, true),
+    }
+}
+
+/// World position of `norm` on `element`. Norms computed on square-cornered
+/// rectangles and ellipses already lie on their outline (`on_outline`);
+/// anything else is moved along the ray from the center onto the current
+/// outline.
+fn norm_point_world(
+    element: &model::Element,
+    norm: model::Point,
+    on_outline: bool,
+) -> Option<egui::Pos2> {
+    let (rect, rotation) = match &element.kind {
+        model::ElementKind::Rect { rect, .. }
+        | model::ElementKind::Ellipse { rect, .. }
+        | model::ElementKind::Triangle { rect, .. }
+        | model::ElementKind::Parallelogram { rect, .. }
+        | model::ElementKind::Trapezoid { rect, .. }
+        | model::ElementKind::Diamond { rect, .. }
+        | model::ElementKind::Hexagon { rect, .. }
+        | model::ElementKind::Cylinder { rect, .. }
+        | model::ElementKind::Polygon { rect, .. } => Some((rect.to_rect(), element.rotation)),
+        _ => None,
+    }?;
+    let center = rect.center();
+    let size = rect.size();
+    let local = egui::vec2(norm.x * size.x, norm.y * size.y);
+    let point = center + rotate_vec2(local, rotation);
+    match &element.kind {
+        model::ElementKind::Rect { corner_radius, .. } if on_outline && *corner_radius <= 0.0 => {
+            Some(point)
+        }
+        model::ElementKind::Ellipse { .. } if on_outline => Some(point),
+        // Polygonal and rounded outlines move when their shape parameters or
+        // aspect change, so the stored norm only fixes a direction from the
+        // center.
+        _ => Some(
+            shape_outline_world(element)
+                .and_then(|outline| outline_point_along_ray(&outline, center, point))
+                .unwrap_or(point),
+        ),
+    }
+}
+
+pub fn element_center_world(doc: &model::Document, element_id: u64) -> Option<egui::Pos2> {
+    let element = doc.elements.iter().find(|e| e.id == element_id)?;
+    match &element.kind {
+        model::ElementKind::Rect { rect, .. }
+        | model::ElementKind::Ellipse { rect, .. }
+        | model::ElementKind::Triangle { rect, .. }
+        | model::ElementKind::Parallelogram { rect, .. }
+        | model::ElementKind::Trapezoid { rect, .. }
+        | model::ElementKind::Diamond { rect, .. }
+        | model::ElementKind::Hexagon { rect, .. }
+        | model::ElementKind::Cylinder { rect, .. }
+        | model::ElementKind::Polygon { rect, .. } => Some(rect.to_rect().center()),
+        model::ElementKind::Text { pos, .. } => Some(pos.to_pos2()),
+        _ => Some(element.bounds().center()),
+    }
+}
+
+/// Bindings for a straight connector from `a_id` to `b_id`, each placed on the
+/// side of its element that faces the other one.
+pub fn facing_bindings(
+    doc: &model::Document,
+    a_id: u64,
+    b_id: u64,
+) -> (Option<model::Binding>, Option<model::Binding>) {
+    let (Some(ca), Some(cb)) = (
+        element_center_world(doc, a_id),
+        element_center_world(doc, b_id),
+    ) else {
+        return (None, None);
+    };
+    let mut dir = cb - ca;
+    if dir.length() <= f32::EPSILON {
+        dir = egui::vec2(1.0, 0.0);
+    } else {
+        dir /= dir.length();
+    }
+    let probe_a = ca + dir * 1_000_000.0;
+    let probe_b = cb - dir * 1_000_000.0;
+    (
+        compute_binding_for_target(doc, a_id, probe_a),
+        compute_binding_for_target(doc, b_id, probe_b),
+    )
+}
+
+/// Bindings at the middle of the sides of `a_id` and `b_id` that face each
+/// other, where an orthogonal connector between them starts and ends. The
+/// connector runs along whichever axis has the wider gap between the two.
+pub fn facing_side_bindings(
+    doc: &model::Document,
+    a_id: u64,
+    b_id: u64,
+) -> (Option<model::Binding>, Option<model::Binding>) {
+    let find = |id: u64| doc.elements.iter().find(|e| e.id == id);
+    let (Some(a), Some(b)) = (find(a_id), find(b_id)) else {
+        return (None, None);
+    };
+    let (ra, rb) = (a.bounds(), b.bounds());
+    let d = rb.center() - ra.center();
+    let gap_x = d.x.abs() - (ra.width() + rb.width()) * 0.5;
+    let gap_y = d.y.abs() - (ra.height() + rb.height()) * 0.5;
+    let dir = if gap_x >= gap_y {
+        egui::vec2(d.x.signum(), 0.0)
+    } else {
+        egui::vec2(0.0, d.y.signum())
+    };
+    let side = |element: &model::Element, dir: egui::Vec2| {
+        // Only shapes have sides to attach to.
+        shape_outline_world(element)?;
+        let local = rotate_vec2(dir, -element.rotation);
+        let norm = if local.x.abs() >= local.y.abs() {
+            model::Point {
+                x: 0.5 * local.x.signum(),
+                y: 0.0,
+            }
+        } else {
+            model::Point {
+                x: 0.0,
+                y: 0.5 * local.y.signum(),
+            }
+        };
+        Some(model::Binding {
+            element_id: element.id,
+            norm,
+            port: None,
+        })
+    };
+    (side(a, dir), side(b, -dir))
+}
+
+pub fn translate_element(element: &mut model::Element, delta_world: egui::Vec2) {
+    match &mut element.kind {
+        model::ElementKind::Rect { rect, .. }
+        | model::ElementKind::Ellipse { rect, .. }
+        | model::ElementKind::Triangle { rect, .. }
+        | model::ElementKind::Parallelogram { rect, .. }
+        | model::ElementKind::Trapezoid { rect, .. }
+        | model::ElementKind::Diamond { rect, .. }
+        | model::ElementKind::Hexagon { rect, .. }
+        | model::ElementKind::Cylinder { rect, .. }
+        | model::ElementKind::Polygon { rect, .. } => {
+            rect.min.x += delta_world.x;
+            rect.min.y += delta_world.y;
+            rect.max.x += delta_world.x;
+            rect.max.y += delta_world.y;
+        }
+        model::ElementKind::Line { a, b, route, .. } => {
+            a.x += delta_world.x;
+            a.y += delta_world.y;
+            b.x += delta_world.x;
+            b.y += delta_world.y;
+            if let model::LineRoute::Orthogonal { bends, .. } = route {
+                for p in bends {
+                    p.x += delta_world.x;
+                    p.y += delta_world.y;
+                }
+            }
+        }
+        model::ElementKind::Polyline { points, .. } | model::ElementKind::Pen { points } => {
+            for p in points {
+                p.x += delta_world.x;
+                p.y += delta_world.y;
+            }
+        }
+        model::ElementKind::Text { pos, .. } => {
+            pos.x += delta_world.x;
+            pos.y += delta_world.y;
+        }
+    }
+}
+
+pub fn snap_element_to_grid(element: &mut model::Element, grid_size: f32) {
+    match &mut element.kind {
+        model::ElementKind::Rect { rect, .. }
+        | model::ElementKind::Ellipse { rect, .. }
+        | model::ElementKind::Triangle { rect, .. }
+        | model::ElementKind::Parallelogram { rect, .. }
+        | model::ElementKind::Trapezoid { rect, .. }
+        | model::ElementKind::Diamond { rect, .. }
+        | model::ElementKind::Hexagon { rect, .. }
+        | model::ElementKind::Cylinder { rect, .. }
+        | model::ElementKind::Polygon { rect, .. } => {
+            let min = rect.min.to_pos2();
+            let snapped_min = egui::pos2(
+                (min.x / grid_size).round() * grid_size,
+                (min.y / grid_size).round() * grid_size,
+            );
+            let delta = snapped_min - min;
+            rect.min.x += delta.x;
+            rect.min.y += delta.y;
+            rect.max.x += delta.x;
+            rect.max.y += delta.y;
+        }
+        model::ElementKind::Text { pos, .. } => {
+            let p = pos.to_pos2();
+            let snapped = egui::pos2(
+                (p.x / grid_size).round() * grid_size,
+                (p.y / grid_size).round() * grid_size,
+            );
+            pos.x = snapped.x;
+            pos.y = snapped.y;
+        }
+        _ => {}
+    }
+}
+
+pub fn hit_test_element(
+    doc: &model::Document,
+    element: &model::Element,
+    world_pos: egui::Pos2,
+    threshold_world: f32,
+) -> bool {
+    match &element.kind {
+        model::ElementKind::Rect {
+            rect,
+            corner_radius,
+            ..
+        } => hit_test_rotated_rect(
+            rect.to_rect(),
+            element.rotation,
+            *corner_radius,
+            world_pos,
+            threshold_world,
+        ),
+        model::ElementKind::Triangle { rect, .. }
+        | model::ElementKind::Parallelogram { rect, .. }
+        | model::ElementKind::Trapezoid { rect, .. } => hit_test_rotated_rect(
+            rect.to_rect(),
+            element.rotation,
+            0.0,
+            world_pos,
+            threshold_world,
+        ),
+        model::ElementKind::Diamond { .. }
+        | model::ElementKind::Hexagon { .. }
+        | model::ElementKind::Cylinder { .. }
+        | model::ElementKind::Polygon { .. } => shape_outline_world(element)
+            .is_some_and(|outline| hit_test_outline(&outline, world_pos, threshold_world)),
+        model::ElementKind::Ellipse { rect, .. } => {
+            hit_test_rotated_ellipse(rect.to_rect(), element.rotation, world_pos, threshold_world)
+        }
+        model::ElementKind::Line {
+            a,
+            b,
+            start_binding,
+            end_binding,
+            route,
+            ..
+        } => {
+            if line_label_rect(doc, element).is_some_and(|r| r.contains(world_pos)) {
+                return true;
+            }
+            let threshold = threshold_world + element.style.stroke.width;
+            if let Some(curve) = line_curve_world(doc, *a, *b, route, start_binding, end_binding) {
+                return distance_to_cubic_bezier(world_pos, &curve) <= threshold;
+            }
+            line_points_world(doc, *a, *b, route, start_binding, end_binding)
+                .windows(2)
+                .any(|w| model::distance_to_segment(world_pos, w[0], w[1]) <= threshold)
+        }
+        model::ElementKind::Polyline { points, .. } | model::ElementKind::Pen { points } => {
+            if points.len() < 2 {
+                return false;
+            }
+            let mut prev = points[0].to_pos2();
+            for p in &points[1..] {
+                let p = p.to_pos2();
+                if model::distance_to_segment(world_pos, prev, p)
+                    <= (threshold_world + element.style.stroke.width)
+                {
+                    return true;
+                }
+                prev = p;
+            }
+            false
+        }
+        model::ElementKind::Text { pos, text } => {
+            let pos = pos.to_pos2();
+            let w = (text.chars().count() as f32).max(1.0) * element.style.text_size * 0.6;
+            let h = element.style.text_size * 1.2;
+            egui::Rect::from_min_size(pos, egui::vec2(w, h))
+                .expand(threshold_world)
+                .contains(world_pos)
+        }
+    }
+}
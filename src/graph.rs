@@ -0,0 +1,311 @@
+//! Shared node/edge representation that the text importers build up before
+//! turning it into positioned elements with bound connectors.
+
+use crate::geometry::{
+    default_curve_controls, facing_bindings, facing_side_bindings, resolve_binding_point,
+};
+use crate::layout;
+use crate::model;
+use crate::text_format;
+
+pub(crate) const NODE_WIDTH: f32 = 140.0;
+pub(crate) const NODE_HEIGHT: f32 = 60.0;
+const GRID_GAP_X: f32 = 80.0;
+const GRID_GAP_Y: f32 = 60.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ShapeKind {
+    Rect,
+    Ellipse,
+    Triangle,
+    Parallelogram,
+    Trapezoid,
+    Diamond,
+    Hexagon,
+    Cylinder,
+    Polygon(u32),
+}
+
+pub(crate) struct Node {
+    pub kind: ShapeKind,
+    pub label: String,
+    pub style: model::Style,
+    pub at: Option<egui::Pos2>,
+    pub size: egui::Vec2,
+    pub group_id: Option<u64>,
+    pub ports: Vec<model::Port>,
+}
+
+impl Node {
+    pub fn new(label: impl Into<String>) -> Self {
+        Self {
+            kind: ShapeKind::Rect,
+            label: label.into(),
+            style: model::Style::default_for_shapes(),
+            at: None,
+            size: egui::vec2(NODE_WIDTH, NODE_HEIGHT),
+            group_id: None,
+            ports: Vec::new(),
+        }
+    }
+
+    /// Widens the node so its longest label line fits.
+    pub fn fit_label(&mut self) {
+        let longest = self
+            .label
+            .lines()
+            .map(text_format::visual_char_count)
+            .max()
+            .unwrap_or(0);
+        let lines = self.label.lines().count().max(1);
+        let width = longest as f32 * self.style.text_size * 0.6 + 32.0;
+        let height = lines as f32 * self.style.text_size * 1.2 + 24.0;
+        self.size.x = self.size.x.max(width);
+        self.size.y = self.size.y.max(height);
+    }
+}
+
+pub(crate) struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub arrow_style: model::ArrowStyle,
+    pub heads: model::ArrowHeads,
+    pub style: model::Style,
+    pub group_id: Option<u64>,
+    pub label: String,
+    pub from_port: Option<String>,
+    pub to_port: Option<String>,
+    pub route: model::LineRoute,
+}
+
+pub(crate) fn edge_style() -> model::Style {
+    let mut style = model::Style::default_for_shapes();
+    style.fill = None;
+    style
+}
+
+/// How nodes without an explicit position are placed.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Placement {
+    Grid,
+    Layered(layout::Direction),
+}
+
+#[derive(Default)]
+pub(crate) struct Graph {
+    pub nodes: Vec<Node>,
+    pub edges: Vec<Edge>,
+    pub groups: Vec<model::Group>,
+}
+
+impl Graph {
+    pub fn add_group(&mut self, parent_id: Option<u64>) -> u64 {
+        let id = self.groups.len() as u64 + 1;
+        self.groups.push(model::Group { id, parent_id });
+        id
+    }
+
+    pub fn is_ancestor(&self, ancestor: u64, mut group_id: u64) -> bool {
+        while let Some(parent) = self
+            .groups
+            .iter()
+            .find(|g| g.id == group_id)
+            .and_then(|g| g.parent_id)
+        {
+            if parent == ancestor {
+                return true;
+            }
+            group_id = parent;
+        }
+        false
+    }
+
+    fn positions(&self, placement: Placement) -> Vec<egui::Pos2> {
+        match placement {
+            Placement::Grid => {
+                let unplaced: Vec<&Node> = self.nodes.iter().filter(|n| n.at.is_none()).collect();
+                let columns = (unplaced.len() as f32).sqrt().ceil().max(1.0) as usize;
+                // Each column is as wide as its widest node, each row as tall
+                // as its tallest one.
+                let mut widths = vec![0.0f32; columns];
+                let mut heights = vec![0.0f32; unplaced.len().div_ceil(columns)];
+                for (slot, node) in unplaced.iter().enumerate() {
+                    widths[slot % columns] = widths[slot % columns].max(node.size.x);
+                    heights[slot / columns] = heights[slot / columns].max(node.size.y);
+                }
+                let offsets = |sizes: &[f32], gap: f32| -> Vec<f32> {
+                    sizes
+                        .iter()
+                        .scan(0.0, |next, size| {
+                            let at = *next;
+                            *next += size + gap;
+                            Some(at)
+                        })
+                        .collect()
+                };
+                let xs = offsets(&widths, GRID_GAP_X);
+                let ys = offsets(&heights, GRID_GAP_Y);
+                let mut slot = 0;
+                self.nodes
+                    .iter()
+                    .map(|node| {
+                        node.at.unwrap_or_else(|| {
+                            let pos = egui::pos2(xs[slot % columns], ys[slot / columns]);
+                            slot += 1;
+                            pos
+                        })
+                    })
+                    .collect()
+            }
+            Placement::Layered(direction) => {
+                let sizes: Vec<egui::Vec2> = self.nodes.iter().map(|n| n.size).collect();
+                let links: Vec<(usize, usize)> =
+                    self.edges.iter().map(|e| (e.from, e.to)).collect();
+                layout::layered(&sizes, &links, direction)
+                    .into_iter()
+                    .zip(&self.nodes)
+                    .map(|(p, node)| node.at.unwrap_or(p))
+                    .collect()
+            }
+        }
+    }
+
+    /// Nodes become elements `1..=n` in order, followed by one bound line per
+    /// edge carrying the edge's label. Edge ends naming a port of their node
+    /// attach to it instead of the facing side.
+    pub fn into_document(self, placement: Placement) -> model::Document {
+        let positions = self.positions(placement);
+        let mut doc = model::Document {
+            elements: Vec::new(),
+            groups: self.groups,
+            ..Default::default()
+        };
+        for (i, (node, min)) in self.nodes.iter().zip(positions).enumerate() {
+            let rect = model::RectF::from_min_max(min, min + node.size);
+            let label = node.label.clone();
+            let kind = match node.kind {
+                ShapeKind::Rect => model::ElementKind::Rect {
+                    rect,
+                    label,
+                    corner_radius: 0.0,
+                },
+                ShapeKind::Ellipse => model::ElementKind::Ellipse { rect, label },
+                ShapeKind::Triangle => model::ElementKind::Triangle {
+                    rect,
+                    label,
+                    apex_ratio: 0.0,
+                },
+                ShapeKind::Parallelogram => model::ElementKind::Parallelogram {
+                    rect,
+                    label,
+                    skew_ratio: 0.25,
+                },
+                ShapeKind::Trapezoid => model::ElementKind::Trapezoid {
+                    rect,
+                    label,
+                    top_inset_ratio: 0.25,
+                },
+                ShapeKind::Diamond => model::ElementKind::Diamond { rect, label },
+                ShapeKind::Hexagon => model::ElementKind::Hexagon {
+                    rect,
+                    label,
+                    inset_ratio: 0.5,
+                },
+                ShapeKind::Cylinder => model::ElementKind::Cylinder {
+                    rect,
+                    label,
+                    cap_ratio: 0.2,
+                },
+                ShapeKind::Polygon(sides) => model::ElementKind::Polygon { rect, label, sides },
+            };
+            doc.elements.push(model::Element {
+                id: i as u64 + 1,
+                group_id: node.group_id,
+                rotation: 0.0,
+                snap_enabled: true,
+                kind,
+                style: node.style.clone(),
+                ports: node.ports.clone(),
+            });
+        }
+        let first_edge_id = self.nodes.len() as u64 + 1;
+        for (id, edge) in (first_edge_id..).zip(self.edges) {
+            let a_id = edge.from as u64 + 1;
+            let b_id = edge.to as u64 + 1;
+            let (start_binding, end_binding) = match edge.route {
+                model::LineRoute::Straight => facing_bindings(&doc, a_id, b_id),
+                model::LineRoute::Orthogonal { .. } | model::LineRoute::Curved { .. } => {
+                    facing_side_bindings(&doc, a_id, b_id)
+                }
+            };
+            let start_binding =
+                port_binding(&doc, a_id, edge.from_port.as_deref()).or(start_binding);
+            let end_binding = port_binding(&doc, b_id, edge.to_port.as_deref()).or(end_binding);
+            let a = start_binding
+                .as_ref()
+                .and_then(|b| resolve_binding_point(&doc, b))
+                .unwrap_or_else(|| doc.elements[edge.from].bounds().center());
+            let b = end_binding
+                .as_ref()
+                .and_then(|b| resolve_binding_point(&doc, b))
+                .unwrap_or_else(|| doc.elements[edge.to].bounds().center());
+            let route = match edge.route {
+                model::LineRoute::Curved { .. } => {
+                    let (c1, c2) = default_curve_controls(&doc, a, b, &start_binding, &end_binding);
+                    model::LineRoute::Curved { c1, c2 }
+                }
+                route => route,
+            };
+            // Move the label clear of the line: above a flat one, beside a
+            // steep one.
+            let size = model::estimated_text_size(&edge.label, edge.style.text_size);
+            let label_offset = if (b.x - a.x).abs() >= (b.y - a.y).abs() {
+                model::Point {
+                    x: 0.0,
+                    y: -(size.y * 0.5 + 4.0),
+                }
+            } else {
+                model::Point {
+                    x: size.x * 0.5 + 4.0,
+                    y: 0.0,
+                }
+            };
+            doc.elements.push(model::Element {
+                id,
+                group_id: edge.group_id,
+                rotation: 0.0,
+                snap_enabled: true,
+                kind: model::ElementKind::Line {
+                    a: model::Point::from_pos2(a),
+                    b: model::Point::from_pos2(b),
+                    arrow: false,
+                    arrow_style: edge.arrow_style,
+                    heads: edge.heads,
+                    start_binding,
+                    end_binding,
+                    route,
+                    label: edge.label,
+                    label_position: 0.5,
+                    label_offset,
+                },
+                style: edge.style,
+                ports: Vec::new(),
+            });
+        }
+        doc
+    }
+}
+
+fn port_binding(
+    doc: &model::Document,
+    element_id: u64,
+    name: Option<&str>,
+) -> Option<model::Binding> {
+    let element = doc.elements.iter().find(|e| e.id == element_id)?;
+    let port = element.port(name?)?;
+    Some(model::Binding {
+        element_id,
+        norm: port.norm(),
+        port: Some(port.name.clone()),
+    })
+}
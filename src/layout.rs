@@ -0,0 +1,227 @@
+//! Layered ("Sugiyama style") placement for imported graphs.
+//!
+//! Cycles are broken by reversing back edges, nodes are ranked by longest
+//! path, long edges get zero-size dummy nodes so crossings can be reduced with
+//! barycenter sweeps, and each rank is then packed around the barycenter of
+//! the rank before it.
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Direction {
+    #[default]
+    TopBottom,
+    BottomTop,
+    LeftRight,
+    RightLeft,
+}
+
+const NODE_GAP: f32 = 48.0;
+const RANK_GAP: f32 = 72.0;
+const ORDER_SWEEPS: usize = 8;
+
+/// Returns the top-left corner of every node.
+pub fn layered(
+    sizes: &[egui::Vec2],
+    edges: &[(usize, usize)],
+    direction: Direction,
+) -> Vec<egui::Pos2> {
+    let n = sizes.len();
+    if n == 0 {
+        return Vec::new();
+    }
+    let horizontal = matches!(direction, Direction::LeftRight | Direction::RightLeft);
+    // Breadth runs along a rank, depth runs from one rank to the next.
+    let breadth = |s: egui::Vec2| if horizontal { s.y } else { s.x };
+    let depth = |s: egui::Vec2| if horizontal { s.x } else { s.y };
+
+    let dag = acyclic_edges(n, edges);
+    let rank = longest_path_ranks(n, &dag);
+
+    // Split edges that span several ranks with dummy nodes.
+    let mut node_rank = rank.clone();
+    let mut breadths: Vec<f32> = sizes.iter().map(|s| breadth(*s)).collect();
+    let mut links: Vec<(usize, usize)> = Vec::new();
+    for &(u, v) in &dag {
+        let mut prev = u;
+        for r in rank[u] + 1..rank[v] {
+            let dummy = node_rank.len();
+            node_rank.push(r);
+            breadths.push(0.0);
+            links.push((prev, dummy));
+            prev = dummy;
+        }
+        links.push((prev, v));
+    }
+    let total = node_rank.len();
+    let rank_count = node_rank.iter().copied().max().unwrap_or(0) + 1;
+    let mut up: Vec<Vec<usize>> = vec![Vec::new(); total];
+    let mut down: Vec<Vec<usize>> = vec![Vec::new(); total];
+    for &(u, v) in &links {
+        down[u].push(v);
+        up[v].push(u);
+    }
+
+    let mut layers: Vec<Vec<usize>> = vec![Vec::new(); rank_count];
+    for (i, r) in node_rank.iter().enumerate() {
+        layers[*r].push(i);
+    }
+    let mut order = vec![0usize; total];
+    let reindex = |layers: &Vec<Vec<usize>>, order: &mut Vec<usize>| {
+        for layer in layers {
+            for (pos, &v) in layer.iter().enumerate() {
+                order[v] = pos;
+            }
+        }
+    };
+    reindex(&layers, &mut order);
+    for sweep in 0..ORDER_SWEEPS {
+        let downward = sweep % 2 == 0;
+        let ranks: Vec<usize> = if downward {
+            (1..rank_count).collect()
+        } else {
+            (0..rank_count.saturating_sub(1)).rev().collect()
+        };
+        for r in ranks {
+            let neighbors = if downward { &up } else { &down };
+            let mut keyed: Vec<(f32, usize)> = layers[r]
+                .iter()
+                .map(|&v| {
+                    let ns = &neighbors[v];
+                    let key = if ns.is_empty() {
+                        order[v] as f32
+                    } else {
+                        ns.iter().map(|&u| order[u] as f32).sum::<f32>() / ns.len() as f32
+                    };
+                    (key, v)
+                })
+                .collect();
+            keyed.sort_by(|a, b| a.0.total_cmp(&b.0));
+            layers[r] = keyed.into_iter().map(|(_, v)| v).collect();
+            reindex(&layers, &mut order);
+        }
+    }
+
+    // Place ranks one after another, each centered under its parents.
+    let mut center = vec![0.0f32; total];
+    for layer in &layers {
+        let desired: Vec<f32> = layer
+            .iter()
+            .map(|&v| {
+                if up[v].is_empty() {
+                    f32::NAN
+                } else {
+                    up[v].iter().map(|&u| center[u]).sum::<f32>() / up[v].len() as f32
+                }
+            })
+            .collect();
+        let packed_width: f32 =
+            layer.iter().map(|&v| breadths[v]).sum::<f32>() + NODE_GAP * (layer.len() as f32 - 1.0);
+        let mut cursor = -packed_width * 0.5;
+        let mut placed = Vec::with_capacity(layer.len());
+        for (i, &v) in layer.iter().enumerate() {
+            let half = breadths[v] * 0.5;
+            let want = if desired[i].is_nan() { cursor + half } else { desired[i] };
+            let c = want.max(cursor + half);
+            placed.push(c);
+            cursor = c + half + NODE_GAP;
+        }
+        // Pushing right only accumulates drift; shift back so the rank sits on
+        // average where its parents asked it to be.
+        let (sum, count) = placed
+            .iter()
+            .zip(&desired)
+            .filter(|(_, d)| !d.is_nan())
+            .fold((0.0, 0), |(s, c), (p, d)| (s + (p - d), c + 1));
+        let shift = if count > 0 { sum / count as f32 } else { 0.0 };
+        for (i, &v) in layer.iter().enumerate() {
+            center[v] = placed[i] - shift;
+        }
+    }
+
+    let mut rank_offset = vec![0.0f32; rank_count];
+    let mut rank_depth = vec![0.0f32; rank_count];
+    for v in 0..n {
+        rank_depth[rank[v]] = rank_depth[rank[v]].max(depth(sizes[v]));
+    }
+    for r in 1..rank_count {
+        rank_offset[r] = rank_offset[r - 1] + rank_depth[r - 1] + RANK_GAP;
+    }
+
+    (0..n)
+        .map(|v| {
+            let size = sizes[v];
+            let r = rank[v];
+            let mut d = rank_offset[r] + (rank_depth[r] - depth(size)) * 0.5;
+            if matches!(direction, Direction::BottomTop | Direction::RightLeft) {
+                d = -d - depth(size);
+            }
+            let b = center[v] - breadth(size) * 0.5;
+            if horizontal {
+                egui::pos2(d, b)
+            } else {
+                egui::pos2(b, d)
+            }
+        })
+        .collect()
+}
+
+/// Drops self loops and reverses the edges that close a cycle, found with a
+/// depth-first search in node order.
+fn acyclic_edges(n: usize, edges: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut adj: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for &(u, v) in edges {
+        if u != v && u < n && v < n {
+            adj[u].push(v);
+        }
+    }
+    // 0 = unvisited, 1 = on the stack, 2 = done
+    let mut state = vec![0u8; n];
+    let mut out = Vec::new();
+    for root in 0..n {
+        if state[root] != 0 {
+            continue;
+        }
+        let mut stack: Vec<(usize, usize)> = vec![(root, 0)];
+        state[root] = 1;
+        while let Some(&mut (u, ref mut next)) = stack.last_mut() {
+            if *next < adj[u].len() {
+                let v = adj[u][*next];
+                *next += 1;
+                match state[v] {
+                    0 => {
+                        out.push((u, v));
+                        state[v] = 1;
+                        stack.push((v, 0));
+                    }
+                    1 => out.push((v, u)),
+                    _ => out.push((u, v)),
+                }
+            } else {
+                state[u] = 2;
+                stack.pop();
+            }
+        }
+    }
+    out
+}
+
+fn longest_path_ranks(n: usize, dag: &[(usize, usize)]) -> Vec<usize> {
+    let mut indegree = vec![0usize; n];
+    let mut adj: Vec<Vec<usize>> = vec![Vec::new(); n];
+    for &(u, v) in dag {
+        adj[u].push(v);
+        indegree[v] += 1;
+    }
+    let mut rank = vec![0usize; n];
+    let mut queue: std::collections::VecDeque<usize> =
+        (0..n).filter(|&v| indegree[v] == 0).collect();
+    while let Some(u) = queue.pop_front() {
+        for &v in &adj[u] {
+            rank[v] = rank[v].max(rank[u] + 1);
+            indegree[v] -= 1;
+            if indegree[v] == 0 {
+                queue.push_back(v);
+            }
+        }
+    }
+    rank
+}
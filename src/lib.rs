@@ -0,0 +1,24 @@
+//! Document model, geometry and exporters shared by the sansuyu editor.
+//!
+//! Everything in this crate is independent of the windowing stack, so it can be
+//! used from build scripts and tests with `default-features = false`.
+
+pub mod dot;
+pub mod drawio;
+pub mod dsl;
+pub mod excalidraw;
+pub mod geometry;
+mod graph;
+pub mod layout;
+pub mod load;
+pub mod mermaid;
+pub mod model;
+pub mod pdf;
+#[cfg(feature = "png")]
+pub mod raster;
+pub mod routing;
+pub mod svg;
+pub mod svg_import;
+pub mod text_format;
+pub mod tikz;
+mod xml;
@@ -0,0 +1,60 @@
+//! Reading diagrams from disk, picking the parser from the file extension.
+
+use crate::{dot, drawio, dsl, excalidraw, mermaid, model, routing, svg, svg_import};
+use std::path::{Path, PathBuf};
+
+/// Parses `src` by the extension of `path`. Orthogonal routes are recomputed,
+/// as stored bends may be missing or out of date.
+pub fn document_from_str(src: &str, path: &Path) -> Result<model::Document, String> {
+    let mut doc = parse_by_extension(src, path)?;
+    routing::update_routes(&mut doc);
+    Ok(doc)
+}
+
+fn parse_by_extension(src: &str, path: &Path) -> Result<model::Document, String> {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "sy" | "txt" => dsl::parse(src).map_err(|e| format!("Parse failed: {e}")),
+        "mmd" | "mermaid" => mermaid::parse(src).map_err(|e| format!("Parse failed: {e}")),
+        "svg" if svg::has_embedded_document(src) => {
+            svg::document_from_svg(src).map_err(|e| format!("Parse failed: {e}"))
+        }
+        "svg" => svg_import::parse(src).map_err(|e| format!("Parse failed: {e}")),
+        "drawio" | "dio" => drawio::parse(src).map_err(|e| format!("Parse failed: {e}")),
+        "excalidraw" => excalidraw::parse(src).map_err(|e| format!("Parse failed: {e}")),
+        "dot" | "gv" => dot::parse(src).map_err(|e| format!("Parse failed: {e}")),
+        _ => serde_json::from_str::<model::Document>(src).map_err(|e| format!("Parse failed: {e}")),
+    }
+}
+
+pub fn load_document(path: &Path) -> Result<model::Document, String> {
+    let src = std::fs::read_to_string(path).map_err(|e| format!("Read failed: {e}"))?;
+    document_from_str(&src, path)
+}
+
+/// Where a document opened from `path` can be saved back without clobbering
+/// its source: the path itself for JSON and for SVGs that embed a document,
+/// otherwise a `.json` sibling.
+pub fn save_path_for(path: &Path) -> PathBuf {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+    let native = match ext.as_str() {
+        "sy" | "txt" | "mmd" | "mermaid" | "drawio" | "dio" | "excalidraw" | "dot" | "gv" => false,
+        "svg" => std::fs::read_to_string(path)
+            .map(|src| svg::has_embedded_document(&src))
+            .unwrap_or(true),
+        _ => true,
+    };
+    if native {
+        path.to_path_buf()
+    } else {
+        path.with_extension("json")
+    }
+}
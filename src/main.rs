@@ -1,8 +1,19 @@
 mod app;
-mod model;
-mod text_format;
+mod cli;
 
 fn main() -> eframe::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|a| cli::is_subcommand(a)) {
+        std::process::exit(cli::run(&args));
+    }
+    let (initial_file, watch) = match cli::launch_args(&args) {
+        Ok(launch) => launch,
+        Err(e) => {
+            eprintln!("{e}\n\n{}", cli::USAGE);
+            std::process::exit(cli::EXIT_USAGE);
+        }
+    };
+
     let icon = load_icon();
     let native_options = eframe::NativeOptions {
         viewport: eframe::egui::ViewportBuilder::default()
@@ -12,7 +23,7 @@ fn main() -> eframe::Result<()> {
     eframe::run_native(
         "SanSuYu",
         native_options,
-        Box::new(|cc| Ok(Box::new(app::DiagramApp::new(cc)))),
+        Box::new(|cc| Ok(Box::new(app::DiagramApp::new(cc, initial_file, watch)))),
     )
 }
 
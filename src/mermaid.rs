@@ -0,0 +1,612 @@
+//! Mermaid `flowchart` / `graph` import.
+//!
+//! Node shapes map onto the closest element kind (`[]` rect, `()` ellipse,
+//! `{}` diamond, `{{}}` hexagon, `[()]` cylinder, `[/ /]` parallelogram,
+//! `[/ \]` trapezoid, anything else a rect), dotted
+//! links become dashed lines, thick links get a heavier stroke and subgraphs
+//! become groups. Layout follows the chart direction.
+
+use crate::graph::{Edge, Graph, Node, Placement, ShapeKind, edge_style};
+use crate::layout::Direction;
+use crate::model;
+use std::collections::HashMap;
+
+const THICK_STROKE_WIDTH: f32 = 4.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum LinkKind {
+    Solid,
+    Dotted,
+    Thick,
+}
+
+struct Link {
+    kind: LinkKind,
+    start_arrow: bool,
+    end_arrow: bool,
+    /// `--o` ends in a circle.
+    end_head: model::ArrowHead,
+    label: String,
+}
+
+struct Parser {
+    graph: Graph,
+    node_index: HashMap<String, usize>,
+    group_stack: Vec<u64>,
+    direction: Direction,
+    seen_header: bool,
+}
+
+pub fn parse(src: &str) -> Result<model::Document, String> {
+    let mut parser = Parser {
+        graph: Graph::default(),
+        node_index: HashMap::new(),
+        group_stack: Vec::new(),
+        direction: Direction::TopBottom,
+        seen_header: false,
+    };
+    for (i, line) in src.lines().enumerate() {
+        parser
+            .line(line)
+            .map_err(|e| format!("line {}: {}", i + 1, e))?;
+    }
+    if !parser.seen_header {
+        return Err("expected a 'flowchart' or 'graph' declaration".to_string());
+    }
+    let direction = parser.direction;
+    Ok(parser.graph.into_document(Placement::Layered(direction)))
+}
+
+fn direction_for(word: &str) -> Option<Direction> {
+    match word.to_ascii_uppercase().as_str() {
+        "TB" | "TD" => Some(Direction::TopBottom),
+        "BT" => Some(Direction::BottomTop),
+        "LR" => Some(Direction::LeftRight),
+        "RL" => Some(Direction::RightLeft),
+        _ => None,
+    }
+}
+
+fn clean_label(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .unwrap_or(text);
+    let text = text
+        .strip_prefix('`')
+        .and_then(|t| t.strip_suffix('`'))
+        .unwrap_or(text);
+    text.replace("<br/>", "\n")
+        .replace("<br />", "\n")
+        .replace("<br>", "\n")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn skip_ws(s: &[char], i: &mut usize) {
+    while *i < s.len() && s[*i].is_whitespace() {
+        *i += 1;
+    }
+}
+
+fn starts_with(s: &[char], i: usize, pat: &str) -> bool {
+    pat.chars().enumerate().all(|(k, p)| s.get(i + k) == Some(&p))
+}
+
+fn find(s: &[char], from: usize, pat: &str) -> Option<usize> {
+    (from..s.len()).find(|&i| starts_with(s, i, pat))
+}
+
+fn collect(s: &[char], from: usize, to: usize) -> String {
+    s[from..to].iter().collect()
+}
+
+/// Matches a closed link operator such as `-->`, `---`, `<-.->` or `==>`.
+/// Returns the end index, the link kind and whether it has a start/end arrow.
+fn closed_link(s: &[char], i: usize) -> Option<(usize, LinkKind, bool, bool)> {
+    let mut j = i;
+    let start_arrow = s.get(j) == Some(&'<');
+    if start_arrow {
+        j += 1;
+    }
+    let head = |j: usize| -> bool {
+        match s.get(j) {
+            Some('>') => true,
+            // `--o` and `--x` heads only when not the start of a node id
+            Some('o') | Some('x') => !s.get(j + 1).is_some_and(|c| is_id_char(*c)),
+            _ => false,
+        }
+    };
+    if s.get(j) == Some(&'-') && s.get(j + 1) == Some(&'.') {
+        j += 1;
+        while s.get(j) == Some(&'.') {
+            j += 1;
+        }
+        if s.get(j) != Some(&'-') {
+            return None;
+        }
+        j += 1;
+        let end_arrow = head(j);
+        return Some((j + end_arrow as usize, LinkKind::Dotted, start_arrow, end_arrow));
+    }
+    let (c, kind) = match s.get(j) {
+        Some('-') => ('-', LinkKind::Solid),
+        Some('=') => ('=', LinkKind::Thick),
+        _ => return None,
+    };
+    let run_start = j;
+    while s.get(j) == Some(&c) {
+        j += 1;
+    }
+    let run = j - run_start;
+    if run >= 2 && head(j) {
+        return Some((j + 1, kind, start_arrow, true));
+    }
+    if run >= 3 && !start_arrow {
+        return Some((j, kind, false, false));
+    }
+    None
+}
+
+/// Matches a link with inline text (`-- text -->`, `-. text .->`,
+/// `== text ==>`) and returns the position after its closing half.
+fn open_link(s: &[char], i: usize) -> Option<(usize, Link)> {
+    let mut j = i;
+    let start_arrow = s.get(j) == Some(&'<');
+    if start_arrow {
+        j += 1;
+    }
+    let (kind, close_prefix, fill) = if starts_with(s, j, "-.") {
+        (LinkKind::Dotted, ".-", '-')
+    } else if starts_with(s, j, "--") {
+        (LinkKind::Solid, "--", '-')
+    } else if starts_with(s, j, "==") {
+        (LinkKind::Thick, "==", '=')
+    } else {
+        return None;
+    };
+    j += 2;
+    if !s.get(j).is_some_and(|c| c.is_whitespace()) {
+        return None;
+    }
+    let close = find(s, j, close_prefix)?;
+    let label = clean_label(&collect(s, j, close));
+    let mut end = close + 2;
+    while s.get(end) == Some(&fill) {
+        end += 1;
+    }
+    let end_arrow = s.get(end) == Some(&'>');
+    if end_arrow {
+        end += 1;
+    }
+    Some((
+        end,
+        Link {
+            kind,
+            start_arrow,
+            end_arrow,
+            end_head: model::ArrowHead::Triangle,
+            label,
+        },
+    ))
+}
+
+impl Parser {
+    fn line(&mut self, raw: &str) -> Result<(), String> {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with("%%") {
+            return Ok(());
+        }
+        let first = line.split_whitespace().next().unwrap_or("");
+        match first {
+            "flowchart" | "graph" if !self.seen_header => {
+                self.seen_header = true;
+                let dir = line.split_whitespace().nth(1).map(|d| d.trim_end_matches(';'));
+                if let Some(dir) = dir.and_then(direction_for) {
+                    self.direction = dir;
+                }
+                return Ok(());
+            }
+            "subgraph" => {
+                let id = self.graph.add_group(self.group_stack.last().copied());
+                self.group_stack.push(id);
+                return Ok(());
+            }
+            "end" if line == "end" => {
+                if self.group_stack.pop().is_none() {
+                    return Err("'end' without matching subgraph".to_string());
+                }
+                return Ok(());
+            }
+            "direction" | "classDef" | "class" | "linkStyle" | "click" => return Ok(()),
+            "style" => return self.style(line),
+            _ => {}
+        }
+        // Front matter and directives before the header are not part of the chart.
+        if !self.seen_header {
+            return Ok(());
+        }
+        let chars: Vec<char> = line.chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            self.statement(&chars, &mut i)?;
+            skip_ws(&chars, &mut i);
+            if chars.get(i) == Some(&';') {
+                i += 1;
+            }
+            skip_ws(&chars, &mut i);
+        }
+        Ok(())
+    }
+
+    fn style(&mut self, line: &str) -> Result<(), String> {
+        let mut parts = line.split_whitespace().skip(1);
+        let Some(name) = parts.next() else {
+            return Err("expected a node id after 'style'".to_string());
+        };
+        let Some(&idx) = self.node_index.get(name) else {
+            return Ok(());
+        };
+        let rest: Vec<&str> = parts.collect();
+        let style = &mut self.graph.nodes[idx].style;
+        for prop in rest.join(" ").split(',') {
+            let Some((key, value)) = prop.split_once(':') else {
+                continue;
+            };
+            let value = value.trim().trim_end_matches(';');
+            match key.trim() {
+                "fill" => style.fill = model::Rgba::parse(value).or(style.fill),
+                "stroke" => {
+                    if let Some(c) = model::Rgba::parse(value) {
+                        style.stroke.color = c;
+                    }
+                }
+                "color" => {
+                    if let Some(c) = model::Rgba::parse(value) {
+                        style.text_color = c;
+                    }
+                }
+                "stroke-width" => {
+                    if let Ok(w) = value.trim_end_matches("px").parse::<f32>() {
+                        style.stroke.width = w.max(0.0);
+                    }
+                }
+                "stroke-dasharray" => style.stroke.line_style = model::LineStyle::Dashed,
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn statement(&mut self, s: &[char], i: &mut usize) -> Result<(), String> {
+        let mut prev = self.node_group(s, i)?;
+        loop {
+            skip_ws(s, i);
+            if *i >= s.len() || s[*i] == ';' {
+                return Ok(());
+            }
+            let link = if let Some((end, kind, start_arrow, end_arrow)) = closed_link(s, *i) {
+                *i = end;
+                let end_head = if end_arrow && s[end - 1] == 'o' {
+                    model::ArrowHead::Circle
+                } else {
+                    model::ArrowHead::Triangle
+                };
+                let mut label = String::new();
+                if s.get(*i) == Some(&'|') {
+                    let close = find(s, *i + 1, "|").ok_or("unterminated '|' label")?;
+                    label = clean_label(&collect(s, *i + 1, close));
+                    *i = close + 1;
+                }
+                Link {
+                    kind,
+                    start_arrow,
+                    end_arrow,
+                    end_head,
+                    label,
+                }
+            } else if let Some((end, link)) = open_link(s, *i) {
+                *i = end;
+                link
+            } else {
+                return Err(format!("expected a link at '{}'", collect(s, *i, s.len())));
+            };
+            skip_ws(s, i);
+            let next = self.node_group(s, i)?;
+            self.add_links(&prev, &next, &link);
+            prev = next;
+        }
+    }
+
+    fn add_links(&mut self, from: &[usize], to: &[usize], link: &Link) {
+        let mut style = edge_style();
+        match link.kind {
+            LinkKind::Solid => {}
+            LinkKind::Dotted => style.stroke.line_style = model::LineStyle::Dashed,
+            LinkKind::Thick => style.stroke.width = THICK_STROKE_WIDTH,
+        }
+        let arrow_style = match (link.start_arrow, link.end_arrow) {
+            (false, false) => model::ArrowStyle::None,
+            (false, true) => model::ArrowStyle::End,
+            (true, false) => model::ArrowStyle::Start,
+            (true, true) => model::ArrowStyle::Both,
+        };
+        for &a in from {
+            for &b in to {
+                let group_id = self.graph.nodes[a].group_id;
+                let shared = group_id == self.graph.nodes[b].group_id;
+                self.graph.edges.push(Edge {
+                    from: a,
+                    to: b,
+                    arrow_style,
+                    heads: model::ArrowHeads {
+                        end: link.end_head,
+                        ..Default::default()
+                    },
+                    style: style.clone(),
+                    group_id: if shared { group_id } else { None },
+                    label: link.label.clone(),
+                    from_port: None,
+                    to_port: None,
+                    route: model::LineRoute::Straight,
+                });
+            }
+        }
+    }
+
+    /// `A`, `A[label]` or `A & B[label] & C`.
+    fn node_group(&mut self, s: &[char], i: &mut usize) -> Result<Vec<usize>, String> {
+        let mut nodes = vec![self.node(s, i)?];
+        loop {
+            let mut j = *i;
+            skip_ws(s, &mut j);
+            if s.get(j) != Some(&'&') {
+                return Ok(nodes);
+            }
+            *i = j + 1;
+            skip_ws(s, i);
+            nodes.push(self.node(s, i)?);
+        }
+    }
+
+    fn node(&mut self, s: &[char], i: &mut usize) -> Result<usize, String> {
+        let start = *i;
+        while *i < s.len() && is_id_char(s[*i]) {
+            *i += 1;
+        }
+        if *i == start {
+            return Err(format!("expected a node id at '{}'", collect(s, start, s.len())));
+        }
+        let name = collect(s, start, *i);
+        let shape = self.shape(s, i)?;
+        if starts_with(s, *i, ":::") {
+            *i += 3;
+            while *i < s.len() && (is_id_char(s[*i]) || s[*i] == '-') {
+                *i += 1;
+            }
+        }
+
+        let group_id = self.group_stack.last().copied();
+        let idx = match self.node_index.get(&name) {
+            Some(&idx) => idx,
+            None => {
+                let idx = self.graph.nodes.len();
+                let mut node = Node::new(name.clone());
+                node.group_id = group_id;
+                self.graph.nodes.push(node);
+                self.node_index.insert(name, idx);
+                idx
+            }
+        };
+        let node = &mut self.graph.nodes[idx];
+        if let Some((kind, label)) = shape {
+            node.kind = kind;
+            node.label = label;
+            node.fit_label();
+            // Defining a node inside a subgraph moves it there.
+            if group_id.is_some() {
+                node.group_id = group_id;
+            }
+        }
+        Ok(idx)
+    }
+
+    fn shape(&mut self, s: &[char], i: &mut usize) -> Result<Option<(ShapeKind, String)>, String> {
+        // (open, close, kind); longer openers first so `((` wins over `(`.
+        const SHAPES: &[(&str, &str, ShapeKind)] = &[
+            ("(((", ")))", ShapeKind::Ellipse),
+            ("((", "))", ShapeKind::Ellipse),
+            ("([", "])", ShapeKind::Ellipse),
+            ("[[", "]]", ShapeKind::Rect),
+            ("[(", ")]", ShapeKind::Cylinder),
+            ("{{", "}}", ShapeKind::Hexagon),
+            ("[", "]", ShapeKind::Rect),
+            ("(", ")", ShapeKind::Ellipse),
+            ("{", "}", ShapeKind::Diamond),
+            (">", "]", ShapeKind::Rect),
+        ];
+        for &(open, close, kind) in SHAPES {
+            if !starts_with(s, *i, open) {
+                continue;
+            }
+            let body_start = *i + open.chars().count();
+            // `[/text/]`, `[\text\]`, `[/text\]` and `[\text/]`
+            if open == "[" && matches!(s.get(body_start), Some('/') | Some('\\')) {
+                let end = (body_start + 1..s.len())
+                    .find(|&k| matches!(s[k], '/' | '\\') && s.get(k + 1) == Some(&']'));
+                if let Some(end) = end {
+                    let label = clean_label(&collect(s, body_start + 1, end));
+                    *i = end + 2;
+                    let kind = if s[end] == s[body_start] {
+                        ShapeKind::Parallelogram
+                    } else {
+                        ShapeKind::Trapezoid
+                    };
+                    return Ok(Some((kind, label)));
+                }
+            }
+            let Some(end) = find(s, body_start, close) else {
+                return Err(format!("missing '{close}'"));
+            };
+            let label = clean_label(&collect(s, body_start, end));
+            *i = end + close.chars().count();
+            return Ok(Some((kind, label)));
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shapes(doc: &model::Document) -> Vec<(&str, &'static str)> {
+        doc.elements
+            .iter()
+            .filter_map(|e| match &e.kind {
+                model::ElementKind::Rect { label, .. } => Some((label.as_str(), "rect")),
+                model::ElementKind::Ellipse { label, .. } => Some((label.as_str(), "ellipse")),
+                model::ElementKind::Diamond { label, .. } => Some((label.as_str(), "diamond")),
+                model::ElementKind::Hexagon { label, .. } => Some((label.as_str(), "hexagon")),
+                model::ElementKind::Cylinder { label, .. } => Some((label.as_str(), "cylinder")),
+                model::ElementKind::Parallelogram { label, .. } => {
+                    Some((label.as_str(), "parallelogram"))
+                }
+                model::ElementKind::Trapezoid { label, .. } => Some((label.as_str(), "trapezoid")),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// `(from id, to id, arrows, end head, label, line style, stroke width)`
+    /// of each line.
+    type LinkSummary<'a> = (
+        u64,
+        u64,
+        model::ArrowStyle,
+        model::ArrowHead,
+        &'a str,
+        model::LineStyle,
+        f32,
+    );
+
+    fn links(doc: &model::Document) -> Vec<LinkSummary<'_>> {
+        doc.elements
+            .iter()
+            .filter_map(|e| match &e.kind {
+                model::ElementKind::Line {
+                    start_binding: Some(s),
+                    end_binding: Some(t),
+                    arrow_style,
+                    heads,
+                    label,
+                    ..
+                } => Some((
+                    s.element_id,
+                    t.element_id,
+                    *arrow_style,
+                    heads.end,
+                    label.as_str(),
+                    e.style.stroke.line_style,
+                    e.style.stroke.width,
+                )),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_the_documented_node_shapes() {
+        let doc = parse(
+            "flowchart LR\n  a[Rect] --- b(Round) --- c{Choice} --- d{{Hex}}\n  \
+             d --- e[(Store)] --- f[/Lean/] --- g[/Top\\] --- h>Flag]",
+        )
+        .unwrap();
+        assert_eq!(
+            shapes(&doc),
+            [
+                ("Rect", "rect"),
+                ("Round", "ellipse"),
+                ("Choice", "diamond"),
+                ("Hex", "hexagon"),
+                ("Store", "cylinder"),
+                ("Lean", "parallelogram"),
+                ("Top", "trapezoid"),
+                ("Flag", "rect"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_link_kinds_and_labels() {
+        use model::ArrowHead::{Circle, Triangle};
+        use model::ArrowStyle::{Both, End, None as Plain};
+        use model::LineStyle::{Dashed, Solid};
+        let doc = parse(
+            "graph TD\n\
+             a --> b\n\
+             a -.-> c\n\
+             a ==> d\n\
+             a -->|yes| e\n\
+             a -- no --> f\n\
+             a <--> g\n\
+             a --o h\n\
+             a --- i",
+        )
+        .unwrap();
+        let width = edge_style().stroke.width;
+        let thick = THICK_STROKE_WIDTH;
+        assert_eq!(
+            links(&doc),
+            [
+                (1, 2, End, Triangle, "", Solid, width),
+                (1, 3, End, Triangle, "", Dashed, width),
+                (1, 4, End, Triangle, "", Solid, thick),
+                (1, 5, End, Triangle, "yes", Solid, width),
+                (1, 6, End, Triangle, "no", Solid, width),
+                (1, 7, Both, Triangle, "", Solid, width),
+                (1, 8, End, Circle, "", Solid, width),
+                (1, 9, Plain, Triangle, "", Solid, width),
+            ]
+        );
+    }
+
+    #[test]
+    fn subgraphs_become_groups_and_style_applies() {
+        let doc = parse(
+            "%% comment\nflowchart TB\n  subgraph one\n    a[A] --> b[B]\n  end\n  b --> c\n  \
+             style c fill:#ff0000,stroke-width:3px",
+        )
+        .unwrap();
+        assert_eq!(doc.groups.len(), 1);
+        let groups: Vec<_> = doc.elements.iter().take(3).map(|e| e.group_id).collect();
+        assert_eq!(groups, [Some(1), Some(1), None]);
+        assert_eq!(doc.elements[2].style.fill, model::Rgba::from_hex("#ff0000"));
+        assert_eq!(doc.elements[2].style.stroke.width, 3.0);
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let cases = [
+            (
+                "flowchart LR\n  a --> b\nend",
+                "line 3: 'end' without matching subgraph",
+            ),
+            ("graph TD\n  a[Open --> b", "line 2: missing ']'"),
+            (
+                "graph TD\n\n  a -->|yes b",
+                "line 3: unterminated '|' label",
+            ),
+            ("graph TD\n  a ~~ b", "line 2: expected a link at '~~ b'"),
+            ("a --> b", "expected a 'flowchart' or 'graph' declaration"),
+        ];
+        for (src, expected) in cases {
+            assert_eq!(parse(src).unwrap_err(), expected, "for {src:?}");
+        }
+    }
+}
@@ -1,4 +1,3 @@
-use eframe::egui;
 use serde::{Deserialize, Serialize};
 use crate::text_format;
 
@@ -60,6 +59,55 @@ impl Rgba {
         let [r, g, b, a] = c.to_array();
         Self { r, g, b, a }
     }
+
+    /// Parses `#rrggbb` or `#rgb`, with or without the `#`.
+    pub fn from_hex(s: &str) -> Option<Self> {
+        let s = s.trim().trim_start_matches('#');
+        if !s.chars().all(|c| c.is_ascii_hexdigit()) {
+            return None;
+        }
+        if s.len() == 6 {
+            let r = u8::from_str_radix(&s[0..2], 16).ok()?;
+            let g = u8::from_str_radix(&s[2..4], 16).ok()?;
+            let b = u8::from_str_radix(&s[4..6], 16).ok()?;
+            return Some(Rgba { r, g, b, a: 255 });
+        }
+        if s.len() == 3 {
+            let r = u8::from_str_radix(&s[0..1], 16).ok()? * 17;
+            let g = u8::from_str_radix(&s[1..2], 16).ok()? * 17;
+            let b = u8::from_str_radix(&s[2..3], 16).ok()? * 17;
+            return Some(Rgba { r, g, b, a: 255 });
+        }
+        None
+    }
+
+    /// Parses a hex color, an `r,g,b` triple or a CSS color name.
+    pub fn parse(s: &str) -> Option<Self> {
+        if let Some(c) = Self::from_hex(s) {
+            return Some(c);
+        }
+        let s = s.trim();
+        let parts: Vec<&str> = s.split(&[',', ' ']).filter(|p| !p.is_empty()).collect();
+        if parts.len() == 3 {
+            let r: u8 = parts[0].trim().parse().ok()?;
+            let g: u8 = parts[1].trim().parse().ok()?;
+            let b: u8 = parts[2].trim().parse().ok()?;
+            return Some(Rgba { r, g, b, a: 255 });
+        }
+        let hex = match s.to_lowercase().as_str() {
+            "black" => "000000",
+            "white" => "ffffff",
+            "red" => "ff0000",
+            "green" => "008000",
+            "blue" => "0000ff",
+            "yellow" => "ffff00",
+            "orange" => "ffa500",
+            "purple" => "800080",
+            "gray" | "grey" => "808080",
+            _ => return None,
+        };
+        Self::from_hex(hex)
+    }
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Default)]
@@ -144,20 +192,17 @@ impl Style {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
 pub struct Document {
     pub elements: Vec<Element>,
     #[serde(default)]
     pub groups: Vec<Group>,
-}
-
-impl Default for Document {
-    fn default() -> Self {
-        Self {
-            elements: vec![],
-            groups: vec![],
-        }
-    }
+    /// Connectors hop over the ones below them where they cross.
+    #[serde(default)]
+    pub line_jumps: bool,
+    /// Connector ends that meet another connector get a dot.
+    #[serde(default)]
+    pub junction_dots: bool,
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
@@ -178,16 +223,87 @@ pub struct Element {
     pub snap_enabled: bool,
     pub kind: ElementKind,
     pub style: Style,
+    #[serde(default)]
+    pub ports: Vec<Port>,
+}
+
+impl Element {
+    pub fn port(&self, name: &str) -> Option<&Port> {
+        self.ports.iter().find(|p| p.name == name)
+    }
 }
 
 fn default_snap_enabled() -> bool {
     true
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub enum PortSide {
+    Top,
+    #[default]
+    Right,
+    Bottom,
+    Left,
+}
+
+impl PortSide {
+    pub const ALL: [PortSide; 4] = [
+        PortSide::Top,
+        PortSide::Right,
+        PortSide::Bottom,
+        PortSide::Left,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            PortSide::Top => "top",
+            PortSide::Right => "right",
+            PortSide::Bottom => "bottom",
+            PortSide::Left => "left",
+        }
+    }
+}
+
+/// A named connection point on one side of a shape.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct Port {
+    pub name: String,
+    #[serde(default)]
+    pub side: PortSide,
+    /// Position along the side, from its top or left end (0) to the other (1).
+    #[serde(default = "default_port_offset")]
+    pub offset: f32,
+}
+
+impl Port {
+    /// Position in the shape's unrotated bounds, `-0.5..=0.5` on each axis
+    /// like [`Binding::norm`].
+    pub fn norm(&self) -> Point {
+        let t = self.offset.clamp(0.0, 1.0) - 0.5;
+        match self.side {
+            PortSide::Top => Point { x: t, y: -0.5 },
+            PortSide::Right => Point { x: 0.5, y: t },
+            PortSide::Bottom => Point { x: t, y: 0.5 },
+            PortSide::Left => Point { x: -0.5, y: t },
+        }
+    }
+}
+
+fn default_port_offset() -> f32 {
+    0.5
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub struct Binding {
     pub element_id: u64,
+    /// Where on the target the end sits: relative to a shape's box, with
+    /// (0, 0) at its center, or for a line or polyline `x` is the fraction
+    /// of the way along it.
     pub norm: Point,
+    /// Name of the port on the target element; `norm` is only a fallback
+    /// when it is set.
+    #[serde(default)]
+    pub port: Option<String>,
 }
 
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Default)]
@@ -199,12 +315,102 @@ pub enum ArrowStyle {
     Both,
 }
 
+/// Shape drawn at a line end that has an arrow.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum ArrowHead {
+    #[default]
+    Triangle,
+    Open,
+    Diamond,
+    HollowDiamond,
+    Circle,
+    Bar,
+    /// The "many" end of an ER relationship.
+    CrowsFoot,
+}
+
+impl ArrowHead {
+    pub const ALL: [ArrowHead; 7] = [
+        ArrowHead::Triangle,
+        ArrowHead::Open,
+        ArrowHead::Diamond,
+        ArrowHead::HollowDiamond,
+        ArrowHead::Circle,
+        ArrowHead::Bar,
+        ArrowHead::CrowsFoot,
+    ];
+
+    /// Name used in diagram text and the editor.
+    pub fn name(self) -> &'static str {
+        match self {
+            ArrowHead::Triangle => "triangle",
+            ArrowHead::Open => "open",
+            ArrowHead::Diamond => "diamond",
+            ArrowHead::HollowDiamond => "hollow-diamond",
+            ArrowHead::Circle => "circle",
+            ArrowHead::Bar => "bar",
+            ArrowHead::CrowsFoot => "crows-foot",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|h| h.name() == name)
+    }
+}
+
+/// Arrowheads for the ends [`ArrowStyle`] turns on, and their size relative
+/// to the default.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ArrowHeads {
+    #[serde(default)]
+    pub start: ArrowHead,
+    #[serde(default)]
+    pub end: ArrowHead,
+    #[serde(default = "default_arrow_scale")]
+    pub scale: f32,
+}
+
+fn default_arrow_scale() -> f32 {
+    1.0
+}
+
+impl Default for ArrowHeads {
+    fn default() -> Self {
+        Self {
+            start: ArrowHead::default(),
+            end: ArrowHead::default(),
+            scale: default_arrow_scale(),
+        }
+    }
+}
+
+/// How a line travels between its two ends.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Default)]
+pub enum LineRoute {
+    #[default]
+    Straight,
+    /// Horizontal and vertical segments that keep clear of other shapes.
+    /// `bends` is recomputed whenever the ends move; `offsets` shifts each
+    /// segment sideways and is dropped when the route changes shape.
+    Orthogonal {
+        #[serde(default)]
+        bends: Vec<Point>,
+        #[serde(default)]
+        offsets: Vec<f32>,
+    },
+    /// Cubic Bézier. The control points are kept relative to the end they
+    /// belong to (`c1` to the start, `c2` to the end) so they follow it.
+    Curved { c1: Point, c2: Point },
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 pub enum ElementKind {
     Rect {
         rect: RectF,
         #[serde(default)]
         label: String,
+        #[serde(default)]
+        corner_radius: f32,
     },
     Ellipse {
         rect: RectF,
@@ -232,6 +438,32 @@ pub enum ElementKind {
         #[serde(default = "default_trapezoid_inset_ratio")]
         top_inset_ratio: f32,
     },
+    Diamond {
+        rect: RectF,
+        #[serde(default)]
+        label: String,
+    },
+    Hexagon {
+        rect: RectF,
+        #[serde(default)]
+        label: String,
+        #[serde(default = "default_hexagon_inset_ratio")]
+        inset_ratio: f32,
+    },
+    Cylinder {
+        rect: RectF,
+        #[serde(default)]
+        label: String,
+        #[serde(default = "default_cylinder_cap_ratio")]
+        cap_ratio: f32,
+    },
+    Polygon {
+        rect: RectF,
+        #[serde(default)]
+        label: String,
+        #[serde(default = "default_polygon_sides")]
+        sides: u32,
+    },
     Line {
         a: Point,
         b: Point,
@@ -240,14 +472,33 @@ pub enum ElementKind {
         #[serde(default)]
         arrow_style: ArrowStyle,
         #[serde(default)]
+        heads: ArrowHeads,
+        #[serde(default)]
         start_binding: Option<Binding>,
         #[serde(default)]
         end_binding: Option<Binding>,
+        #[serde(default)]
+        route: LineRoute,
+        /// Drawn centered `label_position` of the way along the line (0 at
+        /// `a`, 1 at `b`), shifted by `label_offset`.
+        #[serde(default)]
+        label: String,
+        #[serde(default = "default_label_position")]
+        label_position: f32,
+        #[serde(default)]
+        label_offset: Point,
     },
     Polyline {
         points: Vec<Point>,
         #[serde(default)]
         arrow_style: ArrowStyle,
+        #[serde(default)]
+        heads: ArrowHeads,
+        /// The first and last points follow these, like a line's ends.
+        #[serde(default)]
+        start_binding: Option<Binding>,
+        #[serde(default)]
+        end_binding: Option<Binding>,
     },
     Pen {
         points: Vec<Point>,
@@ -266,20 +517,70 @@ fn default_trapezoid_inset_ratio() -> f32 {
     0.25
 }
 
+fn default_hexagon_inset_ratio() -> f32 {
+    0.5
+}
+
+fn default_cylinder_cap_ratio() -> f32 {
+    0.2
+}
+
+fn default_polygon_sides() -> u32 {
+    5
+}
+
+fn default_label_position() -> f32 {
+    0.5
+}
+
 impl Element {
     pub fn bounds(&self) -> egui::Rect {
         match &self.kind {
             ElementKind::Rect { rect, .. }
             | ElementKind::Triangle { rect, .. }
             | ElementKind::Parallelogram { rect, .. }
-            | ElementKind::Trapezoid { rect, .. } => {
+            | ElementKind::Trapezoid { rect, .. }
+            | ElementKind::Diamond { rect, .. }
+            | ElementKind::Hexagon { rect, .. }
+            | ElementKind::Cylinder { rect, .. }
+            | ElementKind::Polygon { rect, .. } => {
                 rotated_rect_aabb(rect.to_rect(), self.rotation).expand(self.style.stroke.width)
             }
             ElementKind::Ellipse { rect, .. } => {
                 rotated_ellipse_aabb(rect.to_rect(), self.rotation).expand(self.style.stroke.width)
             }
-            ElementKind::Line { a, b, .. } => {
-                egui::Rect::from_two_pos(a.to_pos2(), b.to_pos2()).expand(self.style.stroke.width)
+            ElementKind::Line {
+                a,
+                b,
+                route,
+                label,
+                label_position,
+                label_offset,
+                ..
+            } => {
+                let mut rect = egui::Rect::from_two_pos(a.to_pos2(), b.to_pos2());
+                if !label.is_empty() {
+                    // Measured along the straight chord; close enough for a
+                    // bounding box.
+                    let anchor = a.to_pos2().lerp(b.to_pos2(), *label_position)
+                        + label_offset.to_pos2().to_vec2();
+                    let size = estimated_text_size(label, self.style.text_size);
+                    rect = rect.union(egui::Rect::from_center_size(anchor, size));
+                }
+                match route {
+                    LineRoute::Straight => {}
+                    LineRoute::Orthogonal { bends, .. } => {
+                        for p in bends {
+                            rect.extend_with(p.to_pos2());
+                        }
+                    }
+                    // The curve stays inside the hull of its control points.
+                    LineRoute::Curved { c1, c2 } => {
+                        rect.extend_with(a.to_pos2() + c1.to_pos2().to_vec2());
+                        rect.extend_with(b.to_pos2() + c2.to_pos2().to_vec2());
+                    }
+                }
+                rect.expand(self.style.stroke.width)
             }
             ElementKind::Polyline { points, .. } | ElementKind::Pen { points } => {
                 let mut it = points.iter();
@@ -370,6 +671,21 @@ fn rotated_ellipse_aabb(rect: egui::Rect, rotation: f32) -> egui::Rect {
     egui::Rect::from_min_max(min, max)
 }
 
+/// Rough size of rich text laid out at `text_size`, for places without font
+/// metrics.
+pub fn estimated_text_size(text: &str, text_size: f32) -> egui::Vec2 {
+    let lines = text_format::parse_rich_text_lines(text);
+    let widest = lines
+        .iter()
+        .map(|spans| spans.iter().map(|s| s.text.chars().count()).sum::<usize>())
+        .max()
+        .unwrap_or(0);
+    egui::vec2(
+        widest.max(1) as f32 * text_size * 0.6,
+        lines.len().max(1) as f32 * text_size * 1.2,
+    )
+}
+
 pub fn distance_to_segment(p: egui::Pos2, a: egui::Pos2, b: egui::Pos2) -> f32 {
     let ab = b - a;
     let ap = p - a;
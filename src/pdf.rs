@@ -0,0 +1,556 @@
+//! Single-page vector PDF export.
+//!
+//! The page mirrors the SVG export: the same polygons, dash patterns, arrow
+//! markers and page bounds. Text is real PDF text set in the standard
+//! Helvetica and Courier fonts, so it stays selectable and needs no embedding.
+
+use crate::geometry::{
+    ArrowheadPart, JunctionDot, arrow_tails, arrowhead_parts_world, arrowhead_size, hopped_path,
+    junction_dots, line_curve_world, line_hop_radius, line_hops, line_label_anchor,
+    line_label_rect, line_points_world, polyline_points_world, rotated_cylinder_rim_points_world,
+    rotated_ellipse_points_world, rotated_parallelogram_points_world,
+    rotated_rounded_rect_points_world, rotated_trapezoid_points_world,
+    rotated_triangle_points_world, shape_outline_world,
+};
+use crate::svg::{dash_lengths, label_anchor_in_rect, page_bounds};
+use crate::{model, text_format};
+use std::fmt::Write as _;
+
+/// Advance widths of Helvetica for ' '..='~', in thousandths of an em.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum Font {
+    Helvetica,
+    Courier,
+}
+
+impl Font {
+    fn for_family(family: &model::FontFamily) -> Self {
+        match family {
+            model::FontFamily::Monospace => Font::Courier,
+            model::FontFamily::Proportional | model::FontFamily::Custom(_) => Font::Helvetica,
+        }
+    }
+
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Helvetica => "F1",
+            Font::Courier => "F2",
+        }
+    }
+
+    fn char_width(self, byte: u8) -> f32 {
+        match self {
+            Font::Courier => 0.6,
+            Font::Helvetica => match byte {
+                b' '..=b'~' => HELVETICA_WIDTHS[(byte - b' ') as usize] as f32 / 1000.0,
+                _ => 0.556,
+            },
+        }
+    }
+}
+
+/// Text is written in WinAnsiEncoding, which agrees with Latin-1 for the
+/// printable characters; anything outside it becomes '?'.
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x20..=0x7e | 0xa0..=0xff => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+fn text_width(bytes: &[u8], font: Font, size: f32) -> f32 {
+    bytes.iter().map(|b| font.char_width(*b)).sum::<f32>() * size
+}
+
+fn pdf_string(bytes: &[u8], out: &mut Vec<u8>) {
+    out.push(b'(');
+    for &b in bytes {
+        if matches!(b, b'(' | b')' | b'\\') {
+            out.push(b'\\');
+        }
+        out.push(b);
+    }
+    out.push(b')');
+}
+
+fn rgb(rgba: model::Rgba) -> String {
+    format!(
+        "{:.3} {:.3} {:.3}",
+        rgba.r as f32 / 255.0,
+        rgba.g as f32 / 255.0,
+        rgba.b as f32 / 255.0
+    )
+}
+
+fn script_scale(script: text_format::Script) -> f32 {
+    match script {
+        text_format::Script::Normal => 1.0,
+        text_format::Script::Sub | text_format::Script::Sup => 0.7,
+    }
+}
+
+fn script_rise(font_size: f32, script: text_format::Script) -> f32 {
+    match script {
+        text_format::Script::Normal => 0.0,
+        text_format::Script::Sup => font_size * 0.35,
+        text_format::Script::Sub => -font_size * 0.2,
+    }
+}
+
+/// How the first line of text sits relative to its anchor, like SVG's
+/// `dominant-baseline`.
+#[derive(Clone, Copy)]
+enum Baseline {
+    Middle,
+    Hanging,
+}
+
+struct Page {
+    /// Content stream in world coordinates; the page matrix flips y.
+    content: Vec<u8>,
+    /// Distinct (fill, stroke) opacities, each exposed as `/GSn`.
+    alphas: Vec<(u8, u8)>,
+}
+
+impl Page {
+    fn op(&mut self, s: &str) {
+        self.content.extend_from_slice(s.as_bytes());
+        self.content.push(b'\n');
+    }
+
+    fn set_alpha(&mut self, fill: u8, stroke: u8) {
+        let index = match self.alphas.iter().position(|a| *a == (fill, stroke)) {
+            Some(i) => i,
+            None => {
+                self.alphas.push((fill, stroke));
+                self.alphas.len() - 1
+            }
+        };
+        self.op(&format!("/GS{index} gs"));
+    }
+
+    fn path(&mut self, points: &[egui::Pos2], close: bool) {
+        let mut s = String::new();
+        for (i, p) in points.iter().enumerate() {
+            let op = if i == 0 { "m" } else { "l" };
+            let _ = writeln!(s, "{:.3} {:.3} {op}", p.x, p.y);
+        }
+        if close {
+            s.push('h');
+        }
+        self.op(s.trim_end());
+    }
+
+    fn stroke_state(&mut self, style: &model::Style) {
+        let width = style.stroke.width;
+        self.op(&format!("{} RG {width:.3} w", rgb(style.stroke.color)));
+        match dash_lengths(style.stroke.line_style, width) {
+            Some((dash, gap)) => self.op(&format!("[{dash:.3} {gap:.3}] 0 d")),
+            None => self.op("[] 0 d"),
+        }
+    }
+
+    fn shape(&mut self, points: &[egui::Pos2], style: &model::Style) {
+        let fill = style.fill.filter(|c| c.a > 0);
+        self.op("q");
+        self.set_alpha(fill.map_or(255, |c| c.a), style.stroke.color.a);
+        self.stroke_state(style);
+        if let Some(fill) = fill {
+            self.op(&format!("{} rg", rgb(fill)));
+        }
+        self.path(points, true);
+        // A zero width means "thinnest possible" in PDF but "no stroke" in SVG.
+        let stroked = style.stroke.width > 0.0;
+        self.op(match (fill.is_some(), stroked) {
+            (true, true) => "B",
+            (true, false) => "f",
+            (false, true) => "S",
+            (false, false) => "n",
+        });
+        self.op("Q");
+    }
+
+    fn open_path(&mut self, points: &[egui::Pos2], style: &model::Style) {
+        if style.stroke.width <= 0.0 {
+            return;
+        }
+        self.op("q");
+        self.set_alpha(255, style.stroke.color.a);
+        self.stroke_state(style);
+        self.path(points, false);
+        self.op("S");
+        self.op("Q");
+    }
+
+    /// Matches the SVG marker drawn for the same head.
+    fn arrowhead(
+        &mut self,
+        head: model::ArrowHead,
+        from: egui::Pos2,
+        tip: egui::Pos2,
+        scale: f32,
+        style: &model::Style,
+    ) {
+        let size = arrowhead_size(style.stroke.width, scale);
+        for part in arrowhead_parts_world(head, from, tip, size) {
+            self.op("q");
+            match part {
+                ArrowheadPart::Filled(points) => {
+                    self.set_alpha(style.stroke.color.a, 255);
+                    self.op(&format!("{} rg", rgb(style.stroke.color)));
+                    self.path(&points, true);
+                    self.op("f");
+                }
+                ArrowheadPart::Hollow(points) => {
+                    self.set_alpha(255, style.stroke.color.a);
+                    self.op(&format!(
+                        "{} RG {:.3} w [] 0 d 1 1 1 rg",
+                        rgb(style.stroke.color),
+                        style.stroke.width
+                    ));
+                    self.path(&points, true);
+                    self.op("B");
+                }
+                ArrowheadPart::Stroked(points) => {
+                    self.set_alpha(255, style.stroke.color.a);
+                    self.op(&format!(
+                        "{} RG {:.3} w [] 0 d",
+                        rgb(style.stroke.color),
+                        style.stroke.width
+                    ));
+                    self.path(&points, false);
+                    self.op("S");
+                }
+            }
+            self.op("Q");
+        }
+    }
+
+    fn junction_dot(&mut self, dot: &JunctionDot) {
+        let rect = egui::Rect::from_center_size(dot.center, egui::Vec2::splat(dot.radius * 2.0));
+        self.op("q");
+        self.set_alpha(dot.color.a, 255);
+        self.op(&format!("{} rg", rgb(dot.color)));
+        self.path(&rotated_ellipse_points_world(rect, 0.0), true);
+        self.op("f");
+        self.op("Q");
+    }
+
+    /// Lays out rich text the way the SVG `<text>` with `<tspan>`s does: the
+    /// first line sits on the anchor, later lines follow 1.2em apart, and the
+    /// whole block is rotated about `pivot`.
+    fn text(
+        &mut self,
+        anchor: egui::Pos2,
+        text: &str,
+        style: &model::Style,
+        baseline: Baseline,
+        rotation: f32,
+        pivot: egui::Pos2,
+    ) {
+        let size = style.text_size;
+        let font = Font::for_family(&style.font_family);
+        let first_baseline = match baseline {
+            Baseline::Middle => size * 0.35,
+            Baseline::Hanging => size * 0.8,
+        };
+        let (sin, cos) = rotation.sin_cos();
+        self.op("q");
+        self.set_alpha(style.text_color.a, 255);
+        self.op(&format!("{} rg", rgb(style.text_color)));
+        // Rotate about the pivot in world space; each line's text matrix then
+        // undoes the page flip so glyphs are upright.
+        if rotation.abs() > f32::EPSILON {
+            self.op(&format!(
+                "{cos:.5} {sin:.5} {:.5} {cos:.5} {:.3} {:.3} cm",
+                -sin,
+                pivot.x - pivot.x * cos + pivot.y * sin,
+                pivot.y - pivot.x * sin - pivot.y * cos
+            ));
+        }
+        self.op("BT");
+        for (i, spans) in text_format::parse_rich_text_lines(text).iter().enumerate() {
+            let runs: Vec<(text_format::Script, Vec<u8>)> =
+                spans.iter().map(|s| (s.script, encode(&s.text))).collect();
+            let width: f32 = runs
+                .iter()
+                .map(|(script, bytes)| text_width(bytes, font, size * script_scale(*script)))
+                .sum();
+            let x = match style.text_align {
+                model::TextAlign::Left => anchor.x,
+                model::TextAlign::Center => anchor.x - width * 0.5,
+                model::TextAlign::Right => anchor.x - width,
+            };
+            let y = anchor.y + first_baseline + i as f32 * size * 1.2;
+            self.op(&format!("1 0 0 -1 {x:.3} {y:.3} Tm"));
+            for (script, bytes) in runs {
+                self.op(&format!(
+                    "/{} {:.3} Tf {:.3} Ts",
+                    font.resource(),
+                    size * script_scale(script),
+                    script_rise(size, script)
+                ));
+                pdf_string(&bytes, &mut self.content);
+                self.content.extend_from_slice(b" Tj\n");
+            }
+        }
+        self.op("ET");
+        self.op("Q");
+    }
+
+    fn label(&mut self, rect: &model::RectF, label: &str, element: &model::Element) {
+        if label.is_empty() {
+            return;
+        }
+        let r = rect.to_rect();
+        let p = label_anchor_in_rect(r, element.style.text_align);
+        self.text(
+            p,
+            label,
+            &element.style,
+            Baseline::Middle,
+            element.rotation,
+            r.center(),
+        );
+    }
+
+    /// Centers a line's label on its anchor over an optional background box
+    /// in the line's fill color.
+    fn line_label(&mut self, doc: &model::Document, element: &model::Element, label: &str) {
+        let (Some(anchor), Some(rect)) = (
+            line_label_anchor(doc, element),
+            line_label_rect(doc, element),
+        ) else {
+            return;
+        };
+        let mut style = element.style.clone();
+        if style.fill.is_some_and(|c| c.a > 0) {
+            style.stroke.width = 0.0;
+            let corners = [
+                rect.left_top(),
+                rect.right_top(),
+                rect.right_bottom(),
+                rect.left_bottom(),
+            ];
+            self.shape(&corners, &style);
+        }
+        style.text_align = model::TextAlign::Center;
+        let lines = text_format::parse_rich_text_lines(label).len().max(1);
+        let first = anchor - egui::vec2(0.0, (lines - 1) as f32 * style.text_size * 0.6);
+        self.text(first, label, &style, Baseline::Middle, 0.0, anchor);
+    }
+}
+
+pub fn document_to_pdf(doc: &model::Document) -> Vec<u8> {
+    let bounds = page_bounds(doc);
+    let mut page = Page {
+        content: Vec::new(),
+        alphas: Vec::new(),
+    };
+    // PDF's y axis points up; map world coordinates onto the page once.
+    page.op(&format!(
+        "1 0 0 -1 {:.3} {:.3} cm",
+        -bounds.min.x, bounds.max.y
+    ));
+
+    let hops = line_hops(doc);
+    for e in &doc.elements {
+        let hops = hops.get(&e.id).map_or(&[][..], Vec::as_slice);
+        let hop_radius = line_hop_radius(e.style.stroke.width);
+        match &e.kind {
+            model::ElementKind::Rect {
+                rect,
+                label,
+                corner_radius,
+            } => {
+                page.shape(
+                    &rotated_rounded_rect_points_world(rect.to_rect(), e.rotation, *corner_radius),
+                    &e.style,
+                );
+                page.label(rect, label, e);
+            }
+            model::ElementKind::Ellipse { rect, label } => {
+                page.shape(
+                    &rotated_ellipse_points_world(rect.to_rect(), e.rotation),
+                    &e.style,
+                );
+                page.label(rect, label, e);
+            }
+            model::ElementKind::Triangle {
+                rect,
+                label,
+                apex_ratio,
+            } => {
+                let pts = rotated_triangle_points_world(rect.to_rect(), e.rotation, *apex_ratio);
+                page.shape(&pts, &e.style);
+                page.label(rect, label, e);
+            }
+            model::ElementKind::Parallelogram {
+                rect,
+                label,
+                skew_ratio,
+            } => {
+                let pts =
+                    rotated_parallelogram_points_world(rect.to_rect(), e.rotation, *skew_ratio);
+                page.shape(&pts, &e.style);
+                page.label(rect, label, e);
+            }
+            model::ElementKind::Trapezoid {
+                rect,
+                label,
+                top_inset_ratio,
+            } => {
+                let pts =
+                    rotated_trapezoid_points_world(rect.to_rect(), e.rotation, *top_inset_ratio);
+                page.shape(&pts, &e.style);
+                page.label(rect, label, e);
+            }
+            model::ElementKind::Diamond { rect, label }
+            | model::ElementKind::Hexagon { rect, label, .. }
+            | model::ElementKind::Cylinder { rect, label, .. }
+            | model::ElementKind::Polygon { rect, label, .. } => {
+                page.shape(&shape_outline_world(e).unwrap_or_default(), &e.style);
+                if let model::ElementKind::Cylinder { cap_ratio, .. } = &e.kind {
+                    let rim =
+                        rotated_cylinder_rim_points_world(rect.to_rect(), e.rotation, *cap_ratio);
+                    page.open_path(&rim, &e.style);
+                }
+                page.label(rect, label, e);
+            }
+            model::ElementKind::Line {
+                a,
+                b,
+                arrow,
+                arrow_style,
+                heads,
+                start_binding,
+                end_binding,
+                route,
+                label,
+                ..
+            } => {
+                let pts = line_points_world(doc, *a, *b, route, start_binding, end_binding);
+                let curve = line_curve_world(doc, *a, *b, route, start_binding, end_binding);
+                page.open_path(&hopped_path(&pts, hops, hop_radius), &e.style);
+                let n = pts.len();
+                let (start_tail, end_tail) = arrow_tails(&pts, curve.as_ref());
+                if *arrow
+                    || matches!(
+                        arrow_style,
+                        model::ArrowStyle::End | model::ArrowStyle::Both
+                    )
+                {
+                    page.arrowhead(heads.end, end_tail, pts[n - 1], heads.scale, &e.style);
+                }
+                if matches!(
+                    arrow_style,
+                    model::ArrowStyle::Start | model::ArrowStyle::Both
+                ) {
+                    page.arrowhead(heads.start, start_tail, pts[0], heads.scale, &e.style);
+                }
+                page.line_label(doc, e, label);
+            }
+            model::ElementKind::Polyline {
+                points,
+                arrow_style,
+                heads,
+                start_binding,
+                end_binding,
+            } => {
+                if points.len() >= 2 {
+                    let pts = polyline_points_world(doc, points, start_binding, end_binding);
+                    page.open_path(&hopped_path(&pts, hops, hop_radius), &e.style);
+                    let n = pts.len();
+                    if matches!(
+                        arrow_style,
+                        model::ArrowStyle::End | model::ArrowStyle::Both
+                    ) {
+                        page.arrowhead(heads.end, pts[n - 2], pts[n - 1], heads.scale, &e.style);
+                    }
+                    if matches!(
+                        arrow_style,
+                        model::ArrowStyle::Start | model::ArrowStyle::Both
+                    ) {
+                        page.arrowhead(heads.start, pts[1], pts[0], heads.scale, &e.style);
+                    }
+                }
+            }
+            model::ElementKind::Pen { points } => {
+                if points.len() >= 2 {
+                    let pts: Vec<egui::Pos2> = points.iter().map(|p| p.to_pos2()).collect();
+                    page.open_path(&pts, &e.style);
+                }
+            }
+            model::ElementKind::Text { pos, text } => {
+                let p = pos.to_pos2();
+                page.text(p, text, &e.style, Baseline::Hanging, 0.0, p);
+            }
+        }
+    }
+    for dot in junction_dots(doc) {
+        page.junction_dot(&dot);
+    }
+
+    let mut ext_g_states = String::new();
+    for (i, (fill, stroke)) in page.alphas.iter().enumerate() {
+        let _ = write!(
+            ext_g_states,
+            "/GS{i} << /ca {:.3} /CA {:.3} >> ",
+            *fill as f32 / 255.0,
+            *stroke as f32 / 255.0
+        );
+    }
+    let font = |name: &str| {
+        format!("<< /Type /Font /Subtype /Type1 /BaseFont /{name} /Encoding /WinAnsiEncoding >>")
+    };
+    let objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        b"<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_vec(),
+        format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {:.3} {:.3}] /Contents 4 0 R \
+             /Resources << /Font << /F1 5 0 R /F2 6 0 R >> /ExtGState << {ext_g_states}>> >> >>",
+            bounds.width(),
+            bounds.height()
+        )
+        .into_bytes(),
+        {
+            let mut stream = format!("<< /Length {} >>\nstream\n", page.content.len()).into_bytes();
+            stream.extend_from_slice(&page.content);
+            stream.extend_from_slice(b"endstream");
+            stream
+        },
+        font("Helvetica").into_bytes(),
+        font("Courier").into_bytes(),
+    ];
+
+    let mut out: Vec<u8> = b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (i, body) in objects.iter().enumerate() {
+        offsets.push(out.len());
+        out.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        out.extend_from_slice(body);
+        out.extend_from_slice(b"\nendobj\n");
+    }
+    let xref = out.len();
+    let mut tail = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(tail, "{offset:010} 00000 n ");
+    }
+    let _ = write!(
+        tail,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        objects.len() + 1
+    );
+    out.extend_from_slice(tail.as_bytes());
+    out
+}
@@ -0,0 +1,685 @@
+//! CPU rasterizer used for PNG export.
+//!
+//! Elements are turned into the same kind of egui shapes the editor paints,
+//! tessellated by epaint, and the resulting triangles are filled in software
+//! with premultiplied alpha blending. Text is sampled from epaint's font atlas,
+//! so exported labels use the same fonts as the canvas.
+
+use crate::geometry::{
+    ArrowheadPart, arrow_tails, arrowhead_parts_world, arrowhead_size, hopped_path, junction_dots,
+    line_curve_world, line_hop_radius, line_hops, line_label_anchor, line_label_rect,
+    line_points_world, polyline_points_world, rotated_cylinder_rim_points_world,
+    rotated_ellipse_points_world, rotated_parallelogram_points_world,
+    rotated_rounded_rect_points_world, rotated_trapezoid_points_world,
+    rotated_triangle_points_world, shape_outline_world,
+};
+use crate::svg::page_bounds;
+use crate::{model, text_format};
+use egui::epaint::{
+    AlphaFromCoverage, ClippedShape, ColorImage, Fonts, FontsView, Primitive, Shape,
+    TessellationOptions, Tessellator,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const MAX_SIDE: u32 = 16384;
+
+pub struct RasterOptions {
+    /// Output pixels per world unit; 2.0 renders at 192 DPI.
+    pub scale: f32,
+    /// Leave the background transparent instead of filling it with white.
+    pub transparent: bool,
+    pub fonts: egui::FontDefinitions,
+}
+
+impl Default for RasterOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            transparent: false,
+            fonts: egui::FontDefinitions::default(),
+        }
+    }
+}
+
+/// Straight (not premultiplied) RGBA pixels, row by row.
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+pub fn document_to_png(doc: &model::Document, options: &RasterOptions) -> Result<Vec<u8>, String> {
+    let image = render_document(doc, options)?;
+    let mut out = std::io::Cursor::new(Vec::new());
+    image::write_buffer_with_format(
+        &mut out,
+        &image.pixels,
+        image.width,
+        image.height,
+        image::ExtendedColorType::Rgba8,
+        image::ImageFormat::Png,
+    )
+    .map_err(|e| format!("PNG encode failed: {e}"))?;
+    Ok(out.into_inner())
+}
+
+pub fn render_document(
+    doc: &model::Document,
+    options: &RasterOptions,
+) -> Result<RgbaImage, String> {
+    if !(options.scale.is_finite() && options.scale > 0.0) {
+        return Err(format!("Invalid scale {}", options.scale));
+    }
+    let bounds = page_bounds(doc);
+    let width = (bounds.width() * options.scale).ceil().max(1.0);
+    let height = (bounds.height() * options.scale).ceil().max(1.0);
+    if width > MAX_SIDE as f32 || height > MAX_SIDE as f32 {
+        return Err(format!(
+            "Image would be {width}x{height} pixels; the limit is {MAX_SIDE} per side"
+        ));
+    }
+    let (width, height) = (width as u32, height as u32);
+
+    let mut fonts = Fonts::new(8192, AlphaFromCoverage::default(), options.fonts.clone());
+    let shapes = {
+        let mut painter = ShapeBuilder {
+            fonts: fonts.with_pixels_per_point(1.0),
+            origin: bounds.min,
+            scale: options.scale,
+            shapes: Vec::new(),
+            hops: line_hops(doc),
+        };
+        for element in &doc.elements {
+            painter.element(doc, element);
+        }
+        for dot in junction_dots(doc) {
+            painter.shapes.push(Shape::circle_filled(
+                painter.to_pixels(dot.center),
+                dot.radius * painter.scale,
+                dot.color.to_color32(),
+            ));
+        }
+        painter.shapes
+    };
+
+    // The atlas only contains the glyphs once every label has been laid out.
+    let texture = fonts.image();
+    let mut tessellator = Tessellator::new(
+        1.0,
+        TessellationOptions::default(),
+        fonts.font_image_size(),
+        fonts.texture_atlas().prepared_discs(),
+    );
+    let clipped = shapes
+        .into_iter()
+        .map(|shape| ClippedShape {
+            clip_rect: egui::Rect::EVERYTHING,
+            shape,
+        })
+        .collect();
+    let primitives = tessellator.tessellate_shapes(clipped);
+
+    let background = if options.transparent {
+        [0.0; 4]
+    } else {
+        [1.0; 4]
+    };
+    let mut canvas = Canvas {
+        width: width as usize,
+        height: height as usize,
+        pixels: vec![background; width as usize * height as usize],
+        texture: &texture,
+    };
+    for primitive in primitives {
+        if let Primitive::Mesh(mesh) = primitive.primitive {
+            for tri in mesh.indices.chunks_exact(3) {
+                canvas.fill_triangle([
+                    &mesh.vertices[tri[0] as usize],
+                    &mesh.vertices[tri[1] as usize],
+                    &mesh.vertices[tri[2] as usize],
+                ]);
+            }
+        }
+    }
+
+    let mut pixels = Vec::with_capacity(canvas.pixels.len() * 4);
+    for [r, g, b, a] in canvas.pixels {
+        let unmultiply = |c: f32| {
+            if a > 0.0 {
+                (c / a).clamp(0.0, 1.0)
+            } else {
+                0.0
+            }
+        };
+        pixels.extend(
+            [
+                unmultiply(r),
+                unmultiply(g),
+                unmultiply(b),
+                a.clamp(0.0, 1.0),
+            ]
+            .map(|c| (c * 255.0).round() as u8),
+        );
+    }
+    Ok(RgbaImage {
+        width,
+        height,
+        pixels,
+    })
+}
+
+fn font_id(font_size: f32, family: &model::FontFamily, fonts: &FontsView<'_>) -> egui::FontId {
+    let family = match family {
+        model::FontFamily::Proportional => egui::FontFamily::Proportional,
+        model::FontFamily::Monospace => egui::FontFamily::Monospace,
+        model::FontFamily::Custom(name) => {
+            let named = egui::FontFamily::Name(name.clone().into());
+            // Fonts loaded from the editor's font directory are not available headless.
+            if fonts.definitions().families.contains_key(&named) {
+                named
+            } else {
+                egui::FontFamily::Proportional
+            }
+        }
+    };
+    egui::FontId::new(font_size, family)
+}
+
+fn script_scale(script: text_format::Script) -> f32 {
+    match script {
+        text_format::Script::Normal => 1.0,
+        text_format::Script::Sub | text_format::Script::Sup => 0.7,
+    }
+}
+
+fn script_y_offset(font_size: f32, script: text_format::Script) -> f32 {
+    match script {
+        text_format::Script::Normal => 0.0,
+        text_format::Script::Sup => -font_size * 0.35,
+        text_format::Script::Sub => font_size * 0.2,
+    }
+}
+
+fn rotate_pos_about(center: egui::Pos2, p: egui::Pos2, rotation: f32) -> egui::Pos2 {
+    if rotation.abs() <= f32::EPSILON {
+        return p;
+    }
+    let (sin, cos) = rotation.sin_cos();
+    let v = p - center;
+    center + egui::vec2(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
+}
+
+#[derive(Clone, Copy)]
+enum VAlign {
+    Center,
+    Top,
+}
+
+struct ShapeBuilder<'a> {
+    fonts: FontsView<'a>,
+    origin: egui::Pos2,
+    scale: f32,
+    shapes: Vec<Shape>,
+    hops: HashMap<u64, Vec<f32>>,
+}
+
+impl ShapeBuilder<'_> {
+    /// A connector's world path with its line jumps.
+    fn hopped(&self, element: &model::Element, points: &[egui::Pos2]) -> Vec<egui::Pos2> {
+        let hops = self.hops.get(&element.id).map_or(&[][..], Vec::as_slice);
+        hopped_path(points, hops, line_hop_radius(element.style.stroke.width))
+    }
+
+    fn to_pixels(&self, p: egui::Pos2) -> egui::Pos2 {
+        egui::pos2(
+            (p.x - self.origin.x) * self.scale,
+            (p.y - self.origin.y) * self.scale,
+        )
+    }
+
+    fn element(&mut self, doc: &model::Document, element: &model::Element) {
+        let style = &element.style;
+        let stroke = egui::Stroke::new(
+            style.stroke.width * self.scale,
+            style.stroke.color.to_color32(),
+        );
+        let fill = style
+            .fill
+            .map(|c| c.to_color32())
+            .unwrap_or(egui::Color32::TRANSPARENT);
+        let outline = match &element.kind {
+            model::ElementKind::Rect {
+                rect,
+                label,
+                corner_radius,
+            } => Some((
+                rotated_rounded_rect_points_world(rect.to_rect(), element.rotation, *corner_radius),
+                rect,
+                label,
+            )),
+            model::ElementKind::Ellipse { rect, label } => Some((
+                rotated_ellipse_points_world(rect.to_rect(), element.rotation),
+                rect,
+                label,
+            )),
+            model::ElementKind::Triangle {
+                rect,
+                label,
+                apex_ratio,
+            } => Some((
+                rotated_triangle_points_world(rect.to_rect(), element.rotation, *apex_ratio),
+                rect,
+                label,
+            )),
+            model::ElementKind::Parallelogram {
+                rect,
+                label,
+                skew_ratio,
+            } => Some((
+                rotated_parallelogram_points_world(rect.to_rect(), element.rotation, *skew_ratio),
+                rect,
+                label,
+            )),
+            model::ElementKind::Trapezoid {
+                rect,
+                label,
+                top_inset_ratio,
+            } => Some((
+                rotated_trapezoid_points_world(rect.to_rect(), element.rotation, *top_inset_ratio),
+                rect,
+                label,
+            )),
+            model::ElementKind::Diamond { rect, label }
+            | model::ElementKind::Hexagon { rect, label, .. }
+            | model::ElementKind::Cylinder { rect, label, .. }
+            | model::ElementKind::Polygon { rect, label, .. } => {
+                Some((shape_outline_world(element).unwrap_or_default(), rect, label))
+            }
+            _ => None,
+        };
+        if let Some((points, rect, label)) = outline {
+            let mut points: Vec<egui::Pos2> =
+                points.into_iter().map(|p| self.to_pixels(p)).collect();
+            if style.stroke.line_style == model::LineStyle::Solid {
+                self.shapes
+                    .push(Shape::convex_polygon(points, fill, stroke));
+            } else {
+                self.shapes.push(Shape::convex_polygon(
+                    points.clone(),
+                    fill,
+                    egui::Stroke::NONE,
+                ));
+                // Degenerate shapes have no outline to close.
+                if let Some(&first) = points.first() {
+                    points.push(first);
+                }
+                self.styled_path(&points, stroke, style.stroke.line_style);
+            }
+            if let model::ElementKind::Cylinder { cap_ratio, .. } = &element.kind {
+                let rim: Vec<egui::Pos2> =
+                    rotated_cylinder_rim_points_world(rect.to_rect(), element.rotation, *cap_ratio)
+                        .into_iter()
+                        .map(|p| self.to_pixels(p))
+                        .collect();
+                self.styled_path(&rim, stroke, style.stroke.line_style);
+            }
+            if !label.is_empty() {
+                let r = rect.to_rect();
+                let c = r.center();
+                let padding = 8.0_f32.min(r.width() * 0.25);
+                let anchor = match style.text_align {
+                    model::TextAlign::Left => egui::pos2(r.left() + padding, c.y),
+                    model::TextAlign::Center => c,
+                    model::TextAlign::Right => egui::pos2(r.right() - padding, c.y),
+                };
+                let anchor = self.to_pixels(rotate_pos_about(c, anchor, element.rotation));
+                self.rich_text(anchor, label, style, element.rotation, VAlign::Center);
+            }
+            return;
+        }
+
+        match &element.kind {
+            model::ElementKind::Line {
+                a,
+                b,
+                arrow,
+                arrow_style,
+                heads,
+                start_binding,
+                end_binding,
+                route,
+                label,
+                ..
+            } => {
+                let world = line_points_world(doc, *a, *b, route, start_binding, end_binding);
+                let pts: Vec<egui::Pos2> = self
+                    .hopped(element, &world)
+                    .into_iter()
+                    .map(|p| self.to_pixels(p))
+                    .collect();
+                let curve = line_curve_world(doc, *a, *b, route, start_binding, end_binding)
+                    .map(|c| c.map(|p| self.to_pixels(p)));
+                self.styled_path(&pts, stroke, style.stroke.line_style);
+                let n = pts.len();
+                let (start_tail, end_tail) = arrow_tails(&pts, curve.as_ref());
+                if *arrow
+                    || matches!(
+                        arrow_style,
+                        model::ArrowStyle::End | model::ArrowStyle::Both
+                    )
+                {
+                    self.arrowhead(
+                        heads.end,
+                        end_tail,
+                        pts[n - 1],
+                        heads.scale,
+                        style.stroke.width,
+                        stroke,
+                    );
+                }
+                if matches!(
+                    arrow_style,
+                    model::ArrowStyle::Start | model::ArrowStyle::Both
+                ) {
+                    self.arrowhead(
+                        heads.start,
+                        start_tail,
+                        pts[0],
+                        heads.scale,
+                        style.stroke.width,
+                        stroke,
+                    );
+                }
+                if let (Some(anchor), Some(rect)) = (
+                    line_label_anchor(doc, element),
+                    line_label_rect(doc, element),
+                ) {
+                    if style.fill.is_some() {
+                        let rect = egui::Rect::from_min_max(
+                            self.to_pixels(rect.min),
+                            self.to_pixels(rect.max),
+                        );
+                        self.shapes.push(Shape::rect_filled(rect, 0.0, fill));
+                    }
+                    let mut label_style = style.clone();
+                    label_style.text_align = model::TextAlign::Center;
+                    let anchor = self.to_pixels(anchor);
+                    self.rich_text(anchor, label, &label_style, 0.0, VAlign::Center);
+                }
+            }
+            model::ElementKind::Polyline {
+                points,
+                arrow_style,
+                heads,
+                start_binding,
+                end_binding,
+            } => {
+                if points.len() < 2 {
+                    return;
+                }
+                let world = polyline_points_world(doc, points, start_binding, end_binding);
+                let pts: Vec<egui::Pos2> = self
+                    .hopped(element, &world)
+                    .into_iter()
+                    .map(|p| self.to_pixels(p))
+                    .collect();
+                self.styled_path(&pts, stroke, style.stroke.line_style);
+                let n = pts.len();
+                if matches!(
+                    arrow_style,
+                    model::ArrowStyle::End | model::ArrowStyle::Both
+                ) {
+                    self.arrowhead(
+                        heads.end,
+                        pts[n - 2],
+                        pts[n - 1],
+                        heads.scale,
+                        style.stroke.width,
+                        stroke,
+                    );
+                }
+                if matches!(
+                    arrow_style,
+                    model::ArrowStyle::Start | model::ArrowStyle::Both
+                ) {
+                    self.arrowhead(
+                        heads.start,
+                        pts[1],
+                        pts[0],
+                        heads.scale,
+                        style.stroke.width,
+                        stroke,
+                    );
+                }
+            }
+            model::ElementKind::Pen { points } if points.len() >= 2 => {
+                let pts = points.iter().map(|p| self.to_pixels(p.to_pos2())).collect();
+                self.shapes.push(Shape::line(pts, stroke));
+            }
+            model::ElementKind::Text { pos, text } => {
+                let pos = self.to_pixels(pos.to_pos2());
+                self.rich_text(pos, text, style, 0.0, VAlign::Top);
+            }
+            _ => {}
+        }
+    }
+
+    /// Solid paths are drawn as one stroke; dashes keep their phase across
+    /// corners so a dashed polyline reads as a single line.
+    fn styled_path(
+        &mut self,
+        points: &[egui::Pos2],
+        stroke: egui::Stroke,
+        line_style: model::LineStyle,
+    ) {
+        let (dash, gap) = match line_style {
+            model::LineStyle::Solid => {
+                self.shapes.push(Shape::line(points.to_vec(), stroke));
+                return;
+            }
+            model::LineStyle::Dashed => (10.0 * self.scale, 5.0 * self.scale),
+            model::LineStyle::Dotted => (2.0 * self.scale, 4.0 * self.scale),
+        };
+        let mut drawing = true;
+        let mut left = dash;
+        for pair in points.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            let len = (b - a).length();
+            if len <= f32::EPSILON {
+                continue;
+            }
+            let dir = (b - a) / len;
+            let mut pos = 0.0;
+            while pos < len {
+                let step = left.min(len - pos);
+                if drawing {
+                    self.shapes.push(Shape::line_segment(
+                        [a + dir * pos, a + dir * (pos + step)],
+                        stroke,
+                    ));
+                }
+                pos += step;
+                left -= step;
+                if left <= f32::EPSILON {
+                    drawing = !drawing;
+                    left = if drawing { dash } else { gap };
+                }
+            }
+        }
+    }
+
+    /// `tail` and `tip` are in pixels; `width` is the unscaled stroke width.
+    fn arrowhead(
+        &mut self,
+        head: model::ArrowHead,
+        tail: egui::Pos2,
+        tip: egui::Pos2,
+        scale: f32,
+        width: f32,
+        stroke: egui::Stroke,
+    ) {
+        let size = arrowhead_size(width, scale) * self.scale;
+        for part in arrowhead_parts_world(head, tail, tip, size) {
+            self.shapes.push(match part {
+                ArrowheadPart::Filled(points) => {
+                    Shape::convex_polygon(points, stroke.color, egui::Stroke::NONE)
+                }
+                ArrowheadPart::Hollow(points) => {
+                    Shape::convex_polygon(points, egui::Color32::WHITE, stroke)
+                }
+                ArrowheadPart::Stroked(points) => Shape::line(points, stroke),
+            });
+        }
+    }
+
+    fn rich_text(
+        &mut self,
+        anchor: egui::Pos2,
+        text: &str,
+        style: &model::Style,
+        rotation: f32,
+        valign: VAlign,
+    ) {
+        let font_size = style.text_size * self.scale;
+        // epaint panics on fonts without a positive size.
+        if !(font_size.is_finite() && font_size > 0.0) {
+            return;
+        }
+        let color = style.text_color.to_color32();
+        let line_height = font_size * 1.2;
+        let lines = text_format::parse_rich_text_lines(text);
+        let total_height = lines.len().max(1) as f32 * line_height;
+        let mut y = match valign {
+            VAlign::Center => anchor.y - total_height * 0.5,
+            VAlign::Top => anchor.y,
+        };
+        for spans in &lines {
+            let galleys: Vec<(text_format::Script, Arc<egui::Galley>)> = spans
+                .iter()
+                .map(|s| {
+                    let font = font_id(
+                        font_size * script_scale(s.script),
+                        &style.font_family,
+                        &self.fonts,
+                    );
+                    (
+                        s.script,
+                        self.fonts.layout_no_wrap(s.text.clone(), font, color),
+                    )
+                })
+                .collect();
+            let line_width: f32 = galleys.iter().map(|(_, g)| g.size().x).sum();
+            let mut x = match style.text_align {
+                model::TextAlign::Left => anchor.x,
+                model::TextAlign::Center => anchor.x - line_width * 0.5,
+                model::TextAlign::Right => anchor.x - line_width,
+            };
+            for (script, galley) in galleys {
+                let width = galley.size().x;
+                let pos = egui::pos2(x, y + script_y_offset(font_size, script));
+                let mut shape =
+                    Shape::galley(rotate_pos_about(anchor, pos, rotation), galley, color);
+                if let Shape::Text(data) = &mut shape {
+                    data.angle = rotation;
+                }
+                self.shapes.push(shape);
+                x += width;
+            }
+            y += line_height;
+        }
+    }
+}
+
+/// Premultiplied RGBA in 0..=1, blended in gamma space like the editor's GPU painter.
+struct Canvas<'a> {
+    width: usize,
+    height: usize,
+    pixels: Vec<[f32; 4]>,
+    texture: &'a ColorImage,
+}
+
+impl Canvas<'_> {
+    fn sample(&self, uv: egui::Pos2) -> [f32; 4] {
+        let [tw, th] = self.texture.size;
+        let x = (uv.x * tw as f32 - 0.5).clamp(0.0, (tw - 1) as f32);
+        let y = (uv.y * th as f32 - 0.5).clamp(0.0, (th - 1) as f32);
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(tw - 1), (y0 + 1).min(th - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let texel = |x: usize, y: usize| self.texture.pixels[y * tw + x].to_array();
+        let (t00, t10, t01, t11) = (texel(x0, y0), texel(x1, y0), texel(x0, y1), texel(x1, y1));
+        std::array::from_fn(|i| {
+            let top = t00[i] as f32 * (1.0 - fx) + t10[i] as f32 * fx;
+            let bottom = t01[i] as f32 * (1.0 - fx) + t11[i] as f32 * fx;
+            (top * (1.0 - fy) + bottom * fy) / 255.0
+        })
+    }
+
+    fn fill_triangle(&mut self, mut v: [&egui::epaint::Vertex; 3]) {
+        let edge = |a: egui::Pos2, b: egui::Pos2, p: egui::Pos2| {
+            (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+        };
+        let mut area = edge(v[0].pos, v[1].pos, v[2].pos);
+        if area.abs() <= f32::EPSILON {
+            return;
+        }
+        if area < 0.0 {
+            v.swap(1, 2);
+            area = -area;
+        }
+        // Pixels exactly on an edge shared by two triangles belong to only one
+        // of them, otherwise translucent meshes would double up along seams.
+        let owns = |a: egui::Pos2, b: egui::Pos2| {
+            let d = b - a;
+            d.y > 0.0 || (d.y == 0.0 && d.x > 0.0)
+        };
+        let edges = [(1, 2), (2, 0), (0, 1)];
+        let owned = edges.map(|(i, j)| owns(v[i].pos, v[j].pos));
+
+        let min_x = v.iter().map(|v| v.pos.x).fold(f32::INFINITY, f32::min);
+        let max_x = v.iter().map(|v| v.pos.x).fold(f32::NEG_INFINITY, f32::max);
+        let min_y = v.iter().map(|v| v.pos.y).fold(f32::INFINITY, f32::min);
+        let max_y = v.iter().map(|v| v.pos.y).fold(f32::NEG_INFINITY, f32::max);
+        let x0 = (min_x - 0.5).floor().max(0.0) as usize;
+        let y0 = (min_y - 0.5).floor().max(0.0) as usize;
+        let x1 = ((max_x - 0.5).ceil().max(0.0) as usize).min(self.width.saturating_sub(1));
+        let y1 = ((max_y - 0.5).ceil().max(0.0) as usize).min(self.height.saturating_sub(1));
+        if min_x >= self.width as f32 || min_y >= self.height as f32 || max_x < 0.0 || max_y < 0.0 {
+            return;
+        }
+        let colors = v.map(|v| v.color.to_array().map(|c| c as f32 / 255.0));
+
+        for py in y0..=y1 {
+            for px in x0..=x1 {
+                let p = egui::pos2(px as f32 + 0.5, py as f32 + 0.5);
+                let mut w = [0.0f32; 3];
+                let mut inside = true;
+                for (k, &(i, j)) in edges.iter().enumerate() {
+                    let e = edge(v[i].pos, v[j].pos, p);
+                    if e < 0.0 || (e == 0.0 && !owned[k]) {
+                        inside = false;
+                        break;
+                    }
+                    w[k] = e / area;
+                }
+                if !inside {
+                    continue;
+                }
+                let uv = egui::pos2(
+                    w[0] * v[0].uv.x + w[1] * v[1].uv.x + w[2] * v[2].uv.x,
+                    w[0] * v[0].uv.y + w[1] * v[1].uv.y + w[2] * v[2].uv.y,
+                );
+                let texel = self.sample(uv);
+                let dst = &mut self.pixels[py * self.width + px];
+                let src: [f32; 4] = std::array::from_fn(|c| {
+                    (w[0] * colors[0][c] + w[1] * colors[1][c] + w[2] * colors[2][c]) * texel[c]
+                });
+                let keep = 1.0 - src[3];
+                for c in 0..4 {
+                    dst[c] = src[c] + dst[c] * keep;
+                }
+            }
+        }
+    }
+}
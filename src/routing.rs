@@ -0,0 +1,423 @@
+//! Orthogonal ("elbow") connector routing.
+//!
+//! Each end leaves its shape through a short stub perpendicular to the side it
+//! is bound to. The stubs are joined by a shortest path over a sparse grid
+//! built from the edges of the surrounding shapes (grown by a margin), with
+//! every bend charged extra so routes stay simple. Shapes far from the ends are
+//! only considered once the route grows towards them.
+
+use crate::geometry::{binding_exit_direction, resolved_line_endpoints_world};
+use crate::model;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+/// Clearance kept between a route and the shapes it passes.
+const MARGIN: f32 = 16.0;
+
+/// Costs are kept in sixteenths of a world unit so equal routes compare equal.
+const SCALE: f32 = 16.0;
+const BEND_COST: i64 = 32 * SCALE as i64;
+const CULL_PASSES: usize = 4;
+
+/// Unit steps right, left, down and up; `d ^ 1` is the opposite of `d`.
+const DIRS: [egui::Vec2; 4] = [
+    egui::vec2(1.0, 0.0),
+    egui::vec2(-1.0, 0.0),
+    egui::vec2(0.0, 1.0),
+    egui::vec2(0.0, -1.0),
+];
+const NO_DIR: usize = 4;
+
+/// Recomputes the bends of every orthogonal line in `doc`, dropping segment
+/// offsets that no longer match the route.
+pub fn update_routes(doc: &mut model::Document) {
+    let mut routes = Vec::new();
+    for (index, element) in doc.elements.iter().enumerate() {
+        let model::ElementKind::Line {
+            a,
+            b,
+            start_binding,
+            end_binding,
+            route: model::LineRoute::Orthogonal { offsets, .. },
+            ..
+        } = &element.kind
+        else {
+            continue;
+        };
+        let (a, b) = resolved_line_endpoints_world(doc, *a, *b, start_binding, end_binding);
+        let mut points = orthogonal_path(doc, a, b, start_binding.as_ref(), end_binding.as_ref());
+        let keep_offsets = offsets.len() + 1 == points.len();
+        if keep_offsets {
+            apply_segment_offsets(&mut points, offsets);
+        }
+        routes.push((index, points, keep_offsets));
+    }
+    for (index, points, keep_offsets) in routes {
+        if let model::ElementKind::Line {
+            route: model::LineRoute::Orthogonal { bends, offsets },
+            ..
+        } = &mut doc.elements[index].kind
+        {
+            // A line whose ends meet routes to a single point and has no bends.
+            *bends = points
+                .get(1..points.len().saturating_sub(1))
+                .unwrap_or_default()
+                .iter()
+                .map(|p| model::Point::from_pos2(*p))
+                .collect();
+            if !keep_offsets {
+                offsets.clear();
+            }
+        }
+    }
+}
+
+/// Hash of everything [`update_routes`] reads, so callers that run every frame
+/// can skip rerouting while nothing relevant has moved.
+pub fn route_inputs_key(doc: &model::Document) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    let write_point = |hasher: &mut DefaultHasher, p: egui::Pos2| {
+        p.x.to_bits().hash(hasher);
+        p.y.to_bits().hash(hasher);
+    };
+    for element in &doc.elements {
+        element.id.hash(&mut hasher);
+        match &element.kind {
+            model::ElementKind::Line {
+                a,
+                b,
+                start_binding,
+                end_binding,
+                route,
+                ..
+            } => {
+                // Any line can carry the end of an orthogonal one.
+                write_point(&mut hasher, a.to_pos2());
+                write_point(&mut hasher, b.to_pos2());
+                let model::LineRoute::Orthogonal { offsets, .. } = route else {
+                    continue;
+                };
+                for binding in [start_binding, end_binding].into_iter().flatten() {
+                    binding.element_id.hash(&mut hasher);
+                    write_point(&mut hasher, binding.norm.to_pos2());
+                    binding.port.hash(&mut hasher);
+                }
+                for offset in offsets {
+                    offset.to_bits().hash(&mut hasher);
+                }
+            }
+            model::ElementKind::Polyline { points, .. } => {
+                for p in points {
+                    write_point(&mut hasher, p.to_pos2());
+                }
+            }
+            model::ElementKind::Pen { .. } | model::ElementKind::Text { .. } => {}
+            _ => {
+                let bounds = element.bounds();
+                write_point(&mut hasher, bounds.min);
+                write_point(&mut hasher, bounds.max);
+                element.rotation.to_bits().hash(&mut hasher);
+                for port in &element.ports {
+                    port.name.hash(&mut hasher);
+                    port.side.name().hash(&mut hasher);
+                }
+            }
+        }
+    }
+    hasher.finish()
+}
+
+/// Shifts each inner segment of `points` sideways by its entry in `offsets`
+/// (one per segment; the first and last segments stay attached to the ends).
+fn apply_segment_offsets(points: &mut [egui::Pos2], offsets: &[f32]) {
+    if points.len() < 4 {
+        return;
+    }
+    for (i, offset) in offsets.iter().enumerate().take(points.len() - 2).skip(1) {
+        let (p, q) = (points[i], points[i + 1]);
+        let shift = if (p.y - q.y).abs() <= (p.x - q.x).abs() {
+            egui::vec2(0.0, *offset)
+        } else {
+            egui::vec2(*offset, 0.0)
+        };
+        points[i] += shift;
+        points[i + 1] += shift;
+    }
+}
+
+/// Manhattan path from `a` to `b`, both included. Bound ends leave their shape
+/// perpendicular to the side they sit on; free ends may leave either way.
+pub fn orthogonal_path(
+    doc: &model::Document,
+    a: egui::Pos2,
+    b: egui::Pos2,
+    start_binding: Option<&model::Binding>,
+    end_binding: Option<&model::Binding>,
+) -> Vec<egui::Pos2> {
+    let (start_stub, start_dir) = stub(doc, a, start_binding);
+    let (end_stub, end_dir) = stub(doc, b, end_binding);
+    // The path arrives at the end stub travelling back towards the shape.
+    let arrive_dir = if end_dir == NO_DIR {
+        NO_DIR
+    } else {
+        end_dir ^ 1
+    };
+
+    let mut area = egui::Rect::from_two_pos(start_stub, end_stub).expand(MARGIN);
+    let mut route = None;
+    for _ in 0..CULL_PASSES {
+        let obstacles = obstacles(doc, area, start_stub, end_stub);
+        route = search(&obstacles, start_stub, start_dir, end_stub, arrive_dir);
+        let Some(path) = &route else {
+            break;
+        };
+        let reach = path
+            .iter()
+            .fold(egui::Rect::NOTHING, |r, p| {
+                r.union(egui::Rect::from_pos(*p))
+            })
+            .expand(MARGIN);
+        if area.contains_rect(reach) {
+            break;
+        }
+        area = area.union(reach);
+    }
+    let middle = route.unwrap_or_else(|| {
+        let corner = if start_dir >= 2 {
+            egui::pos2(start_stub.x, end_stub.y)
+        } else {
+            egui::pos2(end_stub.x, start_stub.y)
+        };
+        vec![start_stub, corner, end_stub]
+    });
+
+    let mut points = vec![a];
+    points.extend(middle);
+    points.push(b);
+    simplify(points)
+}
+
+/// Point just outside the bound shape that `point` leaves through, and the
+/// direction it leaves in.
+fn stub(
+    doc: &model::Document,
+    point: egui::Pos2,
+    binding: Option<&model::Binding>,
+) -> (egui::Pos2, usize) {
+    let Some(binding) = binding else {
+        return (point, NO_DIR);
+    };
+    let Some(element) = doc.elements.iter().find(|e| e.id == binding.element_id) else {
+        return (point, NO_DIR);
+    };
+    let Some(v) = binding_exit_direction(doc, binding) else {
+        return (point, NO_DIR);
+    };
+    let dir = if v.x.abs() >= v.y.abs() {
+        if v.x >= 0.0 { 0 } else { 1 }
+    } else if v.y >= 0.0 {
+        2
+    } else {
+        3
+    };
+    let clear = element.bounds().expand(MARGIN);
+    let stub = match dir {
+        0 => egui::pos2(clear.max.x, point.y),
+        1 => egui::pos2(clear.min.x, point.y),
+        2 => egui::pos2(point.x, clear.max.y),
+        _ => egui::pos2(point.x, clear.min.y),
+    };
+    (stub, dir)
+}
+
+/// Grown bounds of the shapes touching `area`. Shapes covering a stub are left
+/// out, otherwise overlapping shapes would wall the route in.
+fn obstacles(
+    doc: &model::Document,
+    area: egui::Rect,
+    start: egui::Pos2,
+    end: egui::Pos2,
+) -> Vec<egui::Rect> {
+    doc.elements
+        .iter()
+        .filter(|e| {
+            !matches!(
+                e.kind,
+                model::ElementKind::Line { .. }
+                    | model::ElementKind::Polyline { .. }
+                    | model::ElementKind::Pen { .. }
+                    | model::ElementKind::Text { .. }
+            )
+        })
+        .map(|e| e.bounds().expand(MARGIN))
+        .filter(|r| r.intersects(area) && !strictly_inside(*r, start) && !strictly_inside(*r, end))
+        .collect()
+}
+
+fn strictly_inside(rect: egui::Rect, p: egui::Pos2) -> bool {
+    const EPS: f32 = 0.01;
+    p.x > rect.min.x + EPS
+        && p.x < rect.max.x - EPS
+        && p.y > rect.min.y + EPS
+        && p.y < rect.max.y - EPS
+}
+
+fn grid_coords(values: impl Iterator<Item = f32>) -> Vec<f32> {
+    let mut coords: Vec<f32> = values.collect();
+    coords.sort_by(f32::total_cmp);
+    coords.dedup_by(|a, b| (*a - *b).abs() < 0.01);
+    coords
+}
+
+fn grid_index(coords: &[f32], value: f32) -> usize {
+    let i = coords.partition_point(|c| *c < value);
+    if i > 0 && (i == coords.len() || value - coords[i - 1] < coords[i] - value) {
+        i - 1
+    } else {
+        i
+    }
+}
+
+/// Cheapest path from `from` to `to` over the grid spanned by the obstacle
+/// edges. `from_dir` is the direction already being travelled at the start,
+/// `to_dir` the one required on arrival ([`NO_DIR`] leaves either free).
+fn search(
+    obstacles: &[egui::Rect],
+    from: egui::Pos2,
+    from_dir: usize,
+    to: egui::Pos2,
+    to_dir: usize,
+) -> Option<Vec<egui::Pos2>> {
+    let mid = from.lerp(to, 0.5);
+    let xs = grid_coords(
+        [from.x, to.x, mid.x]
+            .into_iter()
+            .chain(obstacles.iter().flat_map(|r| [r.min.x, r.max.x])),
+    );
+    let ys = grid_coords(
+        [from.y, to.y, mid.y]
+            .into_iter()
+            .chain(obstacles.iter().flat_map(|r| [r.min.y, r.max.y])),
+    );
+    let (nx, ny) = (xs.len(), ys.len());
+    // Grid lines include every obstacle edge, so a grid step is either fully
+    // inside an obstacle or fully outside.
+    let mut right_blocked = vec![false; nx * ny];
+    let mut down_blocked = vec![false; nx * ny];
+    for r in obstacles {
+        let (x0, x1) = (grid_index(&xs, r.min.x), grid_index(&xs, r.max.x));
+        let (y0, y1) = (grid_index(&ys, r.min.y), grid_index(&ys, r.max.y));
+        for iy in y0..=y1 {
+            for ix in x0..=x1 {
+                if ix < x1 && iy > y0 && iy < y1 {
+                    right_blocked[iy * nx + ix] = true;
+                }
+                if iy < y1 && ix > x0 && ix < x1 {
+                    down_blocked[iy * nx + ix] = true;
+                }
+            }
+        }
+    }
+
+    let start = grid_index(&ys, from.y) * nx + grid_index(&xs, from.x);
+    let goal = grid_index(&ys, to.y) * nx + grid_index(&xs, to.x);
+    let state = |node: usize, dir: usize| node * 5 + dir;
+    let mut best = vec![(i64::MAX, i64::MAX); nx * ny * 5];
+    let mut prev = vec![usize::MAX; nx * ny * 5];
+    let mut heap = BinaryHeap::new();
+    best[state(start, from_dir)] = (0, 0);
+    heap.push(Reverse((0i64, 0i64, state(start, from_dir))));
+    let mut found: Option<((i64, i64), usize)> = None;
+
+    while let Some(Reverse((cost, tie, s))) = heap.pop() {
+        if (cost, tie) > best[s] {
+            continue;
+        }
+        if found.is_some_and(|(f, _)| (cost, tie) >= f) {
+            break;
+        }
+        let (node, dir) = (s / 5, s % 5);
+        if node == goal {
+            let extra = match (to_dir, dir) {
+                (NO_DIR, _) | (_, NO_DIR) => Some(0),
+                (t, d) if t == d => Some(0),
+                (t, d) if t == d ^ 1 => None,
+                _ => Some(BEND_COST),
+            };
+            if let Some(extra) = extra {
+                let total = (cost + extra, tie);
+                if found.is_none_or(|(f, _)| total < f) {
+                    found = Some((total, s));
+                }
+            }
+        }
+        let (ix, iy) = (node % nx, node / nx);
+        for (next_dir, step) in DIRS.iter().enumerate() {
+            if dir != NO_DIR && next_dir == dir ^ 1 {
+                continue;
+            }
+            let next = match next_dir {
+                0 if ix + 1 < nx && !right_blocked[node] => node + 1,
+                1 if ix > 0 && !right_blocked[node - 1] => node - 1,
+                2 if iy + 1 < ny && !down_blocked[node] => node + nx,
+                3 if iy > 0 && !down_blocked[node - nx] => node - nx,
+                _ => continue,
+            };
+            let here = egui::pos2(xs[ix], ys[iy]);
+            let there = egui::pos2(xs[next % nx], ys[next / nx]);
+            let mut next_cost = cost + ((there - here).length() * SCALE).round() as i64;
+            let mut next_tie = tie;
+            if dir != NO_DIR && next_dir != dir {
+                next_cost += BEND_COST;
+                // Among equally short routes, prefer bends near the middle.
+                let off_center = if step.x == 0.0 {
+                    (here.x - mid.x).abs()
+                } else {
+                    (here.y - mid.y).abs()
+                };
+                next_tie += (off_center * SCALE).round() as i64;
+            }
+            let t = state(next, next_dir);
+            if (next_cost, next_tie) < best[t] {
+                best[t] = (next_cost, next_tie);
+                prev[t] = s;
+                heap.push(Reverse((next_cost, next_tie, t)));
+            }
+        }
+    }
+
+    let (_, mut s) = found?;
+    let mut nodes = vec![s / 5];
+    while prev[s] != usize::MAX {
+        s = prev[s];
+        nodes.push(s / 5);
+    }
+    nodes.reverse();
+    Some(
+        nodes
+            .into_iter()
+            .map(|node| egui::pos2(xs[node % nx], ys[node / nx]))
+            .collect(),
+    )
+}
+
+/// Drops repeated points and points in the middle of a straight run.
+fn simplify(points: Vec<egui::Pos2>) -> Vec<egui::Pos2> {
+    let mut out: Vec<egui::Pos2> = Vec::with_capacity(points.len());
+    for p in points {
+        if out.last().is_some_and(|q| q.distance(p) < 0.01) {
+            continue;
+        }
+        if out.len() >= 2 {
+            let (a, b) = (out[out.len() - 2], out[out.len() - 1]);
+            let collinear = ((a.x - b.x).abs() < 0.01 && (b.x - p.x).abs() < 0.01)
+                || ((a.y - b.y).abs() < 0.01 && (b.y - p.y).abs() < 0.01);
+            if collinear {
+                out.pop();
+            }
+        }
+        out.push(p);
+    }
+    out
+}
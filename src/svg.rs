@@ -1,25 +1,36 @@
 use crate::{model, text_format};
-use eframe::egui;
 use std::collections::HashMap;
 
-use super::geometry::{
-    resolved_line_endpoints_world, rotated_ellipse_points_world, rotated_parallelogram_points_world,
-    rotated_rect_points_world, rotated_trapezoid_points_world, rotated_triangle_points_world,
+use crate::geometry::{
+    ArrowheadPart, arrowhead_parts, arrowhead_size, clamp_corner_radius, hop_runs, hop_side,
+    junction_dots, line_curve_world, line_hop_radius, line_hops, line_label_anchor,
+    line_label_rect, line_points_world, polyline_points_world, rotated_cylinder_rim_points_world,
+    rotated_ellipse_points_world, rotated_parallelogram_points_world, rotated_rect_points_world,
+    rotated_trapezoid_points_world, rotated_triangle_points_world, shape_outline_world,
 };
+use crate::xml::{escape_xml, unescape_xml};
 
-fn escape_xml(s: &str) -> String {
-    let mut out = String::with_capacity(s.len());
-    for ch in s.chars() {
-        match ch {
-            '&' => out.push_str("&amp;"),
-            '<' => out.push_str("&lt;"),
-            '>' => out.push_str("&gt;"),
-            '"' => out.push_str("&quot;"),
-            '\'' => out.push_str("&apos;"),
-            _ => out.push(ch),
-        }
-    }
-    out
+/// The serialized document rides along in `<metadata>` so the SVG can be
+/// reopened for editing.
+const DOCUMENT_NS: &str = "urn:sansuyu:document";
+const DOCUMENT_OPEN: &str = "<sansuyu:document";
+const DOCUMENT_CLOSE: &str = "</sansuyu:document>";
+
+pub fn has_embedded_document(src: &str) -> bool {
+    src.contains(DOCUMENT_OPEN)
+}
+
+/// Reads back the document embedded by [`document_to_svg`].
+pub fn document_from_svg(src: &str) -> Result<model::Document, String> {
+    let start = src
+        .find(DOCUMENT_OPEN)
+        .and_then(|i| src[i..].find('>').map(|j| i + j + 1))
+        .ok_or_else(|| "SVG has no embedded sansuyu document".to_string())?;
+    let end = src[start..]
+        .find(DOCUMENT_CLOSE)
+        .map(|i| start + i)
+        .ok_or_else(|| format!("unterminated {DOCUMENT_OPEN}> element"))?;
+    serde_json::from_str(&unescape_xml(&src[start..end])).map_err(|e| e.to_string())
 }
 
 fn rgba_to_svg_rgb(rgba: model::Rgba) -> (String, f32) {
@@ -27,14 +38,19 @@ fn rgba_to_svg_rgb(rgba: model::Rgba) -> (String, f32) {
     (format!("rgb({},{},{})", rgba.r, rgba.g, rgba.b), opacity)
 }
 
-fn dasharray(line_style: model::LineStyle, stroke_width: f32) -> Option<String> {
+/// Dash and gap lengths for a stroke, shared with the other vector exporters.
+pub(crate) fn dash_lengths(line_style: model::LineStyle, stroke_width: f32) -> Option<(f32, f32)> {
     match line_style {
         model::LineStyle::Solid => None,
-        model::LineStyle::Dashed => Some(format!("{} {}", stroke_width * 4.0, stroke_width * 2.5)),
-        model::LineStyle::Dotted => Some(format!("{} {}", stroke_width * 0.5, stroke_width * 2.0)),
+        model::LineStyle::Dashed => Some((stroke_width * 4.0, stroke_width * 2.5)),
+        model::LineStyle::Dotted => Some((stroke_width * 0.5, stroke_width * 2.0)),
     }
 }
 
+fn dasharray(line_style: model::LineStyle, stroke_width: f32) -> Option<String> {
+    dash_lengths(line_style, stroke_width).map(|(dash, gap)| format!("{} {}", dash, gap))
+}
+
 fn points_attr(points: &[egui::Pos2]) -> String {
     let mut out = String::new();
     for (i, p) in points.iter().enumerate() {
@@ -46,8 +62,128 @@ fn points_attr(points: &[egui::Pos2]) -> String {
     out
 }
 
-fn marker_id(stroke: model::Rgba) -> String {
-    format!("arrow_{}_{}_{}_{}", stroke.r, stroke.g, stroke.b, stroke.a)
+/// Path data through `points` with a half-circle arc over each hop.
+fn hopped_path_data(points: &[egui::Pos2], hops: &[f32], radius: f32) -> String {
+    let mut d = String::new();
+    let mut last: Option<egui::Pos2> = None;
+    for run in hop_runs(points, hops, radius) {
+        for (i, p) in run.iter().enumerate() {
+            match last {
+                None => d.push_str(&format!("M {:.3} {:.3}", p.x, p.y)),
+                Some(from) if i == 0 => {
+                    let dir = (*p - from).normalized();
+                    let side = hop_side(dir);
+                    let sweep = u8::from(dir.x * side.y - dir.y * side.x < 0.0);
+                    let r = from.distance(*p) * 0.5;
+                    d.push_str(&format!(
+                        " A {:.3} {:.3} 0 0 {} {:.3} {:.3}",
+                        r, r, sweep, p.x, p.y
+                    ));
+                }
+                Some(_) => d.push_str(&format!(" L {:.3} {:.3}", p.x, p.y)),
+            }
+            last = Some(*p);
+        }
+    }
+    d
+}
+
+/// An arrowhead marker: its shape, color, length and stroke width.
+type Marker = (model::ArrowHead, model::Rgba, f32, f32);
+
+/// Markers for the start and end of a line or polyline, where it has arrows.
+fn line_markers(
+    e: &model::Element,
+    arrow_style: model::ArrowStyle,
+    legacy_arrow: bool,
+    heads: &model::ArrowHeads,
+) -> [Option<Marker>; 2] {
+    let end = legacy_arrow
+        || matches!(
+            arrow_style,
+            model::ArrowStyle::End | model::ArrowStyle::Both
+        );
+    let start = matches!(
+        arrow_style,
+        model::ArrowStyle::Start | model::ArrowStyle::Both
+    );
+    let size = arrowhead_size(e.style.stroke.width, heads.scale);
+    let marker =
+        |on: bool, head| on.then_some((head, e.style.stroke.color, size, e.style.stroke.width));
+    [marker(start, heads.start), marker(end, heads.end)]
+}
+
+fn marker_id((head, stroke, size, width): &Marker) -> String {
+    format!(
+        "arrow_{}_{}_{}_{}_{}_{}_{}",
+        head.name(),
+        stroke.r,
+        stroke.g,
+        stroke.b,
+        stroke.a,
+        (size * 100.0).round(),
+        (width * 100.0).round()
+    )
+}
+
+/// `marker-start`/`marker-end` attributes for a line's arrowheads.
+fn marker_attrs(markers: &[Option<Marker>; 2]) -> String {
+    let mut out = String::new();
+    for (marker, attr) in markers.iter().zip(["marker-start", "marker-end"]) {
+        if let Some(m) = marker {
+            out.push_str(&format!(r#" {}="url(#{})""#, attr, marker_id(m)));
+        }
+    }
+    out
+}
+
+/// Marker drawn in user space with the tip on the line's end; the
+/// `auto-start-reverse` orientation turns the start marker outward.
+fn marker_def((head, stroke, size, width): &Marker) -> String {
+    let (rgb, opacity) = rgba_to_svg_rgb(*stroke);
+    let mut out = format!(
+        r#"<marker id="{}" markerWidth="1" markerHeight="1" refX="0" refY="0" orient="auto-start-reverse" markerUnits="userSpaceOnUse" overflow="visible">"#,
+        marker_id(&(*head, *stroke, *size, *width))
+    );
+    out.push('\n');
+    let d = |points: &[egui::Pos2], close: bool| {
+        let mut d = String::new();
+        for (i, p) in points.iter().enumerate() {
+            let op = if i == 0 { "M" } else { "L" };
+            d.push_str(&format!("{op} {:.3} {:.3} ", p.x, p.y));
+        }
+        if close {
+            d.push('z');
+        }
+        d.trim_end().to_string()
+    };
+    for part in arrowhead_parts(*head, *size) {
+        match part {
+            ArrowheadPart::Filled(points) => out.push_str(&format!(
+                r#"<path d="{}" fill="{}" fill-opacity="{:.3}"/>"#,
+                d(&points, true),
+                rgb,
+                opacity
+            )),
+            ArrowheadPart::Hollow(points) => out.push_str(&format!(
+                r#"<path d="{}" fill="white" stroke="{}" stroke-opacity="{:.3}" stroke-width="{:.3}"/>"#,
+                d(&points, true),
+                rgb,
+                opacity,
+                width
+            )),
+            ArrowheadPart::Stroked(points) => out.push_str(&format!(
+                r#"<path d="{}" fill="none" stroke="{}" stroke-opacity="{:.3}" stroke-width="{:.3}"/>"#,
+                d(&points, false),
+                rgb,
+                opacity,
+                width
+            )),
+        }
+        out.push('\n');
+    }
+    out.push_str("</marker>\n");
+    out
 }
 
 fn rich_text_line_content(spans: &[text_format::Span], font_size: f32) -> String {
@@ -105,7 +241,7 @@ fn svg_font_family(family: &model::FontFamily) -> String {
     }
 }
 
-fn label_anchor_in_rect(rect: egui::Rect, align: model::TextAlign) -> egui::Pos2 {
+pub(crate) fn label_anchor_in_rect(rect: egui::Rect, align: model::TextAlign) -> egui::Pos2 {
     let c = rect.center();
     let padding = 8.0_f32.min(rect.width() * 0.25);
     match align {
@@ -115,31 +251,41 @@ fn label_anchor_in_rect(rect: egui::Rect, align: model::TextAlign) -> egui::Pos2
     }
 }
 
-pub(super) fn document_to_svg(doc: &model::Document) -> String {
+/// The exported page: every element's bounds plus a margin, or a blank
+/// 800x600 page for an empty document.
+pub(crate) fn page_bounds(doc: &model::Document) -> egui::Rect {
     let mut bounds: Option<egui::Rect> = None;
     for e in &doc.elements {
         let b = e.bounds();
         bounds = Some(bounds.map(|r| r.union(b)).unwrap_or(b));
     }
     let bounds = bounds.unwrap_or_else(|| egui::Rect::from_min_size(egui::pos2(0.0, 0.0), egui::vec2(800.0, 600.0)));
-    let padding = 24.0;
-    let min_x = bounds.min.x - padding;
-    let min_y = bounds.min.y - padding;
-    let width = bounds.width() + padding * 2.0;
-    let height = bounds.height() + padding * 2.0;
+    bounds.expand(24.0)
+}
+
+pub fn document_to_svg(doc: &model::Document) -> String {
+    let page = page_bounds(doc);
+    let min_x = page.min.x;
+    let min_y = page.min.y;
+    let width = page.width();
+    let height = page.height();
 
-    let mut markers: HashMap<String, model::Rgba> = HashMap::new();
+    let mut markers: HashMap<String, Marker> = HashMap::new();
     for e in &doc.elements {
-        let needs_marker = match &e.kind {
-            model::ElementKind::Line { arrow, arrow_style, .. } => {
-                *arrow || !matches!(arrow_style, model::ArrowStyle::None)
-            }
-            model::ElementKind::Polyline { arrow_style, .. } => !matches!(arrow_style, model::ArrowStyle::None),
-            _ => false,
+        let ends = match &e.kind {
+            model::ElementKind::Line {
+                arrow,
+                arrow_style,
+                heads,
+                ..
+            } => line_markers(e, *arrow_style, *arrow, heads),
+            model::ElementKind::Polyline {
+                arrow_style, heads, ..
+            } => line_markers(e, *arrow_style, false, heads),
+            _ => continue,
         };
-        if needs_marker {
-            let id = marker_id(e.style.stroke.color);
-            markers.entry(id).or_insert(e.style.stroke.color);
+        for marker in ends.into_iter().flatten() {
+            markers.entry(marker_id(&marker)).or_insert(marker);
         }
     }
 
@@ -151,24 +297,21 @@ pub(super) fn document_to_svg(doc: &model::Document) -> String {
         min_x, min_y, width, height, width, height
     ));
     out.push('\n');
+    let json = serde_json::to_string(doc).unwrap_or_default();
+    out.push_str(&format!(
+        r#"<metadata>{DOCUMENT_OPEN} xmlns:sansuyu="{DOCUMENT_NS}">{}{DOCUMENT_CLOSE}</metadata>"#,
+        escape_xml(&json)
+    ));
+    out.push('\n');
     out.push_str("<defs>\n");
-    for (id, stroke) in &markers {
-        let (rgb, opacity) = rgba_to_svg_rgb(*stroke);
-        out.push_str(&format!(
-            r#"<marker id="{}" markerWidth="10" markerHeight="10" refX="10" refY="5" orient="auto-start-reverse" markerUnits="strokeWidth">"#,
-            id
-        ));
-        out.push('\n');
-        out.push_str(&format!(
-            r#"<path d="M 0 0 L 10 5 L 0 10 z" fill="{}" fill-opacity="{:.3}"/>"#,
-            rgb, opacity
-        ));
-        out.push('\n');
-        out.push_str("</marker>\n");
+    for marker in markers.values() {
+        out.push_str(&marker_def(marker));
     }
     out.push_str("</defs>\n");
 
+    let hops = line_hops(doc);
     for e in &doc.elements {
+        let hops = hops.get(&e.id).map_or(&[][..], Vec::as_slice);
         let stroke_width = e.style.stroke.width;
         let (stroke_rgb, stroke_opacity) = rgba_to_svg_rgb(e.style.stroke.color);
         let dash = dasharray(e.style.stroke.line_style, stroke_width);
@@ -185,8 +328,11 @@ pub(super) fn document_to_svg(doc: &model::Document) -> String {
         };
 
         match &e.kind {
-            model::ElementKind::Rect { rect, label } => {
-                let pts = rotated_rect_points_world(rect.to_rect(), e.rotation);
+            model::ElementKind::Rect {
+                rect,
+                label,
+                corner_radius,
+            } => {
                 let fill_attrs = match e.style.fill {
                     Some(rgba) if rgba.a > 0 => {
                         let (rgb, opacity) = rgba_to_svg_rgb(rgba);
@@ -194,12 +340,33 @@ pub(super) fn document_to_svg(doc: &model::Document) -> String {
                     }
                     _ => r#"fill="none""#.to_string(),
                 };
-                out.push_str(&format!(
-                    r#"<polygon points="{}" {} {} />"#,
-                    points_attr(&pts),
-                    stroke_attrs,
-                    fill_attrs
-                ));
+                let r = rect.to_rect();
+                let radius = clamp_corner_radius(r, *corner_radius);
+                if radius > 0.0 {
+                    let c = r.center();
+                    out.push_str(&format!(
+                        r#"<rect x="{:.3}" y="{:.3}" width="{:.3}" height="{:.3}" rx="{:.3}" ry="{:.3}" transform="rotate({:.3} {:.3} {:.3})" {} {} />"#,
+                        r.min.x,
+                        r.min.y,
+                        r.width(),
+                        r.height(),
+                        radius,
+                        radius,
+                        e.rotation.to_degrees(),
+                        c.x,
+                        c.y,
+                        stroke_attrs,
+                        fill_attrs
+                    ));
+                } else {
+                    let pts = rotated_rect_points_world(r, e.rotation);
+                    out.push_str(&format!(
+                        r#"<polygon points="{}" {} {} />"#,
+                        points_attr(&pts),
+                        stroke_attrs,
+                        fill_attrs
+                    ));
+                }
                 out.push('\n');
                 if !label.is_empty() {
                     let r = rect.to_rect();
@@ -393,56 +560,155 @@ pub(super) fn document_to_svg(doc: &model::Document) -> String {
                     out.push('\n');
                 }
             }
+            model::ElementKind::Diamond { rect, label }
+            | model::ElementKind::Hexagon { rect, label, .. }
+            | model::ElementKind::Cylinder { rect, label, .. }
+            | model::ElementKind::Polygon { rect, label, .. } => {
+                let pts = shape_outline_world(e).unwrap_or_default();
+                let fill_attrs = match e.style.fill {
+                    Some(rgba) if rgba.a > 0 => {
+                        let (rgb, opacity) = rgba_to_svg_rgb(rgba);
+                        format!(r#"fill="{}" fill-opacity="{:.3}""#, rgb, opacity)
+                    }
+                    _ => r#"fill="none""#.to_string(),
+                };
+                out.push_str(&format!(
+                    r#"<polygon points="{}" {} {} />"#,
+                    points_attr(&pts),
+                    stroke_attrs,
+                    fill_attrs
+                ));
+                out.push('\n');
+                if let model::ElementKind::Cylinder { cap_ratio, .. } = &e.kind {
+                    let rim =
+                        rotated_cylinder_rim_points_world(rect.to_rect(), e.rotation, *cap_ratio);
+                    out.push_str(&format!(
+                        r#"<polyline points="{}" {} fill="none" />"#,
+                        points_attr(&rim),
+                        stroke_attrs
+                    ));
+                    out.push('\n');
+                }
+                if !label.is_empty() {
+                    let r = rect.to_rect();
+                    let c = r.center();
+                    let p = label_anchor_in_rect(r, e.style.text_align);
+                    let (rgb, opacity) = rgba_to_svg_rgb(e.style.text_color);
+                    let content = rich_text_tspans(label, e.style.text_size, p.x);
+                    out.push_str(&format!(
+                        r#"<text x="{:.3}" y="{:.3}" font-size="{:.3}" font-family="{}" fill="{}" fill-opacity="{:.3}" text-anchor="{}" dominant-baseline="middle" transform="rotate({:.3} {:.3} {:.3})">{}</text>"#,
+                        p.x,
+                        p.y,
+                        e.style.text_size,
+                        svg_font_family(&e.style.font_family),
+                        rgb,
+                        opacity,
+                        svg_text_anchor(e.style.text_align),
+                        e.rotation.to_degrees(),
+                        c.x,
+                        c.y,
+                        content
+                    ));
+                    out.push('\n');
+                }
+            }
             model::ElementKind::Line {
                 a,
                 b,
                 arrow,
                 arrow_style,
+                heads,
                 start_binding,
                 end_binding,
+                route,
+                label,
+                ..
             } => {
-                let (a, b) = resolved_line_endpoints_world(doc, *a, *b, start_binding, end_binding);
+                let pts = line_points_world(doc, *a, *b, route, start_binding, end_binding);
                 let mut attrs = stroke_attrs;
-                let has_end_arrow = *arrow || matches!(arrow_style, model::ArrowStyle::End | model::ArrowStyle::Both);
-                let has_start_arrow = matches!(arrow_style, model::ArrowStyle::Start | model::ArrowStyle::Both);
-                if has_start_arrow || has_end_arrow {
-                    let mid = marker_id(e.style.stroke.color);
-                    if has_start_arrow {
-                        attrs.push_str(&format!(r#" marker-start="url(#{})""#, mid));
-                    }
-                    if has_end_arrow {
-                        attrs.push_str(&format!(r#" marker-end="url(#{})""#, mid));
-                    }
+                attrs.push_str(&marker_attrs(&line_markers(e, *arrow_style, *arrow, heads)));
+                let curve = line_curve_world(doc, *a, *b, route, start_binding, end_binding);
+                if !hops.is_empty() {
+                    out.push_str(&format!(
+                        r#"<path d="{}" {} fill="none" />"#,
+                        hopped_path_data(&pts, hops, line_hop_radius(stroke_width)),
+                        attrs
+                    ));
+                } else if let Some([p0, p1, p2, p3]) = curve {
+                    out.push_str(&format!(
+                        r#"<path d="M {:.3} {:.3} C {:.3} {:.3} {:.3} {:.3} {:.3} {:.3}" {} fill="none" />"#,
+                        p0.x, p0.y, p1.x, p1.y, p2.x, p2.y, p3.x, p3.y, attrs
+                    ));
+                } else if let [a, b] = pts[..] {
+                    out.push_str(&format!(
+                        r#"<line x1="{:.3}" y1="{:.3}" x2="{:.3}" y2="{:.3}" {} />"#,
+                        a.x, a.y, b.x, b.y, attrs
+                    ));
+                } else {
+                    out.push_str(&format!(
+                        r#"<polyline points="{}" {} fill="none" />"#,
+                        points_attr(&pts),
+                        attrs
+                    ));
                 }
-                out.push_str(&format!(
-                    r#"<line x1="{:.3}" y1="{:.3}" x2="{:.3}" y2="{:.3}" {} />"#,
-                    a.x, a.y, b.x, b.y, attrs
-                ));
                 out.push('\n');
+                if let (Some(p), Some(r)) = (line_label_anchor(doc, e), line_label_rect(doc, e)) {
+                    if let Some(fill) = e.style.fill {
+                        let (rgb, opacity) = rgba_to_svg_rgb(fill);
+                        out.push_str(&format!(
+                            r#"<rect x="{:.3}" y="{:.3}" width="{:.3}" height="{:.3}" fill="{}" fill-opacity="{:.3}" />"#,
+                            r.min.x,
+                            r.min.y,
+                            r.width(),
+                            r.height(),
+                            rgb,
+                            opacity
+                        ));
+                        out.push('\n');
+                    }
+                    let (rgb, opacity) = rgba_to_svg_rgb(e.style.text_color);
+                    let content = rich_text_tspans(label, e.style.text_size, p.x);
+                    // Later lines step down from the first, so lift it to
+                    // center the block.
+                    let lines = text_format::parse_rich_text_lines(label).len().max(1);
+                    let y = p.y - (lines - 1) as f32 * e.style.text_size * 0.6;
+                    out.push_str(&format!(
+                        r#"<text x="{:.3}" y="{:.3}" font-size="{:.3}" font-family="{}" fill="{}" fill-opacity="{:.3}" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+                        p.x,
+                        y,
+                        e.style.text_size,
+                        svg_font_family(&e.style.font_family),
+                        rgb,
+                        opacity,
+                        content
+                    ));
+                    out.push('\n');
+                }
             }
             model::ElementKind::Polyline {
                 points,
                 arrow_style,
+                heads,
+                start_binding,
+                end_binding,
             } => {
                 if points.len() >= 2 {
-                    let pts: Vec<egui::Pos2> = points.iter().map(|p| p.to_pos2()).collect();
+                    let pts = polyline_points_world(doc, points, start_binding, end_binding);
                     let mut attrs = stroke_attrs;
-                    let has_end_arrow = matches!(arrow_style, model::ArrowStyle::End | model::ArrowStyle::Both);
-                    let has_start_arrow = matches!(arrow_style, model::ArrowStyle::Start | model::ArrowStyle::Both);
-                    if has_start_arrow || has_end_arrow {
-                        let mid = marker_id(e.style.stroke.color);
-                        if has_start_arrow {
-                            attrs.push_str(&format!(r#" marker-start="url(#{})""#, mid));
-                        }
-                        if has_end_arrow {
-                            attrs.push_str(&format!(r#" marker-end="url(#{})""#, mid));
-                        }
+                    attrs.push_str(&marker_attrs(&line_markers(e, *arrow_style, false, heads)));
+                    if hops.is_empty() {
+                        out.push_str(&format!(
+                            r#"<polyline points="{}" {} fill="none" />"#,
+                            points_attr(&pts),
+                            attrs
+                        ));
+                    } else {
+                        out.push_str(&format!(
+                            r#"<path d="{}" {} fill="none" />"#,
+                            hopped_path_data(&pts, hops, line_hop_radius(stroke_width)),
+                            attrs
+                        ));
                     }
-                    out.push_str(&format!(
-                        r#"<polyline points="{}" {} fill="none" />"#,
-                        points_attr(&pts),
-                        attrs
-                    ));
                     out.push('\n');
                 }
             }
@@ -477,6 +743,15 @@ pub(super) fn document_to_svg(doc: &model::Document) -> String {
         }
     }
 
+    for dot in junction_dots(doc) {
+        let (rgb, opacity) = rgba_to_svg_rgb(dot.color);
+        out.push_str(&format!(
+            r#"<circle cx="{:.3}" cy="{:.3}" r="{:.3}" fill="{}" fill-opacity="{:.3}" />"#,
+            dot.center.x, dot.center.y, dot.radius, rgb, opacity
+        ));
+        out.push('\n');
+    }
+
     out.push_str("</svg>\n");
     out
 }
@@ -0,0 +1,1270 @@
+//! Plain SVG import.
+//!
+//! Converts the basic primitives of an arbitrary SVG drawing into editable
+//! elements: `<rect>`, `<circle>`/`<ellipse>`, `<line>`, `<polyline>`/`<polygon>`,
+//! `<path>` (curves are flattened) and `<text>`. Transforms are baked into the
+//! geometry, keeping rotation where the element kind supports it, and `<g>`
+//! elements with several children become groups.
+
+use crate::model;
+use crate::xml::{self, XmlElement, XmlNode};
+
+const CURVE_SEGMENTS: usize = 16;
+
+fn number(element: &XmlElement, name: &str) -> f32 {
+    element.attr(name).and_then(parse_length).unwrap_or(0.0)
+}
+
+/// Affine transform `[a b c d e f]`, mapping (x, y) to (ax + cy + e, bx + dy + f).
+#[derive(Clone, Copy)]
+struct Transform([f32; 6]);
+
+impl Transform {
+    const IDENTITY: Transform = Transform([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    fn then(self, inner: Transform) -> Transform {
+        let [a, b, c, d, e, f] = self.0;
+        let [a2, b2, c2, d2, e2, f2] = inner.0;
+        Transform([
+            a * a2 + c * b2,
+            b * a2 + d * b2,
+            a * c2 + c * d2,
+            b * c2 + d * d2,
+            a * e2 + c * f2 + e,
+            b * e2 + d * f2 + f,
+        ])
+    }
+
+    fn apply(self, p: egui::Pos2) -> egui::Pos2 {
+        let [a, b, c, d, e, f] = self.0;
+        egui::pos2(a * p.x + c * p.y + e, b * p.x + d * p.y + f)
+    }
+
+    fn apply_vec(self, v: egui::Vec2) -> egui::Vec2 {
+        let [a, b, c, d, _, _] = self.0;
+        egui::vec2(a * v.x + c * v.y, b * v.x + d * v.y)
+    }
+
+    /// Uniform scale factor, used for stroke widths and font sizes.
+    fn scale(self) -> f32 {
+        let [a, b, c, d, _, _] = self.0;
+        (a * d - b * c).abs().sqrt()
+    }
+
+    fn rotation(self) -> f32 {
+        let [a, b, ..] = self.0;
+        b.atan2(a)
+    }
+
+    fn parse(src: &str) -> Transform {
+        let mut out = Transform::IDENTITY;
+        let mut rest = src;
+        while let Some(open) = rest.find('(') {
+            let name = rest[..open].trim_matches(|c: char| c.is_whitespace() || c == ',');
+            let Some(close) = rest[open..].find(')') else {
+                break;
+            };
+            let args = numbers(&rest[open + 1..open + close]);
+            rest = &rest[open + close + 1..];
+            let arg = |i: usize, default: f32| args.get(i).copied().unwrap_or(default);
+            let step = match name {
+                "matrix" if args.len() == 6 => {
+                    Transform([args[0], args[1], args[2], args[3], args[4], args[5]])
+                }
+                "translate" => Transform([1.0, 0.0, 0.0, 1.0, arg(0, 0.0), arg(1, 0.0)]),
+                "scale" => {
+                    let sx = arg(0, 1.0);
+                    Transform([sx, 0.0, 0.0, arg(1, sx), 0.0, 0.0])
+                }
+                "rotate" => {
+                    let (sin, cos) = arg(0, 0.0).to_radians().sin_cos();
+                    let (cx, cy) = (arg(1, 0.0), arg(2, 0.0));
+                    Transform([1.0, 0.0, 0.0, 1.0, cx, cy])
+                        .then(Transform([cos, sin, -sin, cos, 0.0, 0.0]))
+                        .then(Transform([1.0, 0.0, 0.0, 1.0, -cx, -cy]))
+                }
+                "skewX" => Transform([1.0, 0.0, arg(0, 0.0).to_radians().tan(), 1.0, 0.0, 0.0]),
+                "skewY" => Transform([1.0, arg(0, 0.0).to_radians().tan(), 0.0, 1.0, 0.0, 0.0]),
+                _ => Transform::IDENTITY,
+            };
+            out = out.then(step);
+        }
+        out
+    }
+}
+
+/// Parses a comma/whitespace separated number list, including the compact
+/// forms path data allows ("10-5", "0.5.5").
+fn numbers(src: &str) -> Vec<f32> {
+    let mut scanner = Scanner::new(src);
+    let mut out = Vec::new();
+    while let Some(n) = scanner.number() {
+        out.push(n);
+    }
+    out
+}
+
+fn parse_length(src: &str) -> Option<f32> {
+    let s = src.trim();
+    let split = s
+        .find(|c: char| c.is_ascii_alphabetic() || c == '%')
+        .unwrap_or(s.len());
+    let value: f32 = s[..split].trim().parse().ok()?;
+    let factor = match &s[split..] {
+        "" | "px" => 1.0,
+        "pt" => 4.0 / 3.0,
+        "pc" => 16.0,
+        "mm" => 96.0 / 25.4,
+        "cm" => 96.0 / 2.54,
+        "in" => 96.0,
+        "em" => 16.0,
+        _ => return None,
+    };
+    Some(value * factor)
+}
+
+struct Scanner<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            src: src.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while self
+            .src
+            .get(self.pos)
+            .is_some_and(|c| c.is_ascii_whitespace() || *c == b',')
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_separators();
+        self.src.get(self.pos).copied()
+    }
+
+    fn command(&mut self) -> Option<u8> {
+        let c = self.peek()?;
+        if c.is_ascii_alphabetic() && c != b'e' && c != b'E' {
+            self.pos += 1;
+            return Some(c);
+        }
+        None
+    }
+
+    fn number(&mut self) -> Option<f32> {
+        self.skip_separators();
+        let start = self.pos;
+        let digits = |s: &mut Self| {
+            let from = s.pos;
+            while s.src.get(s.pos).is_some_and(u8::is_ascii_digit) {
+                s.pos += 1;
+            }
+            s.pos > from
+        };
+        if matches!(self.src.get(self.pos), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let mut any = digits(self);
+        if self.src.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            any |= digits(self);
+        }
+        if !any {
+            self.pos = start;
+            return None;
+        }
+        if matches!(self.src.get(self.pos), Some(b'e' | b'E')) {
+            let mark = self.pos;
+            self.pos += 1;
+            if matches!(self.src.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if !digits(self) {
+                self.pos = mark;
+            }
+        }
+        std::str::from_utf8(&self.src[start..self.pos])
+            .ok()?
+            .parse()
+            .ok()
+    }
+
+    fn arc_args(&mut self) -> Option<(f32, f32, f32, bool, bool, egui::Pos2)> {
+        let rx = self.number()?;
+        let ry = self.number()?;
+        let rotation = self.number()?;
+        let large_arc = self.flag()?;
+        let sweep = self.flag()?;
+        let x = self.number()?;
+        let y = self.number()?;
+        Some((rx, ry, rotation, large_arc, sweep, egui::pos2(x, y)))
+    }
+
+    /// Arc flags may be written without separators ("a10 10 0 011 5 5").
+    fn flag(&mut self) -> Option<bool> {
+        match self.peek()? {
+            b'0' => {
+                self.pos += 1;
+                Some(false)
+            }
+            b'1' => {
+                self.pos += 1;
+                Some(true)
+            }
+            _ => None,
+        }
+    }
+}
+
+struct Subpath {
+    points: Vec<egui::Pos2>,
+    curved: bool,
+    closed: bool,
+}
+
+fn cubic(
+    p0: egui::Pos2,
+    p1: egui::Pos2,
+    p2: egui::Pos2,
+    p3: egui::Pos2,
+    out: &mut Vec<egui::Pos2>,
+) {
+    for i in 1..=CURVE_SEGMENTS {
+        let t = i as f32 / CURVE_SEGMENTS as f32;
+        let u = 1.0 - t;
+        let p = p0.to_vec2() * (u * u * u)
+            + p1.to_vec2() * (3.0 * u * u * t)
+            + p2.to_vec2() * (3.0 * u * t * t)
+            + p3.to_vec2() * (t * t * t);
+        out.push(p.to_pos2());
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn arc(
+    from: egui::Pos2,
+    mut rx: f32,
+    mut ry: f32,
+    x_rotation: f32,
+    large_arc: bool,
+    sweep: bool,
+    to: egui::Pos2,
+    out: &mut Vec<egui::Pos2>,
+) {
+    // Endpoint to center parameterization, SVG 1.1 appendix F.6.5.
+    rx = rx.abs();
+    ry = ry.abs();
+    if rx == 0.0 || ry == 0.0 || from == to {
+        out.push(to);
+        return;
+    }
+    let (sin, cos) = x_rotation.to_radians().sin_cos();
+    let h = (from - to) * 0.5;
+    let x1 = cos * h.x + sin * h.y;
+    let y1 = -sin * h.x + cos * h.y;
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+    let num = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let den = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut k = (num / den).max(0.0).sqrt();
+    if large_arc == sweep {
+        k = -k;
+    }
+    let cx1 = k * rx * y1 / ry;
+    let cy1 = -k * ry * x1 / rx;
+    let mid = (from.to_vec2() + to.to_vec2()) * 0.5;
+    let center = egui::pos2(cos * cx1 - sin * cy1 + mid.x, sin * cx1 + cos * cy1 + mid.y);
+    let angle = |x: f32, y: f32| y.atan2(x);
+    let start = angle((x1 - cx1) / rx, (y1 - cy1) / ry);
+    let mut delta = angle((-x1 - cx1) / rx, (-y1 - cy1) / ry) - start;
+    if sweep && delta < 0.0 {
+        delta += std::f32::consts::TAU;
+    } else if !sweep && delta > 0.0 {
+        delta -= std::f32::consts::TAU;
+    }
+    let steps = ((delta.abs() / (std::f32::consts::PI / 16.0)).ceil() as usize).max(1);
+    for i in 1..=steps {
+        let theta = start + delta * i as f32 / steps as f32;
+        let (s, c) = theta.sin_cos();
+        let (x, y) = (rx * c, ry * s);
+        out.push(egui::pos2(
+            center.x + cos * x - sin * y,
+            center.y + sin * x + cos * y,
+        ));
+    }
+}
+
+fn parse_path(data: &str) -> Vec<Subpath> {
+    let mut scanner = Scanner::new(data);
+    let mut subpaths: Vec<Subpath> = Vec::new();
+    let mut current = egui::Pos2::ZERO;
+    let mut start = egui::Pos2::ZERO;
+    // Reflected control point for S/T, with the command family it came from.
+    let mut last_control: Option<(u8, egui::Pos2)> = None;
+    let mut command = None;
+    loop {
+        if let Some(c) = scanner.command() {
+            command = Some(c);
+        } else if scanner.peek().is_none() {
+            break;
+        }
+        let Some(cmd) = command else {
+            break;
+        };
+        let relative = cmd.is_ascii_lowercase();
+        let base = if relative {
+            current.to_vec2()
+        } else {
+            egui::Vec2::ZERO
+        };
+        let point = |s: &mut Scanner| -> Option<egui::Pos2> {
+            let x = s.number()?;
+            let y = s.number()?;
+            Some(egui::pos2(x, y) + base)
+        };
+        let upper = cmd.to_ascii_uppercase();
+        if upper != b'M' && subpaths.is_empty() {
+            subpaths.push(Subpath {
+                points: vec![current],
+                curved: false,
+                closed: false,
+            });
+        }
+        let mut control = None;
+        let ok = match upper {
+            b'M' => point(&mut scanner).map(|p| {
+                subpaths.push(Subpath {
+                    points: vec![p],
+                    curved: false,
+                    closed: false,
+                });
+                current = p;
+                start = p;
+                // Further coordinate pairs are implicit line-tos.
+                command = Some(if relative { b'l' } else { b'L' });
+            }),
+            b'L' => point(&mut scanner).map(|p| current = p),
+            b'H' => scanner.number().map(|x| {
+                current.x = if relative { current.x + x } else { x };
+            }),
+            b'V' => scanner.number().map(|y| {
+                current.y = if relative { current.y + y } else { y };
+            }),
+            b'Z' => {
+                current = start;
+                if let Some(sub) = subpaths.last_mut() {
+                    sub.closed = true;
+                }
+                // A new segment after Z starts from the subpath start.
+                command = None;
+                Some(())
+            }
+            b'C' | b'S' => {
+                let c1 = if upper == b'C' {
+                    point(&mut scanner)
+                } else {
+                    Some(match last_control {
+                        Some((b'C', c)) => current + (current - c),
+                        _ => current,
+                    })
+                };
+                c1.and_then(|c1| {
+                    let c2 = point(&mut scanner)?;
+                    let end = point(&mut scanner)?;
+                    let sub = subpaths.last_mut()?;
+                    cubic(current, c1, c2, end, &mut sub.points);
+                    sub.curved = true;
+                    control = Some((b'C', c2));
+                    current = end;
+                    Some(())
+                })
+            }
+            b'Q' | b'T' => {
+                let q = if upper == b'Q' {
+                    point(&mut scanner)
+                } else {
+                    Some(match last_control {
+                        Some((b'Q', c)) => current + (current - c),
+                        _ => current,
+                    })
+                };
+                q.and_then(|q| {
+                    let end = point(&mut scanner)?;
+                    let sub = subpaths.last_mut()?;
+                    // Degree elevation to reuse the cubic sampler.
+                    let c1 = current + (q - current) * (2.0 / 3.0);
+                    let c2 = end + (q - end) * (2.0 / 3.0);
+                    cubic(current, c1, c2, end, &mut sub.points);
+                    sub.curved = true;
+                    control = Some((b'Q', q));
+                    current = end;
+                    Some(())
+                })
+            }
+            b'A' => scanner
+                .arc_args()
+                .and_then(|(rx, ry, rotation, large_arc, sweep, end)| {
+                    let end = end + base;
+                    let sub = subpaths.last_mut()?;
+                    arc(
+                        current,
+                        rx,
+                        ry,
+                        rotation,
+                        large_arc,
+                        sweep,
+                        end,
+                        &mut sub.points,
+                    );
+                    sub.curved = true;
+                    current = end;
+                    Some(())
+                }),
+            _ => None,
+        };
+        if ok.is_none() {
+            break;
+        }
+        if matches!(upper, b'L' | b'H' | b'V')
+            && let Some(sub) = subpaths.last_mut()
+        {
+            sub.points.push(current);
+        }
+        if upper == b'Z' && subpaths.last().is_some_and(|s| s.points.len() > 1) {
+            subpaths.push(Subpath {
+                points: vec![start],
+                curved: false,
+                closed: false,
+            });
+        }
+        last_control = control;
+    }
+    subpaths
+        .into_iter()
+        .filter(|s| s.points.len() >= 2)
+        .collect()
+}
+
+/// Inherited presentation state.
+#[derive(Clone)]
+struct Paint {
+    fill: Option<model::Rgba>,
+    stroke: Option<model::Rgba>,
+    stroke_width: f32,
+    dash: Option<f32>,
+    fill_opacity: f32,
+    stroke_opacity: f32,
+    opacity: f32,
+    font_size: f32,
+    monospace: bool,
+    text_anchor: model::TextAlign,
+    baseline: String,
+    marker_start: bool,
+    marker_end: bool,
+    hidden: bool,
+}
+
+impl Default for Paint {
+    fn default() -> Self {
+        Self {
+            fill: Some(model::Rgba {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 255,
+            }),
+            stroke: None,
+            stroke_width: 1.0,
+            dash: None,
+            fill_opacity: 1.0,
+            stroke_opacity: 1.0,
+            opacity: 1.0,
+            font_size: 16.0,
+            monospace: false,
+            text_anchor: model::TextAlign::Left,
+            baseline: String::new(),
+            marker_start: false,
+            marker_end: false,
+            hidden: false,
+        }
+    }
+}
+
+fn parse_paint(value: &str) -> Option<Option<model::Rgba>> {
+    let value = value.trim();
+    match value {
+        "none" | "transparent" => return Some(None),
+        "currentColor" => {
+            return Some(Some(model::Rgba {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 255,
+            }));
+        }
+        "inherit" => return None,
+        _ => {}
+    }
+    // Gradients and patterns have no element equivalent; use a neutral gray.
+    if value.starts_with("url(") {
+        return Some(Some(model::Rgba {
+            r: 128,
+            g: 128,
+            b: 128,
+            a: 255,
+        }));
+    }
+    if let Some(args) = value
+        .strip_prefix("rgba(")
+        .or_else(|| value.strip_prefix("rgb("))
+        .and_then(|v| v.strip_suffix(')'))
+    {
+        let parts: Vec<&str> = args
+            .split([',', ' ', '/'])
+            .filter(|p| !p.is_empty())
+            .collect();
+        let channel = |s: &str| -> Option<u8> {
+            let v = match s.strip_suffix('%') {
+                Some(p) => p.parse::<f32>().ok()? * 2.55,
+                None => s.parse::<f32>().ok()?,
+            };
+            Some(v.round().clamp(0.0, 255.0) as u8)
+        };
+        let mut c = model::Rgba {
+            r: channel(parts.first()?)?,
+            g: channel(parts.get(1)?)?,
+            b: channel(parts.get(2)?)?,
+            a: 255,
+        };
+        if let Some(alpha) = parts.get(3) {
+            let a = match alpha.strip_suffix('%') {
+                Some(p) => p.parse::<f32>().ok()? / 100.0,
+                None => alpha.parse::<f32>().ok()?,
+            };
+            c.a = (a.clamp(0.0, 1.0) * 255.0).round() as u8;
+        }
+        return Some(Some(c));
+    }
+    if let Some(hex) = value.strip_prefix('#')
+        && hex.len() == 8
+        && let Some(mut c) = model::Rgba::parse(&hex[..6])
+    {
+        c.a = u8::from_str_radix(&hex[6..], 16).ok()?;
+        return Some(Some(c));
+    }
+    if let Some(c) = model::Rgba::parse(value) {
+        return Some(Some(c));
+    }
+    let rgb = |r, g, b| Some(Some(model::Rgba { r, g, b, a: 255 }));
+    match value.to_ascii_lowercase().as_str() {
+        "silver" => rgb(192, 192, 192),
+        "lightgrey" | "lightgray" => rgb(211, 211, 211),
+        "darkgrey" | "darkgray" => rgb(169, 169, 169),
+        "lightblue" => rgb(173, 216, 230),
+        "lightgreen" => rgb(144, 238, 144),
+        "pink" => rgb(255, 192, 203),
+        "navy" => rgb(0, 0, 128),
+        "teal" => rgb(0, 128, 128),
+        "maroon" => rgb(128, 0, 0),
+        "olive" => rgb(128, 128, 0),
+        "lime" => rgb(0, 255, 0),
+        "aqua" | "cyan" => rgb(0, 255, 255),
+        "fuchsia" | "magenta" => rgb(255, 0, 255),
+        "brown" => rgb(165, 42, 42),
+        "gold" => rgb(255, 215, 0),
+        _ => None,
+    }
+}
+
+fn parse_opacity(value: &str) -> Option<f32> {
+    let value = value.trim();
+    let v = match value.strip_suffix('%') {
+        Some(p) => p.parse::<f32>().ok()? / 100.0,
+        None => value.parse().ok()?,
+    };
+    Some(v.clamp(0.0, 1.0))
+}
+
+impl Paint {
+    fn apply(&mut self, key: &str, value: &str) {
+        let value = value.trim();
+        match key {
+            "fill" => {
+                if let Some(c) = parse_paint(value) {
+                    self.fill = c;
+                }
+            }
+            "stroke" => {
+                if let Some(c) = parse_paint(value) {
+                    self.stroke = c;
+                }
+            }
+            "stroke-width" => {
+                if let Some(w) = parse_length(value) {
+                    self.stroke_width = w;
+                }
+            }
+            "stroke-dasharray" => {
+                self.dash = numbers(value).first().copied().filter(|d| *d > 0.0);
+            }
+            "fill-opacity" => {
+                if let Some(o) = parse_opacity(value) {
+                    self.fill_opacity = o;
+                }
+            }
+            "stroke-opacity" => {
+                if let Some(o) = parse_opacity(value) {
+                    self.stroke_opacity = o;
+                }
+            }
+            "font-size" => {
+                if let Some(s) = parse_length(value) {
+                    self.font_size = s;
+                }
+            }
+            "font-family" => {
+                let lower = value.to_ascii_lowercase();
+                self.monospace = ["monospace", "mono", "courier", "consolas", "menlo"]
+                    .iter()
+                    .any(|m| lower.contains(m));
+            }
+            "text-anchor" => {
+                self.text_anchor = match value {
+                    "middle" => model::TextAlign::Center,
+                    "end" => model::TextAlign::Right,
+                    _ => model::TextAlign::Left,
+                };
+            }
+            "dominant-baseline" | "alignment-baseline" => self.baseline = value.to_string(),
+            "marker-start" => self.marker_start = value != "none",
+            "marker-end" => self.marker_end = value != "none",
+            "marker" => {
+                self.marker_start = value != "none";
+                self.marker_end = value != "none";
+            }
+            "display" => self.hidden |= value == "none",
+            "visibility" => self.hidden = value == "hidden" || value == "collapse",
+            _ => {}
+        }
+    }
+
+    /// Presentation attributes first, then the `style` attribute, which wins.
+    fn inherit(&self, element: &XmlElement) -> Paint {
+        let mut paint = self.clone();
+        for (key, value) in &element.attrs {
+            paint.apply(key, value);
+        }
+        if let Some(opacity) = element.attr("opacity").and_then(parse_opacity) {
+            paint.opacity *= opacity;
+        }
+        if let Some(style) = element.attr("style") {
+            for decl in style.split(';') {
+                if let Some((key, value)) = decl.split_once(':') {
+                    let (key, value) = (key.trim(), value.trim());
+                    if key == "opacity" {
+                        if let Some(opacity) = parse_opacity(value) {
+                            paint.opacity *= opacity;
+                        }
+                    } else {
+                        paint.apply(key, value);
+                    }
+                }
+            }
+        }
+        paint
+    }
+
+    fn with_alpha(c: model::Rgba, opacity: f32) -> model::Rgba {
+        model::Rgba {
+            a: (c.a as f32 * opacity).round() as u8,
+            ..c
+        }
+    }
+
+    fn style(&self, scale: f32) -> model::Style {
+        let mut style = model::Style::default_for_shapes();
+        style.fill = self
+            .fill
+            .map(|c| Self::with_alpha(c, self.fill_opacity * self.opacity));
+        match self.stroke {
+            Some(c) => style.stroke.color = Self::with_alpha(c, self.stroke_opacity * self.opacity),
+            None => style.stroke.color.a = 0,
+        }
+        style.stroke.width = self.stroke_width * scale;
+        style.stroke.line_style = match self.dash {
+            Some(dash) if dash <= self.stroke_width => model::LineStyle::Dotted,
+            Some(_) => model::LineStyle::Dashed,
+            None => model::LineStyle::Solid,
+        };
+        style
+    }
+
+    fn arrow_style(&self) -> model::ArrowStyle {
+        match (self.marker_start, self.marker_end) {
+            (true, true) => model::ArrowStyle::Both,
+            (true, false) => model::ArrowStyle::Start,
+            (false, true) => model::ArrowStyle::End,
+            (false, false) => model::ArrowStyle::None,
+        }
+    }
+}
+
+/// Escapes characters the rich text syntax would otherwise interpret.
+fn escape_rich_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '\\' | '^' | '_' | '{' | '}') {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+/// Appends text with whitespace runs collapsed to single spaces.
+fn push_collapsed(out: &mut String, text: &str) {
+    for ch in text.chars() {
+        if ch.is_whitespace() {
+            if !out.is_empty() && !out.ends_with([' ', '\n']) {
+                out.push(' ');
+            }
+        } else {
+            out.push(ch);
+        }
+    }
+}
+
+/// Flattens `<text>` content; positioned `<tspan>`s start new lines and
+/// shifted ones become sub/superscripts.
+fn text_content(element: &XmlElement, out: &mut String) {
+    for child in &element.children {
+        match child {
+            XmlNode::Text(t) => push_collapsed(out, &escape_rich_text(t)),
+            XmlNode::Element(e) if e.local_name() == "tspan" => {
+                let shift = e.attr("baseline-shift").unwrap_or("");
+                let new_line = e.attr("x").is_some()
+                    || e.attr("dy")
+                        .and_then(parse_length)
+                        .is_some_and(|dy| dy != 0.0);
+                if new_line && !out.is_empty() {
+                    out.push('\n');
+                }
+                let mut inner = String::new();
+                text_content(e, &mut inner);
+                match shift {
+                    "super" | "sub" if !inner.is_empty() => {
+                        out.push(if shift == "super" { '^' } else { '_' });
+                        out.push('{');
+                        out.push_str(&inner);
+                        out.push('}');
+                    }
+                    _ => out.push_str(&inner),
+                }
+            }
+            XmlNode::Element(_) => {}
+        }
+    }
+}
+
+/// Recognizes closed four-corner outlines as (possibly rotated) rectangles,
+/// which is how the SVG exporter writes rect elements.
+fn rectangle(points: &[egui::Pos2]) -> Option<(model::RectF, f32)> {
+    let [a, b, c, d, _] = points else {
+        return None;
+    };
+    let tolerance = 0.01 * ((*c - *a).length() + 1.0);
+    let center = egui::pos2((a.x + c.x) * 0.5, (a.y + c.y) * 0.5);
+    let center_bd = egui::pos2((b.x + d.x) * 0.5, (b.y + d.y) * 0.5);
+    if center.distance(center_bd) > tolerance
+        || ((*c - *a).length() - (*d - *b).length()).abs() > tolerance
+    {
+        return None;
+    }
+    let (width, height) = ((*b - *a).length(), (*c - *b).length());
+    if width <= 0.0 || height <= 0.0 {
+        return None;
+    }
+    let half = egui::vec2(width, height) * 0.5;
+    let rect = model::RectF::from_min_max(center - half, center + half);
+    Some((rect, (b.y - a.y).atan2(b.x - a.x)))
+}
+
+struct Importer {
+    elements: Vec<model::Element>,
+    groups: Vec<model::Group>,
+    next_id: u64,
+}
+
+impl Importer {
+    fn next_id(&mut self) -> u64 {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+
+    fn push(
+        &mut self,
+        kind: model::ElementKind,
+        style: model::Style,
+        rotation: f32,
+        group: Option<u64>,
+    ) {
+        let id = self.next_id();
+        self.elements.push(model::Element {
+            id,
+            group_id: group,
+            rotation,
+            snap_enabled: true,
+            kind,
+            style,
+            ports: Vec::new(),
+        });
+    }
+
+    fn walk(
+        &mut self,
+        element: &XmlElement,
+        transform: Transform,
+        paint: &Paint,
+        group: Option<u64>,
+    ) {
+        let paint = paint.inherit(element);
+        if paint.hidden {
+            return;
+        }
+        let mut transform = transform;
+        if let Some(t) = element.attr("transform") {
+            transform = transform.then(Transform::parse(t));
+        }
+        let scale = transform.scale();
+        let name = element.local_name();
+        match name {
+            "svg" | "g" | "a" | "switch" => {
+                if name == "svg" {
+                    let (x, y) = (number(element, "x"), number(element, "y"));
+                    transform = transform.then(Transform([1.0, 0.0, 0.0, 1.0, x, y]));
+                }
+                let group = if name == "g" {
+                    let id = self.next_id();
+                    self.groups.push(model::Group {
+                        id,
+                        parent_id: group,
+                    });
+                    Some(id)
+                } else {
+                    group
+                };
+                for child in &element.children {
+                    if let XmlNode::Element(child) = child {
+                        self.walk(child, transform, &paint, group);
+                    }
+                }
+            }
+            "rect" | "circle" | "ellipse" | "image" => {
+                let (center, size) = match name {
+                    "circle" => {
+                        let r = number(element, "r");
+                        (
+                            egui::pos2(number(element, "cx"), number(element, "cy")),
+                            egui::vec2(r, r) * 2.0,
+                        )
+                    }
+                    "ellipse" => (
+                        egui::pos2(number(element, "cx"), number(element, "cy")),
+                        egui::vec2(number(element, "rx"), number(element, "ry")) * 2.0,
+                    ),
+                    _ => {
+                        let size = egui::vec2(number(element, "width"), number(element, "height"));
+                        (
+                            egui::pos2(number(element, "x"), number(element, "y")) + size * 0.5,
+                            size,
+                        )
+                    }
+                };
+                let width = transform.apply_vec(egui::vec2(size.x, 0.0)).length();
+                let height = transform.apply_vec(egui::vec2(0.0, size.y)).length();
+                if width <= 0.0 || height <= 0.0 {
+                    return;
+                }
+                let center = transform.apply(center);
+                let rect = model::RectF::from_min_max(
+                    center - egui::vec2(width, height) * 0.5,
+                    center + egui::vec2(width, height) * 0.5,
+                );
+                let mut style = paint.style(scale);
+                if name == "image" {
+                    // Placeholder frame for embedded raster images.
+                    style.fill = None;
+                    style.stroke.color = model::Rgba {
+                        r: 128,
+                        g: 128,
+                        b: 128,
+                        a: 255,
+                    };
+                    style.stroke.width = 1.0;
+                    style.stroke.line_style = model::LineStyle::Dashed;
+                }
+                let label = String::new();
+                let kind = if name == "rect" || name == "image" {
+                    // Missing rx/ry fall back to each other; unequal radii are averaged.
+                    let (rx, ry) = (number(element, "rx"), number(element, "ry"));
+                    let (rx, ry) = match (rx > 0.0, ry > 0.0) {
+                        (true, false) => (rx, rx),
+                        (false, true) => (ry, ry),
+                        _ => (rx, ry),
+                    };
+                    let corner_radius = (rx * width / size.x + ry * height / size.y) * 0.5;
+                    model::ElementKind::Rect {
+                        rect,
+                        label,
+                        corner_radius,
+                    }
+                } else {
+                    model::ElementKind::Ellipse { rect, label }
+                };
+                self.push(kind, style, transform.rotation(), group);
+            }
+            "line" => {
+                let a = transform.apply(egui::pos2(number(element, "x1"), number(element, "y1")));
+                let b = transform.apply(egui::pos2(number(element, "x2"), number(element, "y2")));
+                let mut style = paint.style(scale);
+                style.fill = None;
+                let arrow_style = paint.arrow_style();
+                let kind = model::ElementKind::Line {
+                    a: model::Point::from_pos2(a),
+                    b: model::Point::from_pos2(b),
+                    arrow: false,
+                    arrow_style,
+                    heads: model::ArrowHeads::default(),
+                    start_binding: None,
+                    end_binding: None,
+                    route: model::LineRoute::Straight,
+                    label: String::new(),
+                    label_position: 0.5,
+                    label_offset: model::Point::default(),
+                };
+                self.push(kind, style, 0.0, group);
+            }
+            "polyline" | "polygon" => {
+                let coords = numbers(element.attr("points").unwrap_or(""));
+                let mut points: Vec<egui::Pos2> = coords
+                    .chunks_exact(2)
+                    .map(|c| transform.apply(egui::pos2(c[0], c[1])))
+                    .collect();
+                if name == "polygon"
+                    && let Some(first) = points.first().copied()
+                {
+                    points.push(first);
+                }
+                self.push_path(points, false, &paint, scale, group);
+            }
+            "path" => {
+                for sub in parse_path(element.attr("d").unwrap_or("")) {
+                    let mut points: Vec<egui::Pos2> =
+                        sub.points.iter().map(|p| transform.apply(*p)).collect();
+                    if sub.closed
+                        && let Some(first) = points.first().copied()
+                        && points.last() != Some(&first)
+                    {
+                        points.push(first);
+                    }
+                    self.push_path(points, sub.curved, &paint, scale, group);
+                }
+            }
+            "text" => {
+                let mut content = String::new();
+                text_content(element, &mut content);
+                let text = content
+                    .lines()
+                    .map(str::trim)
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                if text.trim().is_empty() {
+                    return;
+                }
+                let first = |key: &str| {
+                    element
+                        .attr(key)
+                        .and_then(|v| numbers(v).first().copied())
+                        .unwrap_or(0.0)
+                };
+                let size = paint.font_size;
+                let ascent = match paint.baseline.as_str() {
+                    "hanging" | "text-before-edge" => 0.0,
+                    "middle" | "central" => 0.5 * size,
+                    _ => 0.8 * size,
+                };
+                let baseline = egui::pos2(first("x") + first("dx"), first("y") + first("dy"));
+                let pos = transform.apply(baseline - egui::vec2(0.0, ascent));
+                let mut style = paint.style(scale);
+                style.text_color = paint.fill.map_or(model::Rgba::default(), |c| {
+                    Paint::with_alpha(c, paint.fill_opacity * paint.opacity)
+                });
+                style.fill = None;
+                style.stroke.color.a = 0;
+                style.text_size = size * scale;
+                style.text_align = paint.text_anchor;
+                if paint.monospace {
+                    style.font_family = model::FontFamily::Monospace;
+                }
+                let kind = model::ElementKind::Text {
+                    pos: model::Point::from_pos2(pos),
+                    text,
+                };
+                self.push(kind, style, transform.rotation(), group);
+            }
+            _ => {}
+        }
+    }
+
+    /// Straight two-point paths become lines, other straight paths polylines
+    /// and curved ones freehand strokes, unless they carry arrow markers.
+    fn push_path(
+        &mut self,
+        points: Vec<egui::Pos2>,
+        curved: bool,
+        paint: &Paint,
+        scale: f32,
+        group: Option<u64>,
+    ) {
+        if points.len() < 2 {
+            return;
+        }
+        let mut style = paint.style(scale);
+        let closed = points.len() > 2 && points.first() == points.last();
+        if !closed {
+            style.fill = None;
+        }
+        if closed
+            && !curved
+            && let Some((rect, rotation)) = rectangle(&points)
+        {
+            let kind = model::ElementKind::Rect {
+                rect,
+                label: String::new(),
+                corner_radius: 0.0,
+            };
+            self.push(kind, style, rotation, group);
+            return;
+        }
+        let arrow_style = paint.arrow_style();
+        let points_model =
+            |pts: &[egui::Pos2]| pts.iter().map(|p| model::Point::from_pos2(*p)).collect();
+        let kind = if points.len() == 2 && !curved {
+            model::ElementKind::Line {
+                a: model::Point::from_pos2(points[0]),
+                b: model::Point::from_pos2(points[1]),
+                arrow: false,
+                arrow_style,
+                heads: model::ArrowHeads::default(),
+                start_binding: None,
+                end_binding: None,
+                route: model::LineRoute::Straight,
+                label: String::new(),
+                label_position: 0.5,
+                label_offset: model::Point::default(),
+            }
+        } else if curved && arrow_style == model::ArrowStyle::None {
+            model::ElementKind::Pen {
+                points: points_model(&points),
+            }
+        } else {
+            model::ElementKind::Polyline {
+                points: points_model(&points),
+                arrow_style,
+                heads: model::ArrowHeads::default(),
+                start_binding: None,
+                end_binding: None,
+            }
+        };
+        self.push(kind, style, 0.0, group);
+    }
+
+    /// Drops groups with fewer than two members, handing their contents to
+    /// the parent group.
+    fn prune_groups(&mut self) {
+        while let Some(index) = self.groups.iter().position(|g| {
+            let elements = self
+                .elements
+                .iter()
+                .filter(|e| e.group_id == Some(g.id))
+                .count();
+            let groups = self
+                .groups
+                .iter()
+                .filter(|c| c.parent_id == Some(g.id))
+                .count();
+            elements + groups < 2
+        }) {
+            let group = self.groups.remove(index);
+            for e in &mut self.elements {
+                if e.group_id == Some(group.id) {
+                    e.group_id = group.parent_id;
+                }
+            }
+            for g in &mut self.groups {
+                if g.parent_id == Some(group.id) {
+                    g.parent_id = group.parent_id;
+                }
+            }
+        }
+    }
+}
+
+pub fn parse(src: &str) -> Result<model::Document, String> {
+    let root = xml::parse(src)?;
+    if root.local_name() != "svg" {
+        return Err("no <svg> root element".to_string());
+    }
+    let mut importer = Importer {
+        elements: Vec::new(),
+        groups: Vec::new(),
+        next_id: 1,
+    };
+    importer.walk(&root, Transform::IDENTITY, &Paint::default(), None);
+    importer.prune_groups();
+    if importer.elements.is_empty() {
+        return Err("SVG contains no supported shapes".to_string());
+    }
+    Ok(model::Document {
+        elements: importer.elements,
+        groups: importer.groups,
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(doc: &model::Document) -> Vec<&'static str> {
+        doc.elements
+            .iter()
+            .map(|e| match &e.kind {
+                model::ElementKind::Rect { .. } => "rect",
+                model::ElementKind::Ellipse { .. } => "ellipse",
+                model::ElementKind::Line { .. } => "line",
+                model::ElementKind::Polyline { .. } => "polyline",
+                model::ElementKind::Pen { .. } => "pen",
+                model::ElementKind::Text { .. } => "text",
+                _ => "other",
+            })
+            .collect()
+    }
+
+    #[test]
+    fn converts_the_basic_primitives() {
+        let doc = parse(
+            r##"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 200 200">
+                <rect x="10" y="10" width="80" height="40" rx="6" fill="#ff0000"/>
+                <circle cx="150" cy="30" r="20" style="fill: none; stroke: #0000ff"/>
+                <line x1="0" y1="100" x2="50" y2="100" stroke="black"/>
+                <polygon points="0,150 40,150 20,180" fill="green"/>
+                <polyline points="60,150 80,170 100,150" fill="none"/>
+                <path d="M 120 150 C 130 130 150 130 160 150" fill="none"/>
+                <text x="10" y="195">Hello <tspan>there</tspan></text>
+            </svg>"##,
+        )
+        .unwrap();
+        assert_eq!(
+            kinds(&doc),
+            [
+                "rect", "ellipse", "line", "polyline", "polyline", "pen", "text"
+            ]
+        );
+
+        let rect = &doc.elements[0];
+        let model::ElementKind::Rect {
+            rect: bounds,
+            corner_radius,
+            ..
+        } = &rect.kind
+        else {
+            unreachable!();
+        };
+        assert_eq!((bounds.min.x, bounds.min.y), (10.0, 10.0));
+        assert_eq!((bounds.max.x, bounds.max.y), (90.0, 50.0));
+        assert_eq!(*corner_radius, 6.0);
+        assert_eq!(rect.style.fill, model::Rgba::from_hex("#ff0000"));
+
+        let circle = &doc.elements[1];
+        assert_eq!(circle.style.fill, None);
+        assert_eq!(
+            circle.style.stroke.color,
+            model::Rgba::from_hex("#0000ff").unwrap()
+        );
+
+        // The polygon is closed and keeps its fill; the polyline is open.
+        let model::ElementKind::Polyline { points, .. } = &doc.elements[3].kind else {
+            unreachable!();
+        };
+        assert_eq!(points.first(), points.last());
+        assert!(doc.elements[3].style.fill.is_some());
+
+        let model::ElementKind::Text { text, .. } = &doc.elements[6].kind else {
+            unreachable!();
+        };
+        assert_eq!(text, "Hello there");
+    }
+
+    #[test]
+    fn bakes_transforms_into_geometry() {
+        let doc = parse(
+            r#"<svg>
+                <g transform="translate(100, 50)">
+                    <rect x="0" y="0" width="20" height="10"/>
+                    <rect x="0" y="0" width="20" height="10" transform="rotate(90)"/>
+                </g>
+            </svg>"#,
+        )
+        .unwrap();
+        let centers: Vec<_> = doc.elements.iter().map(|e| e.bounds().center()).collect();
+        assert_eq!(centers[0], egui::pos2(110.0, 55.0));
+        assert!((centers[1] - egui::pos2(95.0, 60.0)).length() < 1e-3);
+        assert!((doc.elements[1].rotation - std::f32::consts::FRAC_PI_2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn groups_need_two_members() {
+        let doc = parse(
+            r#"<svg>
+                <g id="pair"><circle r="5"/><circle cx="20" r="5"/></g>
+                <g id="single"><circle cx="50" r="5"/></g>
+            </svg>"#,
+        )
+        .unwrap();
+        assert_eq!(doc.groups.len(), 1);
+        let groups: Vec<_> = doc.elements.iter().map(|e| e.group_id).collect();
+        assert_eq!(
+            groups,
+            [Some(doc.groups[0].id), Some(doc.groups[0].id), None]
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_input() {
+        let cases = [
+            ("<html/>", "no <svg> root element"),
+            (
+                "<svg><title>empty</title></svg>",
+                "SVG contains no supported shapes",
+            ),
+            ("<svg><rect></svg>", "expected </rect>, found </svg>"),
+        ];
+        for (src, expected) in cases {
+            assert_eq!(parse(src).unwrap_err(), expected, "for {src:?}");
+        }
+    }
+}
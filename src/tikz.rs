@@ -0,0 +1,625 @@
+//! TikZ export for LaTeX documents.
+//!
+//! The picture uses `x=1pt, y=-1pt`, so world coordinates are written as-is
+//! and one world unit is one point, like the PDF export. Labels become nodes
+//! that keep the surrounding document's typeface (only the size and
+//! monospace switches are set), and sub/superscripts are set in math mode.
+
+use crate::geometry::{
+    ArrowheadPart, arrow_tails, arrowhead_parts_world, arrowhead_size, clamp_corner_radius,
+    hopped_path, junction_dots, line_curve_world, line_hop_radius, line_hops, line_label_anchor,
+    line_label_rect, line_points_world, polyline_points_world, rotated_cylinder_rim_points_world,
+    rotated_parallelogram_points_world, rotated_rect_points_world, rotated_trapezoid_points_world,
+    rotated_triangle_points_world, shape_outline_world,
+};
+use crate::svg::label_anchor_in_rect;
+use crate::{model, text_format};
+use std::fmt::Write as _;
+
+fn num(v: f32) -> String {
+    let s = format!("{:.2}", v);
+    let s = s.trim_end_matches('0').trim_end_matches('.');
+    if s == "-0" {
+        "0".to_string()
+    } else {
+        s.to_string()
+    }
+}
+
+fn coord(p: egui::Pos2) -> String {
+    format!("({},{})", num(p.x), num(p.y))
+}
+
+fn escape_text(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '\\' => out.push_str("\\textbackslash{}"),
+            '{' | '}' | '$' | '&' | '%' | '#' | '_' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            '^' => out.push_str("\\textasciicircum{}"),
+            '~' => out.push_str("\\textasciitilde{}"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+fn escape_math(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '\\' => out.push_str("\\backslash{}"),
+            '{' | '}' | '$' | '&' | '%' | '#' | '_' => {
+                out.push('\\');
+                out.push(ch);
+            }
+            '^' => out.push_str("\\hat{}"),
+            '~' => out.push_str("\\sim{}"),
+            ' ' => out.push_str("\\ "),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Node content: lines joined with `\\`, scripts as `$_{..}$` / `$^{..}$`.
+fn rich_text(text: &str) -> String {
+    let lines: Vec<String> = text_format::parse_rich_text_lines(text)
+        .iter()
+        .map(|spans| {
+            spans
+                .iter()
+                .map(|s| match s.script {
+                    text_format::Script::Normal => escape_text(&s.text),
+                    text_format::Script::Sub => format!("$_{{{}}}$", escape_math(&s.text)),
+                    text_format::Script::Sup => format!("$^{{{}}}$", escape_math(&s.text)),
+                })
+                .collect()
+        })
+        .collect();
+    lines.join("\\\\")
+}
+
+/// Relative LaTeX size switch for a text size, taking the editor's default
+/// of 16 as `\normalsize`.
+fn size_command(text_size: f32) -> Option<&'static str> {
+    match text_size {
+        s if s <= 10.0 => Some("\\scriptsize"),
+        s if s <= 12.0 => Some("\\footnotesize"),
+        s if s <= 14.5 => Some("\\small"),
+        s if s <= 18.0 => None,
+        s if s <= 22.0 => Some("\\large"),
+        s if s <= 28.0 => Some("\\Large"),
+        _ => Some("\\LARGE"),
+    }
+}
+
+fn rotate_pos_about(center: egui::Pos2, p: egui::Pos2, rotation: f32) -> egui::Pos2 {
+    let (sin, cos) = rotation.sin_cos();
+    let v = p - center;
+    center + egui::vec2(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
+}
+
+/// Names for every color the picture uses. Colors that match a named theme
+/// color reuse that name; the rest are numbered.
+struct Palette {
+    named: Vec<(String, model::Rgba)>,
+    used: Vec<(String, model::Rgba)>,
+}
+
+impl Palette {
+    fn name(&mut self, color: model::Rgba) -> String {
+        let same = |c: &model::Rgba| (c.r, c.g, c.b) == (color.r, color.g, color.b);
+        if let Some((name, _)) = self.used.iter().find(|(_, c)| same(c)) {
+            return name.clone();
+        }
+        let name = self
+            .named
+            .iter()
+            .find(|(name, c)| same(c) && !self.used.iter().any(|(n, _)| n == name))
+            .map(|(name, _)| name.clone())
+            .unwrap_or_else(|| format!("sy{}", self.used.len() + 1));
+        self.used.push((name.clone(), color));
+        name
+    }
+}
+
+fn sanitize_color_name(name: &str) -> String {
+    name.chars().filter(|c| c.is_ascii_alphanumeric()).collect()
+}
+
+fn draw_options(palette: &mut Palette, style: &model::Style, filled: bool) -> Vec<String> {
+    let mut opts = Vec::new();
+    let stroke = style.stroke.color;
+    if style.stroke.width > 0.0 && stroke.a > 0 {
+        opts.push(format!("draw={}", palette.name(stroke)));
+        opts.push(format!("line width={}pt", num(style.stroke.width)));
+        if stroke.a < 255 {
+            opts.push(format!("draw opacity={}", num(stroke.a as f32 / 255.0)));
+        }
+        match style.stroke.line_style {
+            model::LineStyle::Solid => {}
+            model::LineStyle::Dashed => opts.push("dashed".to_string()),
+            model::LineStyle::Dotted => opts.push("dotted".to_string()),
+        }
+    }
+    if filled && let Some(fill) = style.fill.filter(|c| c.a > 0) {
+        opts.push(format!("fill={}", palette.name(fill)));
+        if fill.a < 255 {
+            opts.push(format!("fill opacity={}", num(fill.a as f32 / 255.0)));
+        }
+    }
+    opts
+}
+
+/// Which ends carry an arrowhead; `legacy_arrow` is the old `Line::arrow`
+/// flag, which always meant an end arrow.
+fn arrow_ends(arrow_style: model::ArrowStyle, legacy_arrow: bool) -> (bool, bool) {
+    let end = legacy_arrow
+        || matches!(
+            arrow_style,
+            model::ArrowStyle::End | model::ArrowStyle::Both
+        );
+    let start = matches!(
+        arrow_style,
+        model::ArrowStyle::Start | model::ArrowStyle::Both
+    );
+    (start, end)
+}
+
+/// `arrows.meta` tip for an arrowhead, or `None` for the crow's foot, which
+/// TikZ has no tip for.
+fn arrow_tip(head: model::ArrowHead, scale: f32) -> Option<String> {
+    let (name, open) = match head {
+        model::ArrowHead::Triangle => ("Latex", false),
+        model::ArrowHead::Open => ("Straight Barb", false),
+        model::ArrowHead::Diamond => ("Diamond", false),
+        model::ArrowHead::HollowDiamond => ("Diamond", true),
+        model::ArrowHead::Circle => ("Circle", false),
+        model::ArrowHead::Bar => ("Bar", false),
+        model::ArrowHead::CrowsFoot => return None,
+    };
+    let mut opts = Vec::new();
+    if open {
+        opts.push("open".to_string());
+    }
+    if (scale - 1.0).abs() > f32::EPSILON {
+        opts.push(format!("scale={}", num(scale)));
+    }
+    Some(if opts.is_empty() {
+        format!("{{{name}}}")
+    } else {
+        format!("{{{name}[{}]}}", opts.join(", "))
+    })
+}
+
+/// Arrow options for a line: `->`, `<-` or `<->` with the tip set through
+/// `>`, or an explicit `{start}-{end}` pair when the two ends differ.
+fn arrow_options(
+    arrow_style: model::ArrowStyle,
+    legacy_arrow: bool,
+    heads: &model::ArrowHeads,
+) -> Vec<String> {
+    let (start, end) = arrow_ends(arrow_style, legacy_arrow);
+    let start = start.then(|| arrow_tip(heads.start, heads.scale)).flatten();
+    let end = end.then(|| arrow_tip(heads.end, heads.scale)).flatten();
+    match (start, end) {
+        (None, None) => Vec::new(),
+        (Some(s), Some(e)) if s != e => vec![format!("{s}-{e}")],
+        (Some(s), Some(_)) => vec!["<->".to_string(), format!(">={s}")],
+        (Some(s), None) => vec!["<-".to_string(), format!(">={s}")],
+        (None, Some(e)) => vec!["->".to_string(), format!(">={e}")],
+    }
+}
+
+/// Draws the crow's feet among a line's arrowheads as plain paths shaped
+/// like the SVG markers; every other head is a TikZ arrow tip. `ends` holds
+/// the tail and tip of each end.
+fn arrowheads(
+    out: &mut String,
+    palette: &mut Palette,
+    element: &model::Element,
+    arrow_style: model::ArrowStyle,
+    legacy_arrow: bool,
+    heads: &model::ArrowHeads,
+    ends: [(egui::Pos2, egui::Pos2); 2],
+) {
+    let style = &element.style;
+    if style.stroke.width <= 0.0 || style.stroke.color.a == 0 {
+        return;
+    }
+    let (start, end) = arrow_ends(arrow_style, legacy_arrow);
+    let size = arrowhead_size(style.stroke.width, heads.scale);
+    let mut solid = style.clone();
+    solid.stroke.line_style = model::LineStyle::Solid;
+    for (on, head, (tail, tip)) in [(start, heads.start, ends[0]), (end, heads.end, ends[1])] {
+        if !on || arrow_tip(head, heads.scale).is_some() {
+            continue;
+        }
+        for part in arrowhead_parts_world(head, tail, tip, size) {
+            match part {
+                ArrowheadPart::Filled(points) => {
+                    let mut filled = solid.clone();
+                    filled.fill = Some(style.stroke.color);
+                    filled.stroke.width = 0.0;
+                    let opts = draw_options(palette, &filled, true);
+                    path(out, &opts, &points, true);
+                }
+                ArrowheadPart::Hollow(points) => {
+                    let mut hollow = solid.clone();
+                    hollow.fill = Some(model::Rgba {
+                        r: 255,
+                        g: 255,
+                        b: 255,
+                        a: 255,
+                    });
+                    let opts = draw_options(palette, &hollow, true);
+                    path(out, &opts, &points, true);
+                }
+                ArrowheadPart::Stroked(points) => {
+                    let opts = draw_options(palette, &solid, false);
+                    path(out, &opts, &points, false);
+                }
+            }
+        }
+    }
+}
+
+fn node(
+    out: &mut String,
+    palette: &mut Palette,
+    at: egui::Pos2,
+    text: &str,
+    style: &model::Style,
+    vertical: &str,
+    rotation: f32,
+) {
+    let side = match style.text_align {
+        model::TextAlign::Left => "west",
+        model::TextAlign::Center => "",
+        model::TextAlign::Right => "east",
+    };
+    let anchor = match (vertical, side) {
+        ("", "") => "center".to_string(),
+        ("", s) | (s, "") => s.to_string(),
+        (v, s) => format!("{v} {s}"),
+    };
+    let align = match style.text_align {
+        model::TextAlign::Left => "left",
+        model::TextAlign::Center => "center",
+        model::TextAlign::Right => "right",
+    };
+    let mut opts = vec![
+        format!("anchor={anchor}"),
+        format!("align={align}"),
+        "inner sep=0pt".to_string(),
+        format!("text={}", palette.name(style.text_color)),
+    ];
+    if style.text_color.a < 255 {
+        opts.push(format!(
+            "text opacity={}",
+            num(style.text_color.a as f32 / 255.0)
+        ));
+    }
+    let mut font = Vec::new();
+    if let Some(size) = size_command(style.text_size) {
+        font.push(size);
+    }
+    if matches!(style.font_family, model::FontFamily::Monospace) {
+        font.push("\\ttfamily");
+    }
+    if !font.is_empty() {
+        opts.push(format!("font={}", font.join("")));
+    }
+    if rotation.abs() > f32::EPSILON {
+        // The y axis is flipped, so a clockwise turn on screen is a negative angle.
+        opts.push(format!("rotate={}", num(-rotation.to_degrees())));
+    }
+    let _ = writeln!(
+        out,
+        "  \\node[{}] at {} {{{}}};",
+        opts.join(", "),
+        coord(at),
+        rich_text(text)
+    );
+}
+
+fn shape_label(
+    out: &mut String,
+    palette: &mut Palette,
+    rect: &model::RectF,
+    label: &str,
+    element: &model::Element,
+) {
+    if label.is_empty() {
+        return;
+    }
+    let r = rect.to_rect();
+    let anchor = label_anchor_in_rect(r, element.style.text_align);
+    let at = rotate_pos_about(r.center(), anchor, element.rotation);
+    node(
+        out,
+        palette,
+        at,
+        label,
+        &element.style,
+        "",
+        element.rotation,
+    );
+}
+
+/// Centers a line's label on its anchor, over a box in the line's fill color
+/// when it has one.
+fn line_label(
+    out: &mut String,
+    palette: &mut Palette,
+    doc: &model::Document,
+    label: &str,
+    element: &model::Element,
+) {
+    let (Some(at), Some(rect)) = (
+        line_label_anchor(doc, element),
+        line_label_rect(doc, element),
+    ) else {
+        return;
+    };
+    let mut style = element.style.clone();
+    style.stroke.width = 0.0;
+    let opts = draw_options(palette, &style, true);
+    let corners = [
+        rect.left_top(),
+        rect.right_top(),
+        rect.right_bottom(),
+        rect.left_bottom(),
+    ];
+    path(out, &opts, &corners, true);
+    style.text_align = model::TextAlign::Center;
+    node(out, palette, at, label, &style, "", 0.0);
+}
+
+fn path(out: &mut String, opts: &[String], points: &[egui::Pos2], closed: bool) {
+    if opts.is_empty() {
+        return;
+    }
+    let mut p: Vec<String> = points.iter().map(|p| coord(*p)).collect();
+    if closed {
+        p.push("cycle".to_string());
+    }
+    let _ = writeln!(out, "  \\path[{}] {};", opts.join(", "), p.join(" -- "));
+}
+
+/// `color_names` gives theme colors whose names should be reused in the
+/// `\definecolor` entries.
+pub fn document_to_tikz(doc: &model::Document, color_names: &[(String, model::Rgba)]) -> String {
+    let mut palette = Palette {
+        named: color_names
+            .iter()
+            .map(|(name, c)| (sanitize_color_name(name), *c))
+            .filter(|(name, _)| !name.is_empty())
+            .collect(),
+        used: Vec::new(),
+    };
+    let mut body = String::new();
+    let hops = line_hops(doc);
+    for e in &doc.elements {
+        let hops = hops.get(&e.id).map_or(&[][..], Vec::as_slice);
+        let hop_radius = line_hop_radius(e.style.stroke.width);
+        match &e.kind {
+            model::ElementKind::Rect {
+                rect,
+                label,
+                corner_radius,
+            } => {
+                let mut opts = draw_options(&mut palette, &e.style, true);
+                let radius = clamp_corner_radius(rect.to_rect(), *corner_radius);
+                if !opts.is_empty() && radius > 0.0 {
+                    opts.push(format!("rounded corners={}pt", num(radius)));
+                }
+                path(
+                    &mut body,
+                    &opts,
+                    &rotated_rect_points_world(rect.to_rect(), e.rotation),
+                    true,
+                );
+                shape_label(&mut body, &mut palette, rect, label, e);
+            }
+            model::ElementKind::Ellipse { rect, label } => {
+                let mut opts = draw_options(&mut palette, &e.style, true);
+                let r = rect.to_rect();
+                if !opts.is_empty() {
+                    if e.rotation.abs() > f32::EPSILON {
+                        opts.push(format!(
+                            "rotate around={{{}:{}}}",
+                            num(-e.rotation.to_degrees()),
+                            coord(r.center())
+                        ));
+                    }
+                    let _ = writeln!(
+                        body,
+                        "  \\path[{}] {} ellipse [x radius={}, y radius={}];",
+                        opts.join(", "),
+                        coord(r.center()),
+                        num(r.width() * 0.5),
+                        num(r.height() * 0.5)
+                    );
+                }
+                shape_label(&mut body, &mut palette, rect, label, e);
+            }
+            model::ElementKind::Triangle {
+                rect,
+                label,
+                apex_ratio,
+            } => {
+                let opts = draw_options(&mut palette, &e.style, true);
+                let pts = rotated_triangle_points_world(rect.to_rect(), e.rotation, *apex_ratio);
+                path(&mut body, &opts, &pts, true);
+                shape_label(&mut body, &mut palette, rect, label, e);
+            }
+            model::ElementKind::Parallelogram {
+                rect,
+                label,
+                skew_ratio,
+            } => {
+                let opts = draw_options(&mut palette, &e.style, true);
+                let pts =
+                    rotated_parallelogram_points_world(rect.to_rect(), e.rotation, *skew_ratio);
+                path(&mut body, &opts, &pts, true);
+                shape_label(&mut body, &mut palette, rect, label, e);
+            }
+            model::ElementKind::Trapezoid {
+                rect,
+                label,
+                top_inset_ratio,
+            } => {
+                let opts = draw_options(&mut palette, &e.style, true);
+                let pts =
+                    rotated_trapezoid_points_world(rect.to_rect(), e.rotation, *top_inset_ratio);
+                path(&mut body, &opts, &pts, true);
+                shape_label(&mut body, &mut palette, rect, label, e);
+            }
+            model::ElementKind::Diamond { rect, label }
+            | model::ElementKind::Hexagon { rect, label, .. }
+            | model::ElementKind::Cylinder { rect, label, .. }
+            | model::ElementKind::Polygon { rect, label, .. } => {
+                let opts = draw_options(&mut palette, &e.style, true);
+                path(&mut body, &opts, &shape_outline_world(e).unwrap_or_default(), true);
+                if let model::ElementKind::Cylinder { cap_ratio, .. } = &e.kind {
+                    let opts = draw_options(&mut palette, &e.style, false);
+                    let rim =
+                        rotated_cylinder_rim_points_world(rect.to_rect(), e.rotation, *cap_ratio);
+                    path(&mut body, &opts, &rim, false);
+                }
+                shape_label(&mut body, &mut palette, rect, label, e);
+            }
+            model::ElementKind::Line {
+                a,
+                b,
+                arrow,
+                arrow_style,
+                heads,
+                start_binding,
+                end_binding,
+                route,
+                label,
+                ..
+            } => {
+                let pts = line_points_world(doc, *a, *b, route, start_binding, end_binding);
+                let mut opts = draw_options(&mut palette, &e.style, false);
+                if !opts.is_empty() {
+                    opts.splice(0..0, arrow_options(*arrow_style, *arrow, heads));
+                }
+                let curve = line_curve_world(doc, *a, *b, route, start_binding, end_binding);
+                match curve {
+                    Some([p0, p1, p2, p3]) if !opts.is_empty() && hops.is_empty() => {
+                        let _ = writeln!(
+                            body,
+                            "  \\path[{}] {} .. controls {} and {} .. {};",
+                            opts.join(", "),
+                            coord(p0),
+                            coord(p1),
+                            coord(p2),
+                            coord(p3)
+                        );
+                    }
+                    _ => path(
+                        &mut body,
+                        &opts,
+                        &hopped_path(&pts, hops, hop_radius),
+                        false,
+                    ),
+                }
+                let (start_tail, end_tail) = arrow_tails(&pts, curve.as_ref());
+                let ends = [(start_tail, pts[0]), (end_tail, pts[pts.len() - 1])];
+                arrowheads(
+                    &mut body,
+                    &mut palette,
+                    e,
+                    *arrow_style,
+                    *arrow,
+                    heads,
+                    ends,
+                );
+                line_label(&mut body, &mut palette, doc, label, e);
+            }
+            model::ElementKind::Polyline {
+                points,
+                arrow_style,
+                heads,
+                start_binding,
+                end_binding,
+            } => {
+                if points.len() >= 2 {
+                    let pts = polyline_points_world(doc, points, start_binding, end_binding);
+                    let mut opts = draw_options(&mut palette, &e.style, false);
+                    if !opts.is_empty() {
+                        opts.splice(0..0, arrow_options(*arrow_style, false, heads));
+                    }
+                    path(
+                        &mut body,
+                        &opts,
+                        &hopped_path(&pts, hops, hop_radius),
+                        false,
+                    );
+                    let n = pts.len();
+                    let ends = [(pts[1], pts[0]), (pts[n - 2], pts[n - 1])];
+                    arrowheads(&mut body, &mut palette, e, *arrow_style, false, heads, ends);
+                }
+            }
+            model::ElementKind::Pen { points } => {
+                if points.len() >= 2 {
+                    let pts: Vec<egui::Pos2> = points.iter().map(|p| p.to_pos2()).collect();
+                    let mut opts = draw_options(&mut palette, &e.style, false);
+                    if !opts.is_empty() {
+                        opts.push("line cap=round".to_string());
+                        opts.push("line join=round".to_string());
+                    }
+                    path(&mut body, &opts, &pts, false);
+                }
+            }
+            model::ElementKind::Text { pos, text } => {
+                node(
+                    &mut body,
+                    &mut palette,
+                    pos.to_pos2(),
+                    text,
+                    &e.style,
+                    "north",
+                    0.0,
+                );
+            }
+        }
+    }
+
+    for dot in junction_dots(doc) {
+        let mut opts = vec![format!("fill={}", palette.name(dot.color))];
+        if dot.color.a < 255 {
+            opts.push(format!("fill opacity={}", num(dot.color.a as f32 / 255.0)));
+        }
+        let _ = writeln!(
+            body,
+            "  \\fill[{}] {} circle[radius={}pt];",
+            opts.join(", "),
+            coord(dot.center),
+            num(dot.radius)
+        );
+    }
+
+    let mut out = String::new();
+    out.push_str(
+        "% Generated by sansuyu; needs \\usepackage{tikz} and \\usetikzlibrary{arrows.meta}\n",
+    );
+    out.push_str("\\begin{tikzpicture}[x=1pt, y=-1pt]\n");
+    for (name, c) in &palette.used {
+        let _ = writeln!(
+            out,
+            "  \\definecolor{{{name}}}{{RGB}}{{{},{},{}}}",
+            c.r, c.g, c.b
+        );
+    }
+    out.push_str(&body);
+    out.push_str("\\end{tikzpicture}\n");
+    out
+}
@@ -0,0 +1,241 @@
+//! A small XML reader for the SVG and draw.io importers: elements,
+//! attributes and text, with comments, processing instructions and DOCTYPE
+//! declarations skipped.
+
+pub(crate) fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+pub(crate) fn unescape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let Some(semi) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..semi];
+        let decoded = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|h| u32::from_str_radix(h, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|d| d.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(ch) => {
+                out.push(ch);
+                rest = &rest[semi + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+pub(crate) struct XmlElement {
+    pub(crate) name: String,
+    pub(crate) attrs: Vec<(String, String)>,
+    pub(crate) children: Vec<XmlNode>,
+}
+
+pub(crate) enum XmlNode {
+    Element(XmlElement),
+    Text(String),
+}
+
+impl XmlElement {
+    pub(crate) fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub(crate) fn local_name(&self) -> &str {
+        self.name.rsplit(':').next().unwrap_or(&self.name)
+    }
+
+    pub(crate) fn elements(&self) -> impl Iterator<Item = &XmlElement> {
+        self.children.iter().filter_map(|n| match n {
+            XmlNode::Element(e) => Some(e),
+            XmlNode::Text(_) => None,
+        })
+    }
+
+    pub(crate) fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.elements().find(|e| e.local_name() == name)
+    }
+
+    pub(crate) fn into_child(self, name: &str) -> Option<XmlElement> {
+        self.children.into_iter().find_map(|n| match n {
+            XmlNode::Element(e) if e.local_name() == name => Some(e),
+            _ => None,
+        })
+    }
+
+    /// The element's own text, without that of its children.
+    pub(crate) fn text(&self) -> String {
+        self.children
+            .iter()
+            .filter_map(|n| match n {
+                XmlNode::Text(t) => Some(t.as_str()),
+                XmlNode::Element(_) => None,
+            })
+            .collect()
+    }
+}
+
+fn skip_past<'a>(rest: &'a str, end: &str, what: &str) -> Result<&'a str, String> {
+    rest.find(end)
+        .map(|i| &rest[i + end.len()..])
+        .ok_or_else(|| format!("unterminated {what}"))
+}
+
+/// Parses a document and returns its root element.
+pub(crate) fn parse(src: &str) -> Result<XmlElement, String> {
+    let mut stack = vec![XmlElement {
+        name: String::new(),
+        attrs: Vec::new(),
+        children: Vec::new(),
+    }];
+    let mut rest = src;
+    while !rest.is_empty() {
+        if let Some(r) = rest.strip_prefix("<!--") {
+            rest = skip_past(r, "-->", "comment")?;
+        } else if let Some(r) = rest.strip_prefix("<![CDATA[") {
+            let end = r.find("]]>").ok_or("unterminated CDATA section")?;
+            if let Some(top) = stack.last_mut() {
+                top.children.push(XmlNode::Text(r[..end].to_string()));
+            }
+            rest = &r[end + 3..];
+        } else if let Some(r) = rest.strip_prefix("<?") {
+            rest = skip_past(r, "?>", "processing instruction")?;
+        } else if let Some(r) = rest.strip_prefix("<!") {
+            // DOCTYPE, possibly with an internal subset in brackets.
+            let mut depth = 0i32;
+            let end = r
+                .char_indices()
+                .find(|&(_, c)| {
+                    match c {
+                        '[' => depth += 1,
+                        ']' => depth -= 1,
+                        '>' if depth <= 0 => return true,
+                        _ => {}
+                    }
+                    false
+                })
+                .map(|(i, _)| i)
+                .ok_or("unterminated declaration")?;
+            rest = &r[end + 1..];
+        } else if let Some(r) = rest.strip_prefix("</") {
+            let end = r.find('>').ok_or("unterminated closing tag")?;
+            let name = r[..end].trim();
+            if stack.len() < 2 {
+                return Err(format!("unexpected </{name}>"));
+            }
+            let element = stack.pop().unwrap_or_else(|| unreachable!());
+            if element.name != name {
+                return Err(format!("expected </{}>, found </{name}>", element.name));
+            }
+            if let Some(parent) = stack.last_mut() {
+                parent.children.push(XmlNode::Element(element));
+            }
+            rest = &r[end + 1..];
+        } else if let Some(r) = rest.strip_prefix('<') {
+            let (element, self_closing, r) = parse_tag(r)?;
+            if self_closing {
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(XmlNode::Element(element));
+                }
+            } else {
+                stack.push(element);
+            }
+            rest = r;
+        } else {
+            let end = rest.find('<').unwrap_or(rest.len());
+            if let Some(top) = stack.last_mut() {
+                top.children.push(XmlNode::Text(unescape_xml(&rest[..end])));
+            }
+            rest = &rest[end..];
+        }
+    }
+    if stack.len() > 1 {
+        return Err(format!("unclosed <{}>", stack[stack.len() - 1].name));
+    }
+    let document = stack.pop().ok_or("empty document")?;
+    document
+        .children
+        .into_iter()
+        .find_map(|n| match n {
+            XmlNode::Element(e) => Some(e),
+            XmlNode::Text(_) => None,
+        })
+        .ok_or_else(|| "no root element".to_string())
+}
+
+fn parse_tag(src: &str) -> Result<(XmlElement, bool, &str), String> {
+    let name_end = src
+        .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+        .ok_or("unterminated tag")?;
+    let mut element = XmlElement {
+        name: src[..name_end].to_string(),
+        attrs: Vec::new(),
+        children: Vec::new(),
+    };
+    let mut rest = &src[name_end..];
+    loop {
+        rest = rest.trim_start();
+        if let Some(r) = rest.strip_prefix("/>") {
+            return Ok((element, true, r));
+        }
+        if let Some(r) = rest.strip_prefix('>') {
+            return Ok((element, false, r));
+        }
+        let key_end = rest
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .ok_or_else(|| format!("unterminated <{}>", element.name))?;
+        if key_end == 0 {
+            return Err(format!("malformed attribute in <{}>", element.name));
+        }
+        let key = rest[..key_end].to_string();
+        rest = rest[key_end..].trim_start();
+        let Some(r) = rest.strip_prefix('=') else {
+            element.attrs.push((key, String::new()));
+            continue;
+        };
+        rest = r.trim_start();
+        let quote = rest
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .ok_or_else(|| format!("unquoted value for '{key}'"))?;
+        let r = &rest[1..];
+        let end = r
+            .find(quote)
+            .ok_or_else(|| format!("unterminated value for '{key}'"))?;
+        element.attrs.push((key, unescape_xml(&r[..end])));
+        rest = &r[end + 1..];
+    }
+}